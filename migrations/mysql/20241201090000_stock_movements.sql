@@ -0,0 +1,31 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS stock_movements
+(
+    movement_id    BIGINT      NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    correlation_id VARCHAR(36) NOT NULL,
+    kind           VARCHAR(20) NOT NULL,
+    item_id        BIGINT      NOT NULL,
+    shelf_from     BIGINT,
+    shelf_to       BIGINT,
+    count          BIGINT      NOT NULL,
+    user_id        BIGINT      NOT NULL,
+    reason         TEXT,
+    created_at     DATETIME    NOT NULL DEFAULT current_timestamp,
+    INDEX stock_movements_correlation_idx (correlation_id),
+    INDEX stock_movements_item_idx (item_id, created_at),
+    INDEX stock_movements_created_idx (created_at),
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (shelf_from) REFERENCES shelf (shelf_id),
+    FOREIGN KEY (shelf_to) REFERENCES shelf (shelf_id),
+    FOREIGN KEY (user_id) REFERENCES users (user_id)
+);
+
+CREATE TRIGGER stock_movements_no_update
+    BEFORE UPDATE
+    ON stock_movements
+    FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'stock movements are immutable';
+
+CREATE TRIGGER stock_movements_no_delete
+    BEFORE DELETE
+    ON stock_movements
+    FOR EACH ROW SIGNAL SQLSTATE '45000' SET MESSAGE_TEXT = 'stock movements are immutable';
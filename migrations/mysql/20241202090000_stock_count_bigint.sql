@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE stock
+    MODIFY count BIGINT NOT NULL;
@@ -0,0 +1,11 @@
+-- Add migration script here
+ALTER TABLE stock
+    ADD COLUMN lot_no      VARCHAR(64) NOT NULL DEFAULT '',
+    ADD COLUMN mfg_date    DATE,
+    ADD COLUMN expiry_date DATE;
+
+CREATE INDEX stock_item_shelf_lot_idx ON stock (item_id, shelf_id, lot_no);
+CREATE INDEX stock_expiry_date_idx ON stock (expiry_date);
+
+ALTER TABLE stock_movements
+    ADD COLUMN lot_no VARCHAR(64);
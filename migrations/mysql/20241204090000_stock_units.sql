@@ -0,0 +1,27 @@
+-- Add migration script here
+ALTER TABLE items
+    ADD COLUMN serialized BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS stock_units
+(
+    unit_id    BIGINT       NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    item_id    BIGINT       NOT NULL,
+    serial     VARCHAR(128) NOT NULL,
+    shelf_id   BIGINT,
+    lot_no     VARCHAR(64)  NOT NULL DEFAULT '',
+    created_at DATETIME     NOT NULL DEFAULT current_timestamp,
+    updated_at DATETIME ON UPDATE current_timestamp,
+    UNIQUE KEY stock_units_serial_key (item_id, serial),
+    INDEX stock_units_shelf_idx (shelf_id, item_id),
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (shelf_id) REFERENCES shelf (shelf_id)
+);
+
+CREATE TABLE IF NOT EXISTS stock_unit_movements
+(
+    unit_id     BIGINT NOT NULL,
+    movement_id BIGINT NOT NULL,
+    PRIMARY KEY (unit_id, movement_id),
+    FOREIGN KEY (unit_id) REFERENCES stock_units (unit_id),
+    FOREIGN KEY (movement_id) REFERENCES stock_movements (movement_id)
+);
@@ -0,0 +1,21 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS stock_reservations
+(
+    reservation_id BIGINT   NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    item_id        BIGINT   NOT NULL,
+    shelf_id       BIGINT,
+    room_id        BIGINT,
+    count          BIGINT   NOT NULL,
+    user_id        BIGINT   NOT NULL,
+    note           TEXT,
+    expires_at     DATETIME,
+    created_at     DATETIME NOT NULL DEFAULT current_timestamp,
+    CHECK ((shelf_id IS NULL) <> (room_id IS NULL)),
+    CHECK (count > 0),
+    INDEX stock_reservations_item_idx (item_id, shelf_id, room_id),
+    INDEX stock_reservations_expires_idx (expires_at),
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (shelf_id) REFERENCES shelf (shelf_id),
+    FOREIGN KEY (room_id) REFERENCES rooms (room_id),
+    FOREIGN KEY (user_id) REFERENCES users (user_id)
+);
@@ -0,0 +1,23 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS reorder_points
+(
+    reorder_point_id BIGINT   NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    item_id          BIGINT   NOT NULL,
+    room_id          BIGINT,
+    room_key         BIGINT AS (COALESCE(room_id, 0)) STORED,
+    min_level        BIGINT   NOT NULL,
+    max_level        BIGINT   NOT NULL,
+    below_since      DATETIME,
+    created_at       DATETIME NOT NULL DEFAULT current_timestamp,
+    CHECK (min_level >= 0 AND max_level >= min_level),
+    UNIQUE KEY reorder_points_item_room_key (item_id, room_key),
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (room_id) REFERENCES rooms (room_id)
+);
+
+CREATE TABLE IF NOT EXISTS stock_alert_subscriptions
+(
+    user_id    BIGINT   NOT NULL PRIMARY KEY,
+    created_at DATETIME NOT NULL DEFAULT current_timestamp,
+    FOREIGN KEY (user_id) REFERENCES users (user_id)
+);
@@ -0,0 +1,52 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS stocktakes
+(
+    stocktake_id BIGINT      NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    room_id      BIGINT,
+    status       VARCHAR(16) NOT NULL DEFAULT 'open',
+    frozen       BOOLEAN     NOT NULL DEFAULT FALSE,
+    blind        BOOLEAN     NOT NULL DEFAULT FALSE,
+    opened_by    BIGINT      NOT NULL,
+    closed_by    BIGINT,
+    created_at   DATETIME    NOT NULL DEFAULT current_timestamp,
+    closed_at    DATETIME,
+    FOREIGN KEY (room_id) REFERENCES rooms (room_id),
+    FOREIGN KEY (opened_by) REFERENCES users (user_id),
+    FOREIGN KEY (closed_by) REFERENCES users (user_id)
+);
+
+CREATE TABLE IF NOT EXISTS stocktake_shelves
+(
+    stocktake_id BIGINT NOT NULL,
+    shelf_id     BIGINT NOT NULL,
+    PRIMARY KEY (stocktake_id, shelf_id),
+    INDEX stocktake_shelves_shelf_idx (shelf_id),
+    FOREIGN KEY (stocktake_id) REFERENCES stocktakes (stocktake_id),
+    FOREIGN KEY (shelf_id) REFERENCES shelf (shelf_id)
+);
+
+CREATE TABLE IF NOT EXISTS stocktake_counts
+(
+    stocktake_id BIGINT   NOT NULL,
+    item_id      BIGINT   NOT NULL,
+    shelf_id     BIGINT   NOT NULL,
+    counted      BIGINT   NOT NULL,
+    expected     BIGINT,
+    counted_by   BIGINT   NOT NULL,
+    counted_at   DATETIME NOT NULL DEFAULT current_timestamp,
+    CHECK (counted >= 0),
+    PRIMARY KEY (stocktake_id, item_id, shelf_id),
+    FOREIGN KEY (stocktake_id, shelf_id) REFERENCES stocktake_shelves (stocktake_id, shelf_id),
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (counted_by) REFERENCES users (user_id)
+);
+
+CREATE TABLE IF NOT EXISTS stocktake_serials
+(
+    stocktake_id BIGINT       NOT NULL,
+    item_id      BIGINT       NOT NULL,
+    shelf_id     BIGINT       NOT NULL,
+    serial       VARCHAR(128) NOT NULL,
+    PRIMARY KEY (stocktake_id, item_id, shelf_id, serial),
+    FOREIGN KEY (stocktake_id, item_id, shelf_id) REFERENCES stocktake_counts (stocktake_id, item_id, shelf_id)
+);
@@ -0,0 +1,18 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS stock_snapshots
+(
+    snapshot_id      BIGINT   NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    last_movement_id BIGINT   NOT NULL,
+    taken_at         DATETIME NOT NULL DEFAULT current_timestamp,
+    INDEX stock_snapshots_taken_idx (taken_at)
+);
+
+CREATE TABLE IF NOT EXISTS stock_snapshot_lines
+(
+    snapshot_id BIGINT NOT NULL,
+    item_id     BIGINT NOT NULL,
+    shelf_id    BIGINT NOT NULL,
+    count       BIGINT NOT NULL,
+    PRIMARY KEY (snapshot_id, item_id, shelf_id),
+    FOREIGN KEY (snapshot_id) REFERENCES stock_snapshots (snapshot_id)
+);
@@ -0,0 +1,39 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS categories
+(
+    category_id BIGINT      NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    name        VARCHAR(64) NOT NULL,
+    description TEXT,
+    created_at  DATETIME    NOT NULL DEFAULT current_timestamp
+);
+
+ALTER TABLE items
+    ADD COLUMN costing VARCHAR(16) NOT NULL DEFAULT 'fifo';
+ALTER TABLE items
+    ADD COLUMN category_id BIGINT,
+    ADD FOREIGN KEY (category_id) REFERENCES categories (category_id);
+
+CREATE TABLE IF NOT EXISTS cost_layers
+(
+    layer_id   BIGINT     NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    item_id    BIGINT     NOT NULL,
+    currency   VARCHAR(3) NOT NULL,
+    remaining  BIGINT     NOT NULL,
+    value      BIGINT     NOT NULL,
+    created_at DATETIME   NOT NULL DEFAULT current_timestamp,
+    CHECK (remaining >= 0),
+    FOREIGN KEY (item_id) REFERENCES items (item_id)
+);
+
+CREATE TABLE IF NOT EXISTS cost_entries
+(
+    entry_id       BIGINT      NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    correlation_id VARCHAR(36) NOT NULL,
+    item_id        BIGINT      NOT NULL,
+    currency       VARCHAR(3)  NOT NULL,
+    count          BIGINT      NOT NULL,
+    value          BIGINT      NOT NULL,
+    created_at     DATETIME    NOT NULL DEFAULT current_timestamp,
+    INDEX cost_entries_item_idx (item_id, created_at),
+    FOREIGN KEY (item_id) REFERENCES items (item_id)
+);
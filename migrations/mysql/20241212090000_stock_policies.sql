@@ -0,0 +1,14 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS stock_policies
+(
+    policy_id      BIGINT      NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    room_id        BIGINT,
+    allow_empty    BOOLEAN     NOT NULL,
+    keep_empty     BOOLEAN     NOT NULL,
+    allow_negative BOOLEAN     NOT NULL,
+    created_at     DATETIME    NOT NULL DEFAULT current_timestamp,
+    FOREIGN KEY (room_id) REFERENCES rooms (room_id)
+);
+
+ALTER TABLE stock
+    ADD COLUMN flagged_at DATETIME;
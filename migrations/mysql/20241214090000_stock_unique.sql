@@ -0,0 +1,19 @@
+-- Add migration script here
+UPDATE stock
+    JOIN (SELECT MIN(stock_id) stock_id, SUM(count) total
+          FROM stock
+          GROUP BY item_id, shelf_id, lot_no, status
+          HAVING COUNT(*) > 1) d ON d.stock_id = stock.stock_id
+SET stock.count = d.total;
+
+DELETE stock
+FROM stock
+         JOIN (SELECT item_id, shelf_id, lot_no, status, MIN(stock_id) stock_id
+               FROM stock
+               GROUP BY item_id, shelf_id, lot_no, status) d
+              ON d.item_id = stock.item_id AND d.shelf_id = stock.shelf_id AND d.lot_no = stock.lot_no AND
+                 d.status = stock.status
+WHERE stock.stock_id <> d.stock_id;
+
+CREATE UNIQUE INDEX stock_item_shelf_lot_status_idx ON stock (item_id, shelf_id, lot_no, status);
+DROP INDEX stock_item_shelf_lot_idx ON stock;
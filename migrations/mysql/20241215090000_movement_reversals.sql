@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE stock_movements
+    ADD COLUMN reversal_of BIGINT,
+    ADD UNIQUE INDEX stock_movements_reversal_idx (reversal_of),
+    ADD FOREIGN KEY (reversal_of) REFERENCES stock_movements (movement_id);
@@ -0,0 +1,14 @@
+-- Add migration script here
+ALTER TABLE items
+    ADD COLUMN weight_g  BIGINT,
+    ADD COLUMN length_mm BIGINT,
+    ADD COLUMN width_mm  BIGINT,
+    ADD COLUMN height_mm BIGINT;
+
+ALTER TABLE shelf
+    ADD COLUMN max_units      BIGINT,
+    ADD COLUMN max_weight_g   BIGINT,
+    ADD COLUMN max_volume_mm3 BIGINT;
+
+ALTER TABLE stock_policies
+    ADD COLUMN warn_over_capacity BOOLEAN NOT NULL DEFAULT FALSE;
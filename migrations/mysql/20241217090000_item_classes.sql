@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE items
+    ADD COLUMN abc_class     VARCHAR(1),
+    ADD COLUMN xyz_class     VARCHAR(1),
+    ADD COLUMN classified_at DATETIME;
@@ -0,0 +1,27 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS warehouses
+(
+    warehouse_id BIGINT      NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    code         VARCHAR(16) NOT NULL UNIQUE,
+    name         VARCHAR(50) NOT NULL,
+    address      TEXT,
+    time_zone    VARCHAR(64) NOT NULL DEFAULT 'UTC',
+    created_at   DATETIME    NOT NULL DEFAULT current_timestamp,
+    updated_at   DATETIME ON UPDATE current_timestamp
+);
+
+-- the rooms there are so far all belong to the one site
+INSERT INTO warehouses (code, name)
+VALUES ('MAIN', 'Main warehouse');
+
+ALTER TABLE rooms
+    ADD COLUMN warehouse_id BIGINT;
+UPDATE rooms
+SET warehouse_id = (SELECT MIN(warehouse_id) FROM warehouses);
+ALTER TABLE rooms
+    MODIFY warehouse_id BIGINT NOT NULL,
+    ADD FOREIGN KEY (warehouse_id) REFERENCES warehouses (warehouse_id);
+
+-- whether a transfer took stock from one site to another
+ALTER TABLE stock_movements
+    ADD COLUMN cross_site BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,32 @@
+-- Add migration script here
+ALTER TABLE shelf
+    ADD COLUMN layers    BIGINT NOT NULL DEFAULT 1,
+    ADD COLUMN positions BIGINT NOT NULL DEFAULT 1;
+
+CREATE TABLE IF NOT EXISTS bins
+(
+    bin_id     BIGINT   NOT NULL PRIMARY KEY AUTO_INCREMENT,
+    shelf_id   BIGINT   NOT NULL,
+    layer      BIGINT   NOT NULL,
+    position   BIGINT   NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT current_timestamp,
+    FOREIGN KEY (shelf_id) REFERENCES shelf (shelf_id) ON DELETE CASCADE,
+    UNIQUE (shelf_id, layer, position)
+);
+
+-- every shelf so far is a single bin holding all of its stock
+INSERT INTO bins (shelf_id, layer, position)
+SELECT shelf_id, 1, 1
+FROM shelf;
+
+ALTER TABLE stock
+    ADD COLUMN bin_id BIGINT;
+UPDATE stock
+    JOIN bins b ON b.shelf_id = stock.shelf_id
+SET stock.bin_id = b.bin_id;
+ALTER TABLE stock
+    MODIFY bin_id BIGINT NOT NULL,
+    ADD FOREIGN KEY (bin_id) REFERENCES bins (bin_id);
+
+CREATE UNIQUE INDEX stock_item_bin_lot_status_idx ON stock (item_id, shelf_id, bin_id, lot_no, status);
+DROP INDEX stock_item_shelf_lot_status_idx ON stock;
@@ -0,0 +1,6 @@
+-- Add migration script here
+-- archived rooms and shelves are kept for their history, hidden from listings and closed to deposits
+ALTER TABLE rooms
+    ADD COLUMN archived_at DATETIME;
+ALTER TABLE shelf
+    ADD COLUMN archived_at DATETIME;
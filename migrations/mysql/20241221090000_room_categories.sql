@@ -0,0 +1,10 @@
+-- Add migration script here
+-- a room with categories listed here only takes items of those, the others take any item
+CREATE TABLE IF NOT EXISTS room_categories
+(
+    room_id     BIGINT NOT NULL,
+    category_id BIGINT NOT NULL,
+    PRIMARY KEY (room_id, category_id),
+    FOREIGN KEY (room_id) REFERENCES rooms (room_id),
+    FOREIGN KEY (category_id) REFERENCES categories (category_id)
+);
@@ -0,0 +1,7 @@
+-- Add migration script here
+-- the bins moved between, NULL on lines written before. Not a foreign key, the ledger outlives the
+-- bins of a shelf laid out anew.
+ALTER TABLE stock_movements
+    ADD COLUMN bin_from BIGINT;
+ALTER TABLE stock_movements
+    ADD COLUMN bin_to BIGINT;
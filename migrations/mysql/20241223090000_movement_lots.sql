@@ -0,0 +1,21 @@
+-- Add migration script here
+-- the dates of the lot moved, so a reversal puts it back as it was
+ALTER TABLE stock_movements
+    ADD COLUMN mfg_date    DATE,
+    ADD COLUMN expiry_date DATE;
+
+-- what an operation used up of the holds on the stock it took, so reversing it holds the stock again
+CREATE TABLE IF NOT EXISTS reservation_uses
+(
+    correlation_id VARCHAR(36) NOT NULL,
+    reservation_id BIGINT      NOT NULL,
+    item_id        BIGINT      NOT NULL,
+    shelf_id       BIGINT,
+    room_id        BIGINT,
+    count          BIGINT      NOT NULL,
+    user_id        BIGINT      NOT NULL,
+    note           TEXT,
+    expires_at     DATETIME,
+    created_at     DATETIME    NOT NULL,
+    INDEX reservation_uses_correlation_idx (correlation_id)
+);
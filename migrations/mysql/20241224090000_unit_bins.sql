@@ -0,0 +1,21 @@
+-- Add migration script here
+-- units are kept in a bin of their shelf, like the stock counting them
+ALTER TABLE stock_units
+    ADD COLUMN bin_id BIGINT,
+    ADD FOREIGN KEY (bin_id) REFERENCES bins (bin_id);
+-- which bin a unit already on a shelf is in is not known, it goes where most of its lot is
+UPDATE stock_units
+SET bin_id = COALESCE((SELECT s.bin_id
+                       FROM stock s
+                       WHERE s.item_id = stock_units.item_id
+                         AND s.shelf_id = stock_units.shelf_id
+                         AND s.lot_no = stock_units.lot_no
+                         AND s.status = stock_units.status
+                       ORDER BY s.count DESC, s.bin_id
+                       LIMIT 1),
+                      (SELECT b.bin_id
+                       FROM bins b
+                       WHERE b.shelf_id = stock_units.shelf_id
+                       ORDER BY b.layer, b.position
+                       LIMIT 1))
+WHERE shelf_id IS NOT NULL;
@@ -0,0 +1,36 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS stock_movements
+(
+    movement_id    BIGSERIAL PRIMARY KEY,
+    correlation_id TEXT        NOT NULL,
+    kind           TEXT        NOT NULL,
+    item_id        BIGINT      NOT NULL,
+    shelf_from     BIGINT,
+    shelf_to       BIGINT,
+    count          BIGINT      NOT NULL,
+    user_id        BIGINT      NOT NULL,
+    reason         TEXT,
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT now(),
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (shelf_from) REFERENCES shelf (shelf_id),
+    FOREIGN KEY (shelf_to) REFERENCES shelf (shelf_id),
+    FOREIGN KEY (user_id) REFERENCES users (user_id)
+);
+
+CREATE INDEX IF NOT EXISTS stock_movements_correlation_idx ON stock_movements (correlation_id);
+CREATE INDEX IF NOT EXISTS stock_movements_item_idx ON stock_movements (item_id, created_at);
+CREATE INDEX IF NOT EXISTS stock_movements_created_idx ON stock_movements (created_at);
+
+CREATE OR REPLACE FUNCTION reject_stock_movement_change()
+    RETURNS TRIGGER AS
+$$
+BEGIN
+    RAISE EXCEPTION 'stock movements are immutable';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER stock_movements_immutable
+    BEFORE UPDATE OR DELETE
+    ON stock_movements
+    FOR EACH ROW
+EXECUTE PROCEDURE reject_stock_movement_change();
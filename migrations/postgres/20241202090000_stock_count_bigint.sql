@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE stock
+    ALTER COLUMN count TYPE BIGINT;
@@ -0,0 +1,34 @@
+-- Add migration script here
+ALTER TABLE items
+    ADD COLUMN serialized BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS stock_units
+(
+    unit_id    BIGSERIAL PRIMARY KEY,
+    item_id    BIGINT      NOT NULL,
+    serial     TEXT        NOT NULL,
+    shelf_id   BIGINT,
+    lot_no     TEXT        NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ,
+    UNIQUE (item_id, serial),
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (shelf_id) REFERENCES shelf (shelf_id)
+);
+
+CREATE INDEX IF NOT EXISTS stock_units_shelf_idx ON stock_units (shelf_id, item_id);
+
+CREATE TRIGGER stock_units_trig
+    BEFORE UPDATE
+    ON stock_units
+    FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE TABLE IF NOT EXISTS stock_unit_movements
+(
+    unit_id     BIGINT NOT NULL,
+    movement_id BIGINT NOT NULL,
+    PRIMARY KEY (unit_id, movement_id),
+    FOREIGN KEY (unit_id) REFERENCES stock_units (unit_id),
+    FOREIGN KEY (movement_id) REFERENCES stock_movements (movement_id)
+);
@@ -0,0 +1,22 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS stock_reservations
+(
+    reservation_id BIGSERIAL PRIMARY KEY,
+    item_id        BIGINT      NOT NULL,
+    shelf_id       BIGINT,
+    room_id        BIGINT,
+    count          BIGINT      NOT NULL,
+    user_id        BIGINT      NOT NULL,
+    note           TEXT,
+    expires_at     TIMESTAMPTZ,
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK ((shelf_id IS NULL) <> (room_id IS NULL)),
+    CHECK (count > 0),
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (shelf_id) REFERENCES shelf (shelf_id),
+    FOREIGN KEY (room_id) REFERENCES rooms (room_id),
+    FOREIGN KEY (user_id) REFERENCES users (user_id)
+);
+
+CREATE INDEX IF NOT EXISTS stock_reservations_item_idx ON stock_reservations (item_id, shelf_id, room_id);
+CREATE INDEX IF NOT EXISTS stock_reservations_expires_idx ON stock_reservations (expires_at);
@@ -0,0 +1,23 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS reorder_points
+(
+    reorder_point_id BIGSERIAL PRIMARY KEY,
+    item_id          BIGINT      NOT NULL,
+    room_id          BIGINT,
+    min_level        BIGINT      NOT NULL,
+    max_level        BIGINT      NOT NULL,
+    below_since      TIMESTAMPTZ,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (min_level >= 0 AND max_level >= min_level),
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (room_id) REFERENCES rooms (room_id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS reorder_points_item_room_key ON reorder_points (item_id, COALESCE(room_id, 0));
+
+CREATE TABLE IF NOT EXISTS stock_alert_subscriptions
+(
+    user_id    BIGINT      NOT NULL PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    FOREIGN KEY (user_id) REFERENCES users (user_id)
+);
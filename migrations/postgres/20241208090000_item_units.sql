@@ -0,0 +1,14 @@
+-- Add migration script here
+ALTER TABLE items
+    ADD COLUMN base_unit VARCHAR(32) NOT NULL DEFAULT 'pcs';
+
+CREATE TABLE IF NOT EXISTS item_units
+(
+    item_id    BIGINT      NOT NULL,
+    unit       VARCHAR(32) NOT NULL,
+    factor     BIGINT      NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (factor > 0),
+    PRIMARY KEY (item_id, unit),
+    FOREIGN KEY (item_id) REFERENCES items (item_id)
+);
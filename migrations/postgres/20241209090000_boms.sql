@@ -0,0 +1,26 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS boms
+(
+    bom_id         BIGSERIAL PRIMARY KEY,
+    output_item_id BIGINT      NOT NULL,
+    output_count   BIGINT      NOT NULL,
+    version        BIGINT      NOT NULL,
+    note           TEXT,
+    created_by     BIGINT      NOT NULL,
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (output_count > 0),
+    UNIQUE (output_item_id, version),
+    FOREIGN KEY (output_item_id) REFERENCES items (item_id),
+    FOREIGN KEY (created_by) REFERENCES users (user_id)
+);
+
+CREATE TABLE IF NOT EXISTS bom_components
+(
+    bom_id  BIGINT NOT NULL,
+    item_id BIGINT NOT NULL,
+    count   BIGINT NOT NULL,
+    CHECK (count > 0),
+    PRIMARY KEY (bom_id, item_id),
+    FOREIGN KEY (bom_id) REFERENCES boms (bom_id),
+    FOREIGN KEY (item_id) REFERENCES items (item_id)
+);
@@ -0,0 +1,39 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS categories
+(
+    category_id BIGSERIAL   PRIMARY KEY,
+    name        VARCHAR(64) NOT NULL,
+    description TEXT,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+ALTER TABLE items
+    ADD COLUMN costing VARCHAR(16) NOT NULL DEFAULT 'fifo';
+ALTER TABLE items
+    ADD COLUMN category_id BIGINT REFERENCES categories (category_id);
+
+CREATE TABLE IF NOT EXISTS cost_layers
+(
+    layer_id   BIGSERIAL   PRIMARY KEY,
+    item_id    BIGINT      NOT NULL,
+    currency   VARCHAR(3)  NOT NULL,
+    remaining  BIGINT      NOT NULL,
+    value      BIGINT      NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (remaining >= 0),
+    FOREIGN KEY (item_id) REFERENCES items (item_id)
+);
+
+CREATE TABLE IF NOT EXISTS cost_entries
+(
+    entry_id       BIGSERIAL   PRIMARY KEY,
+    correlation_id TEXT        NOT NULL,
+    item_id        BIGINT      NOT NULL,
+    currency       VARCHAR(3)  NOT NULL,
+    count          BIGINT      NOT NULL,
+    value          BIGINT      NOT NULL,
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT now(),
+    FOREIGN KEY (item_id) REFERENCES items (item_id)
+);
+
+CREATE INDEX IF NOT EXISTS cost_entries_item_idx ON cost_entries (item_id, created_at);
@@ -0,0 +1,14 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS stock_policies
+(
+    policy_id      BIGSERIAL   PRIMARY KEY,
+    room_id        BIGINT,
+    allow_empty    BOOLEAN     NOT NULL,
+    keep_empty     BOOLEAN     NOT NULL,
+    allow_negative BOOLEAN     NOT NULL,
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT now(),
+    FOREIGN KEY (room_id) REFERENCES rooms (room_id)
+);
+
+ALTER TABLE stock
+    ADD COLUMN flagged_at TIMESTAMPTZ;
@@ -0,0 +1,20 @@
+-- Add migration script here
+UPDATE stock
+SET count = d.total
+FROM (SELECT MIN(stock_id) stock_id, SUM(count) total
+      FROM stock
+      GROUP BY item_id, shelf_id, lot_no, status
+      HAVING COUNT(*) > 1) d
+WHERE d.stock_id = stock.stock_id;
+
+DELETE
+FROM stock
+    USING stock s
+WHERE s.item_id = stock.item_id
+  AND s.shelf_id = stock.shelf_id
+  AND s.lot_no = stock.lot_no
+  AND s.status = stock.status
+  AND s.stock_id < stock.stock_id;
+
+CREATE UNIQUE INDEX stock_item_shelf_lot_status_idx ON stock (item_id, shelf_id, lot_no, status);
+DROP INDEX stock_item_shelf_lot_idx;
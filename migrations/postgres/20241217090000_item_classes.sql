@@ -0,0 +1,7 @@
+-- Add migration script here
+ALTER TABLE items
+    ADD COLUMN abc_class VARCHAR(1);
+ALTER TABLE items
+    ADD COLUMN xyz_class VARCHAR(1);
+ALTER TABLE items
+    ADD COLUMN classified_at TIMESTAMPTZ;
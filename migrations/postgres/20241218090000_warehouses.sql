@@ -0,0 +1,33 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS warehouses
+(
+    warehouse_id BIGSERIAL PRIMARY KEY,
+    code         VARCHAR(16) NOT NULL UNIQUE,
+    name         TEXT        NOT NULL,
+    address      TEXT,
+    time_zone    VARCHAR(64) NOT NULL DEFAULT 'UTC',
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at   TIMESTAMPTZ
+);
+
+CREATE TRIGGER warehouses_trig
+    BEFORE UPDATE
+    ON warehouses
+    FOR EACH ROW
+EXECUTE PROCEDURE trigger_set_timestamp();
+
+-- the rooms there are so far all belong to the one site
+INSERT INTO warehouses (code, name)
+VALUES ('MAIN', 'Main warehouse');
+
+ALTER TABLE rooms
+    ADD COLUMN warehouse_id BIGINT REFERENCES warehouses (warehouse_id);
+UPDATE rooms
+SET warehouse_id = (SELECT MIN(warehouse_id) FROM warehouses);
+ALTER TABLE rooms
+    ALTER COLUMN warehouse_id SET NOT NULL;
+CREATE INDEX IF NOT EXISTS rooms_warehouse_idx ON rooms (warehouse_id);
+
+-- whether a transfer took stock from one site to another
+ALTER TABLE stock_movements
+    ADD COLUMN cross_site BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,32 @@
+-- Add migration script here
+ALTER TABLE shelf
+    ADD COLUMN layers BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE shelf
+    ADD COLUMN positions BIGINT NOT NULL DEFAULT 1;
+
+CREATE TABLE IF NOT EXISTS bins
+(
+    bin_id     BIGSERIAL PRIMARY KEY,
+    shelf_id   BIGINT      NOT NULL REFERENCES shelf (shelf_id) ON DELETE CASCADE,
+    layer      BIGINT      NOT NULL,
+    position   BIGINT      NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (shelf_id, layer, position)
+);
+
+-- every shelf so far is a single bin holding all of its stock
+INSERT INTO bins (shelf_id, layer, position)
+SELECT shelf_id, 1, 1
+FROM shelf;
+
+ALTER TABLE stock
+    ADD COLUMN bin_id BIGINT REFERENCES bins (bin_id);
+UPDATE stock
+SET bin_id = b.bin_id
+FROM bins b
+WHERE b.shelf_id = stock.shelf_id;
+ALTER TABLE stock
+    ALTER COLUMN bin_id SET NOT NULL;
+
+CREATE UNIQUE INDEX stock_item_bin_lot_status_idx ON stock (item_id, shelf_id, bin_id, lot_no, status);
+DROP INDEX stock_item_shelf_lot_status_idx;
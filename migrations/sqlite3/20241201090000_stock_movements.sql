@@ -0,0 +1,36 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS stock_movements
+(
+    movement_id    INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+    correlation_id TEXT     NOT NULL,
+    kind           TEXT     NOT NULL,
+    item_id        INTEGER  NOT NULL,
+    shelf_from     INTEGER,
+    shelf_to       INTEGER,
+    count          INTEGER  NOT NULL,
+    user_id        INTEGER  NOT NULL,
+    reason         TEXT,
+    created_at     DATETIME NOT NULL DEFAULT current_timestamp,
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (shelf_from) REFERENCES shelf (shelf_id),
+    FOREIGN KEY (shelf_to) REFERENCES shelf (shelf_id),
+    FOREIGN KEY (user_id) REFERENCES users (user_id)
+);
+
+CREATE INDEX IF NOT EXISTS stock_movements_correlation_idx ON stock_movements (correlation_id);
+CREATE INDEX IF NOT EXISTS stock_movements_item_idx ON stock_movements (item_id, created_at);
+CREATE INDEX IF NOT EXISTS stock_movements_created_idx ON stock_movements (created_at);
+
+CREATE TRIGGER stock_movements_no_update
+    BEFORE UPDATE
+    ON stock_movements
+BEGIN
+    SELECT RAISE(ABORT, 'stock movements are immutable');
+END;
+
+CREATE TRIGGER stock_movements_no_delete
+    BEFORE DELETE
+    ON stock_movements
+BEGIN
+    SELECT RAISE(ABORT, 'stock movements are immutable');
+END;
@@ -0,0 +1,35 @@
+-- Add migration script here
+ALTER TABLE items
+    ADD COLUMN serialized BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS stock_units
+(
+    unit_id    INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+    item_id    INTEGER  NOT NULL,
+    serial     TEXT     NOT NULL,
+    shelf_id   INTEGER,
+    lot_no     TEXT     NOT NULL DEFAULT '',
+    created_at DATETIME NOT NULL DEFAULT current_timestamp,
+    updated_at DATETIME,
+    UNIQUE (item_id, serial),
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (shelf_id) REFERENCES shelf (shelf_id)
+);
+
+CREATE INDEX stock_units_shelf_idx ON stock_units (shelf_id, item_id);
+
+CREATE TRIGGER stock_units_trig
+    AFTER UPDATE
+    ON stock_units
+BEGIN
+    UPDATE stock_units SET updated_at = datetime('now') WHERE unit_id = NEW.unit_id;
+END;
+
+CREATE TABLE IF NOT EXISTS stock_unit_movements
+(
+    unit_id     INTEGER NOT NULL,
+    movement_id INTEGER NOT NULL,
+    PRIMARY KEY (unit_id, movement_id),
+    FOREIGN KEY (unit_id) REFERENCES stock_units (unit_id),
+    FOREIGN KEY (movement_id) REFERENCES stock_movements (movement_id)
+);
@@ -0,0 +1,23 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS reorder_points
+(
+    reorder_point_id INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+    item_id          INTEGER  NOT NULL,
+    room_id          INTEGER,
+    min_level        INTEGER  NOT NULL,
+    max_level        INTEGER  NOT NULL,
+    below_since      DATETIME,
+    created_at       DATETIME NOT NULL DEFAULT current_timestamp,
+    CHECK (min_level >= 0 AND max_level >= min_level),
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (room_id) REFERENCES rooms (room_id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS reorder_points_item_room_key ON reorder_points (item_id, COALESCE(room_id, 0));
+
+CREATE TABLE IF NOT EXISTS stock_alert_subscriptions
+(
+    user_id    INTEGER  NOT NULL PRIMARY KEY,
+    created_at DATETIME NOT NULL DEFAULT current_timestamp,
+    FOREIGN KEY (user_id) REFERENCES users (user_id)
+);
@@ -0,0 +1,14 @@
+-- Add migration script here
+ALTER TABLE items
+    ADD COLUMN base_unit VARCHAR(32) NOT NULL DEFAULT 'pcs';
+
+CREATE TABLE IF NOT EXISTS item_units
+(
+    item_id    INTEGER     NOT NULL,
+    unit       VARCHAR(32) NOT NULL,
+    factor     INTEGER     NOT NULL,
+    created_at DATETIME    NOT NULL DEFAULT current_timestamp,
+    CHECK (factor > 0),
+    PRIMARY KEY (item_id, unit),
+    FOREIGN KEY (item_id) REFERENCES items (item_id)
+);
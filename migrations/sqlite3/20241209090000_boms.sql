@@ -0,0 +1,26 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS boms
+(
+    bom_id         INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+    output_item_id INTEGER  NOT NULL,
+    output_count   INTEGER  NOT NULL,
+    version        INTEGER  NOT NULL,
+    note           TEXT,
+    created_by     INTEGER  NOT NULL,
+    created_at     DATETIME NOT NULL DEFAULT current_timestamp,
+    CHECK (output_count > 0),
+    UNIQUE (output_item_id, version),
+    FOREIGN KEY (output_item_id) REFERENCES items (item_id),
+    FOREIGN KEY (created_by) REFERENCES users (user_id)
+);
+
+CREATE TABLE IF NOT EXISTS bom_components
+(
+    bom_id  INTEGER NOT NULL,
+    item_id INTEGER NOT NULL,
+    count   INTEGER NOT NULL,
+    CHECK (count > 0),
+    PRIMARY KEY (bom_id, item_id),
+    FOREIGN KEY (bom_id) REFERENCES boms (bom_id),
+    FOREIGN KEY (item_id) REFERENCES items (item_id)
+);
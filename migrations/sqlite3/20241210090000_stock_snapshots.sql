@@ -0,0 +1,19 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS stock_snapshots
+(
+    snapshot_id      INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+    last_movement_id INTEGER  NOT NULL,
+    taken_at         DATETIME NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX IF NOT EXISTS stock_snapshots_taken_idx ON stock_snapshots (taken_at);
+
+CREATE TABLE IF NOT EXISTS stock_snapshot_lines
+(
+    snapshot_id INTEGER NOT NULL,
+    item_id     INTEGER NOT NULL,
+    shelf_id    INTEGER NOT NULL,
+    count       INTEGER NOT NULL,
+    PRIMARY KEY (snapshot_id, item_id, shelf_id),
+    FOREIGN KEY (snapshot_id) REFERENCES stock_snapshots (snapshot_id)
+);
@@ -0,0 +1,39 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS categories
+(
+    category_id INTEGER     NOT NULL PRIMARY KEY AUTOINCREMENT,
+    name        VARCHAR(64) NOT NULL,
+    description TEXT,
+    created_at  DATETIME    NOT NULL DEFAULT current_timestamp
+);
+
+ALTER TABLE items
+    ADD COLUMN costing VARCHAR(16) NOT NULL DEFAULT 'fifo';
+ALTER TABLE items
+    ADD COLUMN category_id INTEGER REFERENCES categories (category_id);
+
+CREATE TABLE IF NOT EXISTS cost_layers
+(
+    layer_id   INTEGER    NOT NULL PRIMARY KEY AUTOINCREMENT,
+    item_id    INTEGER    NOT NULL,
+    currency   VARCHAR(3) NOT NULL,
+    remaining  INTEGER    NOT NULL,
+    value      INTEGER    NOT NULL,
+    created_at DATETIME   NOT NULL DEFAULT current_timestamp,
+    CHECK (remaining >= 0),
+    FOREIGN KEY (item_id) REFERENCES items (item_id)
+);
+
+CREATE TABLE IF NOT EXISTS cost_entries
+(
+    entry_id       INTEGER    NOT NULL PRIMARY KEY AUTOINCREMENT,
+    correlation_id TEXT       NOT NULL,
+    item_id        INTEGER    NOT NULL,
+    currency       VARCHAR(3) NOT NULL,
+    count          INTEGER    NOT NULL,
+    value          INTEGER    NOT NULL,
+    created_at     DATETIME   NOT NULL DEFAULT current_timestamp,
+    FOREIGN KEY (item_id) REFERENCES items (item_id)
+);
+
+CREATE INDEX IF NOT EXISTS cost_entries_item_idx ON cost_entries (item_id, created_at);
@@ -0,0 +1,9 @@
+-- Add migration script here
+ALTER TABLE stock
+    ADD COLUMN status TEXT NOT NULL DEFAULT 'available';
+
+ALTER TABLE stock_units
+    ADD COLUMN status TEXT NOT NULL DEFAULT 'available';
+
+ALTER TABLE stock_movements
+    ADD COLUMN status TEXT NOT NULL DEFAULT 'available';
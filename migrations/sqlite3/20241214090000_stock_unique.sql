@@ -0,0 +1,16 @@
+-- Add migration script here
+UPDATE stock
+SET count = (SELECT SUM(s.count)
+             FROM stock s
+             WHERE s.item_id = stock.item_id
+               AND s.shelf_id = stock.shelf_id
+               AND s.lot_no = stock.lot_no
+               AND s.status = stock.status)
+WHERE stock_id IN (SELECT MIN(stock_id) FROM stock GROUP BY item_id, shelf_id, lot_no, status HAVING COUNT(*) > 1);
+
+DELETE
+FROM stock
+WHERE stock_id NOT IN (SELECT MIN(stock_id) FROM stock GROUP BY item_id, shelf_id, lot_no, status);
+
+CREATE UNIQUE INDEX stock_item_shelf_lot_status_idx ON stock (item_id, shelf_id, lot_no, status);
+DROP INDEX stock_item_shelf_lot_idx;
@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE stock_movements
+    ADD COLUMN reversal_of INTEGER REFERENCES stock_movements (movement_id);
+
+CREATE UNIQUE INDEX stock_movements_reversal_idx ON stock_movements (reversal_of);
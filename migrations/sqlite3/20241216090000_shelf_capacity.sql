@@ -0,0 +1,19 @@
+-- Add migration script here
+ALTER TABLE items
+    ADD COLUMN weight_g INTEGER;
+ALTER TABLE items
+    ADD COLUMN length_mm INTEGER;
+ALTER TABLE items
+    ADD COLUMN width_mm INTEGER;
+ALTER TABLE items
+    ADD COLUMN height_mm INTEGER;
+
+ALTER TABLE shelf
+    ADD COLUMN max_units INTEGER;
+ALTER TABLE shelf
+    ADD COLUMN max_weight_g INTEGER;
+ALTER TABLE shelf
+    ADD COLUMN max_volume_mm3 INTEGER;
+
+ALTER TABLE stock_policies
+    ADD COLUMN warn_over_capacity BOOLEAN NOT NULL DEFAULT FALSE;
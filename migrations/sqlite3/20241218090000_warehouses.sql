@@ -0,0 +1,49 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS warehouses
+(
+    warehouse_id INTEGER     NOT NULL PRIMARY KEY AUTOINCREMENT,
+    code         VARCHAR(16) NOT NULL UNIQUE,
+    name         TEXT        NOT NULL,
+    address      TEXT,
+    time_zone    VARCHAR(64) NOT NULL DEFAULT 'UTC',
+    created_at   DATETIME    NOT NULL DEFAULT current_timestamp,
+    updated_at   DATETIME
+);
+
+CREATE TRIGGER warehouses_trig
+    AFTER UPDATE
+    ON warehouses
+BEGIN
+    UPDATE warehouses SET updated_at = datetime('now') WHERE warehouse_id = NEW.warehouse_id;
+END;
+
+-- the rooms there are so far all belong to the one site
+INSERT INTO warehouses (code, name)
+VALUES ('MAIN', 'Main warehouse');
+
+-- a column added with a foreign key has to default to NULL, the triggers keep it set
+ALTER TABLE rooms
+    ADD COLUMN warehouse_id INTEGER REFERENCES warehouses (warehouse_id);
+UPDATE rooms
+SET warehouse_id = (SELECT MIN(warehouse_id) FROM warehouses);
+CREATE INDEX IF NOT EXISTS rooms_warehouse_idx ON rooms (warehouse_id);
+
+CREATE TRIGGER rooms_warehouse_insert
+    BEFORE INSERT
+    ON rooms
+    WHEN NEW.warehouse_id IS NULL
+BEGIN
+    SELECT RAISE(ABORT, 'NOT NULL constraint failed: rooms.warehouse_id');
+END;
+
+CREATE TRIGGER rooms_warehouse_update
+    BEFORE UPDATE OF warehouse_id
+    ON rooms
+    WHEN NEW.warehouse_id IS NULL
+BEGIN
+    SELECT RAISE(ABORT, 'NOT NULL constraint failed: rooms.warehouse_id');
+END;
+
+-- whether a transfer took stock from one site to another
+ALTER TABLE stock_movements
+    ADD COLUMN cross_site BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,30 @@
+-- Add migration script here
+ALTER TABLE shelf
+    ADD COLUMN layers INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE shelf
+    ADD COLUMN positions INTEGER NOT NULL DEFAULT 1;
+
+CREATE TABLE IF NOT EXISTS bins
+(
+    bin_id     INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+    shelf_id   INTEGER  NOT NULL,
+    layer      INTEGER  NOT NULL,
+    position   INTEGER  NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT current_timestamp,
+    FOREIGN KEY (shelf_id) REFERENCES shelf (shelf_id) ON DELETE CASCADE,
+    UNIQUE (shelf_id, layer, position)
+);
+
+-- every shelf so far is a single bin holding all of its stock
+INSERT INTO bins (shelf_id, layer, position)
+SELECT shelf_id, 1, 1
+FROM shelf;
+
+-- a column added with a foreign key has to default to NULL, the stock is always put into a bin
+ALTER TABLE stock
+    ADD COLUMN bin_id INTEGER REFERENCES bins (bin_id);
+UPDATE stock
+SET bin_id = (SELECT b.bin_id FROM bins b WHERE b.shelf_id = stock.shelf_id);
+
+CREATE UNIQUE INDEX stock_item_bin_lot_status_idx ON stock (item_id, shelf_id, bin_id, lot_no, status);
+DROP INDEX stock_item_shelf_lot_status_idx;
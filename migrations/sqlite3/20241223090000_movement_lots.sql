@@ -0,0 +1,23 @@
+-- Add migration script here
+-- the dates of the lot moved, so a reversal puts it back as it was
+ALTER TABLE stock_movements
+    ADD COLUMN mfg_date DATE;
+ALTER TABLE stock_movements
+    ADD COLUMN expiry_date DATE;
+
+-- what an operation used up of the holds on the stock it took, so reversing it holds the stock again
+CREATE TABLE IF NOT EXISTS reservation_uses
+(
+    correlation_id TEXT     NOT NULL,
+    reservation_id INTEGER  NOT NULL,
+    item_id        INTEGER  NOT NULL,
+    shelf_id       INTEGER,
+    room_id        INTEGER,
+    count          INTEGER  NOT NULL,
+    user_id        INTEGER  NOT NULL,
+    note           TEXT,
+    expires_at     DATETIME,
+    created_at     DATETIME NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS reservation_uses_correlation_idx ON reservation_uses (correlation_id);
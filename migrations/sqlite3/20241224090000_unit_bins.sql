@@ -0,0 +1,92 @@
+-- Add migration script here
+-- units are kept in a bin of their shelf, like the stock counting them
+ALTER TABLE stock_units
+    ADD COLUMN bin_id INTEGER REFERENCES bins (bin_id);
+-- which bin a unit already on a shelf is in is not known, it goes where most of its lot is
+UPDATE stock_units
+SET bin_id = COALESCE((SELECT s.bin_id
+                       FROM stock s
+                       WHERE s.item_id = stock_units.item_id
+                         AND s.shelf_id = stock_units.shelf_id
+                         AND s.lot_no = stock_units.lot_no
+                         AND s.status = stock_units.status
+                       ORDER BY s.count DESC, s.bin_id
+                       LIMIT 1),
+                      (SELECT b.bin_id
+                       FROM bins b
+                       WHERE b.shelf_id = stock_units.shelf_id
+                       ORDER BY b.layer, b.position
+                       LIMIT 1))
+WHERE shelf_id IS NOT NULL;
+
+-- stock.bin_id was added nullable, and NULLs never collide in the unique index, so the table is
+-- rebuilt with the column NOT NULL. Stock left without a bin goes into the first bin of its shelf,
+-- merged with the stock already there.
+DROP INDEX stock_item_bin_lot_status_idx;
+
+UPDATE stock
+SET bin_id = (SELECT b.bin_id FROM bins b WHERE b.shelf_id = stock.shelf_id ORDER BY b.layer, b.position LIMIT 1)
+WHERE bin_id IS NULL;
+
+UPDATE stock
+SET count = (SELECT SUM(s.count)
+             FROM stock s
+             WHERE s.item_id = stock.item_id
+               AND s.shelf_id = stock.shelf_id
+               AND s.bin_id = stock.bin_id
+               AND s.lot_no = stock.lot_no
+               AND s.status = stock.status)
+WHERE stock_id IN (SELECT MIN(stock_id) FROM stock GROUP BY item_id, shelf_id, bin_id, lot_no, status HAVING COUNT(*) > 1);
+
+DELETE
+FROM stock
+WHERE stock_id NOT IN (SELECT MIN(stock_id) FROM stock GROUP BY item_id, shelf_id, bin_id, lot_no, status);
+
+CREATE TABLE stock_rebuilt
+(
+    stock_id    INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+    item_id     INTEGER  NOT NULL,
+    shelf_id    INTEGER  NOT NULL,
+    bin_id      INTEGER  NOT NULL,
+    count       INTEGER  NOT NULL,
+    lot_no      TEXT     NOT NULL DEFAULT '',
+    mfg_date    DATE,
+    expiry_date DATE,
+    status      TEXT     NOT NULL DEFAULT 'available',
+    flagged_at  DATETIME,
+    created_at  DATETIME NOT NULL DEFAULT current_timestamp,
+    updated_at  DATETIME,
+    FOREIGN KEY (item_id) REFERENCES items (item_id),
+    FOREIGN KEY (shelf_id) REFERENCES shelf (shelf_id),
+    FOREIGN KEY (bin_id) REFERENCES bins (bin_id)
+);
+
+INSERT INTO stock_rebuilt (stock_id, item_id, shelf_id, bin_id, count, lot_no, mfg_date, expiry_date, status, flagged_at,
+                           created_at, updated_at)
+SELECT stock_id,
+       item_id,
+       shelf_id,
+       bin_id,
+       count,
+       lot_no,
+       mfg_date,
+       expiry_date,
+       status,
+       flagged_at,
+       created_at,
+       updated_at
+FROM stock;
+
+DROP TABLE stock;
+ALTER TABLE stock_rebuilt
+    RENAME TO stock;
+
+CREATE INDEX stock_expiry_date_idx ON stock (expiry_date);
+CREATE UNIQUE INDEX stock_item_bin_lot_status_idx ON stock (item_id, shelf_id, bin_id, lot_no, status);
+
+CREATE TRIGGER stock_trig
+    AFTER UPDATE
+    ON stock
+BEGIN
+    UPDATE stock SET updated_at = datetime('now') WHERE stock_id = NEW.stock_id;
+END;
@@ -1,18 +1,26 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
 
+use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 
 use crate::bootstrap::logging;
 use crate::common::AppData;
 use crate::config::Configuration;
+use crate::console::cronjobs::{item_classifier, reorder_checker, reservation_releaser, stock_snapshotter};
 use crate::databases::database;
 use crate::services::authentication::{DbUserAuthenticationRepository, JsonWebToken, Service};
+use crate::services::bom::{self, DbBomRepository};
+use crate::services::category::{self, DbCategoryRepository};
 use crate::services::item::{self, DbItemRepository};
+use crate::services::label;
+use crate::services::reorder::{self, DbReorderRepository};
 use crate::services::room::{self, DbRoomRepository};
 use crate::services::shelf::{self, DbShelfRepository};
 use crate::services::stock::{self, DbStockRepository};
+use crate::services::stocktake::{self, DbStocktakeRepository};
 use crate::services::user::{self, DbBannedUserList, DbUserProfileRepository, DbUserRepository};
+use crate::services::warehouse::{self, DbWarehouseRepository};
 use crate::web::api::v1::auth::Authentication;
 use crate::web::api::Version;
 use crate::{mailer, web};
@@ -20,6 +28,10 @@ use crate::{mailer, web};
 pub struct Running {
     pub api_socket_addr: SocketAddr,
     pub api_server: Option<JoinHandle<Result<(), std::io::Error>>>,
+    pub reservation_releaser: Option<JoinHandle<()>>,
+    pub reorder_checker: JoinHandle<()>,
+    pub stock_snapshotter: Option<JoinHandle<()>>,
+    pub item_classifier: Option<JoinHandle<()>>,
 }
 
 #[allow(clippy::too_many_lines)]
@@ -39,6 +51,14 @@ pub async fn run(configuration: Configuration, api_version: &Version) -> Running
     // From [net] config
     let net_ip = settings.net.v4.clone().unwrap_or("localhost".to_string());
     let net_port = settings.net.v4port;
+    // From [stock] config
+    let reservation_release_interval = settings.stock.reservation_release_interval;
+    let reorder_check_interval = settings.stock.reorder_check_interval;
+    let snapshot_interval = settings.stock.snapshot_interval;
+    let putaway_layer = settings.stock.putaway_layer;
+    let classification_interval = settings.stock.classification_interval;
+    let classification_window = settings.stock.classification_window;
+    let classification_periods = settings.stock.classification_periods;
     // IMPORTANT: drop settings before starting server to avoid read locks that
     // leads to requests hanging.
     drop(settings);
@@ -56,6 +76,11 @@ pub async fn run(configuration: Configuration, api_version: &Version) -> Running
     let shelf_repository = Arc::new(DbShelfRepository::new(database.clone()));
     let item_repository = Arc::new(DbItemRepository::new(database.clone()));
     let stock_repository = Arc::new(DbStockRepository::new(database.clone()));
+    let reorder_repository = Arc::new(DbReorderRepository::new(database.clone()));
+    let stocktake_repository = Arc::new(DbStocktakeRepository::new(database.clone()));
+    let bom_repository = Arc::new(DbBomRepository::new(database.clone()));
+    let category_repository = Arc::new(DbCategoryRepository::new(database.clone()));
+    let warehouse_repository = Arc::new(DbWarehouseRepository::new(database.clone()));
     // Services
     let mailer_service = Arc::new(mailer::Service::new(configuration.clone()).await);
     let registration_service = Arc::new(user::RegistrationService::new(
@@ -69,10 +94,32 @@ pub async fn run(configuration: Configuration, api_version: &Version) -> Running
         user_profile_repository.clone(),
         banned_user_list.clone(),
     ));
-    let room_service = Arc::new(room::Service::new(room_repository.clone()));
+    let room_service = Arc::new(room::Service::new(room_repository.clone(), user_repository.clone()));
     let shelf_service = Arc::new(shelf::Service::new(shelf_repository.clone()));
     let item_service = Arc::new(item::Service::new(item_repository.clone()));
-    let stock_service = Arc::new(stock::Service::new(stock_repository.clone()));
+    let category_service = Arc::new(category::Service::new(category_repository.clone()));
+    let warehouse_service = Arc::new(warehouse::Service::new(warehouse_repository.clone(), user_repository.clone()));
+    let label_service = Arc::new(label::Service::new(room_repository.clone(), shelf_repository.clone()));
+    let (stock_changes_sender, stock_changes_receiver) = mpsc::unbounded_channel();
+    let stock_service = Arc::new(stock::Service::new(
+        stock_repository.clone(),
+        user_repository.clone(),
+        stock_changes_sender.clone(),
+        putaway_layer,
+        classification_window,
+        classification_periods,
+    ));
+    let stocktake_service = Arc::new(stocktake::Service::new(
+        stocktake_repository.clone(),
+        user_repository.clone(),
+        stock_changes_sender.clone(),
+    ));
+    let bom_service = Arc::new(bom::Service::new(
+        bom_repository.clone(),
+        stock_repository.clone(),
+        stock_changes_sender,
+    ));
+    let reorder_service = Arc::new(reorder::Service::new(reorder_repository.clone(), mailer_service.clone()));
     let authentication_service = Arc::new(Service::new(
         configuration.clone(),
         json_web_token.clone(),
@@ -80,6 +127,14 @@ pub async fn run(configuration: Configuration, api_version: &Version) -> Running
         user_profile_repository.clone(),
         user_authentication_repository.clone(),
     ));
+    // Start cronjob to release expired stock reservations
+    let reservation_releaser = reservation_releaser::start(reservation_release_interval, &stock_service);
+    // Start cronjob to check the stock against the reorder points
+    let reorder_checker = reorder_checker::start(reorder_check_interval, &reorder_service, stock_changes_receiver);
+    // Start cronjob to snapshot the stock for past stock listings
+    let stock_snapshotter = stock_snapshotter::start(snapshot_interval, &stock_service);
+    // Start cronjob to classify the items by consumption
+    let item_classifier = item_classifier::start(classification_interval, &stock_service);
     // Build app container
     let app_data = Arc::new(AppData::new(
         configuration.clone(),
@@ -98,11 +153,21 @@ pub async fn run(configuration: Configuration, api_version: &Version) -> Running
         shelf_service,
         item_service,
         stock_service,
+        reorder_service,
+        stocktake_service,
+        bom_service,
+        category_service,
+        warehouse_service,
+        label_service,
     ));
     // Start API server
     let running_api = web::api::start(app_data, &net_ip, net_port, api_version).await;
     Running {
         api_socket_addr: running_api.socket_addr,
         api_server: running_api.api_server,
+        reservation_releaser,
+        reorder_checker,
+        stock_snapshotter,
+        item_classifier,
     }
 }
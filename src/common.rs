@@ -1,18 +1,33 @@
 use std::sync::Arc;
 
 use serde_derive::{Deserialize, Serialize};
+use time::serde::iso8601;
+use time::OffsetDateTime;
 
 use crate::config::Configuration;
 use crate::databases::database::{Database, Sorting};
 use crate::mailer;
+use crate::models::bin::BinId;
+use crate::models::classification::{AbcClass, XyzClass};
+use crate::models::item::ItemId;
+use crate::models::label::LabelFormat;
 use crate::models::room::RoomId;
 use crate::models::shelf::ShelfId;
+use crate::models::user::UserId;
+use crate::models::valuation::ValuationGroup;
+use crate::models::warehouse::WarehouseId;
 use crate::services::authentication::{DbUserAuthenticationRepository, JsonWebToken, Service};
+use crate::services::bom;
+use crate::services::category;
 use crate::services::item;
+use crate::services::label;
+use crate::services::reorder;
 use crate::services::room;
 use crate::services::shelf;
 use crate::services::stock;
+use crate::services::stocktake;
 use crate::services::user::{self, DbBannedUserList, DbUserProfileRepository, DbUserRepository};
+use crate::services::warehouse;
 use crate::web::api::v1::auth::Authentication;
 
 pub struct AppData {
@@ -34,6 +49,12 @@ pub struct AppData {
     pub shelf_service: Arc<shelf::Service>,
     pub item_service: Arc<item::Service>,
     pub stock_service: Arc<stock::Service>,
+    pub reorder_service: Arc<reorder::Service>,
+    pub stocktake_service: Arc<stocktake::Service>,
+    pub bom_service: Arc<bom::Service>,
+    pub category_service: Arc<category::Service>,
+    pub warehouse_service: Arc<warehouse::Service>,
+    pub label_service: Arc<label::Service>,
 }
 
 impl AppData {
@@ -57,6 +78,12 @@ impl AppData {
         shelf_service: Arc<shelf::Service>,
         item_service: Arc<item::Service>,
         stock_service: Arc<stock::Service>,
+        reorder_service: Arc<reorder::Service>,
+        stocktake_service: Arc<stocktake::Service>,
+        bom_service: Arc<bom::Service>,
+        category_service: Arc<category::Service>,
+        warehouse_service: Arc<warehouse::Service>,
+        label_service: Arc<label::Service>,
     ) -> Self {
         AppData {
             cfg,
@@ -77,6 +104,12 @@ impl AppData {
             shelf_service,
             item_service,
             stock_service,
+            reorder_service,
+            stocktake_service,
+            bom_service,
+            category_service,
+            warehouse_service,
+            label_service,
         }
     }
 }
@@ -94,18 +127,36 @@ pub struct PagedConf {
     pub all: Option<bool>,
 }
 
+/// User request to delete many at once.
+#[derive(Debug, Deserialize)]
+pub struct BatchDelCriteria {
+    /// Report what would be deleted and what would fail, without deleting anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
 #[derive(Debug, Serialize)]
 pub struct FailureReason {
     name: String,
     reason: String,
 }
 
-#[derive(Debug, Serialize)]
+/// How a batch delete went: how many ids succeeded, and why each of the others failed.
+#[derive(Debug, Default, Serialize)]
 pub struct BatchDelResult {
     pub s: u64,
     pub f: Option<Vec<FailureReason>>,
 }
 
+impl BatchDelResult {
+    pub fn fail(&mut self, id: i64, reason: &str) {
+        self.f.get_or_insert_with(Vec::new).push(FailureReason {
+            name: id.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
 #[derive(Clone, Copy, Debug, Deserialize)]
 pub enum Cond {
     Item,
@@ -123,11 +174,134 @@ pub struct ExtraRoomId {
     pub room_id: Option<RoomId>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ExtraWarehouseId {
+    pub warehouse_id: Option<WarehouseId>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ExtraShelfId {
     pub shelf_id: Option<ShelfId>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ExtraItemId {
+    pub item_id: Option<ItemId>,
+}
+
+/// User request to list the archived rooms or shelves instead of the others.
+#[derive(Debug, Deserialize)]
+pub struct ExtraArchived {
+    pub archived: Option<bool>,
+}
+
+/// User request to archive a room or shelf, with where to move the stock left on it.
+#[derive(Debug, Deserialize)]
+pub struct ArchiveCriteria {
+    pub shelf_to: Option<ShelfId>,
+    /// Bin of `shelf_to`, its first bin when omitted.
+    pub bin_to: Option<BinId>,
+    pub reason: Option<String>,
+}
+
+/// User request to list the stock as it stood at a past time.
+#[derive(Debug, Deserialize)]
+pub struct ExtraAsOf {
+    #[serde(default, with = "iso8601::option")]
+    pub as_of: Option<OffsetDateTime>,
+}
+
+/// User request to also render stock quantities in another unit.
+#[derive(Debug, Deserialize)]
+pub struct ExtraUnit {
+    pub unit: Option<String>,
+}
+
+/// User request to value the stock.
+#[derive(Debug, Deserialize)]
+pub struct ValuationCriteria {
+    #[serde(default)]
+    pub by: ValuationGroup,
+    /// Only value the stock in this warehouse.
+    pub warehouse_id: Option<WarehouseId>,
+    /// Value the stock as it stood then, now when omitted.
+    #[serde(default, with = "iso8601::option")]
+    pub as_of: Option<OffsetDateTime>,
+}
+
+/// User request to filter the stock movement history.
+#[derive(Debug, Default, Deserialize)]
+pub struct MovementCriteria {
+    pub item_id: Option<ItemId>,
+    /// Matches movements leaving or entering the shelf.
+    pub shelf_id: Option<ShelfId>,
+    /// Matches movements leaving or entering any shelf of the room.
+    pub room_id: Option<RoomId>,
+    /// Matches movements leaving or entering any shelf of the warehouse.
+    pub warehouse_id: Option<WarehouseId>,
+    /// Matches the transfers between warehouses only, or all movements but them.
+    pub cross_site: Option<bool>,
+    pub user_id: Option<UserId>,
+    #[serde(default, with = "iso8601::option")]
+    pub since: Option<OffsetDateTime>,
+    #[serde(default, with = "iso8601::option")]
+    pub until: Option<OffsetDateTime>,
+}
+
+/// User request to only list the items of some ABC and XYZ classes.
+#[derive(Debug, Default, Deserialize)]
+pub struct ItemClassFilter {
+    pub abc_class: Option<AbcClass>,
+    pub xyz_class: Option<XyzClass>,
+}
+
+/// User request to list the items nothing was withdrawn of for a while.
+#[derive(Debug, Deserialize)]
+pub struct DeadStockCriteria {
+    /// Items without withdrawals for at least this many days.
+    pub days: u32,
+    pub room_id: Option<RoomId>,
+    pub warehouse_id: Option<WarehouseId>,
+}
+
+/// User request for shelves to put incoming stock away on.
+#[derive(Debug, Deserialize)]
+pub struct PutawayCriteria {
+    pub item_id: ItemId,
+    pub count: i64,
+    /// Unit `count` is in, the item's base unit when omitted.
+    pub unit: Option<String>,
+    /// Only suggest shelves of this room.
+    pub room_id: Option<RoomId>,
+}
+
+/// User request to list the lots expiring soon.
+#[derive(Debug, Deserialize)]
+pub struct ExpiryCriteria {
+    /// Lots expiring within this many days from today, already expired ones included.
+    pub days: u32,
+    pub room_id: Option<RoomId>,
+    pub warehouse_id: Option<WarehouseId>,
+}
+
+/// User request to print labels.
+#[derive(Debug, Default, Deserialize)]
+pub struct LabelCriteria {
+    #[serde(default)]
+    pub format: LabelFormat,
+    /// Also print a label for every bin of the shelves.
+    #[serde(default)]
+    pub bins: bool,
+}
+
+/// User request to filter the stock reservations.
+#[derive(Debug, Default, Deserialize)]
+pub struct ReservationCriteria {
+    pub item_id: Option<ItemId>,
+    /// The owner of the holds.
+    pub user_id: Option<UserId>,
+}
+
 /// Internal specification for a listings.
 #[derive(Debug, Deserialize)]
 pub struct ListingSpec {
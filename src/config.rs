@@ -255,6 +255,42 @@ impl Default for Api {
     }
 }
 
+/// Core configuration for stock keeping
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stock {
+    /// How often, in seconds, expired reservations are released. 0 disables it.
+    pub reservation_release_interval: u64,
+    /// How often, in seconds, every reorder point is checked, on top of the checks following
+    /// each stock change. 0 leaves only the latter.
+    pub reorder_check_interval: u64,
+    /// How often, in seconds, the stock is snapshotted to rebuild past stock from. 0 disables it.
+    pub snapshot_interval: u64,
+    /// The shelf layer at the handiest height, where put-away suggestions look first.
+    pub putaway_layer: i64,
+    /// How often, in seconds, items are classified by consumption value and demand variability.
+    /// 0 disables it.
+    pub classification_interval: u64,
+    /// How many days back withdrawals count towards the classification of items.
+    pub classification_window: u32,
+    /// How many periods the window is cut into to tell how steady the demand is.
+    pub classification_periods: u32,
+}
+
+impl Default for Stock {
+    fn default() -> Self {
+        Self {
+            reservation_release_interval: 60,
+            reorder_check_interval: 3600,
+            snapshot_interval: 86400,
+            putaway_layer: 2,
+            classification_interval: 86400,
+            classification_window: 365,
+            classification_periods: 12,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct WarehouseIndex {
     /// Logging level. Possible values are: `Off`, `Error`, `Warn`, `Info`,
@@ -274,6 +310,9 @@ pub struct WarehouseIndex {
     pub image_cache: ImageCache,
     /// The API configuration.
     pub api: Api,
+    /// The stock keeping configuration.
+    #[serde(default)]
+    pub stock: Stock,
 }
 
 /// The configuration service.
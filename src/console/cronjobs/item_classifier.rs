@@ -0,0 +1,37 @@
+//! Cronjob classifying the items by consumption value and demand variability.
+//!
+//! Classes only move as withdrawals pile up over the classification window, so
+//! running it about daily is plenty. It also runs at start up, for items not to
+//! wait a whole interval for their first class.
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{error, info};
+use tokio::task::JoinHandle;
+
+use crate::services::stock;
+
+/// Start the job, running every `interval` seconds for as long as the stock service lives. An
+/// `interval` of 0 disables the job, leaving the recompute endpoint.
+#[must_use]
+pub fn start(interval: u64, stock_service: &Arc<stock::Service>) -> Option<JoinHandle<()>> {
+    if interval == 0 {
+        info!("Classifying the items on a schedule is disabled");
+        return None;
+    }
+    let weak_stock_service = Arc::downgrade(stock_service);
+
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval));
+        loop {
+            interval.tick().await;
+            let Some(stock_service) = weak_stock_service.upgrade() else {
+                break;
+            };
+            match stock_service.classify_items().await {
+                Ok(classes) => info!("Classified {} items", classes.len()),
+                Err(e) => error!("Failed to classify the items: {e}"),
+            }
+        }
+    }))
+}
@@ -0,0 +1,5 @@
+//! Jobs run in the background while the API server is up.
+pub mod item_classifier;
+pub mod reorder_checker;
+pub mod reservation_releaser;
+pub mod stock_snapshotter;
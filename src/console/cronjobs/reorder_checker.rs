@@ -0,0 +1,48 @@
+//! Cronjob checking the stock against the reorder points.
+//!
+//! The reorder points of an item are checked as soon as its stock changes, and
+//! every reorder point is checked on a schedule to catch what changed without
+//! going through the stock service.
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::error;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::task::JoinHandle;
+
+use crate::models::item::ItemId;
+use crate::services::reorder;
+
+/// Start the job, checking the items received from `stock_changes` and every item each `interval`
+/// seconds, for as long as the reorder service lives. An `interval` of 0 disables the scheduled
+/// checks, leaving those following stock changes.
+#[must_use]
+pub fn start(
+    interval: u64,
+    reorder_service: &Arc<reorder::Service>,
+    mut stock_changes: UnboundedReceiver<ItemId>,
+) -> JoinHandle<()> {
+    let weak_reorder_service = Arc::downgrade(reorder_service);
+
+    tokio::spawn(async move {
+        let mut interval = (interval > 0).then(|| tokio::time::interval(Duration::from_secs(interval)));
+        loop {
+            let tick = async {
+                match interval.as_mut() {
+                    Some(interval) => interval.tick().await,
+                    None => std::future::pending().await,
+                }
+            };
+            let item_id = tokio::select! {
+                _ = tick => None,
+                Some(item_id) = stock_changes.recv() => Some(item_id),
+            };
+            let Some(reorder_service) = weak_reorder_service.upgrade() else {
+                break;
+            };
+            if let Err(e) = reorder_service.check(item_id).await {
+                error!("Failed to check the reorder points: {e}");
+            }
+        }
+    })
+}
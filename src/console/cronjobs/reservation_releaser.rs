@@ -0,0 +1,38 @@
+//! Cronjob releasing the stock reservations past their expiry.
+//!
+//! Expired holds already stop counting against the available stock the moment
+//! they expire, this job removes them so they stop showing up anywhere and
+//! hands their items to the reorder checker.
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{error, info};
+use tokio::task::JoinHandle;
+
+use crate::services::stock;
+
+/// Start the job, running every `interval` seconds for as long as the stock service lives. An
+/// `interval` of 0 disables the job.
+#[must_use]
+pub fn start(interval: u64, stock_service: &Arc<stock::Service>) -> Option<JoinHandle<()>> {
+    if interval == 0 {
+        info!("Releasing expired stock reservations is disabled");
+        return None;
+    }
+    let weak_stock_service = Arc::downgrade(stock_service);
+
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval));
+        loop {
+            interval.tick().await;
+            let Some(stock_service) = weak_stock_service.upgrade() else {
+                break;
+            };
+            match stock_service.release_expired_reservations().await {
+                Ok(0) => {}
+                Ok(released) => info!("Released {released} expired stock reservations"),
+                Err(e) => error!("Failed to release expired stock reservations: {e}"),
+            }
+        }
+    }))
+}
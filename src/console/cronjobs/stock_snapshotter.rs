@@ -0,0 +1,38 @@
+//! Cronjob taking snapshots of the stock.
+//!
+//! Past stock is rebuilt from the latest snapshot before the time asked for,
+//! so the more often snapshots are taken, the fewer movements are replayed.
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{error, info};
+use tokio::task::JoinHandle;
+
+use crate::services::stock;
+
+/// Start the job, running every `interval` seconds for as long as the stock service lives. An
+/// `interval` of 0 disables the job.
+#[must_use]
+pub fn start(interval: u64, stock_service: &Arc<stock::Service>) -> Option<JoinHandle<()>> {
+    if interval == 0 {
+        info!("Taking stock snapshots is disabled");
+        return None;
+    }
+    let weak_stock_service = Arc::downgrade(stock_service);
+
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval));
+        // the first tick completes immediately, there is nothing new to record at start up
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let Some(stock_service) = weak_stock_service.upgrade() else {
+                break;
+            };
+            match stock_service.take_snapshot().await {
+                Ok(snapshot_id) => info!("Took stock snapshot {snapshot_id}"),
+                Err(e) => error!("Failed to take a stock snapshot: {e}"),
+            }
+        }
+    }))
+}
@@ -1,15 +1,36 @@
+use std::collections::{BTreeMap, HashSet};
+
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime};
 
-use crate::common::BatchDelResult;
+use crate::common::{BatchDelResult, MovementCriteria, ReservationCriteria};
 use crate::databases::mysql::Mysql;
 use crate::databases::postgres::Postgres;
 use crate::databases::sqlite::Sqlite;
-use crate::models::item::{Item, ItemId, ItemInRoom, ItemOnShelf, ItemXShelf};
+use crate::models::aging::{AgingLine, DeadStock};
+use crate::models::batch::{BatchLine, BatchMode};
+use crate::models::bin::{Bin, BinId, ItemInBin};
+use crate::models::bom::{Bom, BomId, NewBom};
+use crate::models::category::{Category, CategoryId};
+use crate::models::classification::{AbcClass, Consumption, ItemClass, XyzClass};
+use crate::models::item::{
+    Item, ItemDimensions, ItemId, ItemInRoom, ItemInWarehouse, ItemOnShelf, ItemUnit, ItemXShelf, Lot, LotInRoom, StockPick,
+};
+use crate::models::movement::{MovementMeta, SnapshotId, StockMovement};
+use crate::models::policy::{FlaggedStock, StockPolicy};
+use crate::models::putaway::PutawaySuggestion;
+use crate::models::reorder::{ReorderLevel, ReorderPoint, ReorderPointId};
+use crate::models::reservation::{NewReservation, Reservation, ReservationId};
 use crate::models::room::{Room, RoomId};
-use crate::models::shelf::{Shelf, ShelfId};
+use crate::models::shelf::{Shelf, ShelfCapacity, ShelfId, ShelfLimits};
+use crate::models::status::StockStatus;
+use crate::models::stocktake::{NewStocktake, Stocktake, StocktakeCount, StocktakeId, StocktakeLine};
+use crate::models::unit::{StockUnit, UnitId};
 use crate::models::user::{User, UserAuthentication, UserCompact, UserId, UserProfile};
+use crate::models::valuation::{Cost, CostLayer, CostingMethod, ValuationGroup, ValuationLine, ValuationShare};
+use crate::models::warehouse::{Warehouse, WarehouseId};
 
 /// Database drivers.
 #[derive(PartialEq, Eq, Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +66,119 @@ pub enum Error {
     ItemNotFound,
     CountMustBePositive,
     InsufficientItem,
+    SerialCountMismatch,
+    SerialNotFound,
+    SerialInStock,
+    StockReserved,
+    ReservationNotFound,
+    ReorderPointNotFound,
+    StocktakeNotFound,
+    StocktakeClosed,
+    StocktakeInProgress,
+    ShelfNotInStocktake,
+    UnitNotFound,
+    BomNotFound,
+    CategoryNotFound,
+    CurrencyMismatch,
+    StockPolicyNotFound,
+    StockConflict,
+    StockChanged,
+    OperationNotFound,
+    OperationReversed,
+    StockConsumed,
+    ShelfOverCapacity,
+    WarehouseNotFound,
+    WarehouseCodeTaken,
+    WarehouseNotEmpty,
+    BinNotFound,
+    BinNotEmpty,
+    LocationArchived,
+    LocationNotEmpty,
+    RoomHasShelves,
+    RoomHasStock,
+    ItemInStock,
+    ItemOnShelf,
+    ItemReferenced,
+}
+
+/// Stock taken out of one lot on a shelf.
+pub struct Draw {
+    /// The lot as left on the shelf.
+    pub left: ItemXShelf,
+    pub taken: i64,
+    /// The units taken, for serialized items.
+    pub serials: Vec<String>,
+}
+
+/// Check that a stock line names one distinct serial per unit of a serialized
+/// item, and no serial at all otherwise.
+///
+/// # Errors
+///
+/// This function will return an `Error::SerialCountMismatch` if it does not.
+pub fn check_serials(serialized: bool, line: &ItemXShelf) -> Result<(), Error> {
+    let distinct: HashSet<&String> = line.serials.iter().collect();
+    let expected = if serialized { line.count } else { 0 };
+    if distinct.len() == line.serials.len() && i64::try_from(line.serials.len()).is_ok_and(|n| n == expected) {
+        Ok(())
+    } else {
+        Err(Error::SerialCountMismatch)
+    }
+}
+
+/// Why an id of a batch delete failed. Errors not about the id itself fail it all the same, the
+/// other ids still being attempted.
+#[must_use]
+pub fn failure_reason(error: &Error) -> &'static str {
+    match error {
+        Error::RoomNotFound | Error::ShelfNotFound | Error::ItemNotFound => "not found",
+        Error::LocationNotEmpty | Error::ItemInStock => "has stock",
+        Error::RoomHasShelves | Error::ItemOnShelf => "referenced by shelf",
+        Error::ItemReferenced => "referenced by stock history",
+        _ => "internal error",
+    }
+}
+
+/// The part of a cost going to `count` out of `total` items, rounded toward zero.
+#[must_use]
+pub fn cost_share(value: i64, count: i64, total: i64) -> i64 {
+    if total == 0 {
+        return value;
+    }
+    i64::try_from(i128::from(value) * i128::from(count) / i128::from(total)).unwrap_or(value)
+}
+
+/// Sum up `shares`, ordered by item and currency, into a valuation line per group and currency. The
+/// costed stock of an item is prorated once over the groups holding it, each taking what its running
+/// total brings, so that the shares of an item add up to its cost whatever the rounding.
+#[must_use]
+pub fn prorate_valuation(shares: Vec<ValuationShare>) -> Vec<ValuationLine> {
+    let mut lines: BTreeMap<(Option<i64>, String), ValuationLine> = BTreeMap::new();
+    let mut item: Option<(ItemId, String)> = None;
+    let mut held = 0;
+    for x in shares {
+        if item
+            .as_ref()
+            .map_or(true, |(item_id, currency)| *item_id != x.item_id || *currency != x.currency)
+        {
+            item = Some((x.item_id, x.currency.clone()));
+            held = 0;
+        }
+        let before = held;
+        held += x.count;
+        let line = lines
+            .entry((x.group_id, x.currency.clone()))
+            .or_insert_with(|| ValuationLine {
+                group_id: x.group_id,
+                group_name: x.group_name,
+                currency: x.currency,
+                count: 0,
+                value: 0,
+            });
+        line.count += cost_share(x.cost_count, held, x.total) - cost_share(x.cost_count, before, x.total);
+        line.value += cost_share(x.cost_value, held, x.total) - cost_share(x.cost_value, before, x.total);
+    }
+    lines.into_values().collect()
 }
 
 /// Get the Driver of the Database from the Connection String
@@ -113,47 +247,172 @@ pub trait Database: Sync + Send {
     /// Delete user and all related user data with `user_id`.
     async fn delete_user(&self, user_id: UserId) -> Result<(), Error>;
     /// Add a new room
-    async fn insert_room_and_get_id(&self, name: &str) -> Result<i64, Error>;
-    async fn insert_room_with_desc_and_get_id(&self, name: &str, desc: &str) -> Result<i64, Error>;
-    /// Delete a room.
-    async fn delete_room(&self, room_id: RoomId) -> Result<(), Error>;
-    async fn delete_rooms(&self, ids: &Vec<RoomId>) -> Result<BatchDelResult, Error>;
-    /// Update a room with `room_id`.
-    async fn update_room(&self, room_id: RoomId, name: &str, desc: &Option<String>) -> Result<(), Error>;
+    async fn insert_room_and_get_id(&self, name: &str, warehouse_id: WarehouseId) -> Result<i64, Error>;
+    async fn insert_room_with_desc_and_get_id(&self, name: &str, desc: &str, warehouse_id: WarehouseId) -> Result<i64, Error>;
+    /// Archive a room along with its shelves, moving the stock left on them to `shelf_to`, into
+    /// `bin_to` when given. Fails with `Error::LocationNotEmpty` when stock remains and there is no
+    /// `shelf_to`, and with `Error::RoomNotFound` when the room is missing or archived already.
+    async fn archive_room(
+        &self,
+        room_id: RoomId,
+        shelf_to: Option<ShelfId>,
+        bin_to: Option<BinId>,
+        meta: &MovementMeta,
+    ) -> Result<(), Error>;
+    /// Restore an archived room along with the shelves archived with it. Fails with
+    /// `Error::RoomNotFound` when the room is missing or not archived.
+    async fn restore_room(&self, room_id: RoomId) -> Result<(), Error>;
+    /// Get the archived rooms.
+    async fn get_archived_rooms(&self) -> Result<Vec<Room>, Error>;
+    /// Archive each room of `ids`, those with shelves left failing with `Error::RoomHasShelves` and
+    /// those archived already with `Error::RoomNotFound`. Nothing changes on a `dry_run`.
+    async fn delete_rooms(&self, ids: &Vec<RoomId>, dry_run: bool) -> Result<BatchDelResult, Error>;
+    /// Update a room with `room_id`. Moving it to another warehouse fails with `Error::RoomHasStock`
+    /// while stock is left in it and with `Error::WarehouseNotFound` for an unknown warehouse.
+    async fn update_room(
+        &self,
+        room_id: RoomId,
+        name: &str,
+        desc: &Option<String>,
+        warehouse_id: WarehouseId,
+    ) -> Result<(), Error>;
     /// Update a room's name with `room_id`.
     async fn update_room_name(&self, room_id: RoomId, name: &str) -> Result<(), Error>;
     /// Update a room's description with `room_id`.
     async fn update_room_desc(&self, room_id: RoomId, desc: &str) -> Result<(), Error>;
+    /// Move a room with `room_id` to another warehouse, failing like `update_room` does.
+    async fn update_room_warehouse(&self, room_id: RoomId, warehouse_id: WarehouseId) -> Result<(), Error>;
     /// Get a `room` from `room_id`.
     async fn get_room_from_id(&self, room_id: RoomId) -> Result<Room, Error>;
     /// Get 'rooms' from criteria
     async fn get_rooms(&self, offset: u64, limit: u8, sort: &Sorting) -> Result<Listing<Room>, Error>;
     async fn get_all_rooms(&self) -> Result<Vec<Room>, Error>;
-    async fn insert_shelf_and_get_id(&self, name: &str, layer: i64, room_id: RoomId) -> Result<ShelfId, Error>;
-    async fn delete_shelf(&self, shelf_id: ShelfId) -> Result<(), Error>;
-    async fn delete_shelves(&self, ids: &Vec<ShelfId>) -> Result<BatchDelResult, Error>;
+    /// Get the categories a room is restricted to, none when it takes any item.
+    async fn get_room_categories(&self, room_id: RoomId) -> Result<Vec<Category>, Error>;
+    /// Restrict a room to items of `category_ids`, lifting the restriction when empty.
+    async fn set_room_categories(&self, room_id: RoomId, category_ids: &[CategoryId]) -> Result<(), Error>;
+    /// Add a warehouse, returning its `warehouse_id`.
+    async fn insert_warehouse(
+        &self,
+        code: &str,
+        name: &str,
+        address: &Option<String>,
+        time_zone: &str,
+    ) -> Result<WarehouseId, Error>;
+    /// Update the fields of a warehouse that are given.
+    async fn update_warehouse(
+        &self,
+        warehouse_id: WarehouseId,
+        code: Option<&str>,
+        name: Option<&str>,
+        address: Option<&str>,
+        time_zone: Option<&str>,
+    ) -> Result<(), Error>;
+    /// Delete a warehouse without rooms.
+    async fn delete_warehouse(&self, warehouse_id: WarehouseId) -> Result<(), Error>;
+    async fn get_warehouse_from_id(&self, warehouse_id: WarehouseId) -> Result<Warehouse, Error>;
+    /// Get every warehouse, by code.
+    async fn get_warehouses(&self) -> Result<Vec<Warehouse>, Error>;
+    /// Add a shelf along with its bins, `layers` of `positions` each.
+    async fn insert_shelf_and_get_id(
+        &self,
+        name: &str,
+        layer: i64,
+        room_id: RoomId,
+        layers: i64,
+        positions: i64,
+    ) -> Result<ShelfId, Error>;
+    /// Archive a shelf, moving the stock left on it to `shelf_to`, into `bin_to` when given. Fails
+    /// with `Error::LocationNotEmpty` when stock remains and there is no `shelf_to`, and with
+    /// `Error::ShelfNotFound` when the shelf is missing or archived already.
+    async fn archive_shelf(
+        &self,
+        shelf_id: ShelfId,
+        shelf_to: Option<ShelfId>,
+        bin_to: Option<BinId>,
+        meta: &MovementMeta,
+    ) -> Result<(), Error>;
+    /// Restore an archived shelf. Fails with `Error::LocationArchived` while its room is archived,
+    /// and with `Error::ShelfNotFound` when the shelf is missing or not archived.
+    async fn restore_shelf(&self, shelf_id: ShelfId) -> Result<(), Error>;
+    /// Archive each shelf of `ids`, those still holding stock failing with `Error::LocationNotEmpty`
+    /// and those archived already with `Error::ShelfNotFound`. Nothing changes on a `dry_run`.
+    async fn delete_shelves(&self, ids: &Vec<ShelfId>, dry_run: bool) -> Result<BatchDelResult, Error>;
     async fn update_shelf(&self, shelf_id: ShelfId, name: &str, layer: i64, room_id: RoomId) -> Result<(), Error>;
     async fn update_shelf_name(&self, shelf_id: ShelfId, name: &str) -> Result<(), Error>;
     async fn update_shelf_layer(&self, shelf_id: ShelfId, layer: i64) -> Result<(), Error>;
     async fn update_shelf_room(&self, shelf_id: ShelfId, room_id: RoomId) -> Result<(), Error>;
+    /// Lay a shelf out in `layers` of `positions` bins each, adding the bins missing and removing
+    /// those left outside. Fails with `Error::BinNotEmpty` when a bin to remove holds stock.
+    async fn update_shelf_layout(&self, shelf_id: ShelfId, layers: i64, positions: i64) -> Result<(), Error>;
+    /// Get the bins of a shelf, by layer and position.
+    async fn get_bins_of_shelf(&self, shelf_id: ShelfId) -> Result<Vec<Bin>, Error>;
+    /// Get the bin at `position` on `layer` of a shelf.
+    async fn get_bin_at(&self, shelf_id: ShelfId, layer: i64, position: i64) -> Result<Bin, Error>;
+    /// Set what a shelf may hold at most.
+    async fn update_shelf_limits(&self, shelf_id: ShelfId, limits: &ShelfLimits) -> Result<(), Error>;
+    /// Get how much of its capacity a shelf uses.
+    async fn get_shelf_capacity(&self, shelf_id: ShelfId) -> Result<ShelfCapacity, Error>;
+    /// Get how much of their capacity shelves use, those of `room_id` when given.
+    async fn get_shelf_capacities(&self, room_id: Option<RoomId>) -> Result<Vec<ShelfCapacity>, Error>;
+    /// Get every shelf, of `room_id` when given, with its capacity, its layer and how much of the item
+    /// it holds, for incoming stock of the item to be put away on.
+    async fn get_putaway_candidates(&self, item_id: ItemId, room_id: Option<RoomId>) -> Result<Vec<PutawaySuggestion>, Error>;
     async fn get_shelf_from_id(&self, shelf_id: ShelfId) -> Result<Shelf, Error>;
     async fn get_shelves(&self, offset: u64, limit: u8, sort: &Sorting) -> Result<Listing<Shelf>, Error>;
     async fn get_shelves_in_room(&self, offset: u64, limit: u8, sort: &Sorting, room_id: RoomId)
         -> Result<Listing<Shelf>, Error>;
     async fn get_all_shelves(&self) -> Result<Vec<Shelf>, Error>;
     async fn get_all_shelves_in_room(&self, room_id: RoomId) -> Result<Vec<Shelf>, Error>;
-    async fn insert_item_and_get_id(&self, name: &str, sn: &str) -> Result<ItemId, Error>;
-    async fn insert_item_with_desc_and_get_id(&self, name: &str, desc: &str, sn: &str) -> Result<ItemId, Error>;
+    /// Get the archived shelves, of `room_id` when given.
+    async fn get_archived_shelves(&self, room_id: Option<RoomId>) -> Result<Vec<Shelf>, Error>;
+    async fn insert_item_and_get_id(&self, name: &str, sn: &str, serialized: bool, base_unit: &str) -> Result<ItemId, Error>;
+    async fn insert_item_with_desc_and_get_id(
+        &self,
+        name: &str,
+        desc: &str,
+        sn: &str,
+        serialized: bool,
+        base_unit: &str,
+    ) -> Result<ItemId, Error>;
+    /// Delete an item nothing refers to. Fails with `Error::ItemInStock` while it has stock,
+    /// `Error::ItemOnShelf` while empty lots of it are kept on a shelf and `Error::ItemReferenced`
+    /// while anything else, such as its stock history, refers to it.
     async fn delete_item(&self, item_id: ItemId) -> Result<(), Error>;
-    async fn delete_items(&self, ids: &Vec<ItemId>) -> Result<BatchDelResult, Error>;
+    /// Delete each item of `ids` as `delete_item` would. Nothing changes on a `dry_run`.
+    async fn delete_items(&self, ids: &Vec<ItemId>, dry_run: bool) -> Result<BatchDelResult, Error>;
     async fn update_item(&self, item_id: ItemId, name: &str, desc: &Option<String>, sn: &str) -> Result<(), Error>;
     async fn update_item_name(&self, item_id: ItemId, name: &str) -> Result<(), Error>;
     async fn update_item_desc(&self, item_id: ItemId, desc: &str) -> Result<(), Error>;
     async fn update_item_sn(&self, item_id: ItemId, sn: &str) -> Result<(), Error>;
     async fn get_item_from_id(&self, item_id: ItemId) -> Result<Item, Error>;
-    async fn get_items(&self, offset: u64, limit: u8, sort: &Sorting) -> Result<Listing<Item>, Error>;
-    async fn get_all_items(&self) -> Result<Vec<Item>, Error>;
-    async fn get_stocks_on_shelves(&self, offset: u64, limit: u8, sort: &Sorting) -> Result<Listing<ItemOnShelf>, Error>;
+    /// Get a page of the items, of the given ABC and XYZ classes when given.
+    async fn get_items(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        abc_class: Option<AbcClass>,
+        xyz_class: Option<XyzClass>,
+    ) -> Result<Listing<Item>, Error>;
+    async fn get_all_items(&self, abc_class: Option<AbcClass>, xyz_class: Option<XyzClass>) -> Result<Vec<Item>, Error>;
+    /// Get the alternative units of an item.
+    async fn get_item_units(&self, item_id: ItemId) -> Result<Vec<ItemUnit>, Error>;
+    /// Set how many base units an alternative unit of an item is worth.
+    async fn upsert_item_unit(&self, item_id: ItemId, unit: &str, factor: i64) -> Result<(), Error>;
+    async fn delete_item_unit(&self, item_id: ItemId, unit: &str) -> Result<(), Error>;
+    /// Get how many base units a unit of an item is worth, 1 for its base unit.
+    async fn get_unit_factor(&self, item_id: ItemId, unit: &str) -> Result<i64, Error>;
+    /// Get the items having a unit, as an alternative unit or as their base unit.
+    async fn get_unit_factors(&self, unit: &str) -> Result<Vec<ItemUnit>, Error>;
+    /// Get the stock per (item, shelf), on the shelves of `warehouse_id` only when given.
+    async fn get_stocks_on_shelves(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<ItemOnShelf>, Error>;
     async fn get_stocks_on_shelf(
         &self,
         offset: u64,
@@ -161,7 +420,14 @@ pub trait Database: Sync + Send {
         sort: &Sorting,
         shelf_id: ShelfId,
     ) -> Result<Listing<ItemOnShelf>, Error>;
-    async fn get_stocks_in_rooms(&self, offset: u64, limit: u8, sort: &Sorting) -> Result<Listing<ItemInRoom>, Error>;
+    /// Get the stock per (item, room), in the rooms of `warehouse_id` only when given.
+    async fn get_stocks_in_rooms(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<ItemInRoom>, Error>;
     async fn get_stocks_in_room(
         &self,
         offset: u64,
@@ -169,10 +435,279 @@ pub trait Database: Sync + Send {
         sort: &Sorting,
         room_id: RoomId,
     ) -> Result<Listing<ItemInRoom>, Error>;
-    async fn transfer_items(&self, item_id: ItemId, count: i64, shelf_from: ShelfId, shelf_to: ShelfId) -> Result<(), Error>;
-    async fn withdraw_items(&self, item_id: ItemId, count: i64, shelf_id: ShelfId) -> Result<(), Error>;
-    async fn deposit_items(&self, item_id: ItemId, count: i64, shelf_id: ShelfId) -> Result<(), Error>;
-    async fn convert_items(&self, from: Vec<ItemXShelf>, into: Vec<ItemXShelf>) -> Result<(), Error>;
+    /// Get the stock per (item, warehouse), in `warehouse_id` only when given.
+    async fn get_stocks_in_warehouses(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<ItemInWarehouse>, Error>;
+    /// Get the stock per (item, shelf) as it stood at `as_of`, only on `shelf_id` and in `warehouse_id` when
+    /// given. Holds are not kept in the ledger, past stock is reported as all available.
+    async fn get_stocks_on_shelves_as_of(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        shelf_id: Option<ShelfId>,
+        warehouse_id: Option<WarehouseId>,
+        as_of: OffsetDateTime,
+    ) -> Result<Listing<ItemOnShelf>, Error>;
+    /// Get the stock per (item, room) as it stood at `as_of`, only in `room_id` and `warehouse_id` when given.
+    async fn get_stocks_in_rooms_as_of(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+        as_of: OffsetDateTime,
+    ) -> Result<Listing<ItemInRoom>, Error>;
+    /// Get the stock per (item, warehouse) as it stood at `as_of`, only in `warehouse_id` when given.
+    async fn get_stocks_in_warehouses_as_of(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        warehouse_id: Option<WarehouseId>,
+        as_of: OffsetDateTime,
+    ) -> Result<Listing<ItemInWarehouse>, Error>;
+    /// Record the current stock as a snapshot to rebuild past stock from.
+    async fn insert_stock_snapshot(&self) -> Result<SnapshotId, Error>;
+    /// Get the lots expiring on or before `until`, summed per room.
+    async fn get_lots_expiring(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        until: Date,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<LotInRoom>, Error>;
+    /// Move items between shelves, or between the bins of a shelf, and record a `transfer` movement
+    /// per lot moved, returning the source and target quantities of each lot.
+    #[allow(clippy::too_many_arguments)]
+    async fn transfer_items(
+        &self,
+        item_id: ItemId,
+        count: i64,
+        shelf_from: ShelfId,
+        shelf_to: ShelfId,
+        bin_to: Option<BinId>,
+        pick: &StockPick,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, Error>;
+    /// Take items off a shelf and record a `withdraw` movement per lot drawn from, returning what is
+    /// left in each of those lots.
+    async fn withdraw_items(
+        &self,
+        item_id: ItemId,
+        count: i64,
+        shelf_id: ShelfId,
+        pick: &StockPick,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, Error>;
+    /// Put items into a lot on a shelf, in `status`, and record a `deposit` movement, returning the new quantity
+    /// of the lot. Serialized items name one serial per unit. Items deposited at a cost add to the cost layers of the item.
+    #[allow(clippy::too_many_arguments)]
+    async fn deposit_items(
+        &self,
+        item_id: ItemId,
+        count: i64,
+        shelf_id: ShelfId,
+        bin_id: Option<BinId>,
+        lot: &Lot,
+        status: StockStatus,
+        serials: &[String],
+        cost: Option<&Cost>,
+        meta: &MovementMeta,
+    ) -> Result<ItemXShelf, Error>;
+    /// Run the lines of a batch in one transaction, each under a savepoint of its own, returning
+    /// the result of every line in order. All-or-nothing batches are rolled back when a line failed.
+    async fn apply_stock_batch(
+        &self,
+        lines: &[BatchLine],
+        mode: BatchMode,
+        meta: &MovementMeta,
+    ) -> Result<Vec<Result<Vec<ItemXShelf>, Error>>, Error>;
+    /// Undo every line of the operation sharing `correlation_id`, recording a `reversal` line linked
+    /// to each. Fails when stock the operation brought onto a shelf is no longer there. Returns the lots
+    /// touched.
+    async fn reverse_operation(&self, correlation_id: &str, meta: &MovementMeta) -> Result<Vec<ItemXShelf>, Error>;
+    /// Consume `from` to produce `into`, recording one `convert` movement per line under a shared correlation id.
+    /// Returns the resulting quantity of every touched line, in request order.
+    async fn convert_items(
+        &self,
+        from: Vec<ItemXShelf>,
+        into: Vec<ItemXShelf>,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, Error>;
+    /// Get a unit of a serialized item by its serial number.
+    async fn get_unit(&self, item_id: ItemId, serial: &str) -> Result<StockUnit, Error>;
+    /// Get the movements a unit took part in, oldest first.
+    async fn get_unit_movements(&self, unit_id: UnitId) -> Result<Vec<StockMovement>, Error>;
+    /// Get the stock movements matching `criteria`.
+    async fn get_movements(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        criteria: &MovementCriteria,
+    ) -> Result<Listing<StockMovement>, Error>;
+    /// Hold stock for a user, refusing more than what is neither on hold nor missing from the shelf
+    /// or room. Returns the new `reservation_id`.
+    async fn insert_reservation(&self, reservation: &NewReservation) -> Result<ReservationId, Error>;
+    async fn get_reservation(&self, reservation_id: ReservationId) -> Result<Reservation, Error>;
+    /// Get the holds still in effect matching `criteria`.
+    async fn get_reservations(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        criteria: &ReservationCriteria,
+    ) -> Result<Listing<Reservation>, Error>;
+    async fn delete_reservation(&self, reservation_id: ReservationId) -> Result<(), Error>;
+    /// Release every hold past its expiry, returning the item of each hold released.
+    async fn delete_expired_reservations(&self) -> Result<Vec<ItemId>, Error>;
+    /// Set the levels of an item in a room, or across every room when `room_id` is `None`, replacing
+    /// the ones already set there. Returns the `reorder_point_id`.
+    async fn upsert_reorder_point(
+        &self,
+        item_id: ItemId,
+        room_id: Option<RoomId>,
+        min_level: i64,
+        max_level: i64,
+    ) -> Result<ReorderPointId, Error>;
+    async fn delete_reorder_point(&self, reorder_point_id: ReorderPointId) -> Result<(), Error>;
+    async fn get_reorder_points(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        item_id: Option<ItemId>,
+    ) -> Result<Listing<ReorderPoint>, Error>;
+    /// Get the reorder points of an item, or of every item, along with the stock available to them.
+    async fn get_reorder_levels(&self, item_id: Option<ItemId>) -> Result<Vec<ReorderLevel>, Error>;
+    /// Get the reorder points with less stock available than their min level.
+    async fn get_reorder_levels_below(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        room_id: Option<RoomId>,
+    ) -> Result<Listing<ReorderLevel>, Error>;
+    /// Flag a reorder point as under its min level from now on, or clear the flag.
+    async fn set_reorder_point_below(&self, reorder_point_id: ReorderPointId, below: bool) -> Result<(), Error>;
+    async fn insert_stock_alert_subscription(&self, user_id: UserId) -> Result<(), Error>;
+    async fn delete_stock_alert_subscription(&self, user_id: UserId) -> Result<(), Error>;
+    /// Get the profiles of the users alerted of low stock.
+    async fn get_stock_alert_subscribers(&self) -> Result<Vec<UserProfile>, Error>;
+    /// Open a count session on the shelves of a room, or on a set of shelves, none of them being
+    /// counted already. Returns the new `stocktake_id`.
+    async fn insert_stocktake(&self, stocktake: &NewStocktake) -> Result<StocktakeId, Error>;
+    async fn get_stocktake(&self, stocktake_id: StocktakeId) -> Result<Stocktake, Error>;
+    async fn get_stocktakes(&self, offset: u64, limit: u8, sort: &Sorting) -> Result<Listing<Stocktake>, Error>;
+    /// Get the lines of a stocktake along with their variances.
+    async fn get_stocktake_lines(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        stocktake_id: StocktakeId,
+    ) -> Result<Listing<StocktakeLine>, Error>;
+    /// Record counts of an open stocktake, replacing earlier counts of the same (item, shelf).
+    async fn upsert_stocktake_counts(
+        &self,
+        stocktake_id: StocktakeId,
+        counts: &[StocktakeCount],
+        user_id: UserId,
+    ) -> Result<(), Error>;
+    /// Close a stocktake and bring the counted stock in line with the counts, recording an `adjust`
+    /// movement per lot corrected. Returns the resulting quantity of every corrected lot.
+    async fn approve_stocktake(&self, stocktake_id: StocktakeId, meta: &MovementMeta) -> Result<Vec<ItemXShelf>, Error>;
+    /// Close a stocktake leaving the stock as it is.
+    async fn cancel_stocktake(&self, stocktake_id: StocktakeId, user_id: UserId) -> Result<(), Error>;
+    /// Add a recipe as the next version of the recipes of its output item.
+    async fn insert_bom(&self, bom: &NewBom) -> Result<BomId, Error>;
+    /// Get a recipe along with its components.
+    async fn get_bom(&self, bom_id: BomId) -> Result<Bom, Error>;
+    /// Get recipes along with their components, only those of `item_id` when given.
+    async fn get_boms(&self, offset: u64, limit: u8, sort: &Sorting, item_id: Option<ItemId>) -> Result<Listing<Bom>, Error>;
+    /// Get the stock of a shelf per (bin, item).
+    async fn get_stocks_in_bins(&self, shelf_id: ShelfId) -> Result<Vec<ItemInBin>, Error>;
+    /// Get the stock of an item on every shelf holding it.
+    async fn get_stocks_of_item(&self, item_id: ItemId) -> Result<Vec<ItemOnShelf>, Error>;
+    /// Switch how the cost of an item is kept, merging its cost layers into one when switching to the average.
+    async fn update_item_costing(&self, item_id: ItemId, costing: CostingMethod) -> Result<(), Error>;
+    async fn update_item_category(&self, item_id: ItemId, category_id: CategoryId) -> Result<(), Error>;
+    /// Set the weight and dimensions of one base unit of an item.
+    async fn update_item_dimensions(&self, item_id: ItemId, dimensions: &ItemDimensions) -> Result<(), Error>;
+    async fn insert_category(&self, name: &str, desc: &Option<String>) -> Result<CategoryId, Error>;
+    async fn get_categories(&self) -> Result<Vec<Category>, Error>;
+    /// Remove a category, leaving its items without one.
+    async fn delete_category(&self, category_id: CategoryId) -> Result<(), Error>;
+    /// Get the cost layers of an item still holding stock, oldest first.
+    async fn get_cost_layers(&self, item_id: ItemId) -> Result<Vec<CostLayer>, Error>;
+    /// Get the value of the costed stock as it stood at a time, per room, warehouse or category and currency,
+    /// of the stock in `warehouse_id` only when given.
+    async fn get_valuation(
+        &self,
+        group: ValuationGroup,
+        warehouse_id: Option<WarehouseId>,
+        as_of: OffsetDateTime,
+    ) -> Result<Vec<ValuationLine>, Error>;
+    /// Set the stock policy of a room, or of every room without one of their own when `room_id` is `None`,
+    /// replacing the one already set there.
+    async fn upsert_stock_policy(&self, policy: &StockPolicy) -> Result<(), Error>;
+    async fn delete_stock_policy(&self, room_id: Option<RoomId>) -> Result<(), Error>;
+    async fn get_stock_policies(&self) -> Result<Vec<StockPolicy>, Error>;
+    /// Get the stock policy in effect on a shelf.
+    async fn get_stock_policy_of_shelf(&self, shelf_id: ShelfId) -> Result<StockPolicy, Error>;
+    /// Get the stock of every item per room, in `room_id` and `warehouse_id` only when given, bucketed by the
+    /// time it was received: after `cutoffs[0]`, then up to each next cutoff and before the last one.
+    async fn get_stock_aging(
+        &self,
+        cutoffs: [OffsetDateTime; 3],
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Vec<AgingLine>, Error>;
+    /// Get the items in stock, on the shelves of `room_id` and `warehouse_id` when given, nothing was
+    /// withdrawn of since `since`, counting from when they came in stock if they never were.
+    async fn get_dead_stock(
+        &self,
+        since: OffsetDateTime,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Vec<DeadStock>, Error>;
+    /// Get what was withdrawn of every item from `from` until before `to`, and the cost it was
+    /// taken out at.
+    async fn get_consumption(&self, from: OffsetDateTime, to: OffsetDateTime) -> Result<Vec<Consumption>, Error>;
+    /// Store the classes of items, all or none of them.
+    async fn update_item_classes(&self, classes: &[ItemClass]) -> Result<(), Error>;
+    /// Get the lots taken below zero and not reconciled yet, on the shelves of `room_id` and `warehouse_id`
+    /// when given.
+    async fn get_flagged_stocks(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<FlaggedStock>, Error>;
+    /// Mark the lots of an item on a shelf as reconciled, returning how many were flagged.
+    async fn clear_stock_flags(&self, item_id: ItemId, shelf_id: ShelfId) -> Result<u64, Error>;
+    /// Put items picked by `pick` among those in `pick.status` in `status` instead, recording a `status`
+    /// movement out of the old status and one into the new per lot. Returns both sides of every lot touched.
+    async fn change_stock_status(
+        &self,
+        item_id: ItemId,
+        count: i64,
+        shelf_id: ShelfId,
+        pick: &StockPick,
+        status: StockStatus,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, Error>;
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -181,3 +716,95 @@ pub struct Listing<T> {
     pub total: u64,
     pub data: Vec<T>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{cost_share, failure_reason, prorate_valuation, Error};
+    use crate::models::valuation::{ValuationLine, ValuationShare};
+
+    fn share(group_id: i64, item_id: i64, count: i64, total: i64, cost_value: i64) -> ValuationShare {
+        ValuationShare {
+            group_id: Some(group_id),
+            group_name: Some(format!("group {group_id}")),
+            item_id,
+            currency: "EUR".to_string(),
+            count,
+            total,
+            cost_count: total,
+            cost_value,
+        }
+    }
+
+    #[test]
+    fn it_should_share_a_cost_rounding_toward_zero() {
+        assert_eq!(cost_share(100, 1, 3), 33);
+        assert_eq!(cost_share(100, 2, 3), 66);
+        assert_eq!(cost_share(100, 3, 3), 100);
+        assert_eq!(cost_share(100, 0, 3), 0);
+        assert_eq!(cost_share(-100, 1, 3), -33);
+    }
+
+    #[test]
+    fn it_should_share_a_cost_without_overflowing() {
+        assert_eq!(cost_share(i64::MAX, i64::MAX - 1, i64::MAX), i64::MAX - 1);
+        assert_eq!(cost_share(i64::MAX, 1, 2), i64::MAX / 2);
+    }
+
+    #[test]
+    fn it_should_give_the_whole_cost_when_there_is_nothing_to_share_it_over() {
+        assert_eq!(cost_share(100, 0, 0), 100);
+    }
+
+    #[test]
+    fn it_should_prorate_the_cost_of_an_item_to_its_total() {
+        let lines = prorate_valuation(vec![share(1, 1, 1, 3, 100), share(2, 1, 1, 3, 100), share(3, 1, 1, 3, 100)]);
+
+        assert_eq!(lines.iter().map(|x| x.value).collect::<Vec<i64>>(), vec![33, 33, 34]);
+        assert_eq!(lines.iter().map(|x| x.value).sum::<i64>(), 100);
+        assert_eq!(lines.iter().map(|x| x.count).sum::<i64>(), 3);
+    }
+
+    #[test]
+    fn it_should_sum_the_items_of_a_group() {
+        let lines = prorate_valuation(vec![share(1, 1, 1, 2, 11), share(2, 1, 1, 2, 11), share(1, 2, 1, 1, 7)]);
+
+        assert_eq!(
+            lines,
+            vec![
+                ValuationLine {
+                    group_id: Some(1),
+                    group_name: Some("group 1".to_string()),
+                    currency: "EUR".to_string(),
+                    count: 2,
+                    value: 12,
+                },
+                ValuationLine {
+                    group_id: Some(2),
+                    group_name: Some("group 2".to_string()),
+                    currency: "EUR".to_string(),
+                    count: 1,
+                    value: 6,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_keep_currencies_apart() {
+        let mut usd = share(1, 1, 1, 1, 5);
+        usd.currency = "USD".to_string();
+        let lines = prorate_valuation(vec![share(1, 1, 1, 1, 3), usd]);
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].value, 3);
+        assert_eq!(lines[1].value, 5);
+    }
+
+    #[test]
+    fn it_should_fail_an_id_of_a_batch_delete_for_any_error() {
+        assert_eq!(failure_reason(&Error::ShelfNotFound), "not found");
+        assert_eq!(failure_reason(&Error::LocationNotEmpty), "has stock");
+        assert_eq!(failure_reason(&Error::RoomHasShelves), "referenced by shelf");
+        assert_eq!(failure_reason(&Error::Error), "internal error");
+    }
+}
@@ -1,18 +1,39 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::str::FromStr;
 use std::time::Duration;
 
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
 use sqlx::mysql::{MySqlConnectOptions, MySqlPoolOptions};
-use sqlx::{query, query_as, Acquire, ConnectOptions, MySqlPool};
+use sqlx::{query, query_as, Acquire, ConnectOptions, MySqlConnection, MySqlPool, Transaction};
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
 
-use crate::common::BatchDelResult;
+use crate::common::{BatchDelResult, MovementCriteria, ReservationCriteria};
 use crate::databases::database;
-use crate::databases::database::{Database, Driver, Error, Listing, Sorting};
-use crate::models::item::{Item, ItemId, ItemInRoom, ItemOnShelf, ItemXShelf};
+use crate::databases::database::{Database, Draw, Driver, Error, Listing, Sorting};
+use crate::models::aging::{AgingLine, DeadStock};
+use crate::models::batch::{BatchLine, BatchMode, StockOp};
+use crate::models::bin::{Bin, BinId, ItemInBin};
+use crate::models::bom::{Bom, BomComponent, BomId, NewBom};
+use crate::models::category::{Category, CategoryId};
+use crate::models::classification::{AbcClass, Consumption, ItemClass, XyzClass};
+use crate::models::item::{
+    Item, ItemDimensions, ItemId, ItemInRoom, ItemInWarehouse, ItemOnShelf, ItemUnit, ItemXShelf, Lot, LotInRoom, StockPick,
+};
+use crate::models::movement::{MovementId, MovementKind, MovementMeta, MovementOp, SnapshotId, StockMovement};
+use crate::models::policy::{FlaggedStock, StockPolicy};
+use crate::models::putaway::PutawaySuggestion;
+use crate::models::reorder::{ReorderLevel, ReorderPoint, ReorderPointId};
+use crate::models::reservation::{NewReservation, Reservation, ReservationId};
 use crate::models::room::{Room, RoomId};
-use crate::models::shelf::{Shelf, ShelfId};
+use crate::models::shelf::{Shelf, ShelfCapacity, ShelfId, ShelfLimits};
+use crate::models::status::StockStatus;
+use crate::models::stocktake::{NewStocktake, Stocktake, StocktakeCount, StocktakeId, StocktakeLine, StocktakeStatus};
+use crate::models::unit::{StockUnit, UnitId};
 use crate::models::user::{User, UserAuthentication, UserCompact, UserId, UserProfile};
+use crate::models::valuation::{Cost, CostLayer, CostingMethod, ValuationGroup, ValuationLine, ValuationShare};
+use crate::models::warehouse::{Warehouse, WarehouseId};
 
 pub struct Mysql {
     pub pool: MySqlPool,
@@ -199,59 +220,131 @@ impl Database for Mysql {
                 }
             })
     }
-    async fn insert_room_and_get_id(&self, name: &str) -> Result<i64, Error> {
-        let sql = "INSERT INTO rooms (name) VALUES (?)";
+    async fn insert_room_and_get_id(&self, name: &str, warehouse_id: WarehouseId) -> Result<i64, Error> {
+        let sql = "INSERT INTO rooms (name, warehouse_id) VALUES (?, ?)";
         query(sql)
             .bind(name)
+            .bind(warehouse_id)
             .execute(&self.pool)
             .await
             .map(|v| v.last_insert_id() as i64)
             .map_err(|_| Error::Error)
     }
-    async fn insert_room_with_desc_and_get_id(&self, name: &str, desc: &str) -> Result<i64, Error> {
-        let sql = "INSERT INTO rooms (name, description) VALUES (?, ?)";
+    async fn insert_room_with_desc_and_get_id(&self, name: &str, desc: &str, warehouse_id: WarehouseId) -> Result<i64, Error> {
+        let sql = "INSERT INTO rooms (name, description, warehouse_id) VALUES (?, ?, ?)";
         query(sql)
             .bind(name)
             .bind(desc)
+            .bind(warehouse_id)
             .execute(&self.pool)
             .await
             .map(|v| v.last_insert_id() as i64)
             .map_err(|_| Error::Error)
     }
-    async fn delete_room(&self, room_id: RoomId) -> Result<(), Error> {
-        let sql = "DELETE FROM rooms WHERE room_id = ?";
-        query(sql)
-            .bind(room_id)
-            .execute(&self.pool)
-            .await
-            .map_err(|_| Error::Error)
-            .and_then(|v| {
-                if v.rows_affected() > 0 {
-                    Ok(())
-                } else {
-                    Err(Error::RoomNotFound)
-                }
-            })
+    async fn archive_room(
+        &self,
+        room_id: RoomId,
+        shelf_to: Option<ShelfId>,
+        bin_to: Option<BinId>,
+        meta: &MovementMeta,
+    ) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            let op = MovementOp::new(MovementKind::Transfer, meta);
+            for shelf_id in Self::archive_room_only(&mut tx, room_id).await? {
+                Self::relocate_stock(&mut tx, &op, shelf_id, shelf_to, bin_to).await?;
+            }
+            Self::relocate_room_reservations(&mut tx, room_id, shelf_to).await
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
     }
-    async fn delete_rooms(&self, ids: &Vec<RoomId>) -> Result<BatchDelResult, Error> {
-        todo!()
+    async fn restore_room(&self, room_id: RoomId) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            let sql = "SELECT room_id FROM rooms WHERE room_id = ? AND archived_at IS NOT NULL";
+            query_as::<_, (RoomId,)>(sql)
+                .bind(room_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?
+                .ok_or(Error::RoomNotFound)?;
+            let sql = "UPDATE shelf
+SET archived_at = NULL
+WHERE room_id = ?
+  AND archived_at = (SELECT r.archived_at FROM rooms r WHERE r.room_id = ?)";
+            query(sql)
+                .bind(room_id)
+                .bind(room_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            let sql = "UPDATE rooms SET archived_at = NULL WHERE room_id = ?";
+            query(sql).bind(room_id).execute(&mut *tx).await.map_err(|_| Error::Error)?;
+            Ok(())
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
     }
-    async fn update_room(&self, room_id: RoomId, name: &str, desc: &Option<String>) -> Result<(), Error> {
-        let sql = "UPDATE rooms SET name = ?, description = ? WHERE room_id = ?";
-        query(sql)
-            .bind(name)
-            .bind(desc)
-            .bind(room_id)
-            .execute(&self.pool)
-            .await
-            .map_err(|_| Error::Error)
-            .and_then(|v| {
-                if v.rows_affected() > 0 {
-                    Ok(())
-                } else {
-                    Err(Error::RoomNotFound)
+    async fn get_archived_rooms(&self) -> Result<Vec<Room>, Error> {
+        let sql = "SELECT * FROM rooms WHERE archived_at IS NOT NULL ORDER BY room_id";
+        query_as::<_, Room>(sql).fetch_all(&self.pool).await.map_err(|_| Error::Error)
+    }
+    async fn delete_rooms(&self, ids: &Vec<RoomId>, dry_run: bool) -> Result<BatchDelResult, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<BatchDelResult, Error> = async {
+            let mut deleted = BatchDelResult::default();
+            for room_id in ids {
+                // a savepoint per id, so a failed one leaves nothing behind
+                let mut savepoint = tx.begin().await.map_err(|_| Error::TransactionError)?;
+                let result = async {
+                    // the shelves are deleted first, archiving them here would take their stock along
+                    if !Self::archive_room_only(&mut savepoint, *room_id).await?.is_empty() {
+                        return Err(Error::RoomHasShelves);
+                    }
+                    Self::relocate_room_reservations(&mut savepoint, *room_id, None).await
                 }
-            })
+                .await;
+                match Self::commit_or_rollback(savepoint, result).await {
+                    Ok(()) => deleted.s += 1,
+                    Err(error) => deleted.fail(*room_id, database::failure_reason(&error)),
+                }
+            }
+            Ok(deleted)
+        }
+        .await;
+        if dry_run {
+            tx.rollback().await.map_err(|_| Error::TransactionError)?;
+            return result;
+        }
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn update_room(
+        &self,
+        room_id: RoomId,
+        name: &str,
+        desc: &Option<String>,
+        warehouse_id: WarehouseId,
+    ) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            Self::move_room(&mut tx, room_id, warehouse_id).await?;
+            let sql = "UPDATE rooms SET name = ?, description = ? WHERE room_id = ?";
+            query(sql)
+                .bind(name)
+                .bind(desc)
+                .bind(room_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            Ok(())
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
     }
     async fn update_room_name(&self, room_id: RoomId, name: &str) -> Result<(), Error> {
         let sql = "UPDATE rooms SET name = ? WHERE room_id = ?";
@@ -285,6 +378,12 @@ impl Database for Mysql {
                 }
             })
     }
+    async fn update_room_warehouse(&self, room_id: RoomId, warehouse_id: WarehouseId) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result = Self::move_room(&mut tx, room_id, warehouse_id).await;
+        Self::commit_or_rollback(tx, result).await
+    }
     async fn get_room_from_id(&self, room_id: RoomId) -> Result<Room, Error> {
         let sql = "SELECT * FROM rooms WHERE room_id = ?";
         query_as::<_, Room>(sql)
@@ -294,7 +393,7 @@ impl Database for Mysql {
             .map_err(|_| Error::RoomNotFound)
     }
     async fn get_rooms(&self, offset: u64, limit: u8, sort: &Sorting) -> Result<Listing<Room>, Error> {
-        let sql = "SELECT COUNT(*) as count FROM rooms";
+        let sql = "SELECT COUNT(*) as count FROM rooms WHERE archived_at IS NULL";
         let count_result: Result<i64, Error> = query_as(sql)
             .fetch_one(&self.pool)
             .await
@@ -307,7 +406,7 @@ impl Database for Mysql {
             Sorting::IdAsc => "room_id ASC".to_string(),
             Sorting::IdDesc => "room_id DESC".to_string(),
         };
-        let sql = format!("SELECT * FROM rooms ORDER BY {sort_query} LIMIT ?, ?");
+        let sql = format!("SELECT * FROM rooms WHERE archived_at IS NULL ORDER BY {sort_query} LIMIT ?, ?");
         let rooms: Vec<Room> = query_as::<_, Room>(&sql)
             .bind(i64::saturating_add_unsigned(0, offset))
             .bind(limit)
@@ -320,44 +419,253 @@ impl Database for Mysql {
         })
     }
     async fn get_all_rooms(&self) -> Result<Vec<Room>, Error> {
-        let sql = "SELECT * FROM rooms";
+        let sql = "SELECT * FROM rooms WHERE archived_at IS NULL";
         let rooms: Vec<Room> = query_as::<_, Room>(&sql)
             .fetch_all(&self.pool)
             .await
             .map_err(|_| Error::Error)?;
         Ok(rooms)
     }
-    async fn insert_shelf_and_get_id(&self, name: &str, layer: i64, room_id: RoomId) -> Result<ShelfId, Error> {
-        let sql = "INSERT INTO shelf (name, layer, room_id) VALUES (?, ?, ?)";
+    async fn get_room_categories(&self, room_id: RoomId) -> Result<Vec<Category>, Error> {
+        let sql = "SELECT c.category_id, c.name, c.description
+FROM categories c
+         JOIN room_categories rc ON rc.category_id = c.category_id
+WHERE rc.room_id = ?
+ORDER BY c.category_id";
+        query_as::<_, Category>(sql)
+            .bind(room_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn set_room_categories(&self, room_id: RoomId, category_ids: &[CategoryId]) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            let sql = "SELECT room_id FROM rooms WHERE room_id = ?";
+            query_as::<_, (RoomId,)>(sql)
+                .bind(room_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?
+                .ok_or(Error::RoomNotFound)?;
+            let sql = "DELETE FROM room_categories WHERE room_id = ?";
+            query(sql).bind(room_id).execute(&mut *tx).await.map_err(|_| Error::Error)?;
+            for category_id in category_ids.iter().collect::<BTreeSet<_>>() {
+                let sql = "SELECT category_id FROM categories WHERE category_id = ?";
+                query_as::<_, (CategoryId,)>(sql)
+                    .bind(category_id)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?
+                    .ok_or(Error::CategoryNotFound)?;
+                let sql = "INSERT INTO room_categories (room_id, category_id) VALUES (?, ?)";
+                query(sql)
+                    .bind(room_id)
+                    .bind(category_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?;
+            }
+            Ok(())
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn insert_warehouse(
+        &self,
+        code: &str,
+        name: &str,
+        address: &Option<String>,
+        time_zone: &str,
+    ) -> Result<WarehouseId, Error> {
+        let sql = "INSERT INTO warehouses (code, name, address, time_zone) VALUES (?, ?, ?, ?)";
         query(sql)
+            .bind(code)
             .bind(name)
-            .bind(layer)
-            .bind(room_id)
+            .bind(address)
+            .bind(time_zone)
             .execute(&self.pool)
             .await
             .map(|v| v.last_insert_id() as i64)
-            .map_err(|err| match err {
-                sqlx::Error::PoolClosed => Error::Error,
+            .map_err(|e| match e {
+                sqlx::Error::Database(err) if err.is_unique_violation() => Error::WarehouseCodeTaken,
                 _ => Error::Error,
             })
     }
-    async fn delete_shelf(&self, shelf_id: ShelfId) -> Result<(), Error> {
-        let sql = "DELETE FROM shelf WHERE shelf_id = ?";
+    async fn update_warehouse(
+        &self,
+        warehouse_id: WarehouseId,
+        code: Option<&str>,
+        name: Option<&str>,
+        address: Option<&str>,
+        time_zone: Option<&str>,
+    ) -> Result<(), Error> {
+        let sql = "UPDATE warehouses
+SET code      = COALESCE(?, code),
+    name      = COALESCE(?, name),
+    address   = COALESCE(?, address),
+    time_zone = COALESCE(?, time_zone)
+WHERE warehouse_id = ?";
         query(sql)
-            .bind(shelf_id)
+            .bind(code)
+            .bind(name)
+            .bind(address)
+            .bind(time_zone)
+            .bind(warehouse_id)
             .execute(&self.pool)
             .await
-            .map_err(|_| Error::Error)
+            .map_err(|e| match e {
+                sqlx::Error::Database(err) if err.is_unique_violation() => Error::WarehouseCodeTaken,
+                _ => Error::Error,
+            })
             .and_then(|v| {
                 if v.rows_affected() > 0 {
                     Ok(())
                 } else {
-                    Err(Error::ShelfNotFound)
+                    Err(Error::WarehouseNotFound)
                 }
             })
     }
-    async fn delete_shelves(&self, ids: &Vec<ShelfId>) -> Result<BatchDelResult, Error> {
-        todo!()
+    async fn delete_warehouse(&self, warehouse_id: WarehouseId) -> Result<(), Error> {
+        let sql = "DELETE FROM warehouses WHERE warehouse_id = ?";
+        query(sql)
+            .bind(warehouse_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(err) if err.is_foreign_key_violation() => Error::WarehouseNotEmpty,
+                _ => Error::Error,
+            })
+            .and_then(|v| {
+                if v.rows_affected() > 0 {
+                    Ok(())
+                } else {
+                    Err(Error::WarehouseNotFound)
+                }
+            })
+    }
+    async fn get_warehouse_from_id(&self, warehouse_id: WarehouseId) -> Result<Warehouse, Error> {
+        let sql = "SELECT * FROM warehouses WHERE warehouse_id = ?";
+        query_as::<_, Warehouse>(sql)
+            .bind(warehouse_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|_| Error::WarehouseNotFound)
+    }
+    async fn get_warehouses(&self) -> Result<Vec<Warehouse>, Error> {
+        let sql = "SELECT * FROM warehouses ORDER BY code";
+        query_as::<_, Warehouse>(sql)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn insert_shelf_and_get_id(
+        &self,
+        name: &str,
+        layer: i64,
+        room_id: RoomId,
+        layers: i64,
+        positions: i64,
+    ) -> Result<ShelfId, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<ShelfId, Error> = async {
+            let sql = "INSERT INTO shelf (name, layer, room_id, layers, positions) VALUES (?, ?, ?, ?, ?)";
+            let shelf_id = query(sql)
+                .bind(name)
+                .bind(layer)
+                .bind(room_id)
+                .bind(layers)
+                .bind(positions)
+                .execute(&mut *tx)
+                .await
+                .map(|v| v.last_insert_id() as i64)
+                .map_err(|_| Error::Error)?;
+            Self::insert_bins(&mut tx, shelf_id, layers, positions).await?;
+            Ok(shelf_id)
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn archive_shelf(
+        &self,
+        shelf_id: ShelfId,
+        shelf_to: Option<ShelfId>,
+        bin_to: Option<BinId>,
+        meta: &MovementMeta,
+    ) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            Self::archive_shelf_only(&mut tx, shelf_id).await?;
+            let op = MovementOp::new(MovementKind::Transfer, meta);
+            Self::relocate_stock(&mut tx, &op, shelf_id, shelf_to, bin_to).await
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn restore_shelf(&self, shelf_id: ShelfId) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            let sql = "SELECT CAST(COUNT(r.archived_at) AS SIGNED) archived
+FROM shelf sf
+         JOIN rooms r ON r.room_id = sf.room_id
+WHERE sf.shelf_id = ?";
+            let (room_archived,): (i64,) = query_as(sql)
+                .bind(shelf_id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            if room_archived > 0 {
+                return Err(Error::LocationArchived);
+            }
+            let sql = "SELECT shelf_id FROM shelf WHERE shelf_id = ? AND archived_at IS NOT NULL";
+            query_as::<_, (ShelfId,)>(sql)
+                .bind(shelf_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?
+                .ok_or(Error::ShelfNotFound)?;
+            let sql = "UPDATE shelf SET archived_at = NULL WHERE shelf_id = ?";
+            query(sql)
+                .bind(shelf_id)
+                .execute(&mut *tx)
+                .await
+                .map(|_| ())
+                .map_err(|_| Error::Error)
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn delete_shelves(&self, ids: &Vec<ShelfId>, dry_run: bool) -> Result<BatchDelResult, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<BatchDelResult, Error> = async {
+            let mut deleted = BatchDelResult::default();
+            for shelf_id in ids {
+                // a savepoint per id, so a failed one leaves nothing behind
+                let mut savepoint = tx.begin().await.map_err(|_| Error::TransactionError)?;
+                let result = async {
+                    Self::archive_shelf_only(&mut savepoint, *shelf_id).await?;
+                    Self::check_shelf_empty(&mut savepoint, *shelf_id).await?;
+                    Self::relocate_reservations(&mut savepoint, *shelf_id, None).await
+                }
+                .await;
+                match Self::commit_or_rollback(savepoint, result).await {
+                    Ok(()) => deleted.s += 1,
+                    Err(error) => deleted.fail(*shelf_id, database::failure_reason(&error)),
+                }
+            }
+            Ok(deleted)
+        }
+        .await;
+        if dry_run {
+            tx.rollback().await.map_err(|_| Error::TransactionError)?;
+            return result;
+        }
+        Self::commit_or_rollback(tx, result).await
     }
     async fn update_shelf(&self, shelf_id: ShelfId, name: &str, layer: i64, room_id: RoomId) -> Result<(), Error> {
         let sql = "UPDATE shelf SET name = ?, layer = ?, room_id = ? WHERE shelf_id = ?";
@@ -425,6 +733,150 @@ impl Database for Mysql {
                 }
             })
     }
+    async fn update_shelf_layout(&self, shelf_id: ShelfId, layers: i64, positions: i64) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            let sql = "UPDATE shelf SET layers = ?, positions = ? WHERE shelf_id = ?";
+            let updated = query(sql)
+                .bind(layers)
+                .bind(positions)
+                .bind(shelf_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?
+                .rows_affected();
+            if updated == 0 {
+                return Err(Error::ShelfNotFound);
+            }
+            Self::insert_bins(&mut tx, shelf_id, layers, positions).await?;
+            // lots kept at zero do not hold the bins they are in
+            let sql = "DELETE
+FROM stock
+WHERE count = 0
+  AND bin_id IN (SELECT bin_id FROM bins WHERE shelf_id = ? AND (layer > ? OR position > ?))";
+            query(sql)
+                .bind(shelf_id)
+                .bind(layers)
+                .bind(positions)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            let sql = "DELETE FROM bins WHERE shelf_id = ? AND (layer > ? OR position > ?)";
+            query(sql)
+                .bind(shelf_id)
+                .bind(layers)
+                .bind(positions)
+                .execute(&mut *tx)
+                .await
+                .map(|_| ())
+                .map_err(|e| match e {
+                    sqlx::Error::Database(err) if err.is_foreign_key_violation() => Error::BinNotEmpty,
+                    _ => Error::Error,
+                })
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn get_bins_of_shelf(&self, shelf_id: ShelfId) -> Result<Vec<Bin>, Error> {
+        let sql = "SELECT b.bin_id, b.shelf_id, sf.room_id, b.layer, b.position
+FROM bins b
+         JOIN shelf sf ON sf.shelf_id = b.shelf_id
+WHERE b.shelf_id = ?
+ORDER BY b.layer, b.position";
+        query_as::<_, Bin>(sql)
+            .bind(shelf_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn get_bin_at(&self, shelf_id: ShelfId, layer: i64, position: i64) -> Result<Bin, Error> {
+        let sql = "SELECT b.bin_id, b.shelf_id, sf.room_id, b.layer, b.position
+FROM bins b
+         JOIN shelf sf ON sf.shelf_id = b.shelf_id
+WHERE b.shelf_id = ?
+  AND b.layer = ?
+  AND b.position = ?";
+        query_as::<_, Bin>(sql)
+            .bind(shelf_id)
+            .bind(layer)
+            .bind(position)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|_| Error::BinNotFound)
+    }
+    async fn update_shelf_limits(&self, shelf_id: ShelfId, limits: &ShelfLimits) -> Result<(), Error> {
+        let sql = "UPDATE shelf SET max_units = ?, max_weight_g = ?, max_volume_mm3 = ? WHERE shelf_id = ?";
+        query(sql)
+            .bind(limits.max_units)
+            .bind(limits.max_weight_g)
+            .bind(limits.max_volume_mm3)
+            .bind(shelf_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+            .and_then(|v| {
+                if v.rows_affected() > 0 {
+                    Ok(())
+                } else {
+                    Err(Error::ShelfNotFound)
+                }
+            })
+    }
+    async fn get_shelf_capacity(&self, shelf_id: ShelfId) -> Result<ShelfCapacity, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        Self::shelf_capacity(&mut conn, shelf_id).await
+    }
+    async fn get_shelf_capacities(&self, room_id: Option<RoomId>) -> Result<Vec<ShelfCapacity>, Error> {
+        let sql = format!(
+            "{SHELF_CAPACITY} WHERE (? IS NULL OR t.room_id = ?)
+  AND t.shelf_id IN (SELECT shelf_id FROM shelf WHERE archived_at IS NULL)
+ORDER BY t.shelf_id"
+        );
+        query_as::<_, ShelfCapacity>(&sql)
+            .bind(room_id)
+            .bind(room_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn get_putaway_candidates(&self, item_id: ItemId, room_id: Option<RoomId>) -> Result<Vec<PutawaySuggestion>, Error> {
+        // shelves frozen by a stocktake take no deposits, and a room restricted to categories only
+        // takes the items in them
+        let sql = format!(
+            "SELECT c.*,
+       sf.layer,
+       CAST(COALESCE((SELECT SUM(si.count)
+                      FROM stock si
+                      WHERE si.item_id = ?
+                        AND si.shelf_id = c.shelf_id
+                        AND si.count > 0), 0) AS SIGNED) on_shelf
+FROM ({SHELF_CAPACITY}) c
+         JOIN shelf sf ON sf.shelf_id = c.shelf_id
+WHERE (? IS NULL OR c.room_id = ?)
+  AND sf.archived_at IS NULL
+  AND NOT EXISTS (SELECT 1
+                  FROM stocktake_shelves ss
+                           JOIN stocktakes st ON st.stocktake_id = ss.stocktake_id
+                  WHERE ss.shelf_id = c.shelf_id
+                    AND st.status = 'open'
+                    AND st.frozen)
+  AND (NOT EXISTS (SELECT 1 FROM room_categories rc WHERE rc.room_id = c.room_id)
+    OR EXISTS (SELECT 1
+               FROM room_categories rc
+                        JOIN items it ON it.category_id = rc.category_id
+               WHERE rc.room_id = c.room_id
+                 AND it.item_id = ?))"
+        );
+        query_as::<_, PutawaySuggestion>(&sql)
+            .bind(item_id)
+            .bind(room_id)
+            .bind(room_id)
+            .bind(item_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
     async fn get_shelf_from_id(&self, shelf_id: ShelfId) -> Result<Shelf, Error> {
         let sql = "SELECT * FROM shelf WHERE shelf_id = ?";
         query_as::<_, Shelf>(sql)
@@ -434,7 +886,7 @@ impl Database for Mysql {
             .map_err(|_| Error::ShelfNotFound)
     }
     async fn get_shelves(&self, offset: u64, limit: u8, sort: &Sorting) -> Result<Listing<Shelf>, Error> {
-        let sql = "SELECT COUNT(*) as count FROM shelf";
+        let sql = "SELECT COUNT(*) as count FROM shelf WHERE archived_at IS NULL";
         let count_result: Result<i64, Error> = query_as(sql)
             .fetch_one(&self.pool)
             .await
@@ -447,7 +899,7 @@ impl Database for Mysql {
             Sorting::IdAsc => "shelf_id ASC".to_string(),
             Sorting::IdDesc => "shelf_id DESC".to_string(),
         };
-        let sql = format!("SELECT * FROM shelf ORDER BY {sort_query} LIMIT ?, ?");
+        let sql = format!("SELECT * FROM shelf WHERE archived_at IS NULL ORDER BY {sort_query} LIMIT ?, ?");
         let shelves: Vec<Shelf> = query_as::<_, Shelf>(&sql)
             .bind(i64::saturating_add_unsigned(0, offset))
             .bind(limit)
@@ -466,7 +918,7 @@ impl Database for Mysql {
         sort: &Sorting,
         room_id: RoomId,
     ) -> Result<Listing<Shelf>, Error> {
-        let sql = "SELECT COUNT(*) as count FROM shelf WHERE room_id = ?";
+        let sql = "SELECT COUNT(*) as count FROM shelf WHERE room_id = ? AND archived_at IS NULL";
         let count_result: Result<i64, Error> = query_as(sql)
             .bind(room_id)
             .fetch_one(&self.pool)
@@ -480,7 +932,7 @@ impl Database for Mysql {
             Sorting::IdAsc => "shelf_id ASC".to_string(),
             Sorting::IdDesc => "shelf_id DESC".to_string(),
         };
-        let sql = format!("SELECT * FROM shelf WHERE room_id = ? ORDER BY {sort_query} LIMIT ?, ?");
+        let sql = format!("SELECT * FROM shelf WHERE room_id = ? AND archived_at IS NULL ORDER BY {sort_query} LIMIT ?, ?");
         let shelves: Vec<Shelf> = query_as::<_, Shelf>(&sql)
             .bind(room_id)
             .bind(i64::saturating_add_unsigned(0, offset))
@@ -494,7 +946,7 @@ impl Database for Mysql {
         })
     }
     async fn get_all_shelves(&self) -> Result<Vec<Shelf>, Error> {
-        let sql = "SELECT * FROM shelf";
+        let sql = "SELECT * FROM shelf WHERE archived_at IS NULL";
         let shelves: Vec<Shelf> = query_as::<_, Shelf>(&sql)
             .fetch_all(&self.pool)
             .await
@@ -502,7 +954,7 @@ impl Database for Mysql {
         Ok(shelves)
     }
     async fn get_all_shelves_in_room(&self, room_id: RoomId) -> Result<Vec<Shelf>, Error> {
-        let sql = "SELECT * FROM shelf WHERE room_id = ?";
+        let sql = "SELECT * FROM shelf WHERE room_id = ? AND archived_at IS NULL";
         let shelves: Vec<Shelf> = query_as::<_, Shelf>(&sql)
             .bind(room_id)
             .fetch_all(&self.pool)
@@ -510,44 +962,75 @@ impl Database for Mysql {
             .map_err(|_| Error::Error)?;
         Ok(shelves)
     }
-    async fn insert_item_and_get_id(&self, name: &str, sn: &str) -> Result<ItemId, Error> {
-        let sql = "INSERT INTO items (name, sn) VALUES (?, ?)";
+    async fn get_archived_shelves(&self, room_id: Option<RoomId>) -> Result<Vec<Shelf>, Error> {
+        let sql = "SELECT * FROM shelf WHERE archived_at IS NOT NULL AND (? IS NULL OR room_id = ?) ORDER BY shelf_id";
+        query_as::<_, Shelf>(sql)
+            .bind(room_id)
+            .bind(room_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn insert_item_and_get_id(&self, name: &str, sn: &str, serialized: bool, base_unit: &str) -> Result<ItemId, Error> {
+        let sql = "INSERT INTO items (name, sn, serialized, base_unit) VALUES (?, ?, ?, ?)";
         query(sql)
             .bind(name)
             .bind(sn)
+            .bind(serialized)
+            .bind(base_unit)
             .execute(&self.pool)
             .await
             .map(|v| v.last_insert_id() as i64)
             .map_err(|_| Error::Error)
     }
-    async fn insert_item_with_desc_and_get_id(&self, name: &str, desc: &str, sn: &str) -> Result<ItemId, Error> {
-        let sql = "INSERT INTO items (name, sn, description) VALUES (?, ?, ?)";
+    async fn insert_item_with_desc_and_get_id(
+        &self,
+        name: &str,
+        desc: &str,
+        sn: &str,
+        serialized: bool,
+        base_unit: &str,
+    ) -> Result<ItemId, Error> {
+        let sql = "INSERT INTO items (name, sn, description, serialized, base_unit) VALUES (?, ?, ?, ?, ?)";
         query(sql)
             .bind(name)
             .bind(sn)
             .bind(desc)
+            .bind(serialized)
+            .bind(base_unit)
             .execute(&self.pool)
             .await
             .map(|v| v.last_insert_id() as i64)
             .map_err(|_| Error::Error)
     }
     async fn delete_item(&self, item_id: ItemId) -> Result<(), Error> {
-        let sql = "DELETE FROM items WHERE item_id = ?";
-        query(sql)
-            .bind(item_id)
-            .execute(&self.pool)
-            .await
-            .map_err(|_| Error::Error)
-            .and_then(|v| {
-                if v.rows_affected() > 0 {
-                    Ok(())
-                } else {
-                    Err(Error::ItemNotFound)
-                }
-            })
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result = Self::delete_unused_item(&mut tx, item_id).await;
+        Self::commit_or_rollback(tx, result).await
     }
-    async fn delete_items(&self, ids: &Vec<ItemId>) -> Result<BatchDelResult, Error> {
-        todo!()
+    async fn delete_items(&self, ids: &Vec<ItemId>, dry_run: bool) -> Result<BatchDelResult, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<BatchDelResult, Error> = async {
+            let mut deleted = BatchDelResult::default();
+            for item_id in ids {
+                // a savepoint per id, so a failed one leaves nothing behind
+                let mut savepoint = tx.begin().await.map_err(|_| Error::TransactionError)?;
+                let result = Self::delete_unused_item(&mut savepoint, *item_id).await;
+                match Self::commit_or_rollback(savepoint, result).await {
+                    Ok(()) => deleted.s += 1,
+                    Err(error) => deleted.fail(*item_id, database::failure_reason(&error)),
+                }
+            }
+            Ok(deleted)
+        }
+        .await;
+        if dry_run {
+            tx.rollback().await.map_err(|_| Error::TransactionError)?;
+            return result;
+        }
+        Self::commit_or_rollback(tx, result).await
     }
     async fn update_item(&self, item_id: ItemId, name: &str, desc: &Option<String>, sn: &str) -> Result<(), Error> {
         let sql = "UPDATE items SET name = ?, description = ?, sn = ? WHERE item_id = ?";
@@ -623,9 +1106,23 @@ impl Database for Mysql {
             .await
             .map_err(|_| Error::ItemNotFound)
     }
-    async fn get_items(&self, offset: u64, limit: u8, sort: &Sorting) -> Result<Listing<Item>, Error> {
-        let sql = "SELECT COUNT(*) as count FROM items";
-        let count_result: Result<i64, Error> = query_as(sql)
+    async fn get_items(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        abc_class: Option<AbcClass>,
+        xyz_class: Option<XyzClass>,
+    ) -> Result<Listing<Item>, Error> {
+        let abc_class = abc_class.as_ref().map(AbcClass::as_str);
+        let xyz_class = xyz_class.as_ref().map(XyzClass::as_str);
+        let filter = "WHERE (? IS NULL OR abc_class = ?) AND (? IS NULL OR xyz_class = ?)";
+        let sql = format!("SELECT COUNT(*) as count FROM items {filter}");
+        let count_result: Result<i64, Error> = query_as(&sql)
+            .bind(abc_class)
+            .bind(abc_class)
+            .bind(xyz_class)
+            .bind(xyz_class)
             .fetch_one(&self.pool)
             .await
             .map(|(v,)| v)
@@ -637,8 +1134,12 @@ impl Database for Mysql {
             Sorting::IdAsc => "item_id ASC".to_string(),
             Sorting::IdDesc => "item_id DESC".to_string(),
         };
-        let sql = format!("SELECT * FROM items ORDER BY {sort_query} LIMIT ?, ?");
+        let sql = format!("SELECT * FROM items {filter} ORDER BY {sort_query} LIMIT ?, ?");
         let items: Vec<Item> = query_as::<_, Item>(&sql)
+            .bind(abc_class)
+            .bind(abc_class)
+            .bind(xyz_class)
+            .bind(xyz_class)
             .bind(i64::saturating_add_unsigned(0, offset))
             .bind(limit)
             .fetch_all(&self.pool)
@@ -649,28 +1150,153 @@ impl Database for Mysql {
             data: items,
         })
     }
-    async fn get_all_items(&self) -> Result<Vec<Item>, Error> {
-        let sql = "SELECT * FROM items";
-        let items: Vec<Item> = query_as::<_, Item>(&sql)
+    async fn get_all_items(&self, abc_class: Option<AbcClass>, xyz_class: Option<XyzClass>) -> Result<Vec<Item>, Error> {
+        let sql = "SELECT * FROM items WHERE (? IS NULL OR abc_class = ?) AND (? IS NULL OR xyz_class = ?)";
+        let items: Vec<Item> = query_as::<_, Item>(sql)
+            .bind(abc_class.as_ref().map(AbcClass::as_str))
+            .bind(abc_class.as_ref().map(AbcClass::as_str))
+            .bind(xyz_class.as_ref().map(XyzClass::as_str))
+            .bind(xyz_class.as_ref().map(XyzClass::as_str))
             .fetch_all(&self.pool)
             .await
             .map_err(|_| Error::Error)?;
         Ok(items)
     }
-    async fn get_stocks_on_shelves(&self, offset: u64, limit: u8, sort: &Sorting) -> Result<Listing<ItemOnShelf>, Error> {
-        todo!()
+    async fn get_item_units(&self, item_id: ItemId) -> Result<Vec<ItemUnit>, Error> {
+        let sql = "SELECT item_id, unit, factor FROM item_units WHERE item_id = ? ORDER BY factor, unit";
+        query_as::<_, ItemUnit>(sql)
+            .bind(item_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
     }
-    async fn get_stocks_on_shelf(
+    async fn upsert_item_unit(&self, item_id: ItemId, unit: &str, factor: i64) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            let sql = "UPDATE item_units SET factor = ? WHERE item_id = ? AND unit = ?";
+            let updated = query(sql)
+                .bind(factor)
+                .bind(item_id)
+                .bind(unit)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?
+                .rows_affected();
+            if updated > 0 {
+                return Ok(());
+            }
+            let sql = "INSERT INTO item_units (item_id, unit, factor) VALUES (?, ?, ?)";
+            query(sql)
+                .bind(item_id)
+                .bind(unit)
+                .bind(factor)
+                .execute(&mut *tx)
+                .await
+                .map(|_| ())
+                .map_err(|_| Error::ItemNotFound)
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn delete_item_unit(&self, item_id: ItemId, unit: &str) -> Result<(), Error> {
+        let sql = "DELETE FROM item_units WHERE item_id = ? AND unit = ?";
+        query(sql)
+            .bind(item_id)
+            .bind(unit)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+            .and_then(|v| {
+                if v.rows_affected() > 0 {
+                    Ok(())
+                } else {
+                    Err(Error::UnitNotFound)
+                }
+            })
+    }
+    async fn get_unit_factor(&self, item_id: ItemId, unit: &str) -> Result<i64, Error> {
+        let sql = "SELECT factor FROM item_units WHERE item_id = ? AND unit = ?
+UNION ALL
+SELECT 1 FROM items WHERE item_id = ? AND base_unit = ?";
+        query_as::<_, (i64,)>(sql)
+            .bind(item_id)
+            .bind(unit)
+            .bind(item_id)
+            .bind(unit)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?
+            .map(|(v,)| v)
+            .ok_or(Error::UnitNotFound)
+    }
+    async fn get_unit_factors(&self, unit: &str) -> Result<Vec<ItemUnit>, Error> {
+        let sql = "SELECT item_id, unit, factor FROM item_units WHERE unit = ?
+UNION ALL
+SELECT item_id, base_unit unit, 1 factor FROM items WHERE base_unit = ?";
+        query_as::<_, ItemUnit>(sql)
+            .bind(unit)
+            .bind(unit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn get_stocks_on_shelves(
         &self,
         offset: u64,
         limit: u8,
         sort: &Sorting,
-        shelf_id: ShelfId,
+        warehouse_id: Option<WarehouseId>,
     ) -> Result<Listing<ItemOnShelf>, Error> {
-        let sql = "SELECT COUNT(*) as count
+        let sql = "SELECT COUNT(*) count
+FROM (SELECT si.item_id
+      FROM stock si
+               JOIN shelf sf ON si.shelf_id = sf.shelf_id
+      WHERE (? IS NULL OR sf.room_id IN (SELECT wr.room_id FROM rooms wr WHERE wr.warehouse_id = ?))
+      GROUP BY si.item_id, si.shelf_id) t";
+        let count_result: Result<i64, Error> = query_as(sql)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "t.item_name ASC, t.shelf_name ASC".to_string(),
+            Sorting::NameDesc => "t.item_name DESC, t.shelf_name DESC".to_string(),
+            Sorting::IdAsc => "t.item_id ASC, t.shelf_id ASC".to_string(),
+            Sorting::IdDesc => "t.item_id DESC, t.shelf_id DESC".to_string(),
+        };
+        let sql = format!(
+            "SELECT t.*, t.status_available - t.reserved available
+FROM ({STOCK_ON_SHELF}
+      WHERE (? IS NULL OR sf.room_id IN (SELECT wr.room_id FROM rooms wr WHERE wr.warehouse_id = ?))
+      GROUP BY it.item_id, sf.shelf_id) t
+ORDER BY {sort_query} LIMIT ?, ?"
+        );
+        let items: Vec<ItemOnShelf> = query_as::<_, ItemOnShelf>(&sql)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: items,
+        })
+    }
+    async fn get_stocks_on_shelf(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        shelf_id: ShelfId,
+    ) -> Result<Listing<ItemOnShelf>, Error> {
+        let sql = "SELECT COUNT(DISTINCT si.item_id) count
 FROM stock si
-         JOIN items it ON it.item_id = si.item_id
-         JOIN shelf sf ON si.shelf_id = sf.shelf_id
 WHERE si.shelf_id = ?";
         let count_result: Result<i64, Error> = query_as(sql)
             .bind(shelf_id)
@@ -679,16 +1305,19 @@ WHERE si.shelf_id = ?";
             .map(|(v,)| v)
             .map_err(|_| Error::Error);
         let count = count_result?;
-        let sql = "SELECT si.item_id  item_id,
-       it.name     item_name,
-       si.shelf_id shelf_id,
-       sf.name     shelf_name,
-       si.count    count,
-       it.sn       sn
-FROM stock si
-         JOIN items it ON it.item_id = si.item_id
-         JOIN shelf sf ON si.shelf_id = sf.shelf_id
-WHERE si.shelf_id = ? LIMIT ?, ?";
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "t.item_name ASC".to_string(),
+            Sorting::NameDesc => "t.item_name DESC".to_string(),
+            Sorting::IdAsc => "t.item_id ASC".to_string(),
+            Sorting::IdDesc => "t.item_id DESC".to_string(),
+        };
+        let sql = format!(
+            "SELECT t.*, t.status_available - t.reserved available
+FROM ({STOCK_ON_SHELF}
+      WHERE sf.shelf_id = ?
+      GROUP BY it.item_id, sf.shelf_id) t
+ORDER BY {sort_query} LIMIT ?, ?"
+        );
         let items: Vec<ItemOnShelf> = query_as::<_, ItemOnShelf>(&sql)
             .bind(shelf_id)
             .bind(i64::saturating_add_unsigned(0, offset))
@@ -701,8 +1330,52 @@ WHERE si.shelf_id = ? LIMIT ?, ?";
             data: items,
         })
     }
-    async fn get_stocks_in_rooms(&self, offset: u64, limit: u8, sort: &Sorting) -> Result<Listing<ItemInRoom>, Error> {
-        todo!()
+    async fn get_stocks_in_rooms(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<ItemInRoom>, Error> {
+        let sql = "SELECT COUNT(*) count
+FROM (SELECT sf.room_id
+      FROM stock si
+               JOIN shelf sf ON si.shelf_id = sf.shelf_id
+      WHERE (? IS NULL OR sf.room_id IN (SELECT wr.room_id FROM rooms wr WHERE wr.warehouse_id = ?))
+      GROUP BY sf.room_id, si.item_id) t";
+        let count_result: Result<i64, Error> = query_as(sql)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "t.item_name ASC, t.room_name ASC".to_string(),
+            Sorting::NameDesc => "t.item_name DESC, t.room_name DESC".to_string(),
+            Sorting::IdAsc => "t.item_id ASC, t.room_id ASC".to_string(),
+            Sorting::IdDesc => "t.item_id DESC, t.room_id DESC".to_string(),
+        };
+        let sql = format!(
+            "SELECT t.*, t.status_available - t.reserved available
+FROM ({STOCK_IN_ROOM}
+      WHERE (? IS NULL OR r.warehouse_id = ?)
+      GROUP BY r.room_id, it.item_id) t
+ORDER BY {sort_query} LIMIT ?, ?"
+        );
+        let items: Vec<ItemInRoom> = query_as::<_, ItemInRoom>(&sql)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: items,
+        })
     }
     async fn get_stocks_in_room(
         &self,
@@ -712,266 +1385,3646 @@ WHERE si.shelf_id = ? LIMIT ?, ?";
         room_id: RoomId,
     ) -> Result<Listing<ItemInRoom>, Error> {
         let sql = "SELECT COUNT(*) count
-FROM (SELECT SUM(si.count) inner_count
+FROM (SELECT si.item_id
       FROM stock si
-               JOIN items it ON it.item_id = si.item_id
                JOIN shelf sf ON si.shelf_id = sf.shelf_id
-               JOIN rooms r ON sf.room_id = r.room_id
+      WHERE sf.room_id = ?
+      GROUP BY si.item_id) t";
+        let count_result: Result<i64, Error> = query_as(sql)
+            .bind(room_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "t.item_name ASC".to_string(),
+            Sorting::NameDesc => "t.item_name DESC".to_string(),
+            Sorting::IdAsc => "t.item_id ASC".to_string(),
+            Sorting::IdDesc => "t.item_id DESC".to_string(),
+        };
+        let sql = format!(
+            "SELECT t.*, t.status_available - t.reserved available
+FROM ({STOCK_IN_ROOM}
       WHERE r.room_id = ?
-      GROUP BY it.item_id)";
+      GROUP BY r.room_id, it.item_id) t
+ORDER BY {sort_query} LIMIT ?, ?"
+        );
+        let items: Vec<ItemInRoom> = query_as::<_, ItemInRoom>(&sql)
+            .bind(room_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| database::Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: items,
+        })
+    }
+    async fn get_stocks_in_warehouses(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<ItemInWarehouse>, Error> {
+        let sql = "SELECT COUNT(*) count
+FROM (SELECT r.warehouse_id
+      FROM stock si
+               JOIN shelf sf ON si.shelf_id = sf.shelf_id
+               JOIN rooms r ON sf.room_id = r.room_id
+      WHERE (? IS NULL OR r.warehouse_id = ?)
+      GROUP BY r.warehouse_id, si.item_id) t";
         let count_result: Result<i64, Error> = query_as(sql)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "t.item_name ASC, t.warehouse_code ASC".to_string(),
+            Sorting::NameDesc => "t.item_name DESC, t.warehouse_code DESC".to_string(),
+            Sorting::IdAsc => "t.item_id ASC, t.warehouse_id ASC".to_string(),
+            Sorting::IdDesc => "t.item_id DESC, t.warehouse_id DESC".to_string(),
+        };
+        let sql = format!(
+            "SELECT t.*, t.status_available - t.reserved available
+FROM ({STOCK_IN_WAREHOUSE}
+      WHERE (? IS NULL OR w.warehouse_id = ?)
+      GROUP BY w.warehouse_id, it.item_id) t
+ORDER BY {sort_query} LIMIT ?, ?"
+        );
+        let items: Vec<ItemInWarehouse> = query_as::<_, ItemInWarehouse>(&sql)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: items,
+        })
+    }
+    async fn get_lots_expiring(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        until: Date,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<LotInRoom>, Error> {
+        let filter = "WHERE si.expiry_date IS NOT NULL
+  AND si.expiry_date <= ?
+  AND (? IS NULL OR sf.room_id = ?)
+  AND (? IS NULL OR sf.room_id IN (SELECT wr.room_id FROM rooms wr WHERE wr.warehouse_id = ?))";
+        let sql = format!(
+            "SELECT COUNT(*) count
+FROM (SELECT sf.room_id
+      FROM stock si
+               JOIN shelf sf ON si.shelf_id = sf.shelf_id
+      {filter}
+      GROUP BY sf.room_id, si.item_id, si.lot_no, si.expiry_date) t"
+        );
+        let count_result: Result<i64, Error> = query_as(&sql)
+            .bind(until)
+            .bind(room_id)
+            .bind(room_id)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
             .fetch_one(&self.pool)
             .await
             .map(|(v,)| v)
             .map_err(|_| Error::Error);
         let count = count_result?;
-        let sql = "SELECT it.item_id, it.name item_name,SUM(si.count) count, r.room_id, r.name room_name
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "it.name ASC, r.name ASC".to_string(),
+            Sorting::NameDesc => "it.name DESC, r.name DESC".to_string(),
+            Sorting::IdAsc => "it.item_id ASC, r.room_id ASC".to_string(),
+            Sorting::IdDesc => "it.item_id DESC, r.room_id DESC".to_string(),
+        };
+        let sql = format!(
+            "SELECT it.item_id,
+       it.name                 item_name,
+       r.room_id,
+       r.name                  room_name,
+       NULLIF(si.lot_no, '')   lot_no,
+       si.expiry_date,
+       CAST(SUM(si.count) AS SIGNED) count
 FROM stock si
          JOIN items it ON it.item_id = si.item_id
          JOIN shelf sf ON si.shelf_id = sf.shelf_id
          JOIN rooms r ON sf.room_id = r.room_id
-WHERE r.room_id = ? GROUP BY it.item_id LIMIT ?, ?";
-        let items: Vec<ItemInRoom> = query_as::<_, ItemInRoom>(&sql)
+{filter}
+GROUP BY r.room_id, it.item_id, si.lot_no, si.expiry_date
+ORDER BY si.expiry_date ASC, {sort_query} LIMIT ?, ?"
+        );
+        let lots: Vec<LotInRoom> = query_as::<_, LotInRoom>(&sql)
+            .bind(until)
+            .bind(room_id)
             .bind(room_id)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
             .bind(i64::saturating_add_unsigned(0, offset))
             .bind(limit)
             .fetch_all(&self.pool)
             .await
-            .map_err(|_| database::Error::Error)?;
+            .map_err(|_| Error::Error)?;
         Ok(Listing {
             total: u64::try_from(count).expect("variable `count` is larger than u32"),
-            data: items,
+            data: lots,
         })
     }
-    async fn transfer_items(&self, item_id: ItemId, count: i64, shelf_from: ShelfId, shelf_to: ShelfId) -> Result<(), Error> {
+    async fn transfer_items(
+        &self,
+        item_id: ItemId,
+        count: i64,
+        shelf_from: ShelfId,
+        shelf_to: ShelfId,
+        bin_to: Option<BinId>,
+        pick: &StockPick,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, Error> {
         if count <= 0 {
             return Err(Error::CountMustBePositive);
         }
         let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
         let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
-        let sql = "SELECT * FROM stock WHERE item_id = ? and shelf_id = ?";
-        let x_from = query_as::<_, ItemXShelf>(sql)
-            .bind(item_id)
-            .bind(shelf_from)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(|_| Error::InsufficientItem)?;
-        let new_from_count = x_from.count - count;
-        if new_from_count <= 0 {
-            drop(tx.rollback().await);
-            return Err(Error::InsufficientItem);
-        }
-        let update_sql = "UPDATE stock SET count = ? WHERE item_id = ? and shelf_id = ?";
-        let update_res = query(update_sql)
-            .bind(new_from_count)
-            .bind(item_id)
-            .bind(shelf_from)
-            .execute(&mut *tx)
-            .await
-            .map_err(|_| Error::Error)
-            .and_then(|v| if v.rows_affected() > 0 { Ok(()) } else { Err(Error::Error) });
-        if update_res.is_err() {
-            drop(tx.rollback().await);
-            return update_res;
-        }
-        let x_to_res = query_as::<_, ItemXShelf>(update_sql)
-            .bind(item_id)
-            .bind(shelf_to)
-            .fetch_one(&mut *tx)
-            .await;
-        if let Ok(x_to) = x_to_res {
-            let update_res = query(update_sql)
-                .bind(x_to.count + count)
-                .bind(item_id)
-                .bind(shelf_to)
-                .execute(&mut *tx)
-                .await
-                .map_err(|_| Error::Error)
-                .and_then(|v| if v.rows_affected() > 0 { Ok(()) } else { Err(Error::Error) });
-            match update_res {
-                Ok(_) => {
-                    drop(tx.commit().await);
-                    Ok(())
-                }
-                Err(e) => {
-                    drop(tx.rollback().await);
-                    Err(e)
-                }
-            }
-        } else {
-            let sql = "INSERT INTO stock (count, item_id, shelf_id) VALUES (?, ?, ?)";
-            let insert_result = query(sql)
-                .bind(count)
-                .bind(item_id)
-                .bind(shelf_to)
-                .execute(&mut *tx)
-                .await
-                .map_err(|_| Error::Error)
-                .and_then(|v| if v.rows_affected() > 0 { Ok(()) } else { Err(Error::Error) });
-            match insert_result {
-                Ok(_) => {
-                    drop(tx.commit().await);
-                    Ok(())
-                }
-                Err(e) => {
-                    drop(tx.rollback().await);
-                    Err(e)
-                }
-            }
-        }
+        let op = MovementOp::new(MovementKind::Transfer, meta);
+        let line = ItemXShelf::picked(item_id, shelf_from, count, pick);
+        let result = Self::transfer_line(&mut tx, &op, &line, shelf_to, bin_to).await;
+        Self::commit_or_rollback(tx, result).await
     }
-    async fn deposit_items(&self, item_id: ItemId, count: i64, shelf_id: ShelfId) -> Result<(), Error> {
+    async fn deposit_items(
+        &self,
+        item_id: ItemId,
+        count: i64,
+        shelf_id: ShelfId,
+        bin_id: Option<BinId>,
+        lot: &Lot,
+        status: StockStatus,
+        serials: &[String],
+        cost: Option<&Cost>,
+        meta: &MovementMeta,
+    ) -> Result<ItemXShelf, Error> {
         if count <= 0 {
             return Err(Error::CountMustBePositive);
         }
         let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
         let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
-        let sql = "SELECT * FROM stock WHERE item_id = ? and shelf_id = ?";
-        let x_res = query_as::<_, ItemXShelf>(sql)
-            .bind(item_id)
-            .bind(shelf_id)
-            .fetch_one(&mut *tx)
-            .await;
-        let new_count;
-        let sql = if let Ok(x) = x_res {
-            new_count = x.count + count;
-            "UPDATE stock SET count = ? WHERE item_id = ? and shelf_id = ?"
-        } else {
-            new_count = count;
-            "INSERT INTO stock (count, item_id, shelf_id) VALUES (?, ?, ?)"
+        let op = MovementOp::new(MovementKind::Deposit, meta);
+        let line = ItemXShelf {
+            status,
+            bin_id,
+            serials: serials.to_vec(),
+            ..ItemXShelf::new(item_id, shelf_id, count, lot)
         };
-        let upsert_result = query(sql)
-            .bind(new_count)
-            .bind(item_id)
-            .bind(shelf_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|_| Error::Error)
-            .and_then(|v| if v.rows_affected() > 0 { Ok(()) } else { Err(Error::Error) });
-        match upsert_result {
-            Ok(_) => {
-                drop(tx.commit().await);
-                Ok(())
-            }
-            Err(e) => {
-                drop(tx.rollback().await);
-                Err(e)
-            }
-        }
+        let result = Self::deposit_line(&mut tx, &op, &line, cost).await;
+        Self::commit_or_rollback(tx, result).await
     }
-    async fn withdraw_items(&self, item_id: ItemId, count: i64, shelf_id: ShelfId) -> Result<(), Error> {
+    async fn withdraw_items(
+        &self,
+        item_id: ItemId,
+        count: i64,
+        shelf_id: ShelfId,
+        pick: &StockPick,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, Error> {
         if count <= 0 {
             return Err(Error::CountMustBePositive);
         }
         let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
         let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
-        let sql = "SELECT * FROM stock WHERE item_id = ? and shelf_id = ?";
-        let x = query_as::<_, ItemXShelf>(sql)
-            .bind(item_id)
-            .bind(shelf_id)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(|_| Error::InsufficientItem)?;
-        let new_count = x.count - count;
-        if new_count <= 0 {
-            drop(tx.rollback().await);
-            return Err(Error::InsufficientItem);
+        let op = MovementOp::new(MovementKind::Withdraw, meta);
+        let line = ItemXShelf::picked(item_id, shelf_id, count, pick);
+        let result = Self::withdraw_line(&mut tx, &op, &line).await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn apply_stock_batch(
+        &self,
+        lines: &[BatchLine],
+        mode: BatchMode,
+        meta: &MovementMeta,
+    ) -> Result<Vec<Result<Vec<ItemXShelf>, Error>>, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        // the movements of every line share a correlation id, so the batch can be traced back as one
+        let correlation_id = Uuid::new_v4().to_string();
+        let mut results = Vec::with_capacity(lines.len());
+        for x in lines {
+            let line_meta = MovementMeta {
+                reason: x.reason.clone().or_else(|| meta.reason.clone()),
+                expected_count: x.expected_count,
+                ..meta.clone()
+            };
+            let op = MovementOp {
+                correlation_id: correlation_id.clone(),
+                kind: x.op.kind(),
+                meta: &line_meta,
+            };
+            // a savepoint per line, so a failed line leaves nothing behind
+            let mut savepoint = tx.begin().await.map_err(|_| Error::TransactionError)?;
+            let result = Self::apply_line(&mut savepoint, &op, x).await;
+            results.push(Self::commit_or_rollback(savepoint, result).await);
         }
-        let sql = "UPDATE stock SET count = ? WHERE item_id = ? and shelf_id = ?";
-        let update_result = query(sql)
-            .bind(new_count)
-            .bind(item_id)
-            .bind(shelf_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|_| Error::Error)
-            .and_then(|v| if v.rows_affected() > 0 { Ok(()) } else { Err(Error::Error) });
-        match update_result {
-            Ok(_) => {
-                drop(tx.commit().await);
-                Ok(())
-            }
-            Err(e) => {
-                drop(tx.rollback().await);
-                Err(e)
-            }
+        if mode == BatchMode::AllOrNothing && results.iter().any(Result::is_err) {
+            tx.rollback().await.map_err(|_| Error::TransactionError)?;
+        } else {
+            tx.commit().await.map_err(|_| Error::TransactionError)?;
         }
+        Ok(results)
     }
 
-    async fn convert_items(&self, from: Vec<ItemXShelf>, into: Vec<ItemXShelf>) -> Result<(), Error> {
+    async fn convert_items(
+        &self,
+        from: Vec<ItemXShelf>,
+        into: Vec<ItemXShelf>,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, Error> {
         // todo, insufficient item must be more clear
+        if from.iter().chain(into.iter()).any(|x| x.count <= 0) {
+            return Err(Error::CountMustBePositive);
+        }
         let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
         let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
-        let select_sql = "SELECT * FROM stock WHERE item_id = ? and shelf_id = ?";
-        let update_sql = "UPDATE stock SET count = ? WHERE item_id = ? and shelf_id = ?";
-        let insert_sql = "INSERT INTO stock (count, item_id, shelf_id) VALUES (?, ?, ?)";
-        for x_from in from {
-            if x_from.count <= 0 {
-                drop(tx.rollback().await);
-                return Err(Error::CountMustBePositive);
+        let op = MovementOp::new(MovementKind::Convert, meta);
+        let result: Result<Vec<ItemXShelf>, Error> = async {
+            let mut levels = Vec::with_capacity(from.len() + into.len());
+            let mut consumed: Option<(String, i64)> = None;
+            for x_from in &from {
+                let policy = Self::stock_policy(&mut tx, x_from.shelf_id).await?;
+                for draw in Self::take_stock(&mut tx, x_from, &policy).await? {
+                    let movement_id = Self::insert_movement(&mut tx, &op, Some(&draw.left), None, draw.taken).await?;
+                    Self::move_units(&mut tx, movement_id, x_from.item_id, &draw.serials, None).await?;
+                    levels.push(draw.left);
+                }
+                if x_from.status == StockStatus::Available {
+                    Self::consume_reservations(&mut tx, &op, x_from.item_id, x_from.shelf_id, x_from.count).await?;
+                }
+                Self::check_reservations(&mut tx, x_from.item_id, x_from.shelf_id, meta.user_id).await?;
+                if let Some((currency, value)) = Self::consume_cost(&mut tx, &op, x_from.item_id, x_from.count).await? {
+                    consumed = match consumed {
+                        Some((c, v)) if c == currency => Some((c, v + value)),
+                        Some(_) => return Err(Error::CurrencyMismatch),
+                        None => Some((currency, value)),
+                    };
+                }
             }
-            let x_res = query_as::<_, ItemXShelf>(select_sql)
-                .bind(x_from.item_id)
-                .bind(x_from.shelf_id)
-                .fetch_one(&mut *tx)
-                .await
-                .map_err(|_| Error::InsufficientItem);
-            if let Err(err) = x_res {
-                drop(tx.rollback().await);
-                return Err(err);
+            // the cost consumed is spread over the lines produced by count, the last one taking what rounding left
+            let total: i64 = into.iter().map(|x| x.count).sum();
+            let mut left = consumed.as_ref().map_or(0, |(_, v)| *v);
+            for (i, x_into) in into.iter().enumerate() {
+                database::check_serials(Self::is_serialized(&mut tx, x_into.item_id).await?, x_into)?;
+                let stock = Self::put_stock(&mut tx, x_into).await?;
+                let movement_id = Self::insert_movement(&mut tx, &op, None, Some(&stock), x_into.count).await?;
+                Self::register_units(&mut tx, movement_id, x_into, stock.bin_id).await?;
+                Self::check_capacity(&mut tx, x_into.shelf_id).await?;
+                if let Some((currency, value)) = &consumed {
+                    let share = if i + 1 == into.len() {
+                        left
+                    } else {
+                        database::cost_share(*value, x_into.count, total)
+                    };
+                    left -= share;
+                    Self::add_cost(&mut tx, &op, x_into.item_id, x_into.count, currency, share).await?;
+                }
+                levels.push(stock);
             }
-            let x = x_res.unwrap();
-            let new_count = x.count - x_from.count;
-            if new_count <= 0 {
-                drop(tx.rollback().await);
+            Ok(levels)
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn get_unit(&self, item_id: ItemId, serial: &str) -> Result<StockUnit, Error> {
+        let sql = "SELECT unit_id, item_id, serial, shelf_id, bin_id, NULLIF(lot_no, '') lot_no, status, created_at, updated_at
+FROM stock_units
+WHERE item_id = ? AND serial = ?";
+        query_as::<_, StockUnit>(sql)
+            .bind(item_id)
+            .bind(serial)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?
+            .ok_or(Error::SerialNotFound)
+    }
+    async fn get_unit_movements(&self, unit_id: UnitId) -> Result<Vec<StockMovement>, Error> {
+        let sql = "SELECT sm.*
+FROM stock_movements sm
+         JOIN stock_unit_movements su ON su.movement_id = sm.movement_id
+WHERE su.unit_id = ?
+ORDER BY sm.movement_id ASC";
+        query_as::<_, StockMovement>(sql)
+            .bind(unit_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn get_movements(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        criteria: &MovementCriteria,
+    ) -> Result<Listing<StockMovement>, Error> {
+        let filter = "WHERE (? IS NULL OR sm.item_id = ?)
+  AND (? IS NULL OR sm.shelf_from = ? OR sm.shelf_to = ?)
+  AND (? IS NULL OR EXISTS (SELECT 1
+                            FROM shelf sf
+                            WHERE sf.shelf_id IN (sm.shelf_from, sm.shelf_to)
+                              AND sf.room_id = ?))
+  AND (? IS NULL OR sm.user_id = ?)
+  AND (? IS NULL OR sm.created_at >= ?)
+  AND (? IS NULL OR sm.created_at < ?)
+  AND (? IS NULL OR EXISTS (SELECT 1
+                            FROM shelf sf
+                                     JOIN rooms r ON r.room_id = sf.room_id
+                            WHERE sf.shelf_id IN (sm.shelf_from, sm.shelf_to)
+                              AND r.warehouse_id = ?))
+  AND (? IS NULL OR sm.cross_site = ?)";
+        let sql = format!("SELECT COUNT(*) as count FROM stock_movements sm {filter}");
+        let count_result: Result<i64, Error> = query_as(&sql)
+            .bind(criteria.item_id)
+            .bind(criteria.item_id)
+            .bind(criteria.shelf_id)
+            .bind(criteria.shelf_id)
+            .bind(criteria.shelf_id)
+            .bind(criteria.room_id)
+            .bind(criteria.room_id)
+            .bind(criteria.user_id)
+            .bind(criteria.user_id)
+            .bind(criteria.since)
+            .bind(criteria.since)
+            .bind(criteria.until)
+            .bind(criteria.until)
+            .bind(criteria.warehouse_id)
+            .bind(criteria.warehouse_id)
+            .bind(criteria.cross_site)
+            .bind(criteria.cross_site)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "it.name ASC, sm.movement_id ASC".to_string(),
+            Sorting::NameDesc => "it.name DESC, sm.movement_id DESC".to_string(),
+            Sorting::IdAsc => "sm.movement_id ASC".to_string(),
+            Sorting::IdDesc => "sm.movement_id DESC".to_string(),
+        };
+        let sql = format!(
+            "SELECT sm.* FROM stock_movements sm JOIN items it ON it.item_id = sm.item_id {filter} ORDER BY {sort_query} LIMIT ?, ?"
+        );
+        let movements: Vec<StockMovement> = query_as::<_, StockMovement>(&sql)
+            .bind(criteria.item_id)
+            .bind(criteria.item_id)
+            .bind(criteria.shelf_id)
+            .bind(criteria.shelf_id)
+            .bind(criteria.shelf_id)
+            .bind(criteria.room_id)
+            .bind(criteria.room_id)
+            .bind(criteria.user_id)
+            .bind(criteria.user_id)
+            .bind(criteria.since)
+            .bind(criteria.since)
+            .bind(criteria.until)
+            .bind(criteria.until)
+            .bind(criteria.warehouse_id)
+            .bind(criteria.warehouse_id)
+            .bind(criteria.cross_site)
+            .bind(criteria.cross_site)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: movements,
+        })
+    }
+    async fn insert_reservation(&self, reservation: &NewReservation) -> Result<ReservationId, Error> {
+        if reservation.count <= 0 {
+            return Err(Error::CountMustBePositive);
+        }
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<ReservationId, Error> = async {
+            let room_id = match (reservation.shelf_id, reservation.room_id) {
+                (Some(shelf_id), None) => {
+                    if Self::free_on_shelf(&mut tx, reservation.item_id, shelf_id, None).await? < reservation.count {
+                        return Err(Error::InsufficientItem);
+                    }
+                    Self::room_of_shelf(&mut tx, shelf_id).await?
+                }
+                (None, Some(room_id)) => room_id,
+                _ => return Err(Error::Error),
+            };
+            if Self::free_in_room(&mut tx, reservation.item_id, room_id, None).await? < reservation.count {
                 return Err(Error::InsufficientItem);
             }
-            let update_result = query(update_sql)
-                .bind(new_count)
-                .bind(x_from.item_id)
-                .bind(x_from.shelf_id)
+            let sql = "INSERT INTO stock_reservations (item_id, shelf_id, room_id, count, user_id, note, expires_at)
+VALUES (?, ?, ?, ?, ?, ?, ?)";
+            query(sql)
+                .bind(reservation.item_id)
+                .bind(reservation.shelf_id)
+                .bind(reservation.room_id)
+                .bind(reservation.count)
+                .bind(reservation.user_id)
+                .bind(&reservation.note)
+                .bind(reservation.expires_at)
                 .execute(&mut *tx)
                 .await
+                .map(|v| v.last_insert_id() as i64)
                 .map_err(|_| Error::Error)
-                .and_then(|v| if v.rows_affected() > 0 { Ok(()) } else { Err(Error::Error) });
-            if let Err(err) = update_result {
-                drop(tx.rollback().await);
-                return Err(err);
-            }
         }
-        for x_into in into {
-            if x_into.count <= 0 {
-                drop(tx.rollback().await);
-                return Err(Error::CountMustBePositive);
-            }
-            let x_res = query_as::<_, ItemXShelf>(select_sql)
-                .bind(x_into.item_id)
-                .bind(x_into.shelf_id)
-                .fetch_one(&mut *tx)
-                .await;
-            let new_count;
-            let sql;
-            if let Ok(old_x) = x_res {
-                new_count = old_x.count + x_into.count;
-                sql = update_sql;
-            } else {
-                new_count = x_into.count;
-                sql = insert_sql;
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn get_reservation(&self, reservation_id: ReservationId) -> Result<Reservation, Error> {
+        let sql = "SELECT * FROM stock_reservations WHERE reservation_id = ?";
+        query_as::<_, Reservation>(sql)
+            .bind(reservation_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?
+            .ok_or(Error::ReservationNotFound)
+    }
+    async fn get_reservations(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        criteria: &ReservationCriteria,
+    ) -> Result<Listing<Reservation>, Error> {
+        let filter = "WHERE (sr.expires_at IS NULL OR sr.expires_at > CURRENT_TIMESTAMP)
+  AND (? IS NULL OR sr.item_id = ?)
+  AND (? IS NULL OR sr.user_id = ?)";
+        let sql = format!("SELECT COUNT(*) count FROM stock_reservations sr {filter}");
+        let count_result: Result<i64, Error> = query_as(&sql)
+            .bind(criteria.item_id)
+            .bind(criteria.item_id)
+            .bind(criteria.user_id)
+            .bind(criteria.user_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "it.name ASC, sr.reservation_id ASC".to_string(),
+            Sorting::NameDesc => "it.name DESC, sr.reservation_id DESC".to_string(),
+            Sorting::IdAsc => "sr.reservation_id ASC".to_string(),
+            Sorting::IdDesc => "sr.reservation_id DESC".to_string(),
+        };
+        let sql = format!(
+            "SELECT sr.* FROM stock_reservations sr JOIN items it ON it.item_id = sr.item_id {filter} ORDER BY {sort_query} LIMIT ?, ?"
+        );
+        let reservations: Vec<Reservation> = query_as::<_, Reservation>(&sql)
+            .bind(criteria.item_id)
+            .bind(criteria.item_id)
+            .bind(criteria.user_id)
+            .bind(criteria.user_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: reservations,
+        })
+    }
+    async fn delete_reservation(&self, reservation_id: ReservationId) -> Result<(), Error> {
+        let sql = "DELETE FROM stock_reservations WHERE reservation_id = ?";
+        query(sql)
+            .bind(reservation_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+            .and_then(|v| {
+                if v.rows_affected() > 0 {
+                    Ok(())
+                } else {
+                    Err(Error::ReservationNotFound)
+                }
+            })
+    }
+    async fn delete_expired_reservations(&self) -> Result<Vec<ItemId>, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<Vec<ItemId>, Error> = async {
+            let sql = "SELECT reservation_id, item_id FROM stock_reservations WHERE expires_at <= CURRENT_TIMESTAMP";
+            let expired: Vec<(ReservationId, ItemId)> = query_as(sql).fetch_all(&mut *tx).await.map_err(|_| Error::Error)?;
+            for (reservation_id, _) in &expired {
+                let sql = "DELETE FROM stock_reservations WHERE reservation_id = ?";
+                query(sql)
+                    .bind(reservation_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?;
+            }
+            Ok(expired.into_iter().map(|(_, item_id)| item_id).collect())
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn upsert_reorder_point(
+        &self,
+        item_id: ItemId,
+        room_id: Option<RoomId>,
+        min_level: i64,
+        max_level: i64,
+    ) -> Result<ReorderPointId, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<ReorderPointId, Error> = async {
+            let sql = "SELECT reorder_point_id FROM reorder_points WHERE item_id = ? AND COALESCE(room_id, 0) = COALESCE(?, 0)";
+            let existing: Option<(ReorderPointId,)> = query_as(sql)
+                .bind(item_id)
+                .bind(room_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            if let Some((reorder_point_id,)) = existing {
+                let sql = "UPDATE reorder_points SET min_level = ?, max_level = ? WHERE reorder_point_id = ?";
+                query(sql)
+                    .bind(min_level)
+                    .bind(max_level)
+                    .bind(reorder_point_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?;
+                return Ok(reorder_point_id);
+            }
+            let sql = "INSERT INTO reorder_points (item_id, room_id, min_level, max_level) VALUES (?, ?, ?, ?)";
+            query(sql)
+                .bind(item_id)
+                .bind(room_id)
+                .bind(min_level)
+                .bind(max_level)
+                .execute(&mut *tx)
+                .await
+                .map(|v| v.last_insert_id() as i64)
+                .map_err(|_| Error::Error)
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn delete_reorder_point(&self, reorder_point_id: ReorderPointId) -> Result<(), Error> {
+        let sql = "DELETE FROM reorder_points WHERE reorder_point_id = ?";
+        query(sql)
+            .bind(reorder_point_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+            .and_then(|v| {
+                if v.rows_affected() > 0 {
+                    Ok(())
+                } else {
+                    Err(Error::ReorderPointNotFound)
+                }
+            })
+    }
+    async fn get_reorder_points(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        item_id: Option<ItemId>,
+    ) -> Result<Listing<ReorderPoint>, Error> {
+        let filter = "WHERE (? IS NULL OR rp.item_id = ?)";
+        let sql = format!("SELECT COUNT(*) count FROM reorder_points rp {filter}");
+        let count_result: Result<i64, Error> = query_as(&sql)
+            .bind(item_id)
+            .bind(item_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "it.name ASC, rp.reorder_point_id ASC".to_string(),
+            Sorting::NameDesc => "it.name DESC, rp.reorder_point_id DESC".to_string(),
+            Sorting::IdAsc => "rp.reorder_point_id ASC".to_string(),
+            Sorting::IdDesc => "rp.reorder_point_id DESC".to_string(),
+        };
+        let sql = format!(
+            "SELECT rp.* FROM reorder_points rp JOIN items it ON it.item_id = rp.item_id {filter} ORDER BY {sort_query} LIMIT ?, ?"
+        );
+        let reorder_points: Vec<ReorderPoint> = query_as::<_, ReorderPoint>(&sql)
+            .bind(item_id)
+            .bind(item_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: reorder_points,
+        })
+    }
+    async fn get_reorder_levels(&self, item_id: Option<ItemId>) -> Result<Vec<ReorderLevel>, Error> {
+        let sql = format!("{REORDER_LEVELS} WHERE (? IS NULL OR t.item_id = ?) ORDER BY t.reorder_point_id");
+        query_as::<_, ReorderLevel>(&sql)
+            .bind(item_id)
+            .bind(item_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn get_reorder_levels_below(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        room_id: Option<RoomId>,
+    ) -> Result<Listing<ReorderLevel>, Error> {
+        let filter = "WHERE t.available < t.min_level AND (? IS NULL OR t.room_id = ?)";
+        let sql = format!("SELECT COUNT(*) count FROM ({REORDER_LEVELS} {filter}) b");
+        let count_result: Result<i64, Error> = query_as(&sql)
+            .bind(room_id)
+            .bind(room_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "t.item_name ASC, t.room_name ASC".to_string(),
+            Sorting::NameDesc => "t.item_name DESC, t.room_name DESC".to_string(),
+            Sorting::IdAsc => "t.item_id ASC, t.room_id ASC".to_string(),
+            Sorting::IdDesc => "t.item_id DESC, t.room_id DESC".to_string(),
+        };
+        let sql = format!("{REORDER_LEVELS} {filter} ORDER BY {sort_query} LIMIT ?, ?");
+        let levels: Vec<ReorderLevel> = query_as::<_, ReorderLevel>(&sql)
+            .bind(room_id)
+            .bind(room_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: levels,
+        })
+    }
+    async fn set_reorder_point_below(&self, reorder_point_id: ReorderPointId, below: bool) -> Result<(), Error> {
+        let sql = if below {
+            "UPDATE reorder_points SET below_since = CURRENT_TIMESTAMP WHERE reorder_point_id = ?"
+        } else {
+            "UPDATE reorder_points SET below_since = NULL WHERE reorder_point_id = ?"
+        };
+        query(sql)
+            .bind(reorder_point_id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(|_| Error::Error)
+    }
+    async fn insert_stock_alert_subscription(&self, user_id: UserId) -> Result<(), Error> {
+        let sql = "INSERT IGNORE INTO stock_alert_subscriptions (user_id) VALUES (?)";
+        query(sql)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(|_| Error::Error)
+    }
+    async fn delete_stock_alert_subscription(&self, user_id: UserId) -> Result<(), Error> {
+        let sql = "DELETE FROM stock_alert_subscriptions WHERE user_id = ?";
+        query(sql)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(|_| Error::Error)
+    }
+    async fn get_stock_alert_subscribers(&self) -> Result<Vec<UserProfile>, Error> {
+        let sql = "SELECT tp.*
+FROM stock_alert_subscriptions sa
+         JOIN user_profiles tp ON tp.user_id = sa.user_id
+WHERE tp.email IS NOT NULL AND tp.email <> ''";
+        query_as::<_, UserProfile>(sql)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn insert_stocktake(&self, stocktake: &NewStocktake) -> Result<StocktakeId, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<StocktakeId, Error> = async {
+            if let Some(room_id) = stocktake.room_id {
+                let sql = "SELECT room_id FROM rooms WHERE room_id = ?";
+                query_as::<_, (RoomId,)>(sql)
+                    .bind(room_id)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?
+                    .ok_or(Error::RoomNotFound)?;
+            }
+            let sql = "INSERT INTO stocktakes (room_id, frozen, blind, opened_by) VALUES (?, ?, ?, ?)";
+            let stocktake_id = query(sql)
+                .bind(stocktake.room_id)
+                .bind(stocktake.frozen)
+                .bind(stocktake.blind)
+                .bind(stocktake.user_id)
+                .execute(&mut *tx)
+                .await
+                .map(|v| v.last_insert_id() as i64)
+                .map_err(|_| Error::Error)?;
+            if let Some(room_id) = stocktake.room_id {
+                let sql =
+                    "INSERT INTO stocktake_shelves (stocktake_id, shelf_id) SELECT ?, shelf_id FROM shelf WHERE room_id = ?";
+                query(sql)
+                    .bind(stocktake_id)
+                    .bind(room_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?;
+            }
+            for shelf_id in &stocktake.shelf_ids {
+                let sql = "INSERT INTO stocktake_shelves (stocktake_id, shelf_id) VALUES (?, ?)";
+                query(sql)
+                    .bind(stocktake_id)
+                    .bind(shelf_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| Error::ShelfNotFound)?;
+            }
+            // a shelf is counted by one session at a time
+            let sql = "SELECT COUNT(*) count
+FROM stocktake_shelves ss
+         JOIN stocktakes st ON st.stocktake_id = ss.stocktake_id
+WHERE st.status = 'open'
+  AND st.stocktake_id <> ?
+  AND ss.shelf_id IN (SELECT shelf_id FROM stocktake_shelves WHERE stocktake_id = ?)";
+            let (overlapping,): (i64,) = query_as(sql)
+                .bind(stocktake_id)
+                .bind(stocktake_id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            if overlapping > 0 {
+                return Err(Error::StocktakeInProgress);
+            }
+            Ok(stocktake_id)
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn get_stocktake(&self, stocktake_id: StocktakeId) -> Result<Stocktake, Error> {
+        let sql = "SELECT * FROM stocktakes WHERE stocktake_id = ?";
+        query_as::<_, Stocktake>(sql)
+            .bind(stocktake_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?
+            .ok_or(Error::StocktakeNotFound)
+    }
+    async fn get_stocktakes(&self, offset: u64, limit: u8, sort: &Sorting) -> Result<Listing<Stocktake>, Error> {
+        let sql = "SELECT COUNT(*) count FROM stocktakes";
+        let count_result: Result<i64, Error> = query_as(sql)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "created_at ASC, stocktake_id ASC".to_string(),
+            Sorting::NameDesc => "created_at DESC, stocktake_id DESC".to_string(),
+            Sorting::IdAsc => "stocktake_id ASC".to_string(),
+            Sorting::IdDesc => "stocktake_id DESC".to_string(),
+        };
+        let sql = format!("SELECT * FROM stocktakes ORDER BY {sort_query} LIMIT ?, ?");
+        let stocktakes: Vec<Stocktake> = query_as::<_, Stocktake>(&sql)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: stocktakes,
+        })
+    }
+    async fn get_stocktake_lines(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        stocktake_id: StocktakeId,
+    ) -> Result<Listing<StocktakeLine>, Error> {
+        let sql = format!("SELECT COUNT(*) count FROM ({STOCKTAKE_LINES}) c");
+        let count_result: Result<i64, Error> = query_as(&sql)
+            .bind(stocktake_id)
+            .bind(stocktake_id)
+            .bind(stocktake_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "t.shelf_name ASC, t.item_name ASC".to_string(),
+            Sorting::NameDesc => "t.shelf_name DESC, t.item_name DESC".to_string(),
+            Sorting::IdAsc => "t.shelf_id ASC, t.item_id ASC".to_string(),
+            Sorting::IdDesc => "t.shelf_id DESC, t.item_id DESC".to_string(),
+        };
+        let sql = format!("{STOCKTAKE_LINES} ORDER BY {sort_query} LIMIT ?, ?");
+        let lines: Vec<StocktakeLine> = query_as::<_, StocktakeLine>(&sql)
+            .bind(stocktake_id)
+            .bind(stocktake_id)
+            .bind(stocktake_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: lines,
+        })
+    }
+    async fn upsert_stocktake_counts(
+        &self,
+        stocktake_id: StocktakeId,
+        counts: &[StocktakeCount],
+        user_id: UserId,
+    ) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            if Self::stocktake_status(&mut tx, stocktake_id).await? != StocktakeStatus::Open {
+                return Err(Error::StocktakeClosed);
+            }
+            for count in counts {
+                let sql = "SELECT shelf_id FROM stocktake_shelves WHERE stocktake_id = ? AND shelf_id = ?";
+                query_as::<_, (ShelfId,)>(sql)
+                    .bind(stocktake_id)
+                    .bind(count.shelf_id)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?
+                    .ok_or(Error::ShelfNotInStocktake)?;
+                let line = ItemXShelf {
+                    serials: count.serials.clone(),
+                    ..ItemXShelf::new(count.item_id, count.shelf_id, count.counted, &Lot::default())
+                };
+                database::check_serials(Self::is_serialized(&mut tx, count.item_id).await?, &line)?;
+                for sql in [
+                    "DELETE FROM stocktake_serials WHERE stocktake_id = ? AND item_id = ? AND shelf_id = ?",
+                    "DELETE FROM stocktake_counts WHERE stocktake_id = ? AND item_id = ? AND shelf_id = ?",
+                ] {
+                    query(sql)
+                        .bind(stocktake_id)
+                        .bind(count.item_id)
+                        .bind(count.shelf_id)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(|_| Error::Error)?;
+                }
+                let sql =
+                    "INSERT INTO stocktake_counts (stocktake_id, item_id, shelf_id, counted, counted_by) VALUES (?, ?, ?, ?, ?)";
+                query(sql)
+                    .bind(stocktake_id)
+                    .bind(count.item_id)
+                    .bind(count.shelf_id)
+                    .bind(count.counted)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?;
+                for serial in &count.serials {
+                    let sql = "INSERT INTO stocktake_serials (stocktake_id, item_id, shelf_id, serial) VALUES (?, ?, ?, ?)";
+                    query(sql)
+                        .bind(stocktake_id)
+                        .bind(count.item_id)
+                        .bind(count.shelf_id)
+                        .bind(serial)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(|_| Error::Error)?;
+                }
+            }
+            Ok(())
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn approve_stocktake(&self, stocktake_id: StocktakeId, meta: &MovementMeta) -> Result<Vec<ItemXShelf>, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let op = MovementOp::new(MovementKind::Adjust, meta);
+        let result: Result<Vec<ItemXShelf>, Error> = async {
+            // closing first lifts the freeze for the adjustments below
+            Self::close_stocktake(&mut tx, stocktake_id, StocktakeStatus::Approved, meta.user_id).await?;
+            let sql = "SELECT item_id, shelf_id, counted FROM stocktake_counts WHERE stocktake_id = ? ORDER BY shelf_id, item_id";
+            let counts: Vec<(ItemId, ShelfId, i64)> = query_as(sql)
+                .bind(stocktake_id)
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            let mut levels = Vec::new();
+            for (item_id, shelf_id, counted) in counts {
+                let sql = "SELECT CAST(COALESCE(SUM(count), 0) AS SIGNED) FROM stock WHERE item_id = ? AND shelf_id = ?";
+                let (expected,): (i64,) = query_as(sql)
+                    .bind(item_id)
+                    .bind(shelf_id)
+                    .fetch_one(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?;
+                let sql = "UPDATE stocktake_counts SET expected = ? WHERE stocktake_id = ? AND item_id = ? AND shelf_id = ?";
+                query(sql)
+                    .bind(expected)
+                    .bind(stocktake_id)
+                    .bind(item_id)
+                    .bind(shelf_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?;
+                // serialized items are reconciled unit by unit
+                let serialized = Self::is_serialized(&mut tx, item_id).await?;
+                let (missing, found) = if serialized {
+                    let sql = "SELECT su.serial
+FROM stock_units su
+WHERE su.item_id = ?
+  AND su.shelf_id = ?
+  AND su.serial NOT IN (SELECT serial FROM stocktake_serials WHERE stocktake_id = ? AND item_id = ? AND shelf_id = ?)";
+                    let missing: Vec<(String,)> = query_as(sql)
+                        .bind(item_id)
+                        .bind(shelf_id)
+                        .bind(stocktake_id)
+                        .bind(item_id)
+                        .bind(shelf_id)
+                        .fetch_all(&mut *tx)
+                        .await
+                        .map_err(|_| Error::Error)?;
+                    let sql = "SELECT ts.serial
+FROM stocktake_serials ts
+WHERE ts.stocktake_id = ?
+  AND ts.item_id = ?
+  AND ts.shelf_id = ?
+  AND ts.serial NOT IN (SELECT serial FROM stock_units WHERE item_id = ? AND shelf_id = ?)";
+                    let found: Vec<(String,)> = query_as(sql)
+                        .bind(stocktake_id)
+                        .bind(item_id)
+                        .bind(shelf_id)
+                        .bind(item_id)
+                        .bind(shelf_id)
+                        .fetch_all(&mut *tx)
+                        .await
+                        .map_err(|_| Error::Error)?;
+                    (
+                        missing.into_iter().map(|(v,)| v).collect::<Vec<String>>(),
+                        found.into_iter().map(|(v,)| v).collect::<Vec<String>>(),
+                    )
+                } else {
+                    (Vec::new(), Vec::new())
+                };
+                let (lost, gained) = if serialized {
+                    (
+                        i64::try_from(missing.len()).map_err(|_| Error::Error)?,
+                        i64::try_from(found.len()).map_err(|_| Error::Error)?,
+                    )
+                } else {
+                    ((expected - counted).max(0), (counted - expected).max(0))
+                };
+                if lost > 0 {
+                    let line = ItemXShelf {
+                        serials: missing,
+                        ..ItemXShelf::new(item_id, shelf_id, lost, &Lot::default())
+                    };
+                    // what was counted missing is gone whatever the policy, down to an empty shelf
+                    let policy = StockPolicy {
+                        allow_empty: true,
+                        ..Self::stock_policy(&mut tx, shelf_id).await?
+                    };
+                    for draw in Self::take_any_status(&mut tx, &line, &policy).await? {
+                        let movement_id = Self::insert_movement(&mut tx, &op, Some(&draw.left), None, draw.taken).await?;
+                        Self::move_units(&mut tx, movement_id, item_id, &draw.serials, None).await?;
+                        levels.push(draw.left);
+                    }
+                    Self::consume_cost(&mut tx, &op, item_id, lost).await?;
+                }
+                if gained > 0 {
+                    let line = ItemXShelf {
+                        serials: found,
+                        ..ItemXShelf::new(item_id, shelf_id, gained, &Lot::default())
+                    };
+                    let stock = Self::put_stock(&mut tx, &line).await?;
+                    let movement_id = Self::insert_movement(&mut tx, &op, None, Some(&stock), gained).await?;
+                    Self::register_units(&mut tx, movement_id, &line, stock.bin_id).await?;
+                    if let Some(cost) = Self::current_cost(&mut tx, item_id, gained).await? {
+                        Self::add_cost(&mut tx, &op, item_id, gained, &cost.currency, cost.value).await?;
+                    }
+                    levels.push(stock);
+                }
+                Self::unflag_stock(&mut tx, item_id, shelf_id).await?;
+            }
+            Ok(levels)
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn cancel_stocktake(&self, stocktake_id: StocktakeId, user_id: UserId) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        Self::close_stocktake(&mut conn, stocktake_id, StocktakeStatus::Cancelled, user_id).await
+    }
+    async fn insert_bom(&self, bom: &NewBom) -> Result<BomId, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<BomId, Error> = async {
+            let sql = "SELECT CAST(COALESCE(MAX(version), 0) + 1 AS SIGNED) version FROM boms WHERE output_item_id = ?";
+            let (version,): (i64,) = query_as(sql)
+                .bind(bom.output_item_id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            let sql = "INSERT INTO boms (output_item_id, output_count, version, note, created_by) VALUES (?, ?, ?, ?, ?)";
+            let bom_id = query(sql)
+                .bind(bom.output_item_id)
+                .bind(bom.output_count)
+                .bind(version)
+                .bind(&bom.note)
+                .bind(bom.user_id)
+                .execute(&mut *tx)
+                .await
+                .map(|v| v.last_insert_id() as i64)
+                .map_err(|_| Error::ItemNotFound)?;
+            for component in &bom.components {
+                let sql = "INSERT INTO bom_components (bom_id, item_id, count) VALUES (?, ?, ?)";
+                query(sql)
+                    .bind(bom_id)
+                    .bind(component.item_id)
+                    .bind(component.count)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| Error::ItemNotFound)?;
+            }
+            Ok(bom_id)
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn get_bom(&self, bom_id: BomId) -> Result<Bom, Error> {
+        let sql = "SELECT * FROM boms WHERE bom_id = ?";
+        let mut bom = query_as::<_, Bom>(sql)
+            .bind(bom_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?
+            .ok_or(Error::BomNotFound)?;
+        bom.components = self.get_bom_components(bom_id).await?;
+        Ok(bom)
+    }
+    async fn get_boms(&self, offset: u64, limit: u8, sort: &Sorting, item_id: Option<ItemId>) -> Result<Listing<Bom>, Error> {
+        let filter = "WHERE (? IS NULL OR b.output_item_id = ?)";
+        let sql = format!("SELECT COUNT(*) count FROM boms b {filter}");
+        let count_result: Result<i64, Error> = query_as(&sql)
+            .bind(item_id)
+            .bind(item_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "it.name ASC, b.version ASC".to_string(),
+            Sorting::NameDesc => "it.name DESC, b.version DESC".to_string(),
+            Sorting::IdAsc => "b.bom_id ASC".to_string(),
+            Sorting::IdDesc => "b.bom_id DESC".to_string(),
+        };
+        let sql = format!(
+            "SELECT b.* FROM boms b JOIN items it ON it.item_id = b.output_item_id {filter} ORDER BY {sort_query} LIMIT ?, ?"
+        );
+        let mut boms: Vec<Bom> = query_as::<_, Bom>(&sql)
+            .bind(item_id)
+            .bind(item_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        for bom in &mut boms {
+            bom.components = self.get_bom_components(bom.bom_id).await?;
+        }
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: boms,
+        })
+    }
+    async fn get_stocks_in_bins(&self, shelf_id: ShelfId) -> Result<Vec<ItemInBin>, Error> {
+        let sql = "SELECT b.bin_id,
+       b.shelf_id,
+       sf.room_id,
+       b.layer,
+       b.position,
+       it.item_id,
+       it.name item_name,
+       CAST(SUM(si.count) AS SIGNED) count,
+       CAST(SUM(CASE WHEN si.status = 'available' THEN si.count ELSE 0 END) AS SIGNED) status_available,
+       CAST(SUM(CASE WHEN si.status = 'quarantined' THEN si.count ELSE 0 END) AS SIGNED) status_quarantined,
+       CAST(SUM(CASE WHEN si.status = 'damaged' THEN si.count ELSE 0 END) AS SIGNED) status_damaged,
+       CAST(SUM(CASE WHEN si.status = 'on_hold' THEN si.count ELSE 0 END) AS SIGNED) status_on_hold
+FROM stock si
+         JOIN bins b ON b.bin_id = si.bin_id
+         JOIN shelf sf ON sf.shelf_id = b.shelf_id
+         JOIN items it ON it.item_id = si.item_id
+WHERE si.shelf_id = ?
+GROUP BY b.bin_id, b.shelf_id, sf.room_id, b.layer, b.position, it.item_id, it.name
+ORDER BY b.layer, b.position, it.item_id";
+        query_as::<_, ItemInBin>(sql)
+            .bind(shelf_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn get_stocks_of_item(&self, item_id: ItemId) -> Result<Vec<ItemOnShelf>, Error> {
+        let sql = format!(
+            "SELECT t.*, t.status_available - t.reserved available
+FROM ({STOCK_ON_SHELF}
+      WHERE it.item_id = ?
+      GROUP BY it.item_id, sf.shelf_id) t
+ORDER BY t.shelf_id"
+        );
+        query_as::<_, ItemOnShelf>(&sql)
+            .bind(item_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn get_stocks_on_shelves_as_of(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        shelf_id: Option<ShelfId>,
+        warehouse_id: Option<WarehouseId>,
+        as_of: OffsetDateTime,
+    ) -> Result<Listing<ItemOnShelf>, Error> {
+        let (snapshot_id, last_movement_id) = self.snapshot_before(as_of).await?;
+        let sql = format!(
+            "{STOCK_AS_OF}
+SELECT COUNT(*) count
+FROM t
+         JOIN shelf sf ON sf.shelf_id = t.shelf_id
+WHERE (? IS NULL OR t.shelf_id = ?)
+  AND (? IS NULL OR sf.room_id IN (SELECT wr.room_id FROM rooms wr WHERE wr.warehouse_id = ?))"
+        );
+        let count_result: Result<i64, Error> = query_as(&sql)
+            .bind(snapshot_id)
+            .bind(last_movement_id)
+            .bind(as_of)
+            .bind(shelf_id)
+            .bind(shelf_id)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "s.item_name ASC, s.shelf_name ASC".to_string(),
+            Sorting::NameDesc => "s.item_name DESC, s.shelf_name DESC".to_string(),
+            Sorting::IdAsc => "s.item_id ASC, s.shelf_id ASC".to_string(),
+            Sorting::IdDesc => "s.item_id DESC, s.shelf_id DESC".to_string(),
+        };
+        let sql = format!(
+            "{STOCK_AS_OF}
+SELECT s.*
+FROM (SELECT it.item_id,
+             it.name                 item_name,
+             sf.shelf_id,
+             sf.name                 shelf_name,
+             t.count,
+             CAST(0 AS SIGNED)        reserved,
+             t.count                 available,
+             t.count                 status_available,
+             CAST(0 AS SIGNED)        status_quarantined,
+             CAST(0 AS SIGNED)        status_damaged,
+             CAST(0 AS SIGNED)        status_on_hold,
+             it.sn,
+             CAST(NULL AS DATE)      earliest_expiry
+      FROM t
+               JOIN items it ON it.item_id = t.item_id
+               JOIN shelf sf ON sf.shelf_id = t.shelf_id
+      WHERE (? IS NULL OR sf.shelf_id = ?)
+        AND (? IS NULL OR sf.room_id IN (SELECT wr.room_id FROM rooms wr WHERE wr.warehouse_id = ?))) s
+ORDER BY {sort_query} LIMIT ?, ?"
+        );
+        let items: Vec<ItemOnShelf> = query_as::<_, ItemOnShelf>(&sql)
+            .bind(snapshot_id)
+            .bind(last_movement_id)
+            .bind(as_of)
+            .bind(shelf_id)
+            .bind(shelf_id)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: items,
+        })
+    }
+    async fn get_stocks_in_rooms_as_of(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+        as_of: OffsetDateTime,
+    ) -> Result<Listing<ItemInRoom>, Error> {
+        let (snapshot_id, last_movement_id) = self.snapshot_before(as_of).await?;
+        let sql = format!(
+            "{STOCK_AS_OF}
+SELECT COUNT(*) count
+FROM (SELECT r.room_id
+      FROM t
+               JOIN shelf sf ON sf.shelf_id = t.shelf_id
+               JOIN rooms r ON r.room_id = sf.room_id
+      WHERE (? IS NULL OR r.room_id = ?)
+        AND (? IS NULL OR r.warehouse_id = ?)
+      GROUP BY r.room_id, t.item_id) c"
+        );
+        let count_result: Result<i64, Error> = query_as(&sql)
+            .bind(snapshot_id)
+            .bind(last_movement_id)
+            .bind(as_of)
+            .bind(room_id)
+            .bind(room_id)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "s.item_name ASC, s.room_name ASC".to_string(),
+            Sorting::NameDesc => "s.item_name DESC, s.room_name DESC".to_string(),
+            Sorting::IdAsc => "s.item_id ASC, s.room_id ASC".to_string(),
+            Sorting::IdDesc => "s.item_id DESC, s.room_id DESC".to_string(),
+        };
+        let sql = format!(
+            "{STOCK_AS_OF}
+SELECT s.*,
+       s.count available,
+       s.count status_available,
+       CAST(0 AS SIGNED) status_quarantined,
+       CAST(0 AS SIGNED) status_damaged,
+       CAST(0 AS SIGNED) status_on_hold
+FROM (SELECT it.item_id,
+             it.name                    item_name,
+             r.room_id,
+             r.name                     room_name,
+             CAST(SUM(t.count) AS SIGNED) count,
+             CAST(0 AS SIGNED)           reserved
+      FROM t
+               JOIN items it ON it.item_id = t.item_id
+               JOIN shelf sf ON sf.shelf_id = t.shelf_id
+               JOIN rooms r ON r.room_id = sf.room_id
+      WHERE (? IS NULL OR r.room_id = ?)
+        AND (? IS NULL OR r.warehouse_id = ?)
+      GROUP BY it.item_id, it.name, r.room_id, r.name) s
+ORDER BY {sort_query} LIMIT ?, ?"
+        );
+        let items: Vec<ItemInRoom> = query_as::<_, ItemInRoom>(&sql)
+            .bind(snapshot_id)
+            .bind(last_movement_id)
+            .bind(as_of)
+            .bind(room_id)
+            .bind(room_id)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: items,
+        })
+    }
+    async fn get_stocks_in_warehouses_as_of(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        warehouse_id: Option<WarehouseId>,
+        as_of: OffsetDateTime,
+    ) -> Result<Listing<ItemInWarehouse>, Error> {
+        let (snapshot_id, last_movement_id) = self.snapshot_before(as_of).await?;
+        let sql = format!(
+            "{STOCK_AS_OF}
+SELECT COUNT(*) count
+FROM (SELECT r.warehouse_id
+      FROM t
+               JOIN shelf sf ON sf.shelf_id = t.shelf_id
+               JOIN rooms r ON r.room_id = sf.room_id
+      WHERE (? IS NULL OR r.warehouse_id = ?)
+      GROUP BY r.warehouse_id, t.item_id) c"
+        );
+        let count_result: Result<i64, Error> = query_as(&sql)
+            .bind(snapshot_id)
+            .bind(last_movement_id)
+            .bind(as_of)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "s.item_name ASC, s.warehouse_code ASC".to_string(),
+            Sorting::NameDesc => "s.item_name DESC, s.warehouse_code DESC".to_string(),
+            Sorting::IdAsc => "s.item_id ASC, s.warehouse_id ASC".to_string(),
+            Sorting::IdDesc => "s.item_id DESC, s.warehouse_id DESC".to_string(),
+        };
+        let sql = format!(
+            "{STOCK_AS_OF}
+SELECT s.*,
+       s.count available,
+       s.count status_available,
+       CAST(0 AS SIGNED) status_quarantined,
+       CAST(0 AS SIGNED) status_damaged,
+       CAST(0 AS SIGNED) status_on_hold
+FROM (SELECT it.item_id,
+             it.name                    item_name,
+             w.warehouse_id,
+             w.code                     warehouse_code,
+             CAST(SUM(t.count) AS SIGNED) count,
+             CAST(0 AS SIGNED)           reserved
+      FROM t
+               JOIN items it ON it.item_id = t.item_id
+               JOIN shelf sf ON sf.shelf_id = t.shelf_id
+               JOIN rooms r ON r.room_id = sf.room_id
+               JOIN warehouses w ON w.warehouse_id = r.warehouse_id
+      WHERE (? IS NULL OR w.warehouse_id = ?)
+      GROUP BY it.item_id, it.name, w.warehouse_id, w.code) s
+ORDER BY {sort_query} LIMIT ?, ?"
+        );
+        let items: Vec<ItemInWarehouse> = query_as::<_, ItemInWarehouse>(&sql)
+            .bind(snapshot_id)
+            .bind(last_movement_id)
+            .bind(as_of)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: items,
+        })
+    }
+    async fn insert_stock_snapshot(&self) -> Result<SnapshotId, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<SnapshotId, Error> = async {
+            let sql = "SELECT CAST(COALESCE(MAX(movement_id), 0) AS SIGNED) last_movement_id FROM stock_movements";
+            let (last_movement_id,): (MovementId,) = query_as(sql).fetch_one(&mut *tx).await.map_err(|_| Error::Error)?;
+            let sql = "INSERT INTO stock_snapshots (last_movement_id) VALUES (?)";
+            let snapshot_id = query(sql)
+                .bind(last_movement_id)
+                .execute(&mut *tx)
+                .await
+                .map(|v| v.last_insert_id() as i64)
+                .map_err(|_| Error::Error)?;
+            let sql = "INSERT INTO stock_snapshot_lines (snapshot_id, item_id, shelf_id, count)
+SELECT ?, item_id, shelf_id, SUM(count)
+FROM stock
+GROUP BY item_id, shelf_id";
+            query(sql)
+                .bind(snapshot_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            Ok(snapshot_id)
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn update_item_costing(&self, item_id: ItemId, costing: CostingMethod) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            let sql = "UPDATE items SET costing = ? WHERE item_id = ?";
+            let updated = query(sql)
+                .bind(costing.as_str())
+                .bind(item_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            if updated.rows_affected() == 0 {
+                return Err(Error::ItemNotFound);
+            }
+            // the average is kept in the oldest layer, the others are emptied into it
+            let layers = Self::open_cost_layers(&mut tx, item_id).await?;
+            if costing == CostingMethod::Average && layers.len() > 1 {
+                let sql = "UPDATE cost_layers SET remaining = ?, value = ? WHERE layer_id = ?";
+                query(sql)
+                    .bind(layers.iter().map(|x| x.remaining).sum::<i64>())
+                    .bind(layers.iter().map(|x| x.value).sum::<i64>())
+                    .bind(layers[0].layer_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?;
+                for layer in &layers[1..] {
+                    let sql = "UPDATE cost_layers SET remaining = 0, value = 0 WHERE layer_id = ?";
+                    query(sql)
+                        .bind(layer.layer_id)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(|_| Error::Error)?;
+                }
+            }
+            Ok(())
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn update_item_category(&self, item_id: ItemId, category_id: CategoryId) -> Result<(), Error> {
+        let sql = "SELECT category_id FROM categories WHERE category_id = ?";
+        let found: Option<(CategoryId,)> = query_as(sql)
+            .bind(category_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        if found.is_none() {
+            return Err(Error::CategoryNotFound);
+        }
+        let sql = "UPDATE items SET category_id = ? WHERE item_id = ?";
+        query(sql)
+            .bind(category_id)
+            .bind(item_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+            .and_then(|v| {
+                if v.rows_affected() > 0 {
+                    Ok(())
+                } else {
+                    Err(Error::ItemNotFound)
+                }
+            })
+    }
+    async fn update_item_dimensions(&self, item_id: ItemId, dimensions: &ItemDimensions) -> Result<(), Error> {
+        let sql = "UPDATE items SET weight_g = ?, length_mm = ?, width_mm = ?, height_mm = ? WHERE item_id = ?";
+        query(sql)
+            .bind(dimensions.weight_g)
+            .bind(dimensions.length_mm)
+            .bind(dimensions.width_mm)
+            .bind(dimensions.height_mm)
+            .bind(item_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+            .and_then(|v| {
+                if v.rows_affected() > 0 {
+                    Ok(())
+                } else {
+                    Err(Error::ItemNotFound)
+                }
+            })
+    }
+    async fn insert_category(&self, name: &str, desc: &Option<String>) -> Result<CategoryId, Error> {
+        let sql = "INSERT INTO categories (name, description) VALUES (?, ?)";
+        query(sql)
+            .bind(name)
+            .bind(desc)
+            .execute(&self.pool)
+            .await
+            .map(|v| v.last_insert_id() as i64)
+            .map_err(|_| Error::Error)
+    }
+    async fn get_categories(&self) -> Result<Vec<Category>, Error> {
+        let sql = "SELECT category_id, name, description FROM categories ORDER BY category_id";
+        query_as::<_, Category>(sql)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn delete_category(&self, category_id: CategoryId) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            let sql = "UPDATE items SET category_id = NULL WHERE category_id = ?";
+            query(sql)
+                .bind(category_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            let sql = "DELETE FROM room_categories WHERE category_id = ?";
+            query(sql)
+                .bind(category_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            let sql = "DELETE FROM categories WHERE category_id = ?";
+            let deleted = query(sql)
+                .bind(category_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            if deleted.rows_affected() == 0 {
+                return Err(Error::CategoryNotFound);
+            }
+            Ok(())
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn get_cost_layers(&self, item_id: ItemId) -> Result<Vec<CostLayer>, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        Self::open_cost_layers(&mut conn, item_id).await
+    }
+    async fn get_valuation(
+        &self,
+        group: ValuationGroup,
+        warehouse_id: Option<WarehouseId>,
+        as_of: OffsetDateTime,
+    ) -> Result<Vec<ValuationLine>, Error> {
+        let (snapshot_id, last_movement_id) = self.snapshot_before(as_of).await?;
+        // the costed stock of an item is spread over the rooms holding it in proportion to their stock
+        let sql = match group {
+            ValuationGroup::Room => format!(
+                "{STOCK_AS_OF},
+     {COST_AS_OF},
+     q AS (SELECT item_id, CAST(SUM(count) AS SIGNED) count FROM t WHERE count > 0 GROUP BY item_id)
+SELECT r.room_id                       group_id,
+       r.name                          group_name,
+       v.item_id,
+       v.currency,
+       CAST(SUM(t.count) AS SIGNED) count,
+       q.count                         total,
+       v.count                         cost_count,
+       v.value                         cost_value
+FROM t
+         JOIN q ON q.item_id = t.item_id
+         JOIN v ON v.item_id = t.item_id
+         JOIN shelf sf ON sf.shelf_id = t.shelf_id
+         JOIN rooms r ON r.room_id = sf.room_id
+WHERE t.count > 0
+  AND (? IS NULL OR r.warehouse_id = ?)
+GROUP BY r.room_id, r.name, v.item_id, v.currency, q.count, v.count, v.value
+ORDER BY v.item_id, v.currency, r.room_id"
+            ),
+            ValuationGroup::Warehouse => format!(
+                "{STOCK_AS_OF},
+     {COST_AS_OF},
+     q AS (SELECT item_id, CAST(SUM(count) AS SIGNED) count FROM t WHERE count > 0 GROUP BY item_id)
+SELECT r.warehouse_id                  group_id,
+       w.name                          group_name,
+       v.item_id,
+       v.currency,
+       CAST(SUM(t.count) AS SIGNED) count,
+       q.count                         total,
+       v.count                         cost_count,
+       v.value                         cost_value
+FROM t
+         JOIN q ON q.item_id = t.item_id
+         JOIN v ON v.item_id = t.item_id
+         JOIN shelf sf ON sf.shelf_id = t.shelf_id
+         JOIN rooms r ON r.room_id = sf.room_id
+         JOIN warehouses w ON w.warehouse_id = r.warehouse_id
+WHERE t.count > 0
+  AND (? IS NULL OR r.warehouse_id = ?)
+GROUP BY r.warehouse_id, w.name, v.item_id, v.currency, q.count, v.count, v.value
+ORDER BY v.item_id, v.currency, r.warehouse_id"
+            ),
+            // for every site an item is counted whole, for a single site only the share it holds
+            ValuationGroup::Category => format!(
+                "{STOCK_AS_OF},
+     {COST_AS_OF},
+     q AS (SELECT item_id, CAST(SUM(count) AS SIGNED) count FROM t WHERE count > 0 GROUP BY item_id),
+     s AS (SELECT t.item_id, CAST(SUM(t.count) AS SIGNED) count
+           FROM t
+                    JOIN shelf sf ON sf.shelf_id = t.shelf_id
+                    JOIN rooms r ON r.room_id = sf.room_id
+           WHERE t.count > 0
+             AND r.warehouse_id = ?
+           GROUP BY t.item_id)
+SELECT c.category_id                                         group_id,
+       c.name                                                group_name,
+       v.item_id,
+       v.currency,
+       CAST(CASE WHEN ? IS NULL THEN 1 ELSE s.count END AS SIGNED) count,
+       CAST(CASE WHEN ? IS NULL THEN 1 ELSE q.count END AS SIGNED) total,
+       v.count                                               cost_count,
+       v.value                                               cost_value
+FROM v
+         JOIN items it ON it.item_id = v.item_id
+         LEFT JOIN categories c ON c.category_id = it.category_id
+         LEFT JOIN q ON q.item_id = v.item_id
+         LEFT JOIN s ON s.item_id = v.item_id
+WHERE ? IS NULL
+   OR s.item_id IS NOT NULL
+ORDER BY v.item_id, v.currency"
+            ),
+        };
+        query_as::<_, ValuationShare>(&sql)
+            .bind(snapshot_id)
+            .bind(last_movement_id)
+            .bind(as_of)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .fetch_all(&self.pool)
+            .await
+            .map(database::prorate_valuation)
+            .map_err(|_| Error::Error)
+    }
+    async fn upsert_stock_policy(&self, policy: &StockPolicy) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            if let Some(room_id) = policy.room_id {
+                let sql = "SELECT room_id FROM rooms WHERE room_id = ?";
+                let found: Option<(RoomId,)> = query_as(sql)
+                    .bind(room_id)
+                    .fetch_optional(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?;
+                if found.is_none() {
+                    return Err(Error::RoomNotFound);
+                }
+            }
+            let sql = "SELECT policy_id FROM stock_policies WHERE COALESCE(room_id, 0) = COALESCE(?, 0)";
+            let existing: Option<(i64,)> = query_as(sql)
+                .bind(policy.room_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            if let Some((policy_id,)) = existing {
+                let sql = "UPDATE stock_policies SET allow_empty = ?, keep_empty = ?, allow_negative = ?, warn_over_capacity = ? WHERE policy_id = ?";
+                query(sql)
+                    .bind(policy.allow_empty)
+                    .bind(policy.keep_empty)
+                    .bind(policy.allow_negative)
+                    .bind(policy.warn_over_capacity)
+                    .bind(policy_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?;
+            } else {
+                let sql = "INSERT INTO stock_policies (room_id, allow_empty, keep_empty, allow_negative, warn_over_capacity) VALUES (?, ?, ?, ?, ?)";
+                query(sql)
+                    .bind(policy.room_id)
+                    .bind(policy.allow_empty)
+                    .bind(policy.keep_empty)
+                    .bind(policy.allow_negative)
+                    .bind(policy.warn_over_capacity)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?;
+            }
+            Ok(())
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn delete_stock_policy(&self, room_id: Option<RoomId>) -> Result<(), Error> {
+        let sql = "DELETE FROM stock_policies WHERE COALESCE(room_id, 0) = COALESCE(?, 0)";
+        query(sql)
+            .bind(room_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+            .and_then(|v| {
+                if v.rows_affected() > 0 {
+                    Ok(())
+                } else {
+                    Err(Error::StockPolicyNotFound)
+                }
+            })
+    }
+    async fn get_stock_policies(&self) -> Result<Vec<StockPolicy>, Error> {
+        let sql =
+            "SELECT room_id, allow_empty, keep_empty, allow_negative, warn_over_capacity FROM stock_policies ORDER BY policy_id";
+        query_as::<_, StockPolicy>(sql)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn get_stock_policy_of_shelf(&self, shelf_id: ShelfId) -> Result<StockPolicy, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        Self::stock_policy(&mut conn, shelf_id).await
+    }
+    async fn get_stock_aging(
+        &self,
+        cutoffs: [OffsetDateTime; 3],
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Vec<AgingLine>, Error> {
+        // a lot numbered lot is as old as its last deposit wherever it went since, stock without a
+        // number as old as the last deposit on its shelf
+        let sql = "SELECT t.item_id,
+       t.item_name,
+       t.room_id,
+       t.room_name,
+       CAST(SUM(CASE WHEN t.received_at > ? THEN t.count ELSE 0 END) AS SIGNED) days_0_30,
+       CAST(SUM(CASE WHEN t.received_at <= ? AND t.received_at > ? THEN t.count ELSE 0 END) AS SIGNED) days_31_90,
+       CAST(SUM(CASE WHEN t.received_at <= ? AND t.received_at > ? THEN t.count ELSE 0 END) AS SIGNED) days_91_180,
+       CAST(SUM(CASE WHEN t.received_at <= ? THEN t.count ELSE 0 END) AS SIGNED) days_over_180,
+       CAST(SUM(t.count) AS SIGNED) total,
+       MIN(t.received_at) oldest_received_at
+FROM (SELECT si.item_id,
+             it.name item_name,
+             r.room_id,
+             r.name  room_name,
+             si.count,
+             COALESCE((SELECT MAX(sm.created_at)
+                       FROM stock_movements sm
+                       WHERE sm.item_id = si.item_id
+                         AND sm.kind IN ('deposit', 'convert')
+                         AND sm.shelf_to IS NOT NULL
+                         AND COALESCE(sm.lot_no, '') = si.lot_no
+                         AND (si.lot_no <> '' OR sm.shelf_to = si.shelf_id)), si.created_at) received_at
+      FROM stock si
+               JOIN items it ON it.item_id = si.item_id
+               JOIN shelf sf ON sf.shelf_id = si.shelf_id
+               JOIN rooms r ON r.room_id = sf.room_id
+      WHERE si.count > 0
+        AND (? IS NULL OR r.room_id = ?)
+        AND (? IS NULL OR r.warehouse_id = ?)) t
+GROUP BY t.item_id, t.item_name, t.room_id, t.room_name
+ORDER BY t.item_id, t.room_id";
+        query_as::<_, AgingLine>(sql)
+            .bind(cutoffs[0])
+            .bind(cutoffs[0])
+            .bind(cutoffs[1])
+            .bind(cutoffs[1])
+            .bind(cutoffs[2])
+            .bind(cutoffs[2])
+            .bind(room_id)
+            .bind(room_id)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn get_dead_stock(
+        &self,
+        since: OffsetDateTime,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Vec<DeadStock>, Error> {
+        // stock consumed by a conversion was used as much as withdrawn, a reversed withdrawal was not
+        let sql = "SELECT t.*
+FROM (SELECT it.item_id,
+             it.name item_name,
+             CAST(SUM(si.count) AS SIGNED) on_hand,
+             MIN(si.created_at) in_stock_since,
+             (SELECT MAX(sm.created_at)
+              FROM stock_movements sm
+              WHERE sm.item_id = it.item_id
+                AND sm.kind IN ('withdraw', 'convert')
+                AND sm.shelf_from IS NOT NULL
+                AND sm.shelf_to IS NULL
+                AND NOT EXISTS (SELECT 1 FROM stock_movements rv WHERE rv.reversal_of = sm.movement_id)) last_withdrawn_at
+      FROM stock si
+               JOIN items it ON it.item_id = si.item_id
+               JOIN shelf sf ON sf.shelf_id = si.shelf_id
+      WHERE si.count > 0
+        AND (? IS NULL OR sf.room_id = ?)
+        AND (? IS NULL OR sf.room_id IN (SELECT wr.room_id FROM rooms wr WHERE wr.warehouse_id = ?))
+      GROUP BY it.item_id, it.name) t
+WHERE COALESCE(t.last_withdrawn_at, t.in_stock_since) <= ?
+ORDER BY COALESCE(t.last_withdrawn_at, t.in_stock_since), t.item_id";
+        query_as::<_, DeadStock>(sql)
+            .bind(room_id)
+            .bind(room_id)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .bind(since)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn get_consumption(&self, from: OffsetDateTime, to: OffsetDateTime) -> Result<Vec<Consumption>, Error> {
+        // what left the stock for good: withdrawals and what conversions used up, unless reversed since
+        let withdrawals = "SELECT sm.item_id, sm.count, sm.correlation_id
+FROM stock_movements sm
+WHERE sm.kind IN ('withdraw', 'convert')
+  AND sm.shelf_from IS NOT NULL
+  AND sm.shelf_to IS NULL
+  AND sm.created_at >= ?
+  AND sm.created_at < ?
+  AND NOT EXISTS (SELECT 1 FROM stock_movements rv WHERE rv.reversal_of = sm.movement_id)";
+        let sql = format!(
+            "SELECT w.item_id,
+       w.count,
+       v.currency,
+       CAST(COALESCE(v.value, 0) AS SIGNED) value
+FROM (SELECT x.item_id, CAST(SUM(x.count) AS SIGNED) count FROM ({withdrawals}) x GROUP BY x.item_id) w
+         LEFT JOIN (SELECT ce.item_id, ce.currency, -SUM(ce.value) value
+                    FROM cost_entries ce
+                    WHERE ce.count < 0
+                      AND ce.correlation_id IN (SELECT x.correlation_id FROM ({withdrawals}) x WHERE x.item_id = ce.item_id)
+                    GROUP BY ce.item_id, ce.currency) v ON v.item_id = w.item_id
+ORDER BY w.item_id, v.currency"
+        );
+        query_as::<_, Consumption>(&sql)
+            .bind(from)
+            .bind(to)
+            .bind(from)
+            .bind(to)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+    async fn update_item_classes(&self, classes: &[ItemClass]) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let result: Result<(), Error> = async {
+            let sql = "UPDATE items SET abc_class = ?, xyz_class = ?, classified_at = CURRENT_TIMESTAMP WHERE item_id = ?";
+            for class in classes {
+                query(sql)
+                    .bind(class.abc_class.as_str())
+                    .bind(class.xyz_class.as_str())
+                    .bind(class.item_id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|_| Error::Error)?;
+            }
+            Ok(())
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn get_flagged_stocks(
+        &self,
+        offset: u64,
+        limit: u8,
+        sort: &Sorting,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<FlaggedStock>, Error> {
+        let filter = "WHERE si.flagged_at IS NOT NULL
+  AND (? IS NULL OR sf.room_id = ?)
+  AND (? IS NULL OR sf.room_id IN (SELECT wr.room_id FROM rooms wr WHERE wr.warehouse_id = ?))";
+        let sql = format!("SELECT COUNT(*) count FROM stock si JOIN shelf sf ON sf.shelf_id = si.shelf_id {filter}");
+        let count_result: Result<i64, Error> = query_as(&sql)
+            .bind(room_id)
+            .bind(room_id)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .fetch_one(&self.pool)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error);
+        let count = count_result?;
+        let sort_query: String = match sort {
+            Sorting::NameAsc => "it.name ASC, si.stock_id ASC".to_string(),
+            Sorting::NameDesc => "it.name DESC, si.stock_id DESC".to_string(),
+            Sorting::IdAsc => "si.stock_id ASC".to_string(),
+            Sorting::IdDesc => "si.stock_id DESC".to_string(),
+        };
+        let sql = format!(
+            "SELECT si.item_id,
+       it.name                 item_name,
+       si.shelf_id,
+       sf.name                 shelf_name,
+       NULLIF(si.lot_no, '')   lot_no,
+       CAST(si.count AS SIGNED) count,
+       si.flagged_at
+FROM stock si
+         JOIN items it ON it.item_id = si.item_id
+         JOIN shelf sf ON sf.shelf_id = si.shelf_id
+{filter}
+ORDER BY {sort_query} LIMIT ?, ?"
+        );
+        let stocks: Vec<FlaggedStock> = query_as::<_, FlaggedStock>(&sql)
+            .bind(room_id)
+            .bind(room_id)
+            .bind(warehouse_id)
+            .bind(warehouse_id)
+            .bind(i64::saturating_add_unsigned(0, offset))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(Listing {
+            total: u64::try_from(count).expect("variable `count` is larger than u32"),
+            data: stocks,
+        })
+    }
+    async fn clear_stock_flags(&self, item_id: ItemId, shelf_id: ShelfId) -> Result<u64, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        Self::unflag_stock(&mut conn, item_id, shelf_id).await
+    }
+    async fn change_stock_status(
+        &self,
+        item_id: ItemId,
+        count: i64,
+        shelf_id: ShelfId,
+        pick: &StockPick,
+        status: StockStatus,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, Error> {
+        if count <= 0 {
+            return Err(Error::CountMustBePositive);
+        }
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let op = MovementOp::new(MovementKind::Status, meta);
+        let line = ItemXShelf::picked(item_id, shelf_id, count, pick);
+        let result: Result<Vec<ItemXShelf>, Error> = async {
+            Self::check_expected(&mut tx, item_id, shelf_id, meta.expected_count).await?;
+            let mut levels = Vec::new();
+            // the stock stays on the shelf, so nothing the policy guards against can happen
+            let policy = StockPolicy {
+                allow_empty: true,
+                ..StockPolicy::default()
+            };
+            for draw in Self::take_stock(&mut tx, &line, &policy).await? {
+                let moved = ItemXShelf {
+                    status,
+                    bin_id: draw.left.bin_id,
+                    ..ItemXShelf::new(item_id, shelf_id, draw.taken, &draw.left.lot())
+                };
+                let changed = Self::put_stock(&mut tx, &moved).await?;
+                let movement_id = Self::insert_movement(&mut tx, &op, Some(&draw.left), None, draw.taken).await?;
+                for serial in &draw.serials {
+                    Self::link_unit(&mut tx, movement_id, item_id, serial).await?;
+                }
+                let movement_id = Self::insert_movement(&mut tx, &op, None, Some(&changed), draw.taken).await?;
+                Self::set_units_status(&mut tx, movement_id, item_id, &draw.serials, status).await?;
+                levels.push(draw.left);
+                levels.push(changed);
+            }
+            Self::check_reservations(&mut tx, item_id, shelf_id, meta.user_id).await?;
+            Ok(levels)
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+    async fn reverse_operation(&self, correlation_id: &str, meta: &MovementMeta) -> Result<Vec<ItemXShelf>, Error> {
+        let mut conn = self.pool.acquire().await.map_err(|_| Error::ConnectionPoolFailed)?;
+        let mut tx = conn.begin().await.map_err(|_| Error::TransactionError)?;
+        let op = MovementOp::new(MovementKind::Reversal, meta);
+        let result: Result<Vec<ItemXShelf>, Error> = async {
+            let sql = "SELECT * FROM stock_movements WHERE correlation_id = ? ORDER BY movement_id DESC
+FOR UPDATE";
+            let lines = query_as::<_, StockMovement>(sql)
+                .bind(correlation_id)
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            if lines.is_empty() {
+                return Err(Error::OperationNotFound);
+            }
+            let sql = "SELECT COUNT(*)
+FROM stock_movements
+WHERE reversal_of IN (SELECT movement_id FROM stock_movements WHERE correlation_id = ?)";
+            let (reversed,): (i64,) = query_as(sql)
+                .bind(correlation_id)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|_| Error::Error)?;
+            if reversed > 0 {
+                return Err(Error::OperationReversed);
+            }
+            // the last line first, so a convert gives back what it produced before getting its input back
+            let mut levels = Vec::new();
+            for x in &lines {
+                levels.extend(Self::reverse_line(&mut tx, &op, x).await?);
+            }
+            Self::restore_reservations(&mut tx, correlation_id).await?;
+            Self::reverse_cost(&mut tx, &op, correlation_id).await?;
+            Ok(levels)
+        }
+        .await;
+        Self::commit_or_rollback(tx, result).await
+    }
+}
+
+impl Mysql {
+    /// Commit `tx` if `result` is ok, roll it back otherwise.
+    async fn commit_or_rollback<T>(tx: Transaction<'_, sqlx::MySql>, result: Result<T, Error>) -> Result<T, Error> {
+        match result {
+            Ok(v) => {
+                tx.commit().await.map_err(|_| Error::TransactionError)?;
+                Ok(v)
+            }
+            Err(e) => {
+                drop(tx.rollback().await);
+                Err(e)
+            }
+        }
+    }
+
+    /// Whether every unit of the item is tracked by its serial number.
+    async fn is_serialized(conn: &mut MySqlConnection, item_id: ItemId) -> Result<bool, Error> {
+        let sql = "SELECT serialized FROM items WHERE item_id = ?";
+        query_as::<_, (bool,)>(sql)
+            .bind(item_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?
+            .map(|(v,)| v)
+            .ok_or(Error::ItemNotFound)
+    }
+
+    /// Take `line.count` items in status `line.status` off a shelf: the units named by `line.serials`,
+    /// or else from lot `line.lot_no` only, or else first-expired-first-out across the lots. Returns
+    /// what was drawn from each lot. The last item on the shelf stays unless `policy` allows emptying it, emptied lots
+    /// are removed unless it keeps them. Where it allows negative stock, what the lots lack is taken
+    /// from the last lot drawn from, or from a new one, which is flagged for reconciliation. A lot
+    /// changed by someone else meanwhile fails with `Error::StockConflict`.
+    async fn take_stock(conn: &mut MySqlConnection, line: &ItemXShelf, policy: &StockPolicy) -> Result<Vec<Draw>, Error> {
+        Self::check_not_frozen(&mut *conn, line.shelf_id).await?;
+        database::check_serials(Self::is_serialized(&mut *conn, line.item_id).await?, line)?;
+        let sql = "SELECT item_id, shelf_id, count, NULLIF(lot_no, '') lot_no, mfg_date, expiry_date, status, bin_id
+FROM stock
+WHERE item_id = ? AND shelf_id = ?
+ORDER BY expiry_date IS NULL, expiry_date, stock_id
+FOR UPDATE";
+        let lots = query_as::<_, ItemXShelf>(sql)
+            .bind(line.item_id)
+            .bind(line.shelf_id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        let on_shelf: i64 = lots.iter().map(|x| x.count).sum();
+        let lots: Vec<ItemXShelf> = lots
+            .into_iter()
+            .filter(|x| x.status == line.status)
+            .filter(|x| line.bin_id.is_none() || x.bin_id == line.bin_id)
+            .filter(|x| match &line.lot_no {
+                Some(lot_no) => x.lot_no.as_deref().unwrap_or_default() == lot_no,
+                None => true,
+            })
+            .collect();
+        let available: i64 = lots.iter().map(|x| x.count.max(0)).sum();
+        // units are never taken below zero, they are either on the shelf or not
+        let negative = policy.allow_negative && line.serials.is_empty();
+        if !negative && (available < line.count || (!policy.allow_empty && on_shelf <= line.count)) {
+            return Err(Error::InsufficientItem);
+        }
+        let mut units = Self::units_by_bin_and_lot(&mut *conn, line).await?;
+        let mut wanted = line.count;
+        let mut plan: Vec<(ItemXShelf, i64, Vec<String>)> = Vec::new();
+        let last = lots.last().cloned();
+        for x in lots {
+            let (taken, serials) = if line.serials.is_empty() {
+                (wanted.min(x.count.max(0)), Vec::new())
+            } else {
+                let serials = units
+                    .remove(&(x.bin_id, x.lot_no.clone().unwrap_or_default()))
+                    .unwrap_or_default();
+                (i64::try_from(serials.len()).map_err(|_| Error::Error)?, serials)
+            };
+            if taken == 0 {
+                continue;
+            }
+            if taken > x.count {
+                return Err(Error::InsufficientItem);
+            }
+            wanted -= taken;
+            plan.push((x, taken, serials));
+        }
+        if wanted > 0 {
+            if !negative {
+                return Err(Error::InsufficientItem);
+            }
+            match (plan.last_mut(), last) {
+                (Some((_, taken, _)), _) => *taken += wanted,
+                (None, Some(x)) => plan.push((x, wanted, Vec::new())),
+                (None, None) => {
+                    let bin_id = Self::bin_of(&mut *conn, line.shelf_id, line.bin_id).await?;
+                    let sql = "INSERT INTO stock (count, item_id, shelf_id, bin_id, lot_no, status) VALUES (0, ?, ?, ?, ?, ?)";
+                    query(sql)
+                        .bind(line.item_id)
+                        .bind(line.shelf_id)
+                        .bind(bin_id)
+                        .bind(line.lot_no.as_deref().unwrap_or_default())
+                        .bind(line.status.as_str())
+                        .execute(&mut *conn)
+                        .await
+                        .map_err(|_| Error::Error)?;
+                    let x = ItemXShelf {
+                        status: line.status,
+                        bin_id: Some(bin_id),
+                        ..ItemXShelf::new(line.item_id, line.shelf_id, 0, &line.lot())
+                    };
+                    plan.push((x, wanted, Vec::new()));
+                }
+            }
+        }
+        let mut draws = Vec::with_capacity(plan.len());
+        for (mut x, taken, serials) in plan {
+            let before = x.count;
+            x.count -= taken;
+            let statement = if x.count != 0 || policy.keep_empty {
+                query(
+                    "UPDATE stock
+SET count      = ?,
+    flagged_at = CASE WHEN CAST(? AS SIGNED) < 0 THEN COALESCE(flagged_at, CURRENT_TIMESTAMP) ELSE flagged_at END
+WHERE item_id = ? AND shelf_id = ? AND lot_no = ? AND status = ? AND bin_id = ? AND count = ?",
+                )
+                .bind(x.count)
+                .bind(x.count)
+            } else {
+                query("DELETE FROM stock WHERE item_id = ? AND shelf_id = ? AND lot_no = ? AND status = ? AND bin_id = ? AND count = ?")
+            };
+            let affected = statement
+                .bind(x.item_id)
+                .bind(x.shelf_id)
+                .bind(x.lot_no.as_deref().unwrap_or_default())
+                .bind(x.status.as_str())
+                .bind(x.bin_id)
+                .bind(before)
+                .execute(&mut *conn)
+                .await
+                .map_err(|_| Error::Error)?
+                .rows_affected();
+            if affected == 0 {
+                return Err(Error::StockConflict);
+            }
+            draws.push(Draw { left: x, taken, serials });
+        }
+        Ok(draws)
+    }
+
+    /// Fail with `Error::StockChanged` unless the shelf holds `expected` items of the item, when it
+    /// is given.
+    async fn check_expected(
+        conn: &mut MySqlConnection,
+        item_id: ItemId,
+        shelf_id: ShelfId,
+        expected: Option<i64>,
+    ) -> Result<(), Error> {
+        let Some(expected) = expected else {
+            return Ok(());
+        };
+        let sql = "SELECT count FROM stock WHERE item_id = ? AND shelf_id = ?
+FOR UPDATE";
+        let counts: Vec<(i64,)> = query_as(sql)
+            .bind(item_id)
+            .bind(shelf_id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        if counts.iter().map(|(count,)| count).sum::<i64>() == expected {
+            Ok(())
+        } else {
+            Err(Error::StockChanged)
+        }
+    }
+
+    /// Run a line of a batch.
+    async fn apply_line(conn: &mut MySqlConnection, op: &MovementOp<'_>, x: &BatchLine) -> Result<Vec<ItemXShelf>, Error> {
+        if x.op.line().count <= 0 {
+            return Err(Error::CountMustBePositive);
+        }
+        match &x.op {
+            StockOp::Deposit(line) => Self::deposit_line(conn, op, line, x.cost.as_ref()).await.map(|v| vec![v]),
+            StockOp::Withdraw(line) => Self::withdraw_line(conn, op, line).await,
+            StockOp::Transfer { line, shelf_to, bin_to } => Self::transfer_line(conn, op, line, *shelf_to, *bin_to).await,
+        }
+    }
+
+    /// Move `line` off its shelf onto `shelf_to`, into `bin_to` or else its first bin, returning the
+    /// source and target quantities of each lot moved.
+    async fn transfer_line(
+        conn: &mut MySqlConnection,
+        op: &MovementOp<'_>,
+        line: &ItemXShelf,
+        shelf_to: ShelfId,
+        bin_to: Option<BinId>,
+    ) -> Result<Vec<ItemXShelf>, Error> {
+        Self::check_expected(&mut *conn, line.item_id, line.shelf_id, op.meta.expected_count).await?;
+        let mut levels = Vec::new();
+        let policy = Self::stock_policy(&mut *conn, line.shelf_id).await?;
+        for draw in Self::take_stock(&mut *conn, line, &policy).await? {
+            let moved = ItemXShelf {
+                status: draw.left.status,
+                bin_id: bin_to,
+                ..ItemXShelf::new(line.item_id, shelf_to, draw.taken, &draw.left.lot())
+            };
+            let arrived = Self::put_stock(&mut *conn, &moved).await?;
+            let movement_id = Self::insert_movement(&mut *conn, op, Some(&draw.left), Some(&arrived), draw.taken).await?;
+            Self::move_units(&mut *conn, movement_id, line.item_id, &draw.serials, Some(&arrived)).await?;
+            levels.push(draw.left);
+            levels.push(arrived);
+        }
+        Self::check_capacity(&mut *conn, shelf_to).await?;
+        Self::check_reservations(&mut *conn, line.item_id, line.shelf_id, op.meta.user_id).await?;
+        Ok(levels)
+    }
+
+    /// Put `line` on its shelf and return the new quantity of the lot. A `cost` adds to the cost
+    /// layers of the item, without one the items come in at the average cost of the stock still
+    /// costed, for withdrawals not to take the cost of other items.
+    async fn deposit_line(
+        conn: &mut MySqlConnection,
+        op: &MovementOp<'_>,
+        line: &ItemXShelf,
+        cost: Option<&Cost>,
+    ) -> Result<ItemXShelf, Error> {
+        Self::check_expected(&mut *conn, line.item_id, line.shelf_id, op.meta.expected_count).await?;
+        database::check_serials(Self::is_serialized(&mut *conn, line.item_id).await?, line)?;
+        let stock = Self::put_stock(&mut *conn, line).await?;
+        let movement_id = Self::insert_movement(&mut *conn, op, None, Some(&stock), line.count).await?;
+        Self::register_units(&mut *conn, movement_id, line, stock.bin_id).await?;
+        Self::check_capacity(&mut *conn, line.shelf_id).await?;
+        let cost = match cost {
+            Some(cost) => Some(cost.clone()),
+            None => Self::current_cost(&mut *conn, line.item_id, line.count).await?,
+        };
+        if let Some(cost) = cost {
+            Self::add_cost(&mut *conn, op, line.item_id, line.count, &cost.currency, cost.value).await?;
+        }
+        Ok(stock)
+    }
+
+    /// Take `line` off its shelf, returning what is left in each lot drawn from.
+    async fn withdraw_line(conn: &mut MySqlConnection, op: &MovementOp<'_>, line: &ItemXShelf) -> Result<Vec<ItemXShelf>, Error> {
+        Self::check_expected(&mut *conn, line.item_id, line.shelf_id, op.meta.expected_count).await?;
+        let mut levels = Vec::new();
+        let policy = Self::stock_policy(&mut *conn, line.shelf_id).await?;
+        for draw in Self::take_stock(&mut *conn, line, &policy).await? {
+            let movement_id = Self::insert_movement(&mut *conn, op, Some(&draw.left), None, draw.taken).await?;
+            Self::move_units(&mut *conn, movement_id, line.item_id, &draw.serials, None).await?;
+            levels.push(draw.left);
+        }
+        if line.status == StockStatus::Available {
+            Self::consume_reservations(&mut *conn, op, line.item_id, line.shelf_id, line.count).await?;
+        }
+        Self::check_reservations(&mut *conn, line.item_id, line.shelf_id, op.meta.user_id).await?;
+        Self::consume_cost(&mut *conn, op, line.item_id, line.count).await?;
+        Ok(levels)
+    }
+
+    /// Undo a movement line: take what it brought onto a shelf back off, put what it took off back
+    /// on, and record a `reversal` line linked to it. Returns the lots touched.
+    async fn reverse_line(conn: &mut MySqlConnection, op: &MovementOp<'_>, x: &StockMovement) -> Result<Vec<ItemXShelf>, Error> {
+        let sql = "SELECT su.serial
+FROM stock_units su
+         JOIN stock_unit_movements um ON um.unit_id = su.unit_id
+WHERE um.movement_id = ?
+ORDER BY su.serial";
+        let serials: Vec<String> = query_as::<_, (String,)>(sql)
+            .bind(x.movement_id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?
+            .into_iter()
+            .map(|(v,)| v)
+            .collect();
+        let lot = Lot {
+            lot_no: x.lot_no.clone(),
+            mfg_date: x.mfg_date,
+            expiry_date: x.expiry_date,
+        };
+        let mut levels = Vec::new();
+        if let Some(shelf_id) = x.shelf_to {
+            let line = ItemXShelf {
+                // the lot without a number is meant, not any lot
+                lot_no: Some(x.lot_no.clone().unwrap_or_default()),
+                status: x.status,
+                bin_id: x.bin_to,
+                serials: serials.clone(),
+                ..ItemXShelf::new(x.item_id, shelf_id, x.count, &lot)
+            };
+            // the shelf held nothing of it before, so it may be emptied again but not taken below zero
+            let policy = StockPolicy {
+                allow_empty: true,
+                allow_negative: false,
+                ..Self::stock_policy(&mut *conn, shelf_id).await?
+            };
+            let draws = match Self::take_stock(&mut *conn, &line, &policy).await {
+                Err(Error::InsufficientItem | Error::SerialNotFound) => return Err(Error::StockConsumed),
+                draws => draws?,
+            };
+            levels.extend(draws.into_iter().map(|draw| draw.left));
+            Self::check_reservations(&mut *conn, x.item_id, shelf_id, op.meta.user_id).await?;
+        } else {
+            for serial in &serials {
+                let sql = "SELECT shelf_id FROM stock_units WHERE item_id = ? AND serial = ?";
+                let (shelf_id,): (Option<ShelfId>,) = query_as(sql)
+                    .bind(x.item_id)
+                    .bind(serial)
+                    .fetch_one(&mut *conn)
+                    .await
+                    .map_err(|_| Error::Error)?;
+                if shelf_id.is_some() {
+                    return Err(Error::SerialInStock);
+                }
+            }
+        }
+        let mut bin_back = None;
+        if let Some(shelf_id) = x.shelf_from {
+            // back into the bin it came from, or the first bin of the shelf once that is gone
+            let bin_id = match Self::bin_of(&mut *conn, shelf_id, x.bin_from).await {
+                Err(Error::BinNotFound) => None,
+                bin_id => Some(bin_id?),
+            };
+            let line = ItemXShelf {
+                status: x.status,
+                bin_id,
+                ..ItemXShelf::new(x.item_id, shelf_id, x.count, &lot)
+            };
+            let back = Self::put_stock(&mut *conn, &line).await?;
+            bin_back = back.bin_id;
+            levels.push(back);
+        }
+        let movement_id = Self::insert_reversal(&mut *conn, op, x).await?;
+        for serial in &serials {
+            let sql = "UPDATE stock_units SET shelf_id = ?, bin_id = ?, lot_no = ?, status = ? WHERE item_id = ? AND serial = ?";
+            query(sql)
+                .bind(x.shelf_from)
+                .bind(bin_back)
+                .bind(x.lot_no.as_deref().unwrap_or_default())
+                .bind(x.status.as_str())
+                .bind(x.item_id)
+                .bind(serial)
+                .execute(&mut *conn)
+                .await
+                .map_err(|_| Error::Error)?;
+            Self::link_unit(&mut *conn, movement_id, x.item_id, serial).await?;
+        }
+        Ok(levels)
+    }
+
+    /// Hold again what an operation used up of the holds on the stock it took, bringing back the
+    /// holds it removed.
+    async fn restore_reservations(conn: &mut MySqlConnection, correlation_id: &str) -> Result<(), Error> {
+        let sql = "INSERT INTO stock_reservations (reservation_id, item_id, shelf_id, room_id, count, user_id, note, expires_at,
+                                created_at)
+SELECT reservation_id, item_id, shelf_id, room_id, CAST(SUM(count) AS SIGNED), user_id, note, expires_at, created_at
+FROM reservation_uses
+WHERE correlation_id = ?
+GROUP BY reservation_id, item_id, shelf_id, room_id, user_id, note, expires_at, created_at
+ON DUPLICATE KEY UPDATE count = stock_reservations.count + VALUES(count)";
+        query(sql)
+            .bind(correlation_id)
+            .execute(&mut *conn)
+            .await
+            .map(|_| ())
+            .map_err(|_| Error::Error)
+    }
+
+    /// Undo the cost entries of an operation. Costs it added are taken back off the newest layers of
+    /// the item, costs it consumed come back as they were taken out.
+    async fn reverse_cost(conn: &mut MySqlConnection, op: &MovementOp<'_>, correlation_id: &str) -> Result<(), Error> {
+        let sql = "SELECT item_id, currency, CAST(count AS SIGNED), CAST(value AS SIGNED)
+FROM cost_entries
+WHERE correlation_id = ?
+ORDER BY entry_id";
+        let entries: Vec<(ItemId, String, i64, i64)> = query_as(sql)
+            .bind(correlation_id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        for (item_id, currency, count, value) in entries {
+            if count < 0 {
+                Self::add_cost(&mut *conn, op, item_id, -count, &currency, -value).await?;
+                continue;
+            }
+            let mut wanted = count;
+            let (mut taken_count, mut taken_value) = (0, 0);
+            for layer in Self::open_cost_layers(&mut *conn, item_id).await?.into_iter().rev() {
+                if wanted == 0 {
+                    break;
+                }
+                let n = wanted.min(layer.remaining);
+                let value = database::cost_share(layer.value, n, layer.remaining);
+                let sql = "UPDATE cost_layers SET remaining = remaining - ?, value = value - ? WHERE layer_id = ?";
+                query(sql)
+                    .bind(n)
+                    .bind(value)
+                    .bind(layer.layer_id)
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(|_| Error::Error)?;
+                wanted -= n;
+                taken_count += n;
+                taken_value += value;
+            }
+            if taken_count > 0 {
+                Self::insert_cost_entry(&mut *conn, op, item_id, &currency, -taken_count, -taken_value).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Group the units named by `line.serials` by the bin and lot they are in. Every unit must be on
+    /// the shelf in status `line.status`, and in bin `line.bin_id` and lot `line.lot_no` when they
+    /// are given.
+    async fn units_by_bin_and_lot(
+        conn: &mut MySqlConnection,
+        line: &ItemXShelf,
+    ) -> Result<BTreeMap<(Option<BinId>, String), Vec<String>>, Error> {
+        let mut units: BTreeMap<(Option<BinId>, String), Vec<String>> = BTreeMap::new();
+        for serial in &line.serials {
+            let sql = "SELECT bin_id, lot_no FROM stock_units WHERE item_id = ? AND serial = ? AND shelf_id = ? AND status = ?";
+            let (bin_id, lot_no): (Option<BinId>, String) = query_as(sql)
+                .bind(line.item_id)
+                .bind(serial)
+                .bind(line.shelf_id)
+                .bind(line.status.as_str())
+                .fetch_optional(&mut *conn)
+                .await
+                .map_err(|_| Error::Error)?
+                .ok_or(Error::SerialNotFound)?;
+            if line.bin_id.is_some_and(|wanted| Some(wanted) != bin_id)
+                || line.lot_no.as_ref().is_some_and(|wanted| *wanted != lot_no)
+            {
+                return Err(Error::SerialNotFound);
+            }
+            units.entry((bin_id, lot_no)).or_default().push(serial.clone());
+        }
+        Ok(units)
+    }
+
+    /// Put `line.count` items into a lot in a bin of a shelf, in status `line.status`, and return the lot as it is now.
+    /// Stock without a bin goes into the first bin of the shelf. Dates already recorded for the lot are kept. The lot
+    /// is added to in place, so deposits made at the same time all count.
+    async fn put_stock(conn: &mut MySqlConnection, line: &ItemXShelf) -> Result<ItemXShelf, Error> {
+        Self::check_not_frozen(&mut *conn, line.shelf_id).await?;
+        Self::check_not_archived(&mut *conn, line.shelf_id).await?;
+        let bin_id = Self::bin_of(&mut *conn, line.shelf_id, line.bin_id).await?;
+        let lot_no = line.lot_no.as_deref().unwrap_or_default();
+        let sql = "INSERT INTO stock (count, mfg_date, expiry_date, item_id, shelf_id, bin_id, lot_no, status)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+ON DUPLICATE KEY UPDATE count       = count + VALUES(count),
+                        mfg_date    = COALESCE(mfg_date, VALUES(mfg_date)),
+                        expiry_date = COALESCE(expiry_date, VALUES(expiry_date))";
+        query(sql)
+            .bind(line.count)
+            .bind(line.mfg_date)
+            .bind(line.expiry_date)
+            .bind(line.item_id)
+            .bind(line.shelf_id)
+            .bind(bin_id)
+            .bind(lot_no)
+            .bind(line.status.as_str())
+            .execute(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        let sql = "SELECT item_id, shelf_id, count, NULLIF(lot_no, '') lot_no, mfg_date, expiry_date, status, bin_id
+FROM stock
+WHERE item_id = ? AND shelf_id = ? AND bin_id = ? AND lot_no = ? AND status = ?";
+        query_as::<_, ItemXShelf>(sql)
+            .bind(line.item_id)
+            .bind(line.shelf_id)
+            .bind(bin_id)
+            .bind(lot_no)
+            .bind(line.status.as_str())
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)
+    }
+
+    /// The bin `bin_id` of a shelf, or the first bin of the shelf when it is `None`. Fails with
+    /// `Error::BinNotFound` when the shelf has no such bin.
+    async fn bin_of(conn: &mut MySqlConnection, shelf_id: ShelfId, bin_id: Option<BinId>) -> Result<BinId, Error> {
+        let sql = "SELECT bin_id
+FROM bins
+WHERE shelf_id = ?
+  AND (? IS NULL OR bin_id = ?)
+ORDER BY layer, position
+LIMIT 1";
+        query_as::<_, (BinId,)>(sql)
+            .bind(shelf_id)
+            .bind(bin_id)
+            .bind(bin_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?
+            .map(|(v,)| v)
+            .ok_or(Error::BinNotFound)
+    }
+
+    /// Add the bins of `layers` of `positions` each a shelf does not have yet.
+    async fn insert_bins(conn: &mut MySqlConnection, shelf_id: ShelfId, layers: i64, positions: i64) -> Result<(), Error> {
+        for layer in 1..=layers {
+            for position in 1..=positions {
+                let sql = "INSERT IGNORE INTO bins (shelf_id, layer, position) VALUES (?, ?, ?)";
+                query(sql)
+                    .bind(shelf_id)
+                    .bind(layer)
+                    .bind(position)
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(|_| Error::Error)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Put the units named by `line.serials` into bin `bin_id` of its shelf as they enter the stock,
+    /// and link them to the movement that brought them in.
+    async fn register_units(
+        conn: &mut MySqlConnection,
+        movement_id: MovementId,
+        line: &ItemXShelf,
+        bin_id: Option<BinId>,
+    ) -> Result<(), Error> {
+        let lot_no = line.lot_no.as_deref().unwrap_or_default();
+        for serial in &line.serials {
+            let sql = "UPDATE stock_units SET shelf_id = ?, bin_id = ?, lot_no = ?, status = ? WHERE item_id = ? AND serial = ? AND shelf_id IS NULL";
+            let updated = query(sql)
+                .bind(line.shelf_id)
+                .bind(bin_id)
+                .bind(lot_no)
+                .bind(line.status.as_str())
+                .bind(line.item_id)
+                .bind(serial)
+                .execute(&mut *conn)
+                .await
+                .map_err(|_| Error::Error)?
+                .rows_affected();
+            if updated == 0 {
+                // a unit already on a shelf violates the unique serial
+                let sql = "INSERT INTO stock_units (item_id, serial, shelf_id, bin_id, lot_no, status) VALUES (?, ?, ?, ?, ?, ?)";
+                query(sql)
+                    .bind(line.item_id)
+                    .bind(serial)
+                    .bind(line.shelf_id)
+                    .bind(bin_id)
+                    .bind(lot_no)
+                    .bind(line.status.as_str())
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(|_| Error::SerialInStock)?;
+            }
+            Self::link_unit(&mut *conn, movement_id, line.item_id, serial).await?;
+        }
+        Ok(())
+    }
+
+    /// Move units into the shelf and bin of the stock row `to`, or out of the stock when it is `None`,
+    /// and link them to the movement.
+    async fn move_units(
+        conn: &mut MySqlConnection,
+        movement_id: MovementId,
+        item_id: ItemId,
+        serials: &[String],
+        to: Option<&ItemXShelf>,
+    ) -> Result<(), Error> {
+        for serial in serials {
+            let sql = "UPDATE stock_units SET shelf_id = ?, bin_id = ? WHERE item_id = ? AND serial = ?";
+            query(sql)
+                .bind(to.map(|x| x.shelf_id))
+                .bind(to.and_then(|x| x.bin_id))
+                .bind(item_id)
+                .bind(serial)
+                .execute(&mut *conn)
+                .await
+                .map_err(|_| Error::Error)?;
+            Self::link_unit(&mut *conn, movement_id, item_id, serial).await?;
+        }
+        Ok(())
+    }
+
+    async fn link_unit(conn: &mut MySqlConnection, movement_id: MovementId, item_id: ItemId, serial: &str) -> Result<(), Error> {
+        let sql = "INSERT INTO stock_unit_movements (unit_id, movement_id)
+SELECT unit_id, ?
+FROM stock_units
+WHERE item_id = ? AND serial = ?";
+        query(sql)
+            .bind(movement_id)
+            .bind(item_id)
+            .bind(serial)
+            .execute(&mut *conn)
+            .await
+            .map(|_| ())
+            .map_err(|_| Error::Error)
+    }
+
+    /// Append one line to the stock ledger, moving `count` out of the stock row `from` into `to`.
+    /// The line is for the lot and status of `from`, or of `to` when nothing is taken.
+    async fn insert_movement(
+        conn: &mut MySqlConnection,
+        op: &MovementOp<'_>,
+        from: Option<&ItemXShelf>,
+        to: Option<&ItemXShelf>,
+        count: i64,
+    ) -> Result<MovementId, Error> {
+        let Some(lot) = from.or(to) else {
+            return Err(Error::Error);
+        };
+        let shelf_from = from.map(|x| x.shelf_id);
+        let shelf_to = to.map(|x| x.shelf_id);
+        let sql =
+            "INSERT INTO stock_movements (correlation_id, kind, item_id, shelf_from, shelf_to, bin_from, bin_to, count, lot_no,
+                             mfg_date, expiry_date, status, user_id, reason, cross_site)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+        EXISTS (SELECT 1
+                FROM shelf a
+                         JOIN rooms ra ON ra.room_id = a.room_id,
+                     shelf b
+                         JOIN rooms rb ON rb.room_id = b.room_id
+                WHERE a.shelf_id = ?
+                  AND b.shelf_id = ?
+                  AND ra.warehouse_id <> rb.warehouse_id))";
+        query(sql)
+            .bind(&op.correlation_id)
+            .bind(op.kind.as_str())
+            .bind(lot.item_id)
+            .bind(shelf_from)
+            .bind(shelf_to)
+            .bind(from.and_then(|x| x.bin_id))
+            .bind(to.and_then(|x| x.bin_id))
+            .bind(count)
+            .bind(lot.lot_no.as_deref())
+            .bind(lot.mfg_date)
+            .bind(lot.expiry_date)
+            .bind(lot.status.as_str())
+            .bind(op.meta.user_id)
+            .bind(&op.meta.reason)
+            .bind(shelf_from)
+            .bind(shelf_to)
+            .execute(&mut *conn)
+            .await
+            .map(|v| v.last_insert_id() as i64)
+            .map_err(|_| Error::Error)
+    }
+
+    /// Record a `reversal` line undoing `x`, linked to it.
+    async fn insert_reversal(conn: &mut MySqlConnection, op: &MovementOp<'_>, x: &StockMovement) -> Result<MovementId, Error> {
+        let sql =
+            "INSERT INTO stock_movements (correlation_id, kind, item_id, shelf_from, shelf_to, bin_from, bin_to, count, lot_no,
+                             mfg_date, expiry_date, status, user_id, reason, reversal_of, cross_site)
+VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+        query(sql)
+            .bind(&op.correlation_id)
+            .bind(op.kind.as_str())
+            .bind(x.item_id)
+            .bind(x.shelf_to)
+            .bind(x.shelf_from)
+            .bind(x.bin_to)
+            .bind(x.bin_from)
+            .bind(x.count)
+            .bind(x.lot_no.as_deref())
+            .bind(x.mfg_date)
+            .bind(x.expiry_date)
+            .bind(x.status.as_str())
+            .bind(op.meta.user_id)
+            .bind(&op.meta.reason)
+            .bind(x.movement_id)
+            .bind(x.cross_site)
+            .execute(&mut *conn)
+            .await
+            .map(|v| v.last_insert_id() as i64)
+            .map_err(|_| Error::Error)
+    }
+
+    async fn room_of_shelf(conn: &mut MySqlConnection, shelf_id: ShelfId) -> Result<RoomId, Error> {
+        let sql = "SELECT room_id FROM shelf WHERE shelf_id = ?";
+        query_as::<_, (RoomId,)>(sql)
+            .bind(shelf_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?
+            .map(|(v,)| v)
+            .ok_or(Error::ShelfNotFound)
+    }
+
+    /// The available items on a shelf not held by reservations on it, leaving out the holds of `user_id`.
+    /// Negative when the shelf holds less than that, stock below zero counting as none.
+    async fn free_on_shelf(
+        conn: &mut MySqlConnection,
+        item_id: ItemId,
+        shelf_id: ShelfId,
+        user_id: Option<UserId>,
+    ) -> Result<i64, Error> {
+        let sql = "SELECT CAST(GREATEST(COALESCE((SELECT SUM(si.count)
+                      FROM stock si
+                      WHERE si.item_id = ? AND si.shelf_id = ? AND si.status = 'available'), 0), 0)
+           - COALESCE((SELECT SUM(sr.count)
+                      FROM stock_reservations sr
+                      WHERE sr.item_id = ?
+                        AND sr.shelf_id = ?
+                        AND (sr.expires_at IS NULL OR sr.expires_at > CURRENT_TIMESTAMP)
+                        AND (? IS NULL OR sr.user_id <> ?)), 0) AS SIGNED)";
+        query_as::<_, (i64,)>(sql)
+            .bind(item_id)
+            .bind(shelf_id)
+            .bind(item_id)
+            .bind(shelf_id)
+            .bind(user_id)
+            .bind(user_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error)
+    }
+
+    /// The available items in a room not held by reservations on it or on any of its shelves, leaving out
+    /// the holds of `user_id`. Negative when the room holds less than that, stock below zero counting as none.
+    async fn free_in_room(
+        conn: &mut MySqlConnection,
+        item_id: ItemId,
+        room_id: RoomId,
+        user_id: Option<UserId>,
+    ) -> Result<i64, Error> {
+        let sql = "SELECT CAST(GREATEST(COALESCE((SELECT SUM(si.count)
+                      FROM stock si
+                               JOIN shelf sf ON sf.shelf_id = si.shelf_id
+                      WHERE si.item_id = ? AND sf.room_id = ? AND si.status = 'available'), 0), 0)
+           - COALESCE((SELECT SUM(sr.count)
+                      FROM stock_reservations sr
+                               LEFT JOIN shelf sf ON sf.shelf_id = sr.shelf_id
+                      WHERE sr.item_id = ?
+                        AND COALESCE(sr.room_id, sf.room_id) = ?
+                        AND (sr.expires_at IS NULL OR sr.expires_at > CURRENT_TIMESTAMP)
+                        AND (? IS NULL OR sr.user_id <> ?)), 0) AS SIGNED)";
+        query_as::<_, (i64,)>(sql)
+            .bind(item_id)
+            .bind(room_id)
+            .bind(item_id)
+            .bind(room_id)
+            .bind(user_id)
+            .bind(user_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map(|(v,)| v)
+            .map_err(|_| Error::Error)
+    }
+
+    /// Refuse a change leaving less of an item on the shelf, or in its room, than other users hold there.
+    async fn check_reservations(
+        conn: &mut MySqlConnection,
+        item_id: ItemId,
+        shelf_id: ShelfId,
+        user_id: UserId,
+    ) -> Result<(), Error> {
+        let room_id = Self::room_of_shelf(&mut *conn, shelf_id).await?;
+        if Self::free_on_shelf(&mut *conn, item_id, shelf_id, Some(user_id)).await? < 0
+            || Self::free_in_room(&mut *conn, item_id, room_id, Some(user_id)).await? < 0
+        {
+            return Err(Error::StockReserved);
+        }
+        Ok(())
+    }
+
+    /// Use up the holds the user of `op` has on `count` items taken off a shelf: those on the shelf
+    /// first, then those on its room, the soonest to expire first. Used up holds are removed, what
+    /// was used of each is kept for a reversal of the operation.
+    async fn consume_reservations(
+        conn: &mut MySqlConnection,
+        op: &MovementOp<'_>,
+        item_id: ItemId,
+        shelf_id: ShelfId,
+        count: i64,
+    ) -> Result<(), Error> {
+        let sql = "SELECT sr.reservation_id, sr.count
+FROM stock_reservations sr
+         JOIN shelf sf ON sf.shelf_id = ?
+WHERE sr.item_id = ?
+  AND sr.user_id = ?
+  AND (sr.shelf_id = sf.shelf_id OR sr.room_id = sf.room_id)
+  AND (sr.expires_at IS NULL OR sr.expires_at > CURRENT_TIMESTAMP)
+ORDER BY sr.shelf_id IS NULL, sr.expires_at IS NULL, sr.expires_at, sr.reservation_id";
+        let holds: Vec<(ReservationId, i64)> = query_as(sql)
+            .bind(shelf_id)
+            .bind(item_id)
+            .bind(op.meta.user_id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        let mut wanted = count;
+        for (reservation_id, held) in holds {
+            if wanted == 0 {
+                break;
             }
-            let upsert_result = query(sql)
-                .bind(new_count)
-                .bind(x_into.item_id)
-                .bind(x_into.shelf_id)
-                .execute(&mut *tx)
+            let used = wanted.min(held);
+            wanted -= used;
+            let sql =
+                "INSERT INTO reservation_uses (correlation_id, reservation_id, item_id, shelf_id, room_id, count, user_id, note,
+                              expires_at, created_at)
+SELECT ?, reservation_id, item_id, shelf_id, room_id, ?, user_id, note, expires_at, created_at
+FROM stock_reservations
+WHERE reservation_id = ?";
+            query(sql)
+                .bind(&op.correlation_id)
+                .bind(used)
+                .bind(reservation_id)
+                .execute(&mut *conn)
                 .await
-                .map_err(|_| Error::Error)
-                .and_then(|v| if v.rows_affected() > 0 { Ok(()) } else { Err(Error::Error) });
-            if let Err(err) = upsert_result {
-                drop(tx.rollback().await);
-                return Err(err);
+                .map_err(|_| Error::Error)?;
+            let statement = if held > used {
+                query("UPDATE stock_reservations SET count = ? WHERE reservation_id = ?").bind(held - used)
+            } else {
+                query("DELETE FROM stock_reservations WHERE reservation_id = ?")
+            };
+            statement
+                .bind(reservation_id)
+                .execute(&mut *conn)
+                .await
+                .map_err(|_| Error::Error)?;
+        }
+        Ok(())
+    }
+
+    async fn stocktake_status(conn: &mut MySqlConnection, stocktake_id: StocktakeId) -> Result<StocktakeStatus, Error> {
+        let sql = "SELECT status FROM stocktakes WHERE stocktake_id = ?";
+        query_as::<_, (StocktakeStatus,)>(sql)
+            .bind(stocktake_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?
+            .map(|(v,)| v)
+            .ok_or(Error::StocktakeNotFound)
+    }
+
+    /// Close an open stocktake as approved or cancelled, lifting its freeze.
+    async fn close_stocktake(
+        conn: &mut MySqlConnection,
+        stocktake_id: StocktakeId,
+        status: StocktakeStatus,
+        user_id: UserId,
+    ) -> Result<(), Error> {
+        if Self::stocktake_status(&mut *conn, stocktake_id).await? != StocktakeStatus::Open {
+            return Err(Error::StocktakeClosed);
+        }
+        let sql = "UPDATE stocktakes SET status = ?, closed_by = ?, closed_at = CURRENT_TIMESTAMP WHERE stocktake_id = ? AND status = 'open'";
+        query(sql)
+            .bind(status.as_str())
+            .bind(user_id)
+            .bind(stocktake_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)
+            .and_then(|v| {
+                if v.rows_affected() > 0 {
+                    Ok(())
+                } else {
+                    Err(Error::StocktakeClosed)
+                }
+            })
+    }
+
+    /// Archive a room along with its shelves, returning the shelves archived now. The stock on them
+    /// is left as it is. Fails with `Error::RoomNotFound` unless the room is there and not archived.
+    async fn archive_room_only(conn: &mut MySqlConnection, room_id: RoomId) -> Result<Vec<ShelfId>, Error> {
+        let sql = "UPDATE rooms SET archived_at = CURRENT_TIMESTAMP WHERE room_id = ? AND archived_at IS NULL";
+        let archived = query(sql)
+            .bind(room_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?
+            .rows_affected();
+        if archived == 0 {
+            return Err(Error::RoomNotFound);
+        }
+        let sql = "SELECT shelf_id FROM shelf WHERE room_id = ? AND archived_at IS NULL ORDER BY shelf_id";
+        let shelves: Vec<(ShelfId,)> = query_as(sql)
+            .bind(room_id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        // the shelves share the time of the room, so restoring the room brings back these and not
+        // those archived on their own before
+        let sql = "UPDATE shelf
+SET archived_at = (SELECT r.archived_at FROM rooms r WHERE r.room_id = ?)
+WHERE room_id = ?
+  AND archived_at IS NULL";
+        query(sql)
+            .bind(room_id)
+            .bind(room_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        Ok(shelves.into_iter().map(|(v,)| v).collect())
+    }
+
+    /// Archive a shelf, leaving the stock on it as it is. Fails with `Error::ShelfNotFound` unless
+    /// the shelf is there and not archived.
+    async fn archive_shelf_only(conn: &mut MySqlConnection, shelf_id: ShelfId) -> Result<(), Error> {
+        let sql = "UPDATE shelf SET archived_at = CURRENT_TIMESTAMP WHERE shelf_id = ? AND archived_at IS NULL";
+        let archived = query(sql)
+            .bind(shelf_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?
+            .rows_affected();
+        if archived == 0 {
+            return Err(Error::ShelfNotFound);
+        }
+        Ok(())
+    }
+
+    /// Move a room to `warehouse_id`, refusing while stock is left in the room since it would leave
+    /// for another site without any movement recording it.
+    async fn move_room(conn: &mut MySqlConnection, room_id: RoomId, warehouse_id: WarehouseId) -> Result<(), Error> {
+        let sql = "SELECT warehouse_id FROM rooms WHERE room_id = ?";
+        let (current,): (WarehouseId,) = query_as(sql)
+            .bind(room_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?
+            .ok_or(Error::RoomNotFound)?;
+        if current == warehouse_id {
+            return Ok(());
+        }
+        let sql = "SELECT CAST(COUNT(*) AS SIGNED) count
+FROM stock s
+JOIN shelf sf ON sf.shelf_id = s.shelf_id
+WHERE sf.room_id = ? AND s.count <> 0";
+        let (stocked,): (i64,) = query_as(sql)
+            .bind(room_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        if stocked > 0 {
+            return Err(Error::RoomHasStock);
+        }
+        let sql = "UPDATE rooms SET warehouse_id = ? WHERE room_id = ?";
+        query(sql)
+            .bind(warehouse_id)
+            .bind(room_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(err) if err.is_foreign_key_violation() => Error::WarehouseNotFound,
+                _ => Error::Error,
+            })?;
+        Ok(())
+    }
+
+    /// Refuse to archive a shelf still holding stock, or below zero.
+    async fn check_shelf_empty(conn: &mut MySqlConnection, shelf_id: ShelfId) -> Result<(), Error> {
+        let sql = "SELECT CAST(COUNT(*) AS SIGNED) count FROM stock WHERE shelf_id = ? AND count <> 0";
+        let (stocked,): (i64,) = query_as(sql)
+            .bind(shelf_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        if stocked > 0 {
+            return Err(Error::LocationNotEmpty);
+        }
+        Ok(())
+    }
+
+    /// Delete an item, unless it has stock, empty lots of it are kept on a shelf or anything else
+    /// refers to it.
+    async fn delete_unused_item(conn: &mut MySqlConnection, item_id: ItemId) -> Result<(), Error> {
+        let sql = "SELECT item_id FROM items WHERE item_id = ?";
+        query_as::<_, (ItemId,)>(sql)
+            .bind(item_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?
+            .ok_or(Error::ItemNotFound)?;
+        let sql = "SELECT CAST(COUNT(*) AS SIGNED) lots, CAST(COUNT(CASE WHEN count <> 0 THEN 1 END) AS SIGNED) stocked
+FROM stock
+WHERE item_id = ?";
+        let (lots, stocked): (i64, i64) = query_as(sql)
+            .bind(item_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        if stocked > 0 {
+            return Err(Error::ItemInStock);
+        }
+        if lots > 0 {
+            return Err(Error::ItemOnShelf);
+        }
+        // movements, units, reservations and the like keep the item, failing the delete
+        let sql = "DELETE FROM items WHERE item_id = ?";
+        query(sql)
+            .bind(item_id)
+            .execute(&mut *conn)
+            .await
+            .map(|_| ())
+            .map_err(|_| Error::ItemReferenced)
+    }
+
+    /// Move the stock left on a shelf being archived to `shelf_to`, into `bin_to` when given, whatever the
+    /// policy of the shelf. Fails with `Error::LocationNotEmpty` when there is stock and nowhere to move
+    /// it, or some of it is below zero.
+    async fn relocate_stock(
+        conn: &mut MySqlConnection,
+        op: &MovementOp<'_>,
+        shelf_id: ShelfId,
+        shelf_to: Option<ShelfId>,
+        bin_to: Option<BinId>,
+    ) -> Result<(), Error> {
+        Self::relocate_reservations(&mut *conn, shelf_id, shelf_to).await?;
+        let sql = "SELECT item_id, status, CAST(SUM(count) AS SIGNED) count, CAST(MIN(count) AS SIGNED) lowest
+FROM stock
+WHERE shelf_id = ?
+  AND count <> 0
+GROUP BY item_id, status
+ORDER BY item_id, status";
+        let left: Vec<(ItemId, StockStatus, i64, i64)> = query_as(sql)
+            .bind(shelf_id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        for (item_id, status, count, lowest) in left {
+            let Some(shelf_to) = shelf_to.filter(|_| lowest > 0) else {
+                return Err(Error::LocationNotEmpty);
+            };
+            let sql = "SELECT serial FROM stock_units WHERE item_id = ? AND shelf_id = ? AND status = ? ORDER BY serial";
+            let serials: Vec<(String,)> = query_as(sql)
+                .bind(item_id)
+                .bind(shelf_id)
+                .bind(status.as_str())
+                .fetch_all(&mut *conn)
+                .await
+                .map_err(|_| Error::Error)?;
+            let line = ItemXShelf {
+                status,
+                serials: serials.into_iter().map(|(v,)| v).collect(),
+                ..ItemXShelf::new(item_id, shelf_id, count, &Lot::default())
+            };
+            let policy = StockPolicy {
+                allow_empty: true,
+                allow_negative: false,
+                ..Self::stock_policy(&mut *conn, shelf_id).await?
+            };
+            for draw in Self::take_stock(&mut *conn, &line, &policy).await? {
+                let moved = ItemXShelf {
+                    status: draw.left.status,
+                    bin_id: bin_to,
+                    ..ItemXShelf::new(item_id, shelf_to, draw.taken, &draw.left.lot())
+                };
+                let arrived = Self::put_stock(&mut *conn, &moved).await?;
+                let movement_id = Self::insert_movement(&mut *conn, op, Some(&draw.left), Some(&arrived), draw.taken).await?;
+                Self::move_units(&mut *conn, movement_id, item_id, &draw.serials, Some(&arrived)).await?;
+            }
+            Self::check_reservations(&mut *conn, item_id, shelf_id, op.meta.user_id).await?;
+            Self::check_capacity(&mut *conn, shelf_to).await?;
+        }
+        Ok(())
+    }
+
+    /// Move the holds on a shelf being archived to `shelf_to` along with its stock, or release them
+    /// when the stock has nowhere to go.
+    async fn relocate_reservations(
+        conn: &mut MySqlConnection,
+        shelf_id: ShelfId,
+        shelf_to: Option<ShelfId>,
+    ) -> Result<(), Error> {
+        let statement = match shelf_to {
+            Some(shelf_to) => query("UPDATE stock_reservations SET shelf_id = ? WHERE shelf_id = ?").bind(shelf_to),
+            None => query("DELETE FROM stock_reservations WHERE shelf_id = ?"),
+        };
+        statement
+            .bind(shelf_id)
+            .execute(&mut *conn)
+            .await
+            .map(|_| ())
+            .map_err(|_| Error::Error)
+    }
+
+    /// Move the holds on a room being archived to the room of `shelf_to` along with its stock, or
+    /// release them when the stock has nowhere to go.
+    async fn relocate_room_reservations(
+        conn: &mut MySqlConnection,
+        room_id: RoomId,
+        shelf_to: Option<ShelfId>,
+    ) -> Result<(), Error> {
+        let statement = match shelf_to {
+            Some(shelf_to) => query(
+                "UPDATE stock_reservations SET room_id = (SELECT sf.room_id FROM shelf sf WHERE sf.shelf_id = ?) WHERE room_id = ?",
+            )
+            .bind(shelf_to),
+            None => query("DELETE FROM stock_reservations WHERE room_id = ?"),
+        };
+        statement
+            .bind(room_id)
+            .execute(&mut *conn)
+            .await
+            .map(|_| ())
+            .map_err(|_| Error::Error)
+    }
+
+    /// Refuse to put stock on an archived shelf, or one in an archived room.
+    async fn check_not_archived(conn: &mut MySqlConnection, shelf_id: ShelfId) -> Result<(), Error> {
+        let sql = "SELECT CAST(COUNT(*) AS SIGNED) count
+FROM shelf sf
+         JOIN rooms r ON r.room_id = sf.room_id
+WHERE sf.shelf_id = ?
+  AND (sf.archived_at IS NOT NULL OR r.archived_at IS NOT NULL)";
+        let (archived,): (i64,) = query_as(sql)
+            .bind(shelf_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        if archived > 0 {
+            return Err(Error::LocationArchived);
+        }
+        Ok(())
+    }
+
+    /// Refuse to move stock in or out of a shelf frozen by an open stocktake.
+    async fn check_not_frozen(conn: &mut MySqlConnection, shelf_id: ShelfId) -> Result<(), Error> {
+        let sql = "SELECT COUNT(*) count
+FROM stocktake_shelves ss
+         JOIN stocktakes st ON st.stocktake_id = ss.stocktake_id
+WHERE ss.shelf_id = ? AND st.status = 'open' AND st.frozen";
+        let (frozen,): (i64,) = query_as(sql)
+            .bind(shelf_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        if frozen > 0 {
+            return Err(Error::StocktakeInProgress);
+        }
+        Ok(())
+    }
+
+    async fn get_bom_components(&self, bom_id: BomId) -> Result<Vec<BomComponent>, Error> {
+        let sql = "SELECT item_id, count FROM bom_components WHERE bom_id = ? ORDER BY item_id";
+        query_as::<_, BomComponent>(sql)
+            .bind(bom_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|_| Error::Error)
+    }
+
+    /// The latest snapshot taken at or before `as_of` and the last movement it includes. Without one,
+    /// `(0, MovementId::MAX)` has the stock rebuilt backwards from the current one instead.
+    async fn snapshot_before(&self, as_of: OffsetDateTime) -> Result<(SnapshotId, MovementId), Error> {
+        let sql = "SELECT snapshot_id, last_movement_id
+FROM stock_snapshots
+WHERE taken_at <= ?
+ORDER BY taken_at DESC, snapshot_id DESC
+LIMIT 1";
+        query_as::<_, (SnapshotId, MovementId)>(sql)
+            .bind(as_of)
+            .fetch_optional(&self.pool)
+            .await
+            .map(|v| v.unwrap_or((0, MovementId::MAX)))
+            .map_err(|_| Error::Error)
+    }
+
+    /// The cost layers of an item still holding stock, oldest first.
+    async fn open_cost_layers(conn: &mut MySqlConnection, item_id: ItemId) -> Result<Vec<CostLayer>, Error> {
+        let sql = "SELECT * FROM cost_layers WHERE item_id = ? AND remaining > 0 ORDER BY layer_id";
+        query_as::<_, CostLayer>(sql)
+            .bind(item_id)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)
+    }
+
+    /// What `count` items of an item are worth at the average cost of its stock still costed, `None`
+    /// when none of it is.
+    async fn current_cost(conn: &mut MySqlConnection, item_id: ItemId, count: i64) -> Result<Option<Cost>, Error> {
+        let layers = Self::open_cost_layers(conn, item_id).await?;
+        let Some(first) = layers.first() else {
+            return Ok(None);
+        };
+        let remaining = layers.iter().map(|x| x.remaining).sum();
+        let value = layers.iter().map(|x| x.value).sum();
+        Ok(Some(Cost {
+            value: database::cost_share(value, count, remaining),
+            currency: first.currency.clone(),
+        }))
+    }
+
+    /// Add items worth `value` to the cost of an item: as a new layer under FIFO, into its single
+    /// layer under the average. The stock of an item is costed in one currency at a time.
+    async fn add_cost(
+        conn: &mut MySqlConnection,
+        op: &MovementOp<'_>,
+        item_id: ItemId,
+        count: i64,
+        currency: &str,
+        value: i64,
+    ) -> Result<(), Error> {
+        let sql = "SELECT costing FROM items WHERE item_id = ?";
+        let (costing,): (CostingMethod,) = query_as(sql)
+            .bind(item_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?
+            .ok_or(Error::ItemNotFound)?;
+        let layers = Self::open_cost_layers(conn, item_id).await?;
+        if layers.iter().any(|x| x.currency != currency) {
+            return Err(Error::CurrencyMismatch);
+        }
+        match (costing, layers.first()) {
+            (CostingMethod::Average, Some(layer)) => {
+                let sql = "UPDATE cost_layers SET remaining = remaining + ?, value = value + ? WHERE layer_id = ?";
+                query(sql)
+                    .bind(count)
+                    .bind(value)
+                    .bind(layer.layer_id)
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(|_| Error::Error)?;
+            }
+            _ => {
+                let sql = "INSERT INTO cost_layers (item_id, currency, remaining, value) VALUES (?, ?, ?, ?)";
+                query(sql)
+                    .bind(item_id)
+                    .bind(currency)
+                    .bind(count)
+                    .bind(value)
+                    .execute(&mut *conn)
+                    .await
+                    .map_err(|_| Error::Error)?;
+            }
+        }
+        Self::insert_cost_entry(conn, op, item_id, currency, count, value).await
+    }
+
+    /// Take items off the cost layers of an item, oldest first, returning the currency and value of
+    /// what was taken. Items beyond what the layers hold carry no cost, `None` when none of them did.
+    async fn consume_cost(
+        conn: &mut MySqlConnection,
+        op: &MovementOp<'_>,
+        item_id: ItemId,
+        count: i64,
+    ) -> Result<Option<(String, i64)>, Error> {
+        let mut wanted = count;
+        let mut taken: Option<(String, i64, i64)> = None;
+        for layer in Self::open_cost_layers(conn, item_id).await? {
+            if wanted == 0 {
+                break;
+            }
+            let n = wanted.min(layer.remaining);
+            let value = database::cost_share(layer.value, n, layer.remaining);
+            let sql = "UPDATE cost_layers SET remaining = remaining - ?, value = value - ? WHERE layer_id = ?";
+            query(sql)
+                .bind(n)
+                .bind(value)
+                .bind(layer.layer_id)
+                .execute(&mut *conn)
+                .await
+                .map_err(|_| Error::Error)?;
+            wanted -= n;
+            let (_, taken_count, taken_value) = taken.get_or_insert((layer.currency, 0, 0));
+            *taken_count += n;
+            *taken_value += value;
+        }
+        let Some((currency, taken_count, taken_value)) = taken else {
+            return Ok(None);
+        };
+        Self::insert_cost_entry(conn, op, item_id, &currency, -taken_count, -taken_value).await?;
+        Ok(Some((currency, taken_value)))
+    }
+
+    /// Append one line to the cost ledger.
+    async fn insert_cost_entry(
+        conn: &mut MySqlConnection,
+        op: &MovementOp<'_>,
+        item_id: ItemId,
+        currency: &str,
+        count: i64,
+        value: i64,
+    ) -> Result<(), Error> {
+        let sql = "INSERT INTO cost_entries (correlation_id, item_id, currency, count, value) VALUES (?, ?, ?, ?, ?)";
+        query(sql)
+            .bind(&op.correlation_id)
+            .bind(item_id)
+            .bind(currency)
+            .bind(count)
+            .bind(value)
+            .execute(&mut *conn)
+            .await
+            .map(|_| ())
+            .map_err(|_| Error::Error)
+    }
+
+    /// The stock policy in effect on a shelf: that of its room, or else the one of every room.
+    async fn stock_policy(conn: &mut MySqlConnection, shelf_id: ShelfId) -> Result<StockPolicy, Error> {
+        let sql = "SELECT sp.room_id, sp.allow_empty, sp.keep_empty, sp.allow_negative, sp.warn_over_capacity
+FROM stock_policies sp
+WHERE sp.room_id IS NULL
+   OR sp.room_id = (SELECT sf.room_id FROM shelf sf WHERE sf.shelf_id = ?)
+ORDER BY sp.room_id IS NULL
+LIMIT 1";
+        query_as::<_, StockPolicy>(sql)
+            .bind(shelf_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map(Option::unwrap_or_default)
+            .map_err(|_| Error::Error)
+    }
+
+    /// How much of its capacity a shelf uses.
+    async fn shelf_capacity(conn: &mut MySqlConnection, shelf_id: ShelfId) -> Result<ShelfCapacity, Error> {
+        let sql = format!("{SHELF_CAPACITY} WHERE t.shelf_id = ?");
+        query_as::<_, ShelfCapacity>(&sql)
+            .bind(shelf_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?
+            .ok_or(Error::ShelfNotFound)
+    }
+
+    /// Refuse stock that leaves a shelf past its capacity, unless the stock policy on the shelf lets
+    /// it fill up with a warning.
+    async fn check_capacity(conn: &mut MySqlConnection, shelf_id: ShelfId) -> Result<(), Error> {
+        if Self::stock_policy(&mut *conn, shelf_id).await?.warn_over_capacity {
+            return Ok(());
+        }
+        // the shelf is locked first, so that stock put on it concurrently is counted too
+        let sql = "SELECT shelf_id FROM shelf WHERE shelf_id = ?
+FOR UPDATE";
+        query(sql)
+            .bind(shelf_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|_| Error::Error)?;
+        if Self::shelf_capacity(&mut *conn, shelf_id).await?.exceeded() {
+            return Err(Error::ShelfOverCapacity);
+        }
+        Ok(())
+    }
+
+    /// Clear the reconciliation flags of an item on a shelf, returning how many lots were flagged.
+    async fn unflag_stock(conn: &mut MySqlConnection, item_id: ItemId, shelf_id: ShelfId) -> Result<u64, Error> {
+        let sql = "UPDATE stock SET flagged_at = NULL WHERE item_id = ? AND shelf_id = ? AND flagged_at IS NOT NULL";
+        query(sql)
+            .bind(item_id)
+            .bind(shelf_id)
+            .execute(&mut *conn)
+            .await
+            .map(|v| v.rows_affected())
+            .map_err(|_| Error::Error)
+    }
+
+    /// Take `line.count` items off a shelf whatever their status, available stock first. Units named
+    /// by `line.serials` are taken from the status each is in. What the shelf lacks is left to `policy`
+    /// as available stock.
+    async fn take_any_status(conn: &mut MySqlConnection, line: &ItemXShelf, policy: &StockPolicy) -> Result<Vec<Draw>, Error> {
+        let mut lines = Vec::new();
+        if line.serials.is_empty() {
+            let mut left = line.count;
+            for status in StockStatus::ALL {
+                let sql =
+                    "SELECT CAST(COALESCE(SUM(count), 0) AS SIGNED) FROM stock WHERE item_id = ? AND shelf_id = ? AND status = ?";
+                let (held,): (i64,) = query_as(sql)
+                    .bind(line.item_id)
+                    .bind(line.shelf_id)
+                    .bind(status.as_str())
+                    .fetch_one(&mut *conn)
+                    .await
+                    .map_err(|_| Error::Error)?;
+                let taken = left.min(held.max(0));
+                if taken > 0 {
+                    lines.push(ItemXShelf {
+                        count: taken,
+                        status,
+                        ..line.clone()
+                    });
+                    left -= taken;
+                }
+            }
+            if left > 0 {
+                lines.push(ItemXShelf {
+                    count: left,
+                    ..line.clone()
+                });
+            }
+        } else {
+            let mut units: BTreeMap<StockStatus, Vec<String>> = BTreeMap::new();
+            for serial in &line.serials {
+                let sql = "SELECT status FROM stock_units WHERE item_id = ? AND serial = ? AND shelf_id = ?";
+                let (status,): (StockStatus,) = query_as(sql)
+                    .bind(line.item_id)
+                    .bind(serial)
+                    .bind(line.shelf_id)
+                    .fetch_optional(&mut *conn)
+                    .await
+                    .map_err(|_| Error::Error)?
+                    .ok_or(Error::SerialNotFound)?;
+                units.entry(status).or_default().push(serial.clone());
             }
+            for (status, serials) in units {
+                lines.push(ItemXShelf {
+                    count: i64::try_from(serials.len()).map_err(|_| Error::Error)?,
+                    status,
+                    serials,
+                    ..line.clone()
+                });
+            }
+        }
+        let mut draws = Vec::new();
+        for x in lines {
+            draws.extend(Self::take_stock(&mut *conn, &x, policy).await?);
+        }
+        Ok(draws)
+    }
+
+    /// Put units in another status on their shelf and link them to the movement.
+    async fn set_units_status(
+        conn: &mut MySqlConnection,
+        movement_id: MovementId,
+        item_id: ItemId,
+        serials: &[String],
+        status: StockStatus,
+    ) -> Result<(), Error> {
+        for serial in serials {
+            let sql = "UPDATE stock_units SET status = ? WHERE item_id = ? AND serial = ?";
+            query(sql)
+                .bind(status.as_str())
+                .bind(item_id)
+                .bind(serial)
+                .execute(&mut *conn)
+                .await
+                .map_err(|_| Error::Error)?;
+            Self::link_unit(&mut *conn, movement_id, item_id, serial).await?;
         }
-        drop(tx.commit().await);
         Ok(())
     }
 }
+
+/// Stock summed per (item, shelf) along with what is held on the shelf, to be grouped by
+/// `it.item_id, sf.shelf_id`.
+const STOCK_ON_SHELF: &str = "SELECT it.item_id,
+             it.name             item_name,
+             sf.shelf_id,
+             sf.name             shelf_name,
+             CAST(SUM(si.count) AS SIGNED) count,
+             CAST(SUM(CASE WHEN si.status = 'available' THEN si.count ELSE 0 END) AS SIGNED) status_available,
+             CAST(SUM(CASE WHEN si.status = 'quarantined' THEN si.count ELSE 0 END) AS SIGNED) status_quarantined,
+             CAST(SUM(CASE WHEN si.status = 'damaged' THEN si.count ELSE 0 END) AS SIGNED) status_damaged,
+             CAST(SUM(CASE WHEN si.status = 'on_hold' THEN si.count ELSE 0 END) AS SIGNED) status_on_hold,
+             CAST(COALESCE((SELECT SUM(sr.count)
+                            FROM stock_reservations sr
+                            WHERE sr.item_id = it.item_id
+                              AND sr.shelf_id = sf.shelf_id
+                              AND (sr.expires_at IS NULL OR sr.expires_at > CURRENT_TIMESTAMP)), 0) AS SIGNED) reserved,
+             it.sn,
+             MIN(si.expiry_date) earliest_expiry
+      FROM stock si
+               JOIN items it ON it.item_id = si.item_id
+               JOIN shelf sf ON si.shelf_id = sf.shelf_id";
+
+/// Stock summed per (item, room) along with what is held on the room or any of its shelves, to be
+/// grouped by `r.room_id, it.item_id`.
+const STOCK_IN_ROOM: &str = "SELECT it.item_id,
+             it.name             item_name,
+             r.room_id,
+             r.name              room_name,
+             CAST(SUM(si.count) AS SIGNED) count,
+             CAST(SUM(CASE WHEN si.status = 'available' THEN si.count ELSE 0 END) AS SIGNED) status_available,
+             CAST(SUM(CASE WHEN si.status = 'quarantined' THEN si.count ELSE 0 END) AS SIGNED) status_quarantined,
+             CAST(SUM(CASE WHEN si.status = 'damaged' THEN si.count ELSE 0 END) AS SIGNED) status_damaged,
+             CAST(SUM(CASE WHEN si.status = 'on_hold' THEN si.count ELSE 0 END) AS SIGNED) status_on_hold,
+             CAST(COALESCE((SELECT SUM(sr.count)
+                            FROM stock_reservations sr
+                                     LEFT JOIN shelf rs ON rs.shelf_id = sr.shelf_id
+                            WHERE sr.item_id = it.item_id
+                              AND COALESCE(sr.room_id, rs.room_id) = r.room_id
+                              AND (sr.expires_at IS NULL OR sr.expires_at > CURRENT_TIMESTAMP)), 0) AS SIGNED) reserved
+      FROM stock si
+               JOIN items it ON it.item_id = si.item_id
+               JOIN shelf sf ON si.shelf_id = sf.shelf_id
+               JOIN rooms r ON sf.room_id = r.room_id";
+
+const STOCK_IN_WAREHOUSE: &str = "SELECT it.item_id,
+             it.name             item_name,
+             w.warehouse_id,
+             w.code              warehouse_code,
+             CAST(SUM(si.count) AS SIGNED) count,
+             CAST(SUM(CASE WHEN si.status = 'available' THEN si.count ELSE 0 END) AS SIGNED) status_available,
+             CAST(SUM(CASE WHEN si.status = 'quarantined' THEN si.count ELSE 0 END) AS SIGNED) status_quarantined,
+             CAST(SUM(CASE WHEN si.status = 'damaged' THEN si.count ELSE 0 END) AS SIGNED) status_damaged,
+             CAST(SUM(CASE WHEN si.status = 'on_hold' THEN si.count ELSE 0 END) AS SIGNED) status_on_hold,
+             CAST(COALESCE((SELECT SUM(sr.count)
+                            FROM stock_reservations sr
+                                     LEFT JOIN shelf rs ON rs.shelf_id = sr.shelf_id
+                            WHERE sr.item_id = it.item_id
+                              AND COALESCE(sr.room_id, rs.room_id) IN
+                                  (SELECT wr.room_id FROM rooms wr WHERE wr.warehouse_id = w.warehouse_id)
+                              AND (sr.expires_at IS NULL OR sr.expires_at > CURRENT_TIMESTAMP)), 0) AS SIGNED) reserved
+      FROM stock si
+               JOIN items it ON it.item_id = si.item_id
+               JOIN shelf sf ON si.shelf_id = sf.shelf_id
+               JOIN rooms r ON sf.room_id = r.room_id
+               JOIN warehouses w ON r.warehouse_id = w.warehouse_id";
+
+/// The stock per (item, shelf) as it stood at a past time, as table `t`. Bound to the snapshot to start
+/// from, the last movement it includes and the time: forward from the snapshot through the movements
+/// after it, or backwards from the current stock through the movements since the time when the snapshot is 0.
+const STOCK_AS_OF: &str =
+    "WITH p AS (SELECT CAST(? AS SIGNED) snapshot_id, CAST(? AS SIGNED) last_movement_id, CAST(? AS DATETIME) as_of),
+     t AS (SELECT m.item_id, m.shelf_id, CAST(SUM(m.count) AS SIGNED) count
+           FROM (SELECT sl.item_id, sl.shelf_id, sl.count
+                 FROM stock_snapshot_lines sl
+                          JOIN p ON sl.snapshot_id = p.snapshot_id
+                 UNION ALL
+                 SELECT si.item_id, si.shelf_id, si.count
+                 FROM stock si
+                          JOIN p ON p.snapshot_id = 0
+                 UNION ALL
+                 SELECT sm.item_id, sm.shelf_to, CASE WHEN sm.created_at <= p.as_of THEN sm.count ELSE -sm.count END
+                 FROM stock_movements sm
+                          JOIN p ON sm.shelf_to IS NOT NULL
+                     AND ((sm.movement_id > p.last_movement_id AND sm.created_at <= p.as_of)
+                         OR (p.snapshot_id = 0 AND sm.created_at > p.as_of))
+                 UNION ALL
+                 SELECT sm.item_id, sm.shelf_from, CASE WHEN sm.created_at <= p.as_of THEN -sm.count ELSE sm.count END
+                 FROM stock_movements sm
+                          JOIN p ON sm.shelf_from IS NOT NULL
+                     AND ((sm.movement_id > p.last_movement_id AND sm.created_at <= p.as_of)
+                         OR (p.snapshot_id = 0 AND sm.created_at > p.as_of))) m
+           GROUP BY m.item_id, m.shelf_id
+           HAVING SUM(m.count) <> 0)";
+
+/// The costed quantity and value of every item as they stood at `p.as_of`, as table `v`, to follow
+/// `STOCK_AS_OF`.
+const COST_AS_OF: &str = "v AS (SELECT ce.item_id,
+                  ce.currency,
+                  CAST(SUM(ce.count) AS SIGNED) count,
+                  CAST(SUM(ce.value) AS SIGNED) value
+           FROM cost_entries ce
+                    JOIN p ON ce.created_at <= p.as_of
+           GROUP BY ce.item_id, ce.currency
+           HAVING SUM(ce.count) > 0)";
+
+/// Every reorder point with the stock available to it, as table `t`.
+const REORDER_LEVELS: &str = "SELECT t.*, t.max_level - t.available suggested_order
+FROM (SELECT rp.reorder_point_id,
+             rp.item_id,
+             it.name item_name,
+             rp.room_id,
+             r.name  room_name,
+             rp.min_level,
+             rp.max_level,
+             CAST(COALESCE((SELECT SUM(si.count)
+                            FROM stock si
+                                     JOIN shelf sf ON sf.shelf_id = si.shelf_id
+                            WHERE si.item_id = rp.item_id
+                              AND (rp.room_id IS NULL OR sf.room_id = rp.room_id)
+                              AND si.status = 'available'), 0)
+                 - COALESCE((SELECT SUM(sr.count)
+                             FROM stock_reservations sr
+                                      LEFT JOIN shelf rs ON rs.shelf_id = sr.shelf_id
+                             WHERE sr.item_id = rp.item_id
+                               AND (rp.room_id IS NULL OR COALESCE(sr.room_id, rs.room_id) = rp.room_id)
+                               AND (sr.expires_at IS NULL OR sr.expires_at > CURRENT_TIMESTAMP)), 0) AS SIGNED) available,
+             rp.below_since
+      FROM reorder_points rp
+               JOIN items it ON it.item_id = rp.item_id
+               LEFT JOIN rooms r ON r.room_id = rp.room_id) t";
+
+/// The lines of a stocktake, binding its id three times, as table `t`. Stock on the counted shelves
+/// makes lines only while the session is open, counted lines keep the stock expected at approval.
+const STOCKTAKE_LINES: &str = "SELECT t.*, t.counted - t.expected variance
+FROM (SELECT l.item_id,
+             it.name item_name,
+             l.shelf_id,
+             sf.name shelf_name,
+             COALESCE(sc.expected, CAST(COALESCE((SELECT SUM(si.count)
+                                                  FROM stock si
+                                                  WHERE si.item_id = l.item_id
+                                                    AND si.shelf_id = l.shelf_id), 0) AS SIGNED)) expected,
+             sc.counted,
+             sc.counted_by,
+             sc.counted_at
+      FROM (SELECT si.item_id, si.shelf_id
+            FROM stock si
+                     JOIN stocktake_shelves ss ON ss.shelf_id = si.shelf_id
+                     JOIN stocktakes st ON st.stocktake_id = ss.stocktake_id
+            WHERE ss.stocktake_id = ? AND st.status = 'open'
+            UNION
+            SELECT item_id, shelf_id
+            FROM stocktake_counts
+            WHERE stocktake_id = ?) l
+               JOIN items it ON it.item_id = l.item_id
+               JOIN shelf sf ON sf.shelf_id = l.shelf_id
+               LEFT JOIN stocktake_counts sc
+                         ON sc.stocktake_id = ? AND sc.item_id = l.item_id AND sc.shelf_id = l.shelf_id) t";
+
+/// How much of its capacity every shelf uses, as table `t`. Only what is in stock counts, lots
+/// taken below zero free nothing.
+const SHELF_CAPACITY: &str = "SELECT t.*,
+       t.max_units - t.units           remaining_units,
+       t.max_weight_g - t.weight_g     remaining_weight_g,
+       t.max_volume_mm3 - t.volume_mm3 remaining_volume_mm3
+FROM (SELECT sf.shelf_id,
+             sf.name,
+             sf.room_id,
+             sf.max_units,
+             sf.max_weight_g,
+             sf.max_volume_mm3,
+             CAST(COALESCE(SUM(si.count), 0) AS SIGNED) units,
+             CAST(COALESCE(SUM(si.count * it.weight_g), 0) AS SIGNED) weight_g,
+             CAST(COALESCE(SUM(si.count * it.length_mm * it.width_mm * it.height_mm), 0) AS SIGNED) volume_mm3
+      FROM shelf sf
+               LEFT JOIN stock si ON si.shelf_id = sf.shelf_id AND si.count > 0
+               LEFT JOIN items it ON it.item_id = si.item_id
+      GROUP BY sf.shelf_id, sf.name, sf.room_id, sf.max_units, sf.max_weight_g, sf.max_volume_mm3) t";
@@ -120,6 +120,102 @@ pub enum ServiceError {
     SourceMustBePositive,
     #[display("Target must be positive")]
     TargetMustBePositive,
+    #[display("Serials must name every unit of a serialized item, and only those")]
+    SerialCountMismatch,
+    #[display("Serial not found")]
+    SerialNotFound,
+    #[display("Serial already in stock")]
+    SerialInStock,
+    #[display("Stock reserved by someone else")]
+    StockReserved,
+    #[display("Reservation not found")]
+    ReservationNotFound,
+    #[display("Reserve either on a shelf or on a room")]
+    ReservationPlaceNotValid,
+    #[display("Reorder point not found")]
+    ReorderPointNotFound,
+    #[display("Levels not valid, 0 <= min <= max")]
+    ReorderLevelsNotValid,
+    #[display("Failed to send low stock email.")]
+    FailedToSendLowStockEmail,
+    #[display("Stocktake not found")]
+    StocktakeNotFound,
+    #[display("Stocktake already closed")]
+    StocktakeClosed,
+    #[display("Shelf is being counted")]
+    StocktakeInProgress,
+    #[display("Shelf not counted by this stocktake")]
+    ShelfNotInStocktake,
+    #[display("Count either a room or a set of shelves")]
+    StocktakeScopeNotValid,
+    #[display("Counted quantity must not be negative")]
+    CountedMustNotBeNegative,
+    #[display("Unit not found")]
+    UnitNotFound,
+    #[display("Unit not valid, a non-empty name other than the base unit and a positive factor")]
+    UnitNotValid,
+    #[display("Recipe not found")]
+    BomNotFound,
+    #[display("Recipe not valid, positive counts of distinct components other than the output")]
+    BomNotValid,
+    #[display("Picked lines must add up to what the recipe takes")]
+    PicksNotMatchingBom,
+    #[display("Components short")]
+    ComponentsShort,
+    #[display("Category not found")]
+    CategoryNotFound,
+    #[display("Cost not valid, a non-negative unit cost along with a three-letter currency code")]
+    CostNotValid,
+    #[display("Stock of the item is costed in another currency")]
+    CurrencyMismatch,
+    #[display("Stock policy not found")]
+    StockPolicyNotFound,
+    #[display("Stock is already in that status")]
+    StatusUnchanged,
+    #[display("Stock was changed by another request, try again")]
+    StockConflict,
+    #[display("Stock on the shelf is not the expected count")]
+    StockChanged,
+    #[display("Not applied, another line of the batch failed")]
+    BatchAborted,
+    #[display("Operation not found")]
+    OperationNotFound,
+    #[display("Operation already reversed")]
+    OperationReversed,
+    #[display("Stock the operation brought in has since been consumed")]
+    StockConsumed,
+    #[display("Not enough room left on the shelf")]
+    ShelfOverCapacity,
+    #[display("Warehouse not found")]
+    WarehouseNotFound,
+    #[display("Warehouse code already taken")]
+    WarehouseCodeTaken,
+    #[display("Warehouse still has rooms")]
+    WarehouseNotEmpty,
+    #[display("Warehouse not valid, a code of up to 16 letters, digits or dashes, a name and a time zone like Europe/Berlin")]
+    WarehouseNotValid,
+    #[display("Bin not found on the shelf")]
+    BinNotFound,
+    #[display("Bins to remove still hold stock")]
+    BinNotEmpty,
+    #[display("Bin address not valid, expected one like R01-S03-L2-P04")]
+    BinAddressNotValid,
+    #[display("Shelf layout not valid, 1 to 99 layers of 1 to 99 positions")]
+    ShelfLayoutNotValid,
+    #[display("Room or shelf is archived")]
+    LocationArchived,
+    #[display("Location still holds stock, give a shelf to move it to")]
+    LocationNotEmpty,
+    #[display("Room still has shelves")]
+    RoomHasShelves,
+    #[display("Room still holds stock, move it out before moving the room to another warehouse")]
+    RoomHasStock,
+    #[display("Item still has stock")]
+    ItemInStock,
+    #[display("Item is still kept on a shelf")]
+    ItemOnShelf,
+    #[display("Item is still referenced by its stock history")]
+    ItemReferenced,
 }
 
 impl From<sqlx::Error> for ServiceError {
@@ -207,6 +303,54 @@ pub fn http_status_code_for_service_error(error: &ServiceError) -> StatusCode {
         ServiceError::CountMustBePositive => StatusCode::BAD_REQUEST,
         ServiceError::SourceMustBePositive => StatusCode::BAD_REQUEST,
         ServiceError::TargetMustBePositive => StatusCode::BAD_REQUEST,
+        ServiceError::SerialCountMismatch => StatusCode::BAD_REQUEST,
+        ServiceError::SerialNotFound => StatusCode::NOT_FOUND,
+        ServiceError::SerialInStock => StatusCode::CONFLICT,
+        ServiceError::StockReserved => StatusCode::CONFLICT,
+        ServiceError::ReservationNotFound => StatusCode::NOT_FOUND,
+        ServiceError::ReservationPlaceNotValid => StatusCode::BAD_REQUEST,
+        ServiceError::ReorderPointNotFound => StatusCode::NOT_FOUND,
+        ServiceError::ReorderLevelsNotValid => StatusCode::BAD_REQUEST,
+        ServiceError::FailedToSendLowStockEmail => StatusCode::INTERNAL_SERVER_ERROR,
+        ServiceError::StocktakeNotFound => StatusCode::NOT_FOUND,
+        ServiceError::StocktakeClosed => StatusCode::CONFLICT,
+        ServiceError::StocktakeInProgress => StatusCode::CONFLICT,
+        ServiceError::ShelfNotInStocktake => StatusCode::BAD_REQUEST,
+        ServiceError::StocktakeScopeNotValid => StatusCode::BAD_REQUEST,
+        ServiceError::CountedMustNotBeNegative => StatusCode::BAD_REQUEST,
+        ServiceError::UnitNotFound => StatusCode::NOT_FOUND,
+        ServiceError::UnitNotValid => StatusCode::BAD_REQUEST,
+        ServiceError::BomNotFound => StatusCode::NOT_FOUND,
+        ServiceError::BomNotValid => StatusCode::BAD_REQUEST,
+        ServiceError::PicksNotMatchingBom => StatusCode::BAD_REQUEST,
+        ServiceError::ComponentsShort => StatusCode::CONFLICT,
+        ServiceError::CategoryNotFound => StatusCode::NOT_FOUND,
+        ServiceError::CostNotValid => StatusCode::BAD_REQUEST,
+        ServiceError::CurrencyMismatch => StatusCode::CONFLICT,
+        ServiceError::StockPolicyNotFound => StatusCode::NOT_FOUND,
+        ServiceError::StatusUnchanged => StatusCode::BAD_REQUEST,
+        ServiceError::StockConflict => StatusCode::CONFLICT,
+        ServiceError::StockChanged => StatusCode::CONFLICT,
+        ServiceError::BatchAborted => StatusCode::CONFLICT,
+        ServiceError::OperationNotFound => StatusCode::NOT_FOUND,
+        ServiceError::OperationReversed => StatusCode::CONFLICT,
+        ServiceError::StockConsumed => StatusCode::CONFLICT,
+        ServiceError::ShelfOverCapacity => StatusCode::CONFLICT,
+        ServiceError::WarehouseNotFound => StatusCode::NOT_FOUND,
+        ServiceError::WarehouseCodeTaken => StatusCode::CONFLICT,
+        ServiceError::WarehouseNotEmpty => StatusCode::CONFLICT,
+        ServiceError::WarehouseNotValid => StatusCode::BAD_REQUEST,
+        ServiceError::BinNotFound => StatusCode::NOT_FOUND,
+        ServiceError::BinNotEmpty => StatusCode::CONFLICT,
+        ServiceError::BinAddressNotValid => StatusCode::BAD_REQUEST,
+        ServiceError::ShelfLayoutNotValid => StatusCode::BAD_REQUEST,
+        ServiceError::LocationArchived => StatusCode::CONFLICT,
+        ServiceError::LocationNotEmpty => StatusCode::CONFLICT,
+        ServiceError::RoomHasShelves => StatusCode::CONFLICT,
+        ServiceError::RoomHasStock => StatusCode::CONFLICT,
+        ServiceError::ItemInStock => StatusCode::CONFLICT,
+        ServiceError::ItemOnShelf => StatusCode::CONFLICT,
+        ServiceError::ItemReferenced => StatusCode::CONFLICT,
     }
 }
 
@@ -227,5 +371,38 @@ pub fn map_database_error_to_service_error(error: &database::Error) -> ServiceEr
         database::Error::ItemNotFound => ServiceError::ItemNotFound,
         database::Error::InsufficientItem => ServiceError::InsufficientItem,
         database::Error::CountMustBePositive => ServiceError::CountMustBePositive,
+        database::Error::SerialCountMismatch => ServiceError::SerialCountMismatch,
+        database::Error::SerialNotFound => ServiceError::SerialNotFound,
+        database::Error::SerialInStock => ServiceError::SerialInStock,
+        database::Error::StockReserved => ServiceError::StockReserved,
+        database::Error::ReservationNotFound => ServiceError::ReservationNotFound,
+        database::Error::ReorderPointNotFound => ServiceError::ReorderPointNotFound,
+        database::Error::StocktakeNotFound => ServiceError::StocktakeNotFound,
+        database::Error::StocktakeClosed => ServiceError::StocktakeClosed,
+        database::Error::StocktakeInProgress => ServiceError::StocktakeInProgress,
+        database::Error::ShelfNotInStocktake => ServiceError::ShelfNotInStocktake,
+        database::Error::UnitNotFound => ServiceError::UnitNotFound,
+        database::Error::BomNotFound => ServiceError::BomNotFound,
+        database::Error::CategoryNotFound => ServiceError::CategoryNotFound,
+        database::Error::CurrencyMismatch => ServiceError::CurrencyMismatch,
+        database::Error::StockPolicyNotFound => ServiceError::StockPolicyNotFound,
+        database::Error::StockConflict => ServiceError::StockConflict,
+        database::Error::StockChanged => ServiceError::StockChanged,
+        database::Error::OperationNotFound => ServiceError::OperationNotFound,
+        database::Error::OperationReversed => ServiceError::OperationReversed,
+        database::Error::StockConsumed => ServiceError::StockConsumed,
+        database::Error::ShelfOverCapacity => ServiceError::ShelfOverCapacity,
+        database::Error::WarehouseNotFound => ServiceError::WarehouseNotFound,
+        database::Error::WarehouseCodeTaken => ServiceError::WarehouseCodeTaken,
+        database::Error::WarehouseNotEmpty => ServiceError::WarehouseNotEmpty,
+        database::Error::BinNotFound => ServiceError::BinNotFound,
+        database::Error::BinNotEmpty => ServiceError::BinNotEmpty,
+        database::Error::LocationArchived => ServiceError::LocationArchived,
+        database::Error::LocationNotEmpty => ServiceError::LocationNotEmpty,
+        database::Error::RoomHasShelves => ServiceError::RoomHasShelves,
+        database::Error::RoomHasStock => ServiceError::RoomHasStock,
+        database::Error::ItemInStock => ServiceError::ItemInStock,
+        database::Error::ItemOnShelf => ServiceError::ItemOnShelf,
+        database::Error::ItemReferenced => ServiceError::ItemReferenced,
     }
 }
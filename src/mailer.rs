@@ -12,6 +12,7 @@ use tera::{try_get_value, Context, Tera};
 
 use crate::config::Configuration;
 use crate::errors::ServiceError;
+use crate::models::reorder::ReorderLevel;
 use crate::utils::clock;
 use crate::web::api::v1::routes::API_VERSION_URL_PREFIX;
 
@@ -117,6 +118,25 @@ impl Service {
         }
     }
 
+    /// Send a Low Stock Email listing the reorder points the stock just fell under.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if unable to send an email.
+    pub async fn send_low_stock_mail(&self, to: &str, username: &str, levels: &[ReorderLevel]) -> Result<(), ServiceError> {
+        let builder = self.get_builder(to).await;
+
+        let mail = build_low_stock_letter(username, levels, builder)?;
+
+        match self.mailer.send(mail).await {
+            Ok(_res) => Ok(()),
+            Err(e) => {
+                eprintln!("Failed to send email: {e}");
+                Err(ServiceError::FailedToSendLowStockEmail)
+            }
+        }
+    }
+
     async fn get_builder(&self, to: &str) -> MessageBuilder {
         let settings = self.cfg.settings.read().await;
 
@@ -192,13 +212,42 @@ fn build_content(verification_url: &str, username: &str) -> Result<(String, Stri
     Ok((plain_body, html_body))
 }
 
+fn build_low_stock_letter(username: &str, levels: &[ReorderLevel], builder: MessageBuilder) -> Result<Message, ServiceError> {
+    builder
+        .subject("Warehouse Management - Low stock")
+        .header(lettre::message::header::ContentType::TEXT_PLAIN)
+        .body(build_low_stock_content(username, levels))
+        .map_err(|e| {
+            log::error!("{e}");
+            ServiceError::InternalServerError
+        })
+}
+
+fn build_low_stock_content(username: &str, levels: &[ReorderLevel]) -> String {
+    let lines: Vec<String> = levels
+        .iter()
+        .map(|level| {
+            let place = level.room_name.as_deref().unwrap_or("all rooms");
+            format!(
+                "- {} in {place}: {} available, min {}, suggested order {}",
+                level.item_name, level.available, level.min_level, level.suggested_order
+            )
+        })
+        .collect();
+    format!(
+        "Hello {username},\n\nThe stock of these items fell under their reorder point:\n\n{}\n",
+        lines.join("\n")
+    )
+}
+
 pub type Mailer = AsyncSmtpTransport<Tokio1Executor>;
 
 #[cfg(test)]
 mod tests {
     use lettre::Message;
 
-    use super::{build_content, build_letter};
+    use super::{build_content, build_letter, build_low_stock_letter};
+    use crate::models::reorder::ReorderLevel;
 
     #[test]
     fn it_should_build_a_letter() {
@@ -216,4 +265,26 @@ mod tests {
         assert_ne!(plain_body, "");
         assert_ne!(html_body, "");
     }
+
+    #[test]
+    fn it_should_build_a_low_stock_letter() {
+        let builder = Message::builder()
+            .from("from@a.b.c".parse().unwrap())
+            .reply_to("reply@a.b.c".parse().unwrap())
+            .to("to@a.b.c".parse().unwrap());
+        let levels = vec![ReorderLevel {
+            reorder_point_id: 1,
+            item_id: 1,
+            item_name: "bolt".to_string(),
+            room_id: None,
+            room_name: None,
+            min_level: 10,
+            max_level: 50,
+            available: 4,
+            suggested_order: 46,
+            below_since: None,
+        }];
+
+        let _letter = build_low_stock_letter("user", &levels, builder).unwrap();
+    }
 }
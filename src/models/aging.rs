@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::serde::iso8601;
+use time::OffsetDateTime;
+
+use crate::models::item::ItemId;
+use crate::models::room::RoomId;
+
+/// Days since stock was received where each bucket of the aging report ends, the last one going on
+/// from there.
+pub const AGING_BUCKETS: [i64; 3] = [30, 90, 180];
+
+/// The stock of an item in a room split by how long it has been sitting there, counted from the
+/// last deposit of its lot, or from when it came on its shelf where no deposit is on record.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, FromRow)]
+pub struct AgingLine {
+    pub item_id: ItemId,
+    pub item_name: String,
+    pub room_id: RoomId,
+    pub room_name: String,
+    pub days_0_30: i64,
+    pub days_31_90: i64,
+    pub days_91_180: i64,
+    pub days_over_180: i64,
+    pub total: i64,
+    #[serde(with = "iso8601")]
+    pub oldest_received_at: OffsetDateTime,
+}
+
+/// An item in stock that nothing was withdrawn of for a while.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, FromRow)]
+pub struct DeadStock {
+    pub item_id: ItemId,
+    pub item_name: String,
+    pub on_hand: i64,
+    /// when the oldest stock of the item came on its shelf
+    #[serde(with = "iso8601")]
+    pub in_stock_since: OffsetDateTime,
+    /// `None` when nothing was ever withdrawn
+    #[serde(with = "iso8601::option")]
+    pub last_withdrawn_at: Option<OffsetDateTime>,
+}
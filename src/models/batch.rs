@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::bin::BinId;
+use crate::models::item::ItemXShelf;
+use crate::models::movement::MovementKind;
+use crate::models::shelf::ShelfId;
+use crate::models::valuation::{Cost, UnitCost};
+
+/// What happens to a batch when some of its lines fail.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode {
+    /// Nothing is kept unless every line goes through.
+    #[default]
+    AllOrNothing,
+    /// The lines that go through are kept.
+    BestEffort,
+}
+
+/// A stock operation on the lot, lots or units a line picks.
+#[derive(Clone, Debug)]
+pub enum StockOp {
+    Deposit(ItemXShelf),
+    Withdraw(ItemXShelf),
+    Transfer {
+        line: ItemXShelf,
+        shelf_to: ShelfId,
+        /// the first bin of `shelf_to` when `None`
+        bin_to: Option<BinId>,
+    },
+}
+
+impl StockOp {
+    #[must_use]
+    pub fn kind(&self) -> MovementKind {
+        match self {
+            StockOp::Deposit(_) => MovementKind::Deposit,
+            StockOp::Withdraw(_) => MovementKind::Withdraw,
+            StockOp::Transfer { .. } => MovementKind::Transfer,
+        }
+    }
+
+    #[must_use]
+    pub fn line(&self) -> &ItemXShelf {
+        match self {
+            StockOp::Deposit(line) | StockOp::Withdraw(line) | StockOp::Transfer { line, .. } => line,
+        }
+    }
+
+    /// The shelf the op puts stock on, if any.
+    #[must_use]
+    pub fn shelf_to(&self) -> Option<ShelfId> {
+        match self {
+            StockOp::Deposit(line) => Some(line.shelf_id),
+            StockOp::Withdraw(_) => None,
+            StockOp::Transfer { shelf_to, .. } => Some(*shelf_to),
+        }
+    }
+
+    pub fn line_mut(&mut self) -> &mut ItemXShelf {
+        match self {
+            StockOp::Deposit(line) | StockOp::Withdraw(line) | StockOp::Transfer { line, .. } => line,
+        }
+    }
+}
+
+/// A line of a batch as asked for, counted in `unit`.
+#[derive(Clone, Debug)]
+pub struct StockLine {
+    pub op: StockOp,
+    /// the item's base unit when `None`
+    pub unit: Option<String>,
+    /// only used by deposits, what one `unit` cost
+    pub unit_cost: Option<UnitCost>,
+    /// the reason of the batch when `None`
+    pub reason: Option<String>,
+    pub expected_count: Option<i64>,
+}
+
+/// A line of a batch counted in the base unit of the item.
+#[derive(Clone, Debug)]
+pub struct BatchLine {
+    pub op: StockOp,
+    /// only used by deposits, what the whole line cost
+    pub cost: Option<Cost>,
+    pub reason: Option<String>,
+    pub expected_count: Option<i64>,
+}
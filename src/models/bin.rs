@@ -0,0 +1,238 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use super::item::ItemId;
+use super::room::RoomId;
+use super::shelf::ShelfId;
+use super::status::StatusQuantities;
+
+pub type BinId = i64;
+
+/// A slot of a shelf, at a position on one of its layers. Layers count from the bottom and
+/// positions from the left, both from 1.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct Bin {
+    pub bin_id: BinId,
+    pub shelf_id: ShelfId,
+    pub room_id: RoomId,
+    pub layer: i64,
+    pub position: i64,
+    /// where pickers find the bin, such as `R01-S03-L2-P04`
+    #[sqlx(skip)]
+    pub address: String,
+}
+
+impl Bin {
+    #[must_use]
+    pub fn address(&self) -> BinAddress {
+        BinAddress {
+            room_id: Some(self.room_id),
+            shelf_id: self.shelf_id,
+            layer: self.layer,
+            position: self.position,
+        }
+    }
+
+    /// The bin with its address filled in.
+    #[must_use]
+    pub fn addressed(self) -> Self {
+        Self {
+            address: self.address().to_string(),
+            ..self
+        }
+    }
+}
+
+/// An item in a bin, summed over its lots.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, FromRow)]
+pub struct ItemInBin {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub bin: Bin,
+    pub item_id: ItemId,
+    pub item_name: String,
+    /// on hand
+    pub count: i64,
+    /// on hand by status
+    #[sqlx(flatten)]
+    pub by_status: StatusQuantities,
+}
+
+/// The address of a room, such as `R01`, which the addresses of its shelves start with.
+#[must_use]
+pub fn room_address(room_id: RoomId) -> String {
+    format!("R{room_id:02}")
+}
+
+/// The address of a shelf, such as `R01-S03`, which the addresses of its bins start with.
+#[must_use]
+pub fn shelf_address(room_id: RoomId, shelf_id: ShelfId) -> String {
+    format!("{}-S{shelf_id:02}", room_address(room_id))
+}
+
+/// The human-readable address of a bin, `R<room>-S<shelf>-L<layer>-P<position>`. Rooms, shelves and
+/// positions are padded to two digits. The room may be left out when reading an address, the shelf
+/// alone telling where the bin is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BinAddress {
+    pub room_id: Option<RoomId>,
+    pub shelf_id: ShelfId,
+    pub layer: i64,
+    pub position: i64,
+}
+
+impl BinAddress {
+    /// Whether the address points into the room the shelf of `bin` is in, as far as it tells.
+    #[must_use]
+    pub fn is_in_room_of(&self, bin: &Bin) -> bool {
+        self.room_id.map_or(true, |room_id| room_id == bin.room_id)
+    }
+}
+
+impl fmt::Display for BinAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shelf = match self.room_id {
+            Some(room_id) => shelf_address(room_id, self.shelf_id),
+            None => format!("S{:02}", self.shelf_id),
+        };
+        write!(f, "{shelf}-L{}-P{:02}", self.layer, self.position)
+    }
+}
+
+impl FromStr for BinAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |part: &str, prefix: char| {
+            part.strip_prefix(prefix)
+                .or_else(|| part.strip_prefix(prefix.to_ascii_lowercase()))
+                .and_then(|x| x.parse::<i64>().ok())
+                .filter(|x| *x > 0)
+                .ok_or_else(|| format!("not a bin address: {s}"))
+        };
+        let parts: Vec<&str> = s.trim().split('-').collect();
+        let (room, shelf, layer, position) = match parts[..] {
+            [shelf, layer, position] => (None, shelf, layer, position),
+            [room, shelf, layer, position] => (Some(room), shelf, layer, position),
+            _ => return Err(format!("not a bin address: {s}")),
+        };
+        Ok(BinAddress {
+            room_id: room.map(|room| number(room, 'R')).transpose()?,
+            shelf_id: number(shelf, 'S')?,
+            layer: number(layer, 'L')?,
+            position: number(position, 'P')?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bin, BinAddress};
+
+    #[test]
+    fn it_should_print_a_bin_address_padded() {
+        let address = BinAddress {
+            room_id: Some(1),
+            shelf_id: 3,
+            layer: 2,
+            position: 4,
+        };
+
+        assert_eq!(address.to_string(), "R01-S03-L2-P04");
+    }
+
+    #[test]
+    fn it_should_read_back_the_address_it_prints() {
+        for address in [
+            BinAddress {
+                room_id: Some(1),
+                shelf_id: 3,
+                layer: 2,
+                position: 4,
+            },
+            BinAddress {
+                room_id: None,
+                shelf_id: 3,
+                layer: 2,
+                position: 4,
+            },
+            BinAddress {
+                room_id: Some(120),
+                shelf_id: 1234,
+                layer: 11,
+                position: 100,
+            },
+        ] {
+            assert_eq!(address.to_string().parse::<BinAddress>(), Ok(address));
+        }
+    }
+
+    #[test]
+    fn it_should_read_an_address_in_lower_case_and_with_spaces_around() {
+        assert_eq!(
+            " r1-s3-l2-p4 ".parse::<BinAddress>(),
+            Ok(BinAddress {
+                room_id: Some(1),
+                shelf_id: 3,
+                layer: 2,
+                position: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn it_should_read_an_address_without_the_room() {
+        assert_eq!(
+            "S03-L2-P04".parse::<BinAddress>(),
+            Ok(BinAddress {
+                room_id: None,
+                shelf_id: 3,
+                layer: 2,
+                position: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn it_should_match_a_bin_only_in_the_room_the_address_names() {
+        let bin = Bin {
+            bin_id: 7,
+            shelf_id: 3,
+            room_id: 1,
+            layer: 2,
+            position: 4,
+            address: String::new(),
+        };
+
+        assert!("R01-S03-L2-P04".parse::<BinAddress>().unwrap().is_in_room_of(&bin));
+        assert!("S03-L2-P04".parse::<BinAddress>().unwrap().is_in_room_of(&bin));
+        assert!(!"R99-S03-L2-P04".parse::<BinAddress>().unwrap().is_in_room_of(&bin));
+    }
+
+    #[test]
+    fn it_should_not_read_malformed_addresses() {
+        for address in [
+            "",
+            "S03",
+            "S03-L2",
+            "R01-S03-L2-P04-X1",
+            "R01-R02-S03-L2-P04",
+            "X03-L2-P04",
+            "S03-P2-L04",
+            "S-L2-P04",
+            "S03-L2-P",
+            "S03-L2-Pxx",
+            "S00-L2-P04",
+            "S03-L-1-P04",
+            "R01-S03-L2-P04-",
+            "R00-S03-L2-P04",
+            "Q01-S03-L2-P04",
+            "S03--L2-P04",
+        ] {
+            assert!(address.parse::<BinAddress>().is_err(), "{address:?} read as a bin address");
+        }
+    }
+}
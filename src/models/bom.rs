@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::serde::iso8601;
+use time::OffsetDateTime;
+
+use crate::models::item::{ItemId, ItemXShelf};
+use crate::models::user::UserId;
+
+pub type BomId = i64;
+
+/// A bill of materials: what it takes to assemble `output_count` of an item. Recipes are never
+/// changed, a new version of the recipe of the same item is added instead.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct Bom {
+    pub bom_id: BomId,
+    pub output_item_id: ItemId,
+    pub output_count: i64,
+    /// 1 for the first recipe of the item, counting up.
+    pub version: i64,
+    pub note: Option<String>,
+    pub created_by: UserId,
+    #[serde(with = "iso8601")]
+    pub created_at: OffsetDateTime,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub components: Vec<BomComponent>,
+}
+
+/// An item consumed by a recipe, `count` of it per assembly.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct BomComponent {
+    pub item_id: ItemId,
+    pub count: i64,
+}
+
+/// A recipe to add.
+#[derive(Debug)]
+pub struct NewBom {
+    pub output_item_id: ItemId,
+    pub output_count: i64,
+    pub components: Vec<BomComponent>,
+    pub note: Option<String>,
+    pub user_id: UserId,
+}
+
+/// An item there is not enough of to assemble or disassemble.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Shortage {
+    pub item_id: ItemId,
+    pub needed: i64,
+    /// what can be taken from the shelves considered
+    pub available: i64,
+}
+
+/// What came of assembling or disassembling.
+#[derive(Debug)]
+pub enum Assembly {
+    /// The stock was converted, with the resulting stock of every line.
+    Converted(Vec<ItemXShelf>),
+    /// Nothing was converted for lack of these items.
+    Short(Vec<Shortage>),
+}
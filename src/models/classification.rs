@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sqlx::error::BoxDynError;
+use sqlx::FromRow;
+
+use crate::models::item::ItemId;
+
+/// Where an item stands by consumption value. Ranked by value, an item is `A` while the items
+/// above it took less than 80% of the value consumed, `B` while they took less than 95%, and `C`
+/// otherwise. The item crossing a threshold stays in the class above it, so `A` can hold well
+/// over 80% of the value when a few items take most of it.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AbcClass {
+    A,
+    B,
+    C,
+}
+
+impl AbcClass {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AbcClass::A => "A",
+            AbcClass::B => "B",
+            AbcClass::C => "C",
+        }
+    }
+}
+
+impl fmt::Display for AbcClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AbcClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "A" => Ok(AbcClass::A),
+            "B" => Ok(AbcClass::B),
+            "C" => Ok(AbcClass::C),
+            _ => Err(format!("unknown ABC class: {s}")),
+        }
+    }
+}
+
+impl<DB: sqlx::Database> sqlx::Type<DB> for AbcClass
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for AbcClass
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: <DB as sqlx::Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let class = <String as sqlx::Decode<DB>>::decode(value)?;
+        Ok(class.parse()?)
+    }
+}
+
+/// How steady the demand for an item is, by the coefficient of variation of what is consumed of it
+/// per period: `X` up to 0.5, `Y` up to 1, `Z` above that or without any demand.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum XyzClass {
+    X,
+    Y,
+    Z,
+}
+
+impl XyzClass {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            XyzClass::X => "X",
+            XyzClass::Y => "Y",
+            XyzClass::Z => "Z",
+        }
+    }
+}
+
+impl fmt::Display for XyzClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for XyzClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "X" => Ok(XyzClass::X),
+            "Y" => Ok(XyzClass::Y),
+            "Z" => Ok(XyzClass::Z),
+            _ => Err(format!("unknown XYZ class: {s}")),
+        }
+    }
+}
+
+impl<DB: sqlx::Database> sqlx::Type<DB> for XyzClass
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for XyzClass
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: <DB as sqlx::Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let class = <String as sqlx::Decode<DB>>::decode(value)?;
+        Ok(class.parse()?)
+    }
+}
+
+/// What was withdrawn of an item over a period, and the cost it was taken out at. There is a row
+/// for each currency the withdrawals were costed in, each with the whole count withdrawn.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, FromRow)]
+pub struct Consumption {
+    pub item_id: ItemId,
+    pub count: i64,
+    /// none when nothing withdrawn was costed
+    pub currency: Option<String>,
+    /// in minor units of `currency`
+    pub value: i64,
+}
+
+/// The classes of an item.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ItemClass {
+    pub item_id: ItemId,
+    pub abc_class: AbcClass,
+    pub xyz_class: XyzClass,
+}
+
+/// What was consumed of an item: the count in each period and the value per currency.
+struct Usage {
+    counts: Vec<i64>,
+    values: HashMap<String, i64>,
+}
+
+/// Classify items by what was consumed of them in each of `periods`. Costs in different currencies
+/// do not add up, so the items are ranked by value once per currency, against the others costed in
+/// it. An item costed in several currencies is ranked in each and takes the best class it gets.
+/// Items none of whose withdrawals were costed have no value to rank and are `C`.
+#[must_use]
+pub fn classify(item_ids: &[ItemId], periods: &[Vec<Consumption>]) -> Vec<ItemClass> {
+    let mut usage: HashMap<ItemId, Usage> = HashMap::new();
+    for (i, period) in periods.iter().enumerate() {
+        for x in period {
+            let usage = usage.entry(x.item_id).or_insert_with(|| Usage {
+                counts: vec![0; periods.len()],
+                values: HashMap::new(),
+            });
+            // every row of an item carries its whole count for the period
+            usage.counts[i] = x.count;
+            if let Some(currency) = &x.currency {
+                *usage.values.entry(currency.clone()).or_default() += x.value;
+            }
+        }
+    }
+    let mut groups: HashMap<&str, Vec<(ItemId, i64)>> = HashMap::new();
+    for (item_id, usage) in &usage {
+        for (currency, value) in &usage.values {
+            groups.entry(currency).or_default().push((*item_id, (*value).max(0)));
+        }
+    }
+    let mut abc_classes: HashMap<ItemId, AbcClass> = HashMap::new();
+    for mut ranked in groups.into_values() {
+        ranked.sort_unstable_by_key(|(item_id, weight)| (-weight, *item_id));
+        let total = i128::from(ranked.iter().map(|(_, weight)| weight).sum::<i64>());
+        let mut before: i128 = 0;
+        for (item_id, weight) in ranked {
+            // an item is in the class its share starts in
+            let abc_class = if weight == 0 {
+                AbcClass::C
+            } else if before * 100 < total * 80 {
+                AbcClass::A
+            } else if before * 100 < total * 95 {
+                AbcClass::B
+            } else {
+                AbcClass::C
+            };
+            before += i128::from(weight);
+            let best = abc_classes.entry(item_id).or_insert(abc_class);
+            *best = (*best).min(abc_class);
+        }
+    }
+    let mut classes: Vec<ItemClass> = item_ids
+        .iter()
+        .map(|item_id| ItemClass {
+            item_id: *item_id,
+            abc_class: abc_classes.get(item_id).copied().unwrap_or(AbcClass::C),
+            xyz_class: usage.get(item_id).map_or(XyzClass::Z, |x| xyz_class(&x.counts)),
+        })
+        .collect();
+    classes.sort_unstable_by_key(|x| x.item_id);
+    classes
+}
+
+/// The XYZ class of counts consumed per period. With `n` periods, `s` the sum of the counts and `q`
+/// the sum of their squares, the coefficient of variation is at most `c` when `n*q - s*s <= c*c*s*s`.
+fn xyz_class(counts: &[i64]) -> XyzClass {
+    let n = i128::try_from(counts.len()).unwrap_or(i128::MAX);
+    let s: i128 = counts.iter().map(|x| i128::from(*x)).sum();
+    if s <= 0 {
+        return XyzClass::Z;
+    }
+    let q: i128 = counts.iter().map(|x| i128::from(*x) * i128::from(*x)).sum();
+    let spread = n * q - s * s;
+    if spread * 4 <= s * s {
+        XyzClass::X
+    } else if spread <= s * s {
+        XyzClass::Y
+    } else {
+        XyzClass::Z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify, xyz_class, AbcClass, Consumption, ItemClass, XyzClass};
+
+    fn consumed(item_id: i64, count: i64, cost: Option<(&str, i64)>) -> Consumption {
+        Consumption {
+            item_id,
+            count,
+            currency: cost.map(|(currency, _)| currency.to_string()),
+            value: cost.map_or(0, |(_, value)| value),
+        }
+    }
+
+    fn abc(classes: &[ItemClass]) -> Vec<AbcClass> {
+        classes.iter().map(|x| x.abc_class).collect()
+    }
+
+    #[test]
+    fn it_should_put_an_item_in_the_class_its_share_starts_in() {
+        let period = vec![
+            consumed(1, 1, Some(("EUR", 79))),
+            consumed(2, 1, Some(("EUR", 1))),
+            consumed(3, 1, Some(("EUR", 15))),
+            consumed(4, 1, Some(("EUR", 5))),
+        ];
+
+        let classes = classify(&[1, 2, 3, 4], &[period]);
+
+        // ranked 79, 15, 5, 1: the 15 starts at 79%, the 5 at 94%, the 1 at 99%
+        assert_eq!(abc(&classes), vec![AbcClass::A, AbcClass::C, AbcClass::A, AbcClass::B]);
+    }
+
+    #[test]
+    fn it_should_put_items_starting_exactly_at_a_threshold_in_the_next_class() {
+        let period = vec![
+            consumed(1, 1, Some(("EUR", 80))),
+            consumed(2, 1, Some(("EUR", 15))),
+            consumed(3, 1, Some(("EUR", 5))),
+        ];
+
+        let classes = classify(&[1, 2, 3], &[period]);
+
+        assert_eq!(abc(&classes), vec![AbcClass::A, AbcClass::B, AbcClass::C]);
+    }
+
+    #[test]
+    fn it_should_classify_items_without_consumption_as_c_and_z() {
+        let classes = classify(&[1, 2], &[vec![consumed(1, 3, Some(("EUR", 10)))], vec![]]);
+
+        assert_eq!(
+            classes[1],
+            ItemClass {
+                item_id: 2,
+                abc_class: AbcClass::C,
+                xyz_class: XyzClass::Z,
+            }
+        );
+    }
+
+    #[test]
+    fn it_should_rank_items_against_those_costed_in_the_same_currency() {
+        let period = vec![
+            consumed(1, 1, Some(("EUR", 1_000))),
+            consumed(2, 1, Some(("EUR", 10))),
+            consumed(3, 1, Some(("JPY", 50))),
+        ];
+
+        let classes = classify(&[1, 2, 3], &[period]);
+
+        // the yen are not outweighed by the euros
+        assert_eq!(abc(&classes), vec![AbcClass::A, AbcClass::C, AbcClass::A]);
+    }
+
+    #[test]
+    fn it_should_classify_costless_items_as_c() {
+        let period = vec![
+            consumed(1, 1, Some(("EUR", 1_000))),
+            consumed(2, 90, None),
+            consumed(3, 10, None),
+        ];
+
+        let classes = classify(&[1, 2, 3], &[period]);
+
+        assert_eq!(abc(&classes), vec![AbcClass::A, AbcClass::C, AbcClass::C]);
+    }
+
+    #[test]
+    fn it_should_rank_an_item_in_each_currency_it_was_costed_in() {
+        let periods = vec![
+            vec![consumed(1, 5, Some(("EUR", 100))), consumed(2, 1, Some(("USD", 100)))],
+            vec![consumed(1, 5, Some(("USD", 1))), consumed(2, 1, Some(("USD", 100)))],
+        ];
+
+        let classes = classify(&[1, 2], &periods);
+
+        // the euros make item 1 an A though its dollars alone would make it a C
+        assert_eq!(abc(&classes), vec![AbcClass::A, AbcClass::A]);
+    }
+
+    #[test]
+    fn it_should_count_the_periods_of_an_item_costed_in_several_currencies_once() {
+        let periods = vec![
+            vec![consumed(1, 4, Some(("EUR", 10))), consumed(1, 4, Some(("USD", 10)))],
+            vec![consumed(1, 4, Some(("EUR", 10)))],
+        ];
+
+        let classes = classify(&[1], &periods);
+
+        assert_eq!(classes[0].xyz_class, XyzClass::X);
+    }
+
+    #[test]
+    fn it_should_classify_steady_demand_as_x() {
+        assert_eq!(xyz_class(&[5, 5, 5, 5]), XyzClass::X);
+    }
+
+    #[test]
+    fn it_should_put_a_coefficient_of_variation_of_exactly_half_in_x() {
+        // mean 2, standard deviation 1
+        assert_eq!(xyz_class(&[1, 3]), XyzClass::X);
+    }
+
+    #[test]
+    fn it_should_put_a_coefficient_of_variation_of_exactly_one_in_y() {
+        // mean 1, standard deviation 1
+        assert_eq!(xyz_class(&[0, 2]), XyzClass::Y);
+    }
+
+    #[test]
+    fn it_should_classify_demand_in_a_single_period_of_many_as_z() {
+        assert_eq!(xyz_class(&[4, 0, 0]), XyzClass::Z);
+    }
+
+    #[test]
+    fn it_should_classify_no_demand_as_z() {
+        assert_eq!(xyz_class(&[0, 0]), XyzClass::Z);
+        assert_eq!(xyz_class(&[]), XyzClass::Z);
+    }
+}
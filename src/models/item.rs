@@ -1,14 +1,23 @@
 use serde_derive::{Deserialize, Serialize};
 use sqlx::FromRow;
 use time::serde::iso8601;
-use time::OffsetDateTime;
+use time::{Date, OffsetDateTime};
 
+use crate::models::bin::BinId;
+use crate::models::category::CategoryId;
+use crate::models::classification::{AbcClass, XyzClass};
 use crate::models::room::RoomId;
 use crate::models::shelf::ShelfId;
+use crate::models::status::{StatusQuantities, StockStatus};
+use crate::models::valuation::CostingMethod;
+use crate::models::warehouse::WarehouseId;
 
 #[allow(clippy::module_name_repetitions)]
 pub type ItemId = i64;
 
+/// The base unit of items created without one.
+pub const DEFAULT_BASE_UNIT: &str = "pcs";
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
 pub struct Item {
     pub item_id: ItemId,
@@ -16,12 +25,52 @@ pub struct Item {
     pub description: Option<String>,
     ///serial number
     pub sn: String,
+    /// every unit in stock is tracked by its own serial number
+    pub serialized: bool,
+    /// the unit every count of the item is kept in
+    pub base_unit: String,
+    /// how the cost of its stock is kept
+    pub costing: CostingMethod,
+    pub category_id: Option<CategoryId>,
+    /// weight of one base unit, in grams
+    pub weight_g: Option<i64>,
+    /// outer dimensions of one base unit, in millimetres
+    pub length_mm: Option<i64>,
+    pub width_mm: Option<i64>,
+    pub height_mm: Option<i64>,
+    /// by consumption value, `None` until the item is first classified
+    pub abc_class: Option<AbcClass>,
+    /// by demand variability
+    pub xyz_class: Option<XyzClass>,
+    #[serde(with = "iso8601::option")]
+    pub classified_at: Option<OffsetDateTime>,
     #[serde(with = "iso8601")]
     pub created_at: OffsetDateTime,
     #[serde(with = "iso8601::option")]
     pub updated_at: Option<OffsetDateTime>,
 }
 
+/// Weight and outer dimensions of one base unit of an item, what it takes of the capacity of a
+/// shelf. Items without them only count in units there.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ItemDimensions {
+    pub weight_g: Option<i64>,
+    pub length_mm: Option<i64>,
+    pub width_mm: Option<i64>,
+    pub height_mm: Option<i64>,
+}
+
+impl From<&Item> for ItemDimensions {
+    fn from(item: &Item) -> Self {
+        Self {
+            weight_g: item.weight_g,
+            length_mm: item.length_mm,
+            width_mm: item.width_mm,
+            height_mm: item.height_mm,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
 pub struct ItemCompact {
     pub item_id: ItemId,
@@ -34,28 +83,199 @@ pub struct ItemCompact {
     pub room_id: RoomId,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
 pub struct ItemXShelf {
     pub item_id: ItemId,
     pub shelf_id: ShelfId,
     pub count: i64,
+    /// lot or batch number, `None` for stock that is not lot-controlled
+    pub lot_no: Option<String>,
+    pub mfg_date: Option<Date>,
+    pub expiry_date: Option<Date>,
+    #[serde(default)]
+    pub status: StockStatus,
+    /// bin of the shelf the stock is in. Putting stock without one puts it in the first bin of the
+    /// shelf, taking it without one takes it from any bin.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub bin_id: Option<BinId>,
+    /// unit serials of a serialized item, one per unit counted
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub serials: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+impl ItemXShelf {
+    #[must_use]
+    pub fn new(item_id: ItemId, shelf_id: ShelfId, count: i64, lot: &Lot) -> Self {
+        Self {
+            item_id,
+            shelf_id,
+            count,
+            lot_no: lot.lot_no.clone(),
+            mfg_date: lot.mfg_date,
+            expiry_date: lot.expiry_date,
+            status: StockStatus::default(),
+            bin_id: None,
+            serials: Vec::new(),
+        }
+    }
+
+    /// A line taking `count` items picked by `pick` off a shelf.
+    #[must_use]
+    pub fn picked(item_id: ItemId, shelf_id: ShelfId, count: i64, pick: &StockPick) -> Self {
+        Self {
+            item_id,
+            shelf_id,
+            count,
+            lot_no: pick.lot_no.clone(),
+            mfg_date: None,
+            expiry_date: None,
+            status: pick.status,
+            bin_id: pick.bin_id,
+            serials: pick.serials.clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn lot(&self) -> Lot {
+        Lot {
+            lot_no: self.lot_no.clone(),
+            mfg_date: self.mfg_date,
+            expiry_date: self.expiry_date,
+        }
+    }
+}
+
+/// Batch information carried by a stock line.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Lot {
+    pub lot_no: Option<String>,
+    pub mfg_date: Option<Date>,
+    pub expiry_date: Option<Date>,
+}
+
+/// Which stock of an item to take off a shelf: the named units, the given lot,
+/// or first-expired-first-out when neither is given, in `status` only. From
+/// `bin_id` only when it is given.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StockPick {
+    pub lot_no: Option<String>,
+    #[serde(default)]
+    pub serials: Vec<String>,
+    #[serde(default)]
+    pub status: StockStatus,
+    pub bin_id: Option<BinId>,
+}
+
+/// An alternative unit of an item, worth `factor` of its base unit.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct ItemUnit {
+    pub item_id: ItemId,
+    pub unit: String,
+    pub factor: i64,
+}
+
+/// Stock quantities expressed in another unit than the base unit.
+#[derive(Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub struct UnitQuantities {
+    pub unit: String,
+    pub count: f64,
+    pub reserved: f64,
+    pub available: f64,
+}
+
+impl UnitQuantities {
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new(unit: &ItemUnit, count: i64, reserved: i64, available: i64) -> Self {
+        let factor = unit.factor as f64;
+        Self {
+            unit: unit.unit.clone(),
+            count: count as f64 / factor,
+            reserved: reserved as f64 / factor,
+            available: available as f64 / factor,
+        }
+    }
+}
+
+/// An item on a shelf, summed over its lots.
+#[derive(Debug, Serialize, Deserialize, PartialEq, PartialOrd, FromRow)]
 pub struct ItemOnShelf {
     pub item_id: ItemId,
     pub item_name: String,
     pub shelf_id: ShelfId,
     pub shelf_name: String,
+    /// on hand
     pub count: i64,
+    /// held for someone by reservations on the shelf
+    pub reserved: i64,
+    /// available stock not held by reservations
+    pub available: i64,
+    /// on hand by status
+    #[sqlx(flatten)]
+    pub by_status: StatusQuantities,
     pub sn: String,
+    /// the soonest expiry among the lots on the shelf
+    pub earliest_expiry: Option<Date>,
+    /// the quantities in the unit asked for, if the item has it
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_unit: Option<UnitQuantities>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+/// An item in a room, summed over the room's shelves.
+#[derive(Debug, Serialize, Deserialize, PartialEq, PartialOrd, FromRow)]
 pub struct ItemInRoom {
     pub item_id: ItemId,
     pub item_name: String,
     pub room_id: RoomId,
     pub room_name: String,
+    /// on hand
+    pub count: i64,
+    /// held for someone by reservations on the room or any of its shelves
+    pub reserved: i64,
+    /// available stock not held by reservations
+    pub available: i64,
+    /// on hand by status
+    #[sqlx(flatten)]
+    pub by_status: StatusQuantities,
+    /// the quantities in the unit asked for, if the item has it
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_unit: Option<UnitQuantities>,
+}
+
+/// An item at a site, summed over the shelves of all its rooms.
+#[derive(Debug, Serialize, Deserialize, PartialEq, PartialOrd, FromRow)]
+pub struct ItemInWarehouse {
+    pub item_id: ItemId,
+    pub item_name: String,
+    pub warehouse_id: WarehouseId,
+    pub warehouse_code: String,
+    /// on hand
+    pub count: i64,
+    /// held for someone by reservations on any room or shelf of the site
+    pub reserved: i64,
+    /// available stock not held by reservations
+    pub available: i64,
+    /// on hand by status
+    #[sqlx(flatten)]
+    pub by_status: StatusQuantities,
+    /// the quantities in the unit asked for, if the item has it
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_unit: Option<UnitQuantities>,
+}
+
+/// A lot of an item in a room, summed over the room's shelves.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct LotInRoom {
+    pub item_id: ItemId,
+    pub item_name: String,
+    pub room_id: RoomId,
+    pub room_name: String,
+    pub lot_no: Option<String>,
+    pub expiry_date: Date,
     pub count: i64,
 }
@@ -0,0 +1,382 @@
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::bin::{room_address, shelf_address, Bin};
+use crate::models::room::Room;
+use crate::models::shelf::Shelf;
+use crate::models::warehouse::Warehouse;
+use crate::utils::barcode::code128;
+
+/// Labels are 100 by 50 mm.
+const WIDTH_MM: f64 = 100.0;
+const HEIGHT_MM: f64 = 50.0;
+/// Space left blank around the label, and on either side of the barcode for it to scan.
+const MARGIN_MM: f64 = 5.0;
+/// Thinnest bar the printers render reliably, and the widest worth using.
+const MAX_MODULE_MM: f64 = 0.5;
+/// ZPL addresses the label in dots, 8 per mm at 203 dpi.
+const DOTS_PER_MM: f64 = 8.0;
+const PT_PER_MM: f64 = 72.0 / 25.4;
+
+/// What to render labels as.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelFormat {
+    /// for thermal label printers, which draw the barcode themselves
+    #[default]
+    Zpl,
+    /// one page per label
+    Pdf,
+    /// the labels one below the other on a single sheet
+    Svg,
+}
+
+impl LabelFormat {
+    #[must_use]
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            LabelFormat::Zpl => "application/zpl",
+            LabelFormat::Pdf => "application/pdf",
+            LabelFormat::Svg => "image/svg+xml",
+        }
+    }
+
+    #[must_use]
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LabelFormat::Zpl => "zpl",
+            LabelFormat::Pdf => "pdf",
+            LabelFormat::Svg => "svg",
+        }
+    }
+
+    /// Render `labels` as one document.
+    #[must_use]
+    pub fn render(&self, labels: &[Label]) -> Vec<u8> {
+        match self {
+            LabelFormat::Zpl => render_zpl(labels).into_bytes(),
+            LabelFormat::Pdf => render_pdf(labels),
+            LabelFormat::Svg => render_svg(labels).into_bytes(),
+        }
+    }
+}
+
+/// A location label: a name, a line about where it is, and its address as a Code 128 barcode
+/// with the address printed below.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Label {
+    pub title: String,
+    pub caption: String,
+    pub code: String,
+}
+
+impl Label {
+    #[must_use]
+    pub fn room(room: &Room, warehouse: &Warehouse) -> Self {
+        Self {
+            title: room.name.clone(),
+            caption: warehouse.name.clone(),
+            code: room_address(room.room_id),
+        }
+    }
+
+    #[must_use]
+    pub fn shelf(shelf: &Shelf, room: &Room) -> Self {
+        Self {
+            title: shelf.name.clone(),
+            caption: room.name.clone(),
+            code: shelf_address(shelf.room_id, shelf.shelf_id),
+        }
+    }
+
+    #[must_use]
+    pub fn bin(shelf: &Shelf, bin: &Bin) -> Self {
+        Self {
+            title: shelf.name.clone(),
+            caption: format!("Layer {}, position {}", bin.layer, bin.position),
+            code: bin.address().to_string(),
+        }
+    }
+}
+
+/// The bars of a code as (offset, width) in mm from the left edge of the label, centered between
+/// the margins.
+fn bars(code: &str) -> Vec<(f64, f64)> {
+    let Some(widths) = code128(code) else {
+        return Vec::new();
+    };
+    let modules: u32 = widths.iter().map(|x| u32::from(*x)).sum();
+    let module = ((WIDTH_MM - 2.0 * MARGIN_MM) / f64::from(modules)).min(MAX_MODULE_MM);
+    let mut x = (WIDTH_MM - module * f64::from(modules)) / 2.0;
+    let mut bars = Vec::with_capacity(widths.len() / 2 + 1);
+    for (i, w) in widths.into_iter().enumerate() {
+        let width = module * f64::from(w);
+        if i % 2 == 0 {
+            bars.push((x, width));
+        }
+        x += width;
+    }
+    bars
+}
+
+/// Field data for `^FH`, which reads `_` followed by two hex digits as that character.
+fn zpl_field(s: &str) -> String {
+    s.replace('_', "_5F").replace('^', "_5E").replace('~', "_7E")
+}
+
+fn render_zpl(labels: &[Label]) -> String {
+    let dots = |mm: f64| (mm * DOTS_PER_MM).round();
+    let mut zpl = String::new();
+    for x in labels {
+        // ^CI28 reads the field data as UTF-8, ^BY2 makes the narrowest bar 2 dots wide
+        let _ = write!(
+            zpl,
+            "^XA\n^CI28\n^PW{}\n^LL{}\n\
+             ^FO{m},{}^A0N,56,56^FH^FD{}^FS\n\
+             ^FO{m},{}^A0N,32,32^FH^FD{}^FS\n\
+             ^FO{m},{}^BY2^BCN,{},Y,N,N^FH^FD{}^FS\n^XZ\n",
+            dots(WIDTH_MM),
+            dots(HEIGHT_MM),
+            dots(MARGIN_MM),
+            zpl_field(&x.title),
+            dots(12.5),
+            zpl_field(&x.caption),
+            dots(19.0),
+            dots(18.0),
+            zpl_field(&x.code),
+            m = dots(MARGIN_MM),
+        );
+    }
+    zpl
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_svg(labels: &[Label]) -> String {
+    let height = HEIGHT_MM * f64::from(u32::try_from(labels.len()).unwrap_or(u32::MAX));
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH_MM}mm\" height=\"{height}mm\" \
+         viewBox=\"0 0 {WIDTH_MM} {height}\" font-family=\"Helvetica, Arial, sans-serif\">\n"
+    );
+    for (i, x) in labels.iter().enumerate() {
+        let top = HEIGHT_MM * f64::from(u32::try_from(i).unwrap_or(u32::MAX));
+        let _ = writeln!(svg, "<g transform=\"translate(0 {top})\">");
+        // a hairline to cut along
+        let _ = writeln!(
+            svg,
+            "<rect x=\"0.1\" y=\"0.1\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"#bbb\" stroke-width=\"0.2\"/>",
+            WIDTH_MM - 0.2,
+            HEIGHT_MM - 0.2
+        );
+        let _ = writeln!(
+            svg,
+            "<text x=\"{MARGIN_MM}\" y=\"11\" font-size=\"7\" font-weight=\"bold\">{}</text>",
+            xml_escape(&x.title)
+        );
+        let _ = writeln!(
+            svg,
+            "<text x=\"{MARGIN_MM}\" y=\"17\" font-size=\"4\">{}</text>",
+            xml_escape(&x.caption)
+        );
+        for (left, width) in bars(&x.code) {
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{left:.3}\" y=\"20\" width=\"{width:.3}\" height=\"18\" fill=\"#000\"/>"
+            );
+        }
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"44\" font-size=\"4.5\" font-family=\"Courier, monospace\" text-anchor=\"middle\">{}</text>",
+            WIDTH_MM / 2.0,
+            xml_escape(&x.code)
+        );
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// A PDF string of `s` in the Latin-1 range of the standard fonts, other characters shown as `?`.
+fn pdf_string(s: &str) -> Vec<u8> {
+    let mut out = vec![b'('];
+    for c in s.chars() {
+        match c {
+            '(' | ')' | '\\' => out.extend([b'\\', c as u8]),
+            ' '..='~' | '\u{a0}'..='\u{ff}' => out.push(u8::try_from(u32::from(c)).unwrap_or(b'?')),
+            _ => out.push(b'?'),
+        }
+    }
+    out.push(b')');
+    out
+}
+
+/// The drawing of a label, in mm from the bottom left corner of the page.
+fn pdf_page(x: &Label) -> Vec<u8> {
+    let mut page = format!("{PT_PER_MM:.5} 0 0 {PT_PER_MM:.5} 0 0 cm\n").into_bytes();
+    let mut text = |font: &str, size: f64, left: f64, baseline: f64, s: &str| {
+        page.extend(format!("BT /{font} {size} Tf {left:.3} {baseline:.3} Td ").into_bytes());
+        page.extend(pdf_string(s));
+        page.extend(b" Tj ET\n");
+    };
+    text("F2", 7.0, MARGIN_MM, HEIGHT_MM - 11.0, &x.title);
+    text("F1", 4.0, MARGIN_MM, HEIGHT_MM - 17.0, &x.caption);
+    // Courier is 0.6 em wide per character, which centers the code under the bars
+    let code_width = 4.5 * 0.6 * f64::from(u32::try_from(x.code.chars().count()).unwrap_or(0));
+    text("F3", 4.5, (WIDTH_MM - code_width) / 2.0, HEIGHT_MM - 44.0, &x.code);
+    for (left, width) in bars(&x.code) {
+        page.extend(format!("{left:.3} {:.3} {width:.3} 18 re\n", HEIGHT_MM - 38.0).into_bytes());
+    }
+    page.extend(b"f\n");
+    page
+}
+
+fn render_pdf(labels: &[Label]) -> Vec<u8> {
+    // catalog, page tree and the three fonts come first, then a page and its content per label
+    let first_page = 6;
+    let kids: Vec<String> = (0..labels.len()).map(|i| format!("{} 0 R", first_page + 2 * i)).collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), labels.len()).into_bytes(),
+    ];
+    for font in ["Helvetica", "Helvetica-Bold", "Courier"] {
+        objects.push(format!("<< /Type /Font /Subtype /Type1 /BaseFont /{font} /Encoding /WinAnsiEncoding >>").into_bytes());
+    }
+    for (i, x) in labels.iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R >> >> /Contents {} 0 R >>",
+                WIDTH_MM * PT_PER_MM,
+                HEIGHT_MM * PT_PER_MM,
+                first_page + 2 * i + 1
+            )
+            .into_bytes(),
+        );
+        let content = pdf_page(x);
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend(b"\nendstream");
+        objects.push(stream);
+    }
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.into_iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{offset:010} 00000 n \n").into_bytes());
+    }
+    pdf.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            labels.len() * 2 + first_page
+        )
+        .into_bytes(),
+    );
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Label, LabelFormat};
+
+    fn labels() -> Vec<Label> {
+        vec![
+            Label {
+                title: "Shelf (A)".to_string(),
+                caption: "Cold room".to_string(),
+                code: "R01-S03".to_string(),
+            },
+            Label {
+                title: "Shelf B".to_string(),
+                caption: "Layer 2, position 4".to_string(),
+                code: "R01-S04-L2-P04".to_string(),
+            },
+        ]
+    }
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack.windows(needle.len()).position(|x| x == needle)
+    }
+
+    #[test]
+    fn it_should_point_the_pdf_cross_reference_at_the_objects() {
+        let pdf = LabelFormat::Pdf.render(&labels());
+        let text = String::from_utf8_lossy(&pdf);
+
+        let startxref = text.rsplit("startxref\n").next().unwrap();
+        let xref: usize = startxref.lines().next().unwrap().parse().unwrap();
+        assert!(pdf[xref..].starts_with(b"xref\n"));
+        let mut lines = text[xref..].lines().skip(1);
+        let size: usize = lines.next().unwrap().split(' ').nth(1).unwrap().parse().unwrap();
+        assert_eq!(size, 2 * 2 + 6);
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+        for object in 1..size {
+            let entry = lines.next().unwrap();
+            assert_eq!(entry.len(), 19);
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(
+                pdf[offset..].starts_with(format!("{object} 0 obj\n").as_bytes()),
+                "object {object}"
+            );
+        }
+        assert!(text.contains(&format!("/Size {size} ")));
+    }
+
+    #[test]
+    fn it_should_give_the_pdf_streams_their_length() {
+        let pdf = LabelFormat::Pdf.render(&labels());
+
+        let mut rest = &pdf[..];
+        let mut streams = 0;
+        while let Some(at) = find(rest, b"<< /Length ") {
+            rest = &rest[at + 11..];
+            let digits = rest.iter().take_while(|x| x.is_ascii_digit()).count();
+            let length: usize = std::str::from_utf8(&rest[..digits]).unwrap().parse().unwrap();
+            let start = find(rest, b"stream\n").unwrap() + 7;
+            assert!(rest[start + length..].starts_with(b"\nendstream"));
+            streams += 1;
+        }
+        assert_eq!(streams, 2);
+    }
+
+    #[test]
+    fn it_should_escape_pdf_strings() {
+        let pdf = LabelFormat::Pdf.render(&labels());
+
+        assert!(find(&pdf, b"(Shelf \\(A\\)) Tj").is_some());
+    }
+
+    #[test]
+    fn it_should_render_one_zpl_label_each() {
+        let zpl = String::from_utf8(LabelFormat::Zpl.render(&labels())).unwrap();
+
+        assert_eq!(zpl.matches("^XA").count(), 2);
+        assert_eq!(zpl.matches("^XZ").count(), 2);
+        assert!(zpl.contains("^BY2^BCN,144,Y,N,N^FH^FDR01-S04-L2-P04^FS"));
+    }
+
+    #[test]
+    fn it_should_escape_zpl_field_data() {
+        let label = Label {
+            title: "a_b^c~d".to_string(),
+            caption: String::new(),
+            code: "S01".to_string(),
+        };
+
+        let zpl = String::from_utf8(LabelFormat::Zpl.render(&[label])).unwrap();
+
+        assert!(zpl.contains("^FDa_5Fb_5Ec_7Ed^FS"));
+    }
+}
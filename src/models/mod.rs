@@ -1,8 +1,24 @@
+pub mod aging;
+pub mod batch;
+pub mod bin;
+pub mod bom;
 pub mod category;
+pub mod classification;
 pub mod file;
 pub mod item;
+pub mod label;
+pub mod movement;
 pub mod permission;
+pub mod policy;
+pub mod putaway;
+pub mod reorder;
+pub mod reservation;
 pub mod role;
 pub mod room;
 pub mod shelf;
+pub mod status;
+pub mod stocktake;
+pub mod unit;
 pub mod user;
+pub mod valuation;
+pub mod warehouse;
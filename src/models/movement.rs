@@ -0,0 +1,168 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sqlx::error::BoxDynError;
+use sqlx::FromRow;
+use time::serde::iso8601;
+use time::{Date, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::models::bin::BinId;
+use crate::models::item::ItemId;
+use crate::models::shelf::ShelfId;
+use crate::models::status::StockStatus;
+use crate::models::user::UserId;
+
+pub type MovementId = i64;
+
+pub type SnapshotId = i64;
+
+/// The stock operation that produced a movement.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum MovementKind {
+    Deposit,
+    Withdraw,
+    Transfer,
+    Convert,
+    /// A correction posted by an approved stocktake.
+    Adjust,
+    /// Stock put in another status on its shelf, a line out of the old status and one into the new.
+    Status,
+    /// A line undoing one of an earlier operation, the other way round.
+    Reversal,
+}
+
+impl MovementKind {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MovementKind::Deposit => "deposit",
+            MovementKind::Withdraw => "withdraw",
+            MovementKind::Transfer => "transfer",
+            MovementKind::Convert => "convert",
+            MovementKind::Adjust => "adjust",
+            MovementKind::Status => "status",
+            MovementKind::Reversal => "reversal",
+        }
+    }
+}
+
+impl fmt::Display for MovementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MovementKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "deposit" => Ok(MovementKind::Deposit),
+            "withdraw" => Ok(MovementKind::Withdraw),
+            "transfer" => Ok(MovementKind::Transfer),
+            "convert" => Ok(MovementKind::Convert),
+            "adjust" => Ok(MovementKind::Adjust),
+            "status" => Ok(MovementKind::Status),
+            "reversal" => Ok(MovementKind::Reversal),
+            _ => Err(format!("unknown movement kind: {s}")),
+        }
+    }
+}
+
+impl<DB: sqlx::Database> sqlx::Type<DB> for MovementKind
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for MovementKind
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: <DB as sqlx::Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let kind = <String as sqlx::Decode<DB>>::decode(value)?;
+        Ok(kind.parse()?)
+    }
+}
+
+/// One line of the stock ledger. Rows are never updated or deleted, every
+/// stock mutation appends one row per touched (item, shelf) pair.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct StockMovement {
+    pub movement_id: MovementId,
+    /// Shared by every line written by the same stock operation.
+    pub correlation_id: String,
+    pub kind: MovementKind,
+    pub item_id: ItemId,
+    pub shelf_from: Option<ShelfId>,
+    pub shelf_to: Option<ShelfId>,
+    /// the bins of the shelves moved between, unknown on lines written before bins were recorded
+    pub bin_from: Option<BinId>,
+    pub bin_to: Option<BinId>,
+    pub count: i64,
+    pub lot_no: Option<String>,
+    /// the dates of the lot moved, unknown on lines written before they were recorded
+    pub mfg_date: Option<Date>,
+    pub expiry_date: Option<Date>,
+    /// the status of the stock moved
+    pub status: StockStatus,
+    pub user_id: UserId,
+    pub reason: Option<String>,
+    /// the line undone by this `reversal` line
+    pub reversal_of: Option<MovementId>,
+    /// set when the shelves moved between are in different warehouses
+    pub cross_site: bool,
+    #[serde(with = "iso8601")]
+    pub created_at: OffsetDateTime,
+}
+
+/// The stock of every (item, shelf) as it stood once the movements up to
+/// `last_movement_id` were applied. Past stock is rebuilt from the latest
+/// snapshot before the time asked for, forward through the ledger.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct StockSnapshot {
+    pub snapshot_id: SnapshotId,
+    pub last_movement_id: MovementId,
+    #[serde(with = "iso8601")]
+    pub taken_at: OffsetDateTime,
+}
+
+/// Who is moving stock and why.
+#[derive(Clone, Debug)]
+pub struct MovementMeta {
+    pub user_id: UserId,
+    pub reason: Option<String>,
+    /// how many items of the item the shelf taken from, or deposited to, must hold in the base unit
+    /// for the change to go ahead
+    pub expected_count: Option<i64>,
+}
+
+/// A single stock operation. Every movement line it writes shares its
+/// correlation id, so a `convert` can be traced back as one unit.
+#[derive(Clone, Debug)]
+pub struct MovementOp<'a> {
+    pub correlation_id: String,
+    pub kind: MovementKind,
+    pub meta: &'a MovementMeta,
+}
+
+impl<'a> MovementOp<'a> {
+    #[must_use]
+    pub fn new(kind: MovementKind, meta: &'a MovementMeta) -> Self {
+        Self {
+            correlation_id: Uuid::new_v4().to_string(),
+            kind,
+            meta,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use serde_derive::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::serde::iso8601;
+use time::OffsetDateTime;
+
+use crate::models::item::ItemId;
+use crate::models::room::RoomId;
+use crate::models::shelf::ShelfId;
+
+/// How far stock may be taken off the shelves of a room, or of every room without a policy of
+/// their own when `room_id` is `None`. Without any policy, the last item of a shelf stays on it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, FromRow)]
+pub struct StockPolicy {
+    pub room_id: Option<RoomId>,
+    /// shelves may be emptied
+    pub allow_empty: bool,
+    /// lots taken down to zero are kept instead of removed
+    pub keep_empty: bool,
+    /// stock may be taken below zero, flagging the lot for reconciliation
+    pub allow_negative: bool,
+    /// shelves may be filled past their capacity, answering with a warning instead of refusing
+    pub warn_over_capacity: bool,
+}
+
+/// A lot taken below zero, waiting to be reconciled.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct FlaggedStock {
+    pub item_id: ItemId,
+    pub item_name: String,
+    pub shelf_id: ShelfId,
+    pub shelf_name: String,
+    pub lot_no: Option<String>,
+    pub count: i64,
+    #[serde(with = "iso8601")]
+    pub flagged_at: OffsetDateTime,
+}
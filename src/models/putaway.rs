@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::models::shelf::ShelfCapacity;
+
+/// A shelf to put incoming stock of an item away on, and how much of it goes there.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, FromRow)]
+pub struct PutawaySuggestion {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub shelf: ShelfCapacity,
+    pub layer: i64,
+    /// the item already in stock on the shelf
+    pub on_shelf: i64,
+    /// how much of the incoming count fits on the shelf
+    #[sqlx(skip)]
+    pub fits: i64,
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::serde::iso8601;
+use time::OffsetDateTime;
+
+use crate::models::item::ItemId;
+use crate::models::room::RoomId;
+
+pub type ReorderPointId = i64;
+
+/// The stock levels to keep an item within, in one room or across every room.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct ReorderPoint {
+    pub reorder_point_id: ReorderPointId,
+    pub item_id: ItemId,
+    /// `None` for the levels of the whole warehouse.
+    pub room_id: Option<RoomId>,
+    /// The item is reordered once less than this is available.
+    pub min_level: i64,
+    /// The level to reorder up to.
+    pub max_level: i64,
+    /// When the available stock fell under `min_level`, `None` while it is not under.
+    #[serde(with = "iso8601::option")]
+    pub below_since: Option<OffsetDateTime>,
+    #[serde(with = "iso8601")]
+    pub created_at: OffsetDateTime,
+}
+
+/// A reorder point along with the stock it watches.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct ReorderLevel {
+    pub reorder_point_id: ReorderPointId,
+    pub item_id: ItemId,
+    pub item_name: String,
+    pub room_id: Option<RoomId>,
+    pub room_name: Option<String>,
+    pub min_level: i64,
+    pub max_level: i64,
+    /// on hand and not reserved
+    pub available: i64,
+    /// how many to order to get back to `max_level`
+    pub suggested_order: i64,
+    #[serde(with = "iso8601::option")]
+    pub below_since: Option<OffsetDateTime>,
+}
+
+impl ReorderLevel {
+    #[must_use]
+    pub fn is_below(&self) -> bool {
+        self.available < self.min_level
+    }
+}
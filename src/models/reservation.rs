@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::serde::iso8601;
+use time::OffsetDateTime;
+
+use crate::models::item::ItemId;
+use crate::models::room::RoomId;
+use crate::models::shelf::ShelfId;
+use crate::models::user::UserId;
+
+pub type ReservationId = i64;
+
+/// Stock held for a user without taking it off the shelves. A hold is placed either on one shelf
+/// or on a whole room, never both.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct Reservation {
+    pub reservation_id: ReservationId,
+    pub item_id: ItemId,
+    pub shelf_id: Option<ShelfId>,
+    pub room_id: Option<RoomId>,
+    pub count: i64,
+    /// The owner of the hold, the only one allowed to dip into it.
+    pub user_id: UserId,
+    pub note: Option<String>,
+    /// The hold is released once this passes, `None` holds until released by hand.
+    #[serde(with = "iso8601::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    #[serde(with = "iso8601")]
+    pub created_at: OffsetDateTime,
+}
+
+/// A hold to place.
+#[derive(Clone, Debug)]
+pub struct NewReservation {
+    pub item_id: ItemId,
+    pub shelf_id: Option<ShelfId>,
+    pub room_id: Option<RoomId>,
+    pub count: i64,
+    pub user_id: UserId,
+    pub note: Option<String>,
+    pub expires_at: Option<OffsetDateTime>,
+}
@@ -3,6 +3,8 @@ use sqlx::FromRow;
 use time::serde::iso8601;
 use time::OffsetDateTime;
 
+use crate::models::warehouse::WarehouseId;
+
 pub type RoomId = i64;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
@@ -10,8 +12,13 @@ pub struct Room {
     pub room_id: RoomId,
     pub name: String,
     pub description: Option<String>,
+    /// the site the room is in
+    pub warehouse_id: WarehouseId,
     #[serde(with = "iso8601")]
     pub created_at: OffsetDateTime,
     #[serde(with = "iso8601::option")]
     pub updated_at: Option<OffsetDateTime>,
+    /// when the room was archived, it is left out of listings and takes no deposits until restored
+    #[serde(with = "iso8601::option")]
+    pub archived_at: Option<OffsetDateTime>,
 }
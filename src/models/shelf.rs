@@ -3,6 +3,7 @@ use sqlx::FromRow;
 use time::serde::iso8601;
 use time::OffsetDateTime;
 
+use super::item::ItemDimensions;
 use super::room::RoomId;
 
 pub type ShelfId = i64;
@@ -13,8 +14,185 @@ pub struct Shelf {
     pub name: String,
     pub layer: i64,
     pub room_id: RoomId,
+    /// layers of bins the shelf is divided in
+    pub layers: i64,
+    /// bins on each layer
+    pub positions: i64,
+    /// most items the shelf holds, counted in their base units
+    pub max_units: Option<i64>,
+    pub max_weight_g: Option<i64>,
+    pub max_volume_mm3: Option<i64>,
     #[serde(with = "iso8601")]
     pub created_at: OffsetDateTime,
     #[serde(with = "iso8601::option")]
     pub updated_at: Option<OffsetDateTime>,
+    /// when the shelf was archived, it is left out of listings and takes no deposits until restored
+    #[serde(with = "iso8601::option")]
+    pub archived_at: Option<OffsetDateTime>,
+}
+
+/// What a shelf may hold at most, without limit where `None`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ShelfLimits {
+    pub max_units: Option<i64>,
+    pub max_weight_g: Option<i64>,
+    pub max_volume_mm3: Option<i64>,
+}
+
+/// How much of its capacity a shelf uses. Weight and volume only add up the items that have them;
+/// the remaining capacity is `None` where the shelf has no limit and negative once past it.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, FromRow)]
+pub struct ShelfCapacity {
+    pub shelf_id: ShelfId,
+    pub name: String,
+    pub room_id: RoomId,
+    pub max_units: Option<i64>,
+    pub max_weight_g: Option<i64>,
+    pub max_volume_mm3: Option<i64>,
+    pub units: i64,
+    pub weight_g: i64,
+    pub volume_mm3: i64,
+    pub remaining_units: Option<i64>,
+    pub remaining_weight_g: Option<i64>,
+    pub remaining_volume_mm3: Option<i64>,
+}
+
+impl ShelfCapacity {
+    /// Whether the shelf holds more than one of its limits allows.
+    #[must_use]
+    pub fn exceeded(&self) -> bool {
+        [self.remaining_units, self.remaining_weight_g, self.remaining_volume_mm3]
+            .into_iter()
+            .flatten()
+            .any(|x| x < 0)
+    }
+
+    /// How many more of an item the shelf takes, `None` when none of its limits bounds it. An item
+    /// too large to measure in mm³ fits on no shelf with a volume limit.
+    #[must_use]
+    pub fn room_for(&self, item: &ItemDimensions) -> Option<i64> {
+        let volume = match (item.length_mm, item.width_mm, item.height_mm) {
+            (Some(l), Some(w), Some(h)) => Some(l.saturating_mul(w).saturating_mul(h)),
+            _ => None,
+        };
+        let mut room: Option<i64> = None;
+        for (left, size) in [
+            (self.remaining_units, Some(1)),
+            (self.remaining_weight_g, item.weight_g),
+            (self.remaining_volume_mm3, volume),
+        ] {
+            if let (Some(left), Some(size)) = (left, size) {
+                if size > 0 {
+                    let fits = left.max(0) / size;
+                    room = Some(room.map_or(fits, |x| x.min(fits)));
+                }
+            }
+        }
+        room
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShelfCapacity;
+    use crate::models::item::ItemDimensions;
+
+    fn capacity(
+        remaining_units: Option<i64>,
+        remaining_weight_g: Option<i64>,
+        remaining_volume_mm3: Option<i64>,
+    ) -> ShelfCapacity {
+        ShelfCapacity {
+            shelf_id: 1,
+            name: "S1".to_string(),
+            room_id: 1,
+            max_units: remaining_units.map(|_| 100),
+            max_weight_g: remaining_weight_g.map(|_| 100_000),
+            max_volume_mm3: remaining_volume_mm3.map(|_| 1_000_000),
+            units: 0,
+            weight_g: 0,
+            volume_mm3: 0,
+            remaining_units,
+            remaining_weight_g,
+            remaining_volume_mm3,
+        }
+    }
+
+    fn item(weight_g: i64, length_mm: i64, width_mm: i64, height_mm: i64) -> ItemDimensions {
+        ItemDimensions {
+            weight_g: Some(weight_g),
+            length_mm: Some(length_mm),
+            width_mm: Some(width_mm),
+            height_mm: Some(height_mm),
+        }
+    }
+
+    #[test]
+    fn it_should_not_bound_a_shelf_without_limits() {
+        let shelf = capacity(None, None, None);
+
+        assert_eq!(shelf.room_for(&item(10, 10, 10, 10)), None);
+        assert!(!shelf.exceeded());
+    }
+
+    #[test]
+    fn it_should_not_bound_by_what_the_item_does_not_measure() {
+        let shelf = capacity(None, Some(100), Some(100));
+
+        assert_eq!(shelf.room_for(&ItemDimensions::default()), None);
+    }
+
+    #[test]
+    fn it_should_count_units_for_items_without_dimensions() {
+        let shelf = capacity(Some(7), Some(100), Some(100));
+
+        assert_eq!(shelf.room_for(&ItemDimensions::default()), Some(7));
+    }
+
+    #[test]
+    fn it_should_take_the_tightest_limit() {
+        // 40 units, 25 by weight, 8 by volume
+        let shelf = capacity(Some(40), Some(250), Some(8000));
+
+        assert_eq!(shelf.room_for(&item(10, 10, 10, 10)), Some(8));
+        assert_eq!(shelf.room_for(&item(10, 1, 1, 1)), Some(25));
+        assert_eq!(shelf.room_for(&item(1, 1, 1, 1)), Some(40));
+    }
+
+    #[test]
+    fn it_should_round_down_what_fits() {
+        let shelf = capacity(None, Some(29), None);
+
+        assert_eq!(shelf.room_for(&item(10, 1, 1, 1)), Some(2));
+    }
+
+    #[test]
+    fn it_should_be_full_but_not_exceeded_at_the_limit() {
+        let shelf = capacity(Some(0), Some(0), Some(0));
+
+        assert_eq!(shelf.room_for(&item(1, 1, 1, 1)), Some(0));
+        assert!(!shelf.exceeded());
+    }
+
+    #[test]
+    fn it_should_be_exceeded_past_any_limit() {
+        assert!(capacity(Some(-1), None, None).exceeded());
+        assert!(capacity(None, Some(-1), None).exceeded());
+        assert!(capacity(Some(10), Some(10), Some(-1)).exceeded());
+        assert_eq!(capacity(Some(-5), None, None).room_for(&item(1, 1, 1, 1)), Some(0));
+    }
+
+    #[test]
+    fn it_should_fit_no_item_too_large_to_measure() {
+        let shelf = capacity(None, None, Some(1_000_000));
+
+        assert_eq!(shelf.room_for(&item(1, i64::MAX, 2, 2)), Some(0));
+    }
+
+    #[test]
+    fn it_should_ignore_items_weighing_nothing() {
+        let shelf = capacity(Some(3), Some(0), None);
+
+        assert_eq!(shelf.room_for(&item(0, 1, 1, 1)), Some(3));
+    }
 }
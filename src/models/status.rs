@@ -0,0 +1,96 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sqlx::error::BoxDynError;
+use sqlx::FromRow;
+
+/// The condition stock is kept in. Only `Available` stock is free to be withdrawn, reserved or
+/// picked unless a status is asked for.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum StockStatus {
+    #[default]
+    Available,
+    /// Returned or suspect goods waiting for inspection.
+    Quarantined,
+    Damaged,
+    /// Set aside by someone, pending a decision.
+    OnHold,
+}
+
+impl StockStatus {
+    /// Every status, `Available` first.
+    pub const ALL: [StockStatus; 4] = [
+        StockStatus::Available,
+        StockStatus::Quarantined,
+        StockStatus::Damaged,
+        StockStatus::OnHold,
+    ];
+
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StockStatus::Available => "available",
+            StockStatus::Quarantined => "quarantined",
+            StockStatus::Damaged => "damaged",
+            StockStatus::OnHold => "on_hold",
+        }
+    }
+}
+
+impl fmt::Display for StockStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for StockStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "available" => Ok(StockStatus::Available),
+            "quarantined" => Ok(StockStatus::Quarantined),
+            "damaged" => Ok(StockStatus::Damaged),
+            "on_hold" => Ok(StockStatus::OnHold),
+            _ => Err(format!("unknown stock status: {s}")),
+        }
+    }
+}
+
+impl<DB: sqlx::Database> sqlx::Type<DB> for StockStatus
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for StockStatus
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: <DB as sqlx::Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let status = <String as sqlx::Decode<DB>>::decode(value)?;
+        Ok(status.parse()?)
+    }
+}
+
+/// Quantities on hand broken down by status.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct StatusQuantities {
+    #[sqlx(rename = "status_available")]
+    pub available: i64,
+    #[sqlx(rename = "status_quarantined")]
+    pub quarantined: i64,
+    #[sqlx(rename = "status_damaged")]
+    pub damaged: i64,
+    #[sqlx(rename = "status_on_hold")]
+    pub on_hold: i64,
+}
@@ -0,0 +1,144 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sqlx::error::BoxDynError;
+use sqlx::FromRow;
+use time::serde::iso8601;
+use time::OffsetDateTime;
+
+use crate::models::item::ItemId;
+use crate::models::room::RoomId;
+use crate::models::shelf::ShelfId;
+use crate::models::user::UserId;
+
+pub type StocktakeId = i64;
+
+/// Where a stocktake is in its life. Counts are taken while it is open.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum StocktakeStatus {
+    Open,
+    Approved,
+    Cancelled,
+}
+
+impl StocktakeStatus {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StocktakeStatus::Open => "open",
+            StocktakeStatus::Approved => "approved",
+            StocktakeStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl fmt::Display for StocktakeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for StocktakeStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(StocktakeStatus::Open),
+            "approved" => Ok(StocktakeStatus::Approved),
+            "cancelled" => Ok(StocktakeStatus::Cancelled),
+            _ => Err(format!("unknown stocktake status: {s}")),
+        }
+    }
+}
+
+impl<DB: sqlx::Database> sqlx::Type<DB> for StocktakeStatus
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for StocktakeStatus
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: <DB as sqlx::Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let status = <String as sqlx::Decode<DB>>::decode(value)?;
+        Ok(status.parse()?)
+    }
+}
+
+/// A count session over a room or a set of shelves.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct Stocktake {
+    pub stocktake_id: StocktakeId,
+    /// The room counted, `None` when the session was opened on a set of shelves.
+    pub room_id: Option<RoomId>,
+    pub status: StocktakeStatus,
+    /// No stock moves in or out of the counted shelves while the session is open.
+    pub frozen: bool,
+    /// Counters do not get to see the quantities they are expected to find.
+    pub blind: bool,
+    pub opened_by: UserId,
+    /// The supervisor who approved or cancelled the session.
+    pub closed_by: Option<UserId>,
+    #[serde(with = "iso8601")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "iso8601::option")]
+    pub closed_at: Option<OffsetDateTime>,
+}
+
+/// A count session to open. The shelves of `room_id` are counted, or else `shelf_ids`.
+#[derive(Clone, Debug)]
+pub struct NewStocktake {
+    pub room_id: Option<RoomId>,
+    pub shelf_ids: Vec<ShelfId>,
+    pub frozen: bool,
+    pub blind: bool,
+    pub user_id: UserId,
+}
+
+/// How many of an item a counter found on a shelf.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StocktakeCount {
+    pub item_id: ItemId,
+    pub shelf_id: ShelfId,
+    pub counted: i64,
+    /// The units found, one per unit counted, for serialized items.
+    #[serde(default)]
+    pub serials: Vec<String>,
+}
+
+/// An (item, shelf) pair of a stocktake: one with stock on a counted shelf, or one counted.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct StocktakeLine {
+    pub item_id: ItemId,
+    pub item_name: String,
+    pub shelf_id: ShelfId,
+    pub shelf_name: String,
+    /// on hand, as of the approval once the session is approved
+    pub expected: Option<i64>,
+    /// `None` until counted
+    pub counted: Option<i64>,
+    /// `counted - expected`
+    pub variance: Option<i64>,
+    pub counted_by: Option<UserId>,
+    #[serde(with = "iso8601::option")]
+    pub counted_at: Option<OffsetDateTime>,
+}
+
+impl StocktakeLine {
+    /// Leave out what a blind counter must not see.
+    pub fn hide_expected(&mut self) {
+        self.expected = None;
+        self.variance = None;
+    }
+}
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::serde::iso8601;
+use time::OffsetDateTime;
+
+use crate::models::bin::BinId;
+use crate::models::item::ItemId;
+use crate::models::movement::StockMovement;
+use crate::models::shelf::ShelfId;
+use crate::models::status::StockStatus;
+
+pub type UnitId = i64;
+
+/// A single physical unit of a serialized item.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct StockUnit {
+    pub unit_id: UnitId,
+    pub item_id: ItemId,
+    pub serial: String,
+    /// The shelf the unit is on, `None` once it left the stock.
+    pub shelf_id: Option<ShelfId>,
+    /// The bin of the shelf the unit is in.
+    pub bin_id: Option<BinId>,
+    pub lot_no: Option<String>,
+    pub status: StockStatus,
+    #[serde(with = "iso8601")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "iso8601::option")]
+    pub updated_at: Option<OffsetDateTime>,
+}
+
+/// A unit along with every movement it took part in, oldest first.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnitHistory {
+    #[serde(flatten)]
+    pub unit: StockUnit,
+    pub movements: Vec<StockMovement>,
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sqlx::error::BoxDynError;
+use sqlx::FromRow;
+use time::serde::iso8601;
+use time::OffsetDateTime;
+
+use crate::models::item::ItemId;
+
+pub type LayerId = i64;
+
+/// How the cost of the stock of an item is kept and taken out.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum CostingMethod {
+    /// A layer per deposit, the oldest consumed first.
+    #[default]
+    Fifo,
+    /// A single layer at the moving weighted average cost.
+    Average,
+}
+
+impl CostingMethod {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CostingMethod::Fifo => "fifo",
+            CostingMethod::Average => "average",
+        }
+    }
+}
+
+impl fmt::Display for CostingMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CostingMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fifo" => Ok(CostingMethod::Fifo),
+            "average" => Ok(CostingMethod::Average),
+            _ => Err(format!("unknown costing method: {s}")),
+        }
+    }
+}
+
+impl<DB: sqlx::Database> sqlx::Type<DB> for CostingMethod
+where
+    String: sqlx::Type<DB>,
+{
+    fn type_info() -> DB::TypeInfo {
+        <String as sqlx::Type<DB>>::type_info()
+    }
+
+    fn compatible(ty: &DB::TypeInfo) -> bool {
+        <String as sqlx::Type<DB>>::compatible(ty)
+    }
+}
+
+impl<'r, DB: sqlx::Database> sqlx::Decode<'r, DB> for CostingMethod
+where
+    String: sqlx::Decode<'r, DB>,
+{
+    fn decode(value: <DB as sqlx::Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+        let method = <String as sqlx::Decode<DB>>::decode(value)?;
+        Ok(method.parse()?)
+    }
+}
+
+/// What one unit of a deposit cost, in the unit deposited.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UnitCost {
+    /// in minor units of the currency, cents for `USD`
+    pub unit_cost: i64,
+    /// ISO 4217 code
+    pub currency: String,
+}
+
+/// What a deposit cost as a whole.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Cost {
+    /// in minor units of the currency, cents for `USD`
+    pub value: i64,
+    /// ISO 4217 code
+    pub currency: String,
+}
+
+/// Items of the stock of an item still carrying the cost they came in at.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct CostLayer {
+    pub layer_id: LayerId,
+    pub item_id: ItemId,
+    pub currency: String,
+    pub remaining: i64,
+    /// total value of what remains, in minor units
+    pub value: i64,
+    #[serde(with = "iso8601")]
+    pub created_at: OffsetDateTime,
+}
+
+/// What the stock is grouped by in a valuation.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ValuationGroup {
+    #[default]
+    Room,
+    Warehouse,
+    Category,
+}
+
+/// The value of the costed stock of a group in one currency.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct ValuationLine {
+    /// the room, warehouse or category, `None` for items without a category
+    pub group_id: Option<i64>,
+    pub group_name: Option<String>,
+    pub currency: String,
+    pub count: i64,
+    /// in minor units
+    pub value: i64,
+}
+
+/// The items of a group holding costed stock of an item, out of all the items of it held, along with
+/// the costed part of the stock of the item.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, FromRow)]
+pub struct ValuationShare {
+    pub group_id: Option<i64>,
+    pub group_name: Option<String>,
+    pub item_id: ItemId,
+    pub currency: String,
+    pub count: i64,
+    pub total: i64,
+    pub cost_count: i64,
+    /// in minor units
+    pub cost_value: i64,
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::serde::iso8601;
+use time::OffsetDateTime;
+
+pub type WarehouseId = i64;
+
+/// A site, every room is in exactly one.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, FromRow)]
+pub struct Warehouse {
+    pub warehouse_id: WarehouseId,
+    /// short unique code, such as `MAIN`
+    pub code: String,
+    pub name: String,
+    pub address: Option<String>,
+    /// IANA time zone of the site, such as `Europe/Berlin`
+    pub time_zone: String,
+    #[serde(with = "iso8601")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "iso8601::option")]
+    pub updated_at: Option<OffsetDateTime>,
+}
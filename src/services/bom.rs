@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::common::ListingSpec;
+use crate::databases::database::{Database, Error, Listing};
+use crate::errors::ServiceError;
+use crate::models::bom::{Assembly, Bom, BomId, NewBom, Shortage};
+use crate::models::item::{ItemId, ItemOnShelf, ItemXShelf, Lot};
+use crate::models::movement::MovementMeta;
+use crate::models::policy::StockPolicy;
+use crate::models::shelf::ShelfId;
+use crate::services::stock::DbStockRepository;
+
+pub struct Service {
+    bom_repository: Arc<DbBomRepository>,
+    stock_repository: Arc<DbStockRepository>,
+    /// Items whose stock changed, for the reorder checker.
+    stock_changes: UnboundedSender<ItemId>,
+}
+
+impl Service {
+    #[must_use]
+    pub fn new(
+        bom_repository: Arc<DbBomRepository>,
+        stock_repository: Arc<DbStockRepository>,
+        stock_changes: UnboundedSender<ItemId>,
+    ) -> Self {
+        Self {
+            bom_repository,
+            stock_repository,
+            stock_changes,
+        }
+    }
+    /// Add a recipe for an item, as the next version of the recipes of the item.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::BomNotValid` if counts are not positive, there are no components, a
+    ///   component is listed twice or is the output item itself.
+    /// * `ServiceError::ItemNotFound` if the output or a component does not exist.
+    pub async fn add_bom(&self, bom: &NewBom) -> Result<BomId, ServiceError> {
+        let mut item_ids: Vec<ItemId> = bom.components.iter().map(|x| x.item_id).collect();
+        item_ids.sort_unstable();
+        item_ids.dedup();
+        if bom.output_count <= 0
+            || bom.components.is_empty()
+            || bom.components.iter().any(|x| x.count <= 0)
+            || item_ids.len() != bom.components.len()
+            || item_ids.contains(&bom.output_item_id)
+        {
+            return Err(ServiceError::BomNotValid);
+        }
+        self.bom_repository.create(bom).await.map_err(ServiceError::from)
+    }
+    pub async fn get_bom(&self, bom_id: BomId) -> Result<Bom, ServiceError> {
+        self.bom_repository.get_one(bom_id).await.map_err(ServiceError::from)
+    }
+    pub async fn get_boms(&self, spec: &ListingSpec, item_id: Option<ItemId>) -> Result<Listing<Bom>, ServiceError> {
+        self.bom_repository
+            .get_many(spec, item_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    /// Assemble `quantity` times the recipe onto a shelf. Components come from the `from` lines
+    /// of that item when given, and are picked from the shelves holding them otherwise: the
+    /// target shelf first, then those holding the most.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::CountMustBePositive` if the quantity is not positive.
+    /// * `ServiceError::BomNotFound` if the recipe does not exist.
+    /// * `ServiceError::PicksNotMatchingBom` if the `from` lines of a component do not add up to what the recipe takes.
+    /// * `ServiceError::InsufficientItem` if a component can not be taken from a line it was picked from.
+    pub async fn assemble(
+        &self,
+        bom_id: BomId,
+        quantity: i64,
+        shelf_id: ShelfId,
+        from: Vec<ItemXShelf>,
+        serials: Vec<String>,
+        meta: &MovementMeta,
+    ) -> Result<Assembly, ServiceError> {
+        if quantity <= 0 {
+            return Err(ServiceError::CountMustBePositive);
+        }
+        let bom = self.get_bom(bom_id).await?;
+        let mut lines = Vec::new();
+        let mut shortages = Vec::new();
+        for component in &bom.components {
+            let needed = component.count.checked_mul(quantity).ok_or(ServiceError::PayloadNotValid)?;
+            match self.pick(component.item_id, needed, shelf_id, &from).await? {
+                Ok(picked) => lines.extend(picked),
+                Err(shortage) => shortages.push(shortage),
+            }
+        }
+        if !shortages.is_empty() {
+            return Ok(Assembly::Short(shortages));
+        }
+        let output_count = bom.output_count.checked_mul(quantity).ok_or(ServiceError::PayloadNotValid)?;
+        let mut output = ItemXShelf::new(bom.output_item_id, shelf_id, output_count, &Lot::default());
+        output.serials = serials;
+        let meta = MovementMeta {
+            user_id: meta.user_id,
+            reason: meta.reason.clone().or_else(|| Some(format!("assemble bom {bom_id}"))),
+            expected_count: None,
+        };
+        self.convert(lines, vec![output], &meta).await
+    }
+    /// Take `quantity` times the output of the recipe apart, putting the components onto a shelf.
+    /// The output comes from the `from` lines when given, and is picked from the shelves holding
+    /// it otherwise.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::CountMustBePositive` if the quantity is not positive.
+    /// * `ServiceError::BomNotFound` if the recipe does not exist.
+    /// * `ServiceError::PicksNotMatchingBom` if the `from` lines do not add up to the output taken apart.
+    /// * `ServiceError::InsufficientItem` if the output can not be taken from a line it was picked from.
+    pub async fn disassemble(
+        &self,
+        bom_id: BomId,
+        quantity: i64,
+        shelf_id: ShelfId,
+        from: Vec<ItemXShelf>,
+        meta: &MovementMeta,
+    ) -> Result<Assembly, ServiceError> {
+        if quantity <= 0 {
+            return Err(ServiceError::CountMustBePositive);
+        }
+        let bom = self.get_bom(bom_id).await?;
+        let needed = bom.output_count.checked_mul(quantity).ok_or(ServiceError::PayloadNotValid)?;
+        let lines = match self.pick(bom.output_item_id, needed, shelf_id, &from).await? {
+            Ok(picked) => picked,
+            Err(shortage) => return Ok(Assembly::Short(vec![shortage])),
+        };
+        let mut into = Vec::new();
+        for component in &bom.components {
+            let count = component.count.checked_mul(quantity).ok_or(ServiceError::PayloadNotValid)?;
+            into.push(ItemXShelf::new(component.item_id, shelf_id, count, &Lot::default()));
+        }
+        let meta = MovementMeta {
+            user_id: meta.user_id,
+            reason: meta.reason.clone().or_else(|| Some(format!("disassemble bom {bom_id}"))),
+            expected_count: None,
+        };
+        self.convert(lines, into, &meta).await
+    }
+    async fn convert(&self, from: Vec<ItemXShelf>, into: Vec<ItemXShelf>, meta: &MovementMeta) -> Result<Assembly, ServiceError> {
+        let mut item_ids: Vec<ItemId> = from.iter().chain(into.iter()).map(|x| x.item_id).collect();
+        item_ids.sort_unstable();
+        item_ids.dedup();
+        let stocks = self
+            .stock_repository
+            .convert(from, into, meta)
+            .await
+            .map_err(ServiceError::from)?;
+        for item_id in item_ids {
+            let _ = self.stock_changes.send(item_id);
+        }
+        Ok(Assembly::Converted(stocks))
+    }
+    /// Lines taking `needed` of an item: the given ones if there are any for the item, or else
+    /// picked from the shelves holding it, `prefer` first.
+    async fn pick(
+        &self,
+        item_id: ItemId,
+        needed: i64,
+        prefer: ShelfId,
+        given: &[ItemXShelf],
+    ) -> Result<Result<Vec<ItemXShelf>, Shortage>, ServiceError> {
+        let given: Vec<ItemXShelf> = given.iter().filter(|x| x.item_id == item_id).cloned().collect();
+        if !given.is_empty() {
+            if given.iter().map(|x| x.count).sum::<i64>() != needed {
+                return Err(ServiceError::PicksNotMatchingBom);
+            }
+            return Ok(Ok(given));
+        }
+        let stocks = self
+            .stock_repository
+            .get_all_of_item(item_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)?;
+        let mut takeable = Vec::with_capacity(stocks.len());
+        for stock in stocks {
+            let policy = self
+                .stock_repository
+                .get_policy_of_shelf(stock.shelf_id)
+                .await
+                .map_err(|_| ServiceError::InternalServerError)?;
+            let count = takeable_count(&stock, &policy);
+            takeable.push((stock, count));
+        }
+        takeable.sort_by_key(|(x, count)| (x.shelf_id != prefer, -count));
+        let available: i64 = takeable.iter().map(|(_, count)| count).sum();
+        if available < needed {
+            return Ok(Err(Shortage {
+                item_id,
+                needed,
+                available,
+            }));
+        }
+        let mut lines = Vec::new();
+        let mut wanted = needed;
+        for (stock, count) in takeable {
+            if wanted == 0 {
+                break;
+            }
+            let taken = wanted.min(count);
+            if taken > 0 {
+                lines.push(ItemXShelf::new(item_id, stock.shelf_id, taken, &Lot::default()));
+                wanted -= taken;
+            }
+        }
+        Ok(Ok(lines))
+    }
+}
+
+/// What can be taken of an item on a shelf: what is not reserved, short of the last item unless
+/// the policy of the shelf lets it be emptied. Picks never take a shelf below zero.
+fn takeable_count(stock: &ItemOnShelf, policy: &StockPolicy) -> i64 {
+    if policy.allow_empty {
+        stock.available.max(0)
+    } else {
+        stock.available.min(stock.count - 1).max(0)
+    }
+}
+
+pub struct DbBomRepository {
+    database: Arc<Box<dyn Database>>,
+}
+
+impl DbBomRepository {
+    #[must_use]
+    pub fn new(database: Arc<Box<dyn Database>>) -> Self {
+        Self { database }
+    }
+    pub async fn create(&self, bom: &NewBom) -> Result<BomId, Error> {
+        self.database.insert_bom(bom).await
+    }
+    pub async fn get_one(&self, bom_id: BomId) -> Result<Bom, Error> {
+        self.database.get_bom(bom_id).await
+    }
+    pub async fn get_many(&self, spec: &ListingSpec, item_id: Option<ItemId>) -> Result<Listing<Bom>, Error> {
+        self.database.get_boms(spec.offset, spec.limit, &spec.sort, item_id).await
+    }
+}
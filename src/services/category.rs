@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use crate::databases::database::{Database, Error};
+use crate::errors::ServiceError;
+use crate::models::category::{Category, CategoryId};
+use crate::web::api::v1::contexts::category::forms::AddCategoryForm;
+
+pub struct Service {
+    category_repository: Arc<DbCategoryRepository>,
+}
+
+impl Service {
+    #[must_use]
+    pub fn new(category_repository: Arc<DbCategoryRepository>) -> Self {
+        Self { category_repository }
+    }
+    pub async fn add_category(&self, category_form: &AddCategoryForm) -> Result<CategoryId, ServiceError> {
+        if category_form.description.as_ref().is_some_and(|x| x.len() > 200) {
+            return Err(ServiceError::DescNotValid);
+        }
+        self.category_repository
+            .add(&category_form.name, &category_form.description)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    /// Remove a category, leaving its items without one.
+    pub async fn remove_category(&self, category_id: &CategoryId) -> Result<(), ServiceError> {
+        self.category_repository
+            .delete_one(category_id)
+            .await
+            .map_err(|error: Error| match error {
+                Error::CategoryNotFound => ServiceError::CategoryNotFound,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    pub async fn get_all_categories(&self) -> Result<Vec<Category>, ServiceError> {
+        self.category_repository
+            .get_all()
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+}
+
+pub struct DbCategoryRepository {
+    database: Arc<Box<dyn Database>>,
+}
+
+impl DbCategoryRepository {
+    #[must_use]
+    pub fn new(database: Arc<Box<dyn Database>>) -> Self {
+        Self { database }
+    }
+    pub async fn add(&self, name: &str, desc: &Option<String>) -> Result<CategoryId, Error> {
+        self.database.insert_category(name, desc).await
+    }
+    pub async fn delete_one(&self, category_id: &CategoryId) -> Result<(), Error> {
+        self.database.delete_category(*category_id).await
+    }
+    pub async fn get_all(&self) -> Result<Vec<Category>, Error> {
+        self.database.get_categories().await
+    }
+}
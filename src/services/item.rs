@@ -1,9 +1,11 @@
 use std::sync::Arc;
 
-use crate::common::{BatchDelResult, ListingSpec};
+use crate::common::{BatchDelResult, ItemClassFilter, ListingSpec};
 use crate::databases::database::{Database, Error, Listing};
 use crate::errors::ServiceError;
-use crate::models::item::{Item, ItemId};
+use crate::models::category::CategoryId;
+use crate::models::item::{Item, ItemDimensions, ItemId, ItemUnit};
+use crate::models::valuation::{CostLayer, CostingMethod};
 
 pub struct Service {
     item_repository: Arc<DbItemRepository>,
@@ -14,35 +16,54 @@ impl Service {
     pub fn new(item_repository: Arc<DbItemRepository>) -> Self {
         Self { item_repository }
     }
-    pub async fn add_item(&self, name: &str, sn: &str) -> Result<ItemId, ServiceError> {
+    pub async fn add_item(&self, name: &str, sn: &str, serialized: bool, base_unit: &str) -> Result<ItemId, ServiceError> {
+        if base_unit.is_empty() {
+            return Err(ServiceError::UnitNotValid);
+        }
         self.item_repository
-            .add(name, sn)
+            .add(name, sn, serialized, base_unit)
             .await
             .map_err(|_| ServiceError::InternalServerError)
     }
-    pub async fn add_item_with_desc(&self, name: &str, desc: &str, sn: &str) -> Result<ItemId, ServiceError> {
+    pub async fn add_item_with_desc(
+        &self,
+        name: &str,
+        desc: &str,
+        sn: &str,
+        serialized: bool,
+        base_unit: &str,
+    ) -> Result<ItemId, ServiceError> {
+        if base_unit.is_empty() {
+            return Err(ServiceError::UnitNotValid);
+        }
         self.item_repository
-            .add_with_desc(name, desc, sn)
+            .add_with_desc(name, desc, sn, serialized, base_unit)
             .await
             .map_err(|_| ServiceError::InternalServerError)
     }
+    /// Delete an item that has no stock and nothing refers to.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::ItemNotFound` if the item does not exist.
+    /// * `ServiceError::ItemInStock` if some of it is still in stock.
+    /// * `ServiceError::ItemOnShelf` if empty lots of it are still kept on a shelf.
+    /// * `ServiceError::ItemReferenced` if its stock history refers to it.
     pub async fn remove_item(&self, item_id: &ItemId) -> Result<(), ServiceError> {
-        self.item_repository
-            .delete_one(item_id)
-            .await
-            .map_err(|error: Error| match error {
-                Error::ItemNotFound => ServiceError::ItemNotFound,
-                _ => ServiceError::InternalServerError,
-            })
+        self.item_repository.delete_one(item_id).await.map_err(ServiceError::from)
     }
-    pub async fn remove_items(&self, ids: &Vec<ItemId>) -> Result<BatchDelResult, ServiceError> {
+    /// Delete the items among `ids` that have no stock and are not referenced, reporting why each of the others failed. With `dry_run` nothing is changed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a `ServiceError::InternalServerError` if the database fails.
+    pub async fn remove_items(&self, ids: &Vec<ItemId>, dry_run: bool) -> Result<BatchDelResult, ServiceError> {
         self.item_repository
-            .delete_many(ids)
+            .delete_many(ids, dry_run)
             .await
-            .map_err(|error: Error| match error {
-                Error::ItemNotFound => ServiceError::ItemNotFound,
-                _ => ServiceError::InternalServerError,
-            })
+            .map_err(ServiceError::from)
     }
     pub async fn update_item(&self, item_id: &ItemId, name: &str, desc: &Option<String>, sn: &str) -> Result<(), ServiceError> {
         self.item_repository
@@ -87,15 +108,109 @@ impl Service {
             .await
             .map_err(|_| ServiceError::ItemNotFound)
     }
-    pub async fn get_items(&self, spec: &ListingSpec) -> Result<Listing<Item>, ServiceError> {
+    pub async fn get_items(&self, spec: &ListingSpec, filter: &ItemClassFilter) -> Result<Listing<Item>, ServiceError> {
+        self.item_repository
+            .get_many(spec, filter)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    pub async fn get_all_items(&self, filter: &ItemClassFilter) -> Result<Vec<Item>, ServiceError> {
         self.item_repository
-            .get_many(spec)
+            .get_all(filter)
             .await
             .map_err(|_| ServiceError::InternalServerError)
     }
-    pub async fn get_all_items(&self) -> Result<Vec<Item>, ServiceError> {
+    pub async fn get_item_units(&self, item_id: &ItemId) -> Result<Vec<ItemUnit>, ServiceError> {
+        self.get_item(item_id).await?;
         self.item_repository
-            .get_all()
+            .get_units(item_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    /// Define an alternative unit of an item as `factor` of its base unit, or change the factor
+    /// of an existing one.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::ItemNotFound` if the item does not exist.
+    /// * `ServiceError::UnitNotValid` if the unit is empty or the base unit, or the factor is not positive.
+    pub async fn set_item_unit(&self, item_id: &ItemId, unit: &str, factor: i64) -> Result<(), ServiceError> {
+        let item = self.get_item(item_id).await?;
+        if unit.is_empty() || unit == item.base_unit || factor <= 0 {
+            return Err(ServiceError::UnitNotValid);
+        }
+        self.item_repository
+            .upsert_unit(item_id, unit, factor)
+            .await
+            .map_err(|error: Error| match error {
+                Error::ItemNotFound => ServiceError::ItemNotFound,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    pub async fn remove_item_unit(&self, item_id: &ItemId, unit: &str) -> Result<(), ServiceError> {
+        self.item_repository
+            .delete_unit(item_id, unit)
+            .await
+            .map_err(|error: Error| match error {
+                Error::UnitNotFound => ServiceError::UnitNotFound,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    /// Switch how the cost of the stock of an item is kept. Switching to the average merges the
+    /// cost layers of the item into one.
+    pub async fn update_item_costing(&self, item_id: &ItemId, costing: CostingMethod) -> Result<(), ServiceError> {
+        self.item_repository
+            .update_costing(item_id, costing)
+            .await
+            .map_err(|error: Error| match error {
+                Error::ItemNotFound => ServiceError::ItemNotFound,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    pub async fn update_item_category(&self, item_id: &ItemId, category_id: CategoryId) -> Result<(), ServiceError> {
+        self.item_repository
+            .update_category(item_id, category_id)
+            .await
+            .map_err(|error: Error| match error {
+                Error::ItemNotFound => ServiceError::ItemNotFound,
+                Error::CategoryNotFound => ServiceError::CategoryNotFound,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    /// Set the weight and dimensions of one base unit of an item, what its stock takes of the
+    /// capacity of a shelf.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::PayloadNotValid` if one of them is negative.
+    /// * `ServiceError::ItemNotFound` if the item does not exist.
+    pub async fn update_item_dimensions(&self, item_id: &ItemId, dimensions: &ItemDimensions) -> Result<(), ServiceError> {
+        let sizes = [
+            dimensions.weight_g,
+            dimensions.length_mm,
+            dimensions.width_mm,
+            dimensions.height_mm,
+        ];
+        if sizes.into_iter().flatten().any(|x| x < 0) {
+            return Err(ServiceError::PayloadNotValid);
+        }
+        self.item_repository
+            .update_dimensions(item_id, dimensions)
+            .await
+            .map_err(|error: Error| match error {
+                Error::ItemNotFound => ServiceError::ItemNotFound,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    /// Get the cost layers of an item still holding stock, oldest first.
+    pub async fn get_item_cost_layers(&self, item_id: &ItemId) -> Result<Vec<CostLayer>, ServiceError> {
+        self.get_item(item_id).await?;
+        self.item_repository
+            .get_cost_layers(item_id)
             .await
             .map_err(|_| ServiceError::InternalServerError)
     }
@@ -110,17 +225,26 @@ impl DbItemRepository {
     pub fn new(database: Arc<Box<dyn Database>>) -> Self {
         Self { database }
     }
-    pub async fn add(&self, name: &str, sn: &str) -> Result<ItemId, Error> {
-        self.database.insert_item_and_get_id(name, sn).await
+    pub async fn add(&self, name: &str, sn: &str, serialized: bool, base_unit: &str) -> Result<ItemId, Error> {
+        self.database.insert_item_and_get_id(name, sn, serialized, base_unit).await
     }
-    pub async fn add_with_desc(&self, name: &str, desc: &str, sn: &str) -> Result<ItemId, Error> {
-        self.database.insert_item_with_desc_and_get_id(name, desc, sn).await
+    pub async fn add_with_desc(
+        &self,
+        name: &str,
+        desc: &str,
+        sn: &str,
+        serialized: bool,
+        base_unit: &str,
+    ) -> Result<ItemId, Error> {
+        self.database
+            .insert_item_with_desc_and_get_id(name, desc, sn, serialized, base_unit)
+            .await
     }
     pub async fn delete_one(&self, item_id: &ItemId) -> Result<(), Error> {
         self.database.delete_item(*item_id).await
     }
-    pub async fn delete_many(&self, item_ids: &Vec<ItemId>) -> Result<BatchDelResult, Error> {
-        self.database.delete_items(item_ids).await
+    pub async fn delete_many(&self, item_ids: &Vec<ItemId>, dry_run: bool) -> Result<BatchDelResult, Error> {
+        self.database.delete_items(item_ids, dry_run).await
     }
     pub async fn update(&self, item_id: &ItemId, name: &str, desc: &Option<String>, sn: &str) -> Result<(), Error> {
         self.database.update_item(*item_id, name, desc, sn).await
@@ -137,10 +261,33 @@ impl DbItemRepository {
     pub async fn get_one(&self, item_id: &ItemId) -> Result<Item, Error> {
         self.database.get_item_from_id(*item_id).await
     }
-    pub async fn get_many(&self, spec: &ListingSpec) -> Result<Listing<Item>, Error> {
-        self.database.get_items(spec.offset, spec.limit, &spec.sort).await
+    pub async fn get_many(&self, spec: &ListingSpec, filter: &ItemClassFilter) -> Result<Listing<Item>, Error> {
+        self.database
+            .get_items(spec.offset, spec.limit, &spec.sort, filter.abc_class, filter.xyz_class)
+            .await
+    }
+    pub async fn get_all(&self, filter: &ItemClassFilter) -> Result<Vec<Item>, Error> {
+        self.database.get_all_items(filter.abc_class, filter.xyz_class).await
+    }
+    pub async fn get_units(&self, item_id: &ItemId) -> Result<Vec<ItemUnit>, Error> {
+        self.database.get_item_units(*item_id).await
+    }
+    pub async fn upsert_unit(&self, item_id: &ItemId, unit: &str, factor: i64) -> Result<(), Error> {
+        self.database.upsert_item_unit(*item_id, unit, factor).await
+    }
+    pub async fn delete_unit(&self, item_id: &ItemId, unit: &str) -> Result<(), Error> {
+        self.database.delete_item_unit(*item_id, unit).await
+    }
+    pub async fn update_costing(&self, item_id: &ItemId, costing: CostingMethod) -> Result<(), Error> {
+        self.database.update_item_costing(*item_id, costing).await
+    }
+    pub async fn update_category(&self, item_id: &ItemId, category_id: CategoryId) -> Result<(), Error> {
+        self.database.update_item_category(*item_id, category_id).await
+    }
+    pub async fn update_dimensions(&self, item_id: &ItemId, dimensions: &ItemDimensions) -> Result<(), Error> {
+        self.database.update_item_dimensions(*item_id, dimensions).await
     }
-    pub async fn get_all(&self) -> Result<Vec<Item>, Error> {
-        self.database.get_all_items().await
+    pub async fn get_cost_layers(&self, item_id: &ItemId) -> Result<Vec<CostLayer>, Error> {
+        self.database.get_cost_layers(*item_id).await
     }
 }
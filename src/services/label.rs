@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use crate::databases::database::Error;
+use crate::errors::ServiceError;
+use crate::models::label::Label;
+use crate::models::room::{Room, RoomId};
+use crate::models::shelf::{Shelf, ShelfId};
+use crate::services::room::DbRoomRepository;
+use crate::services::shelf::DbShelfRepository;
+
+pub struct Service {
+    room_repository: Arc<DbRoomRepository>,
+    shelf_repository: Arc<DbShelfRepository>,
+}
+
+impl Service {
+    #[must_use]
+    pub fn new(room_repository: Arc<DbRoomRepository>, shelf_repository: Arc<DbShelfRepository>) -> Self {
+        Self {
+            room_repository,
+            shelf_repository,
+        }
+    }
+    /// Get the label of a room, captioned with its warehouse.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a `ServiceError::RoomNotFound` if the room does not exist.
+    pub async fn room_label(&self, room_id: &RoomId) -> Result<Label, ServiceError> {
+        let room = self.get_room(room_id).await?;
+        let warehouse = self
+            .room_repository
+            .get_warehouse(&room.warehouse_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)?;
+        Ok(Label::room(&room, &warehouse))
+    }
+    /// Get the label of a shelf, followed by the labels of its bins when `bins` is set.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a `ServiceError::ShelfNotFound` if the shelf does not exist.
+    pub async fn shelf_labels(&self, shelf_id: &ShelfId, bins: bool) -> Result<Vec<Label>, ServiceError> {
+        let shelf = self
+            .shelf_repository
+            .get_one(shelf_id)
+            .await
+            .map_err(|error: Error| match error {
+                Error::ShelfNotFound => ServiceError::ShelfNotFound,
+                _ => ServiceError::InternalServerError,
+            })?;
+        let room = self.get_room(&shelf.room_id).await?;
+        self.labels_of(&room, vec![shelf], bins).await
+    }
+    /// Get the labels of all shelves of a room, each followed by the labels of its bins when
+    /// `bins` is set, to print a room at once.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a `ServiceError::RoomNotFound` if the room does not exist.
+    pub async fn room_shelf_labels(&self, room_id: &RoomId, bins: bool) -> Result<Vec<Label>, ServiceError> {
+        let room = self.get_room(room_id).await?;
+        let shelves = self
+            .shelf_repository
+            .get_all(Some(*room_id))
+            .await
+            .map_err(|_| ServiceError::InternalServerError)?;
+        self.labels_of(&room, shelves, bins).await
+    }
+    async fn get_room(&self, room_id: &RoomId) -> Result<Room, ServiceError> {
+        self.room_repository
+            .get_one(room_id)
+            .await
+            .map_err(|error: Error| match error {
+                Error::RoomNotFound => ServiceError::RoomNotFound,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    async fn labels_of(&self, room: &Room, shelves: Vec<Shelf>, bins: bool) -> Result<Vec<Label>, ServiceError> {
+        let mut labels = Vec::with_capacity(shelves.len());
+        for shelf in shelves {
+            labels.push(Label::shelf(&shelf, room));
+            if bins {
+                let shelf_bins = self
+                    .shelf_repository
+                    .get_bins(&shelf.shelf_id)
+                    .await
+                    .map_err(|_| ServiceError::InternalServerError)?;
+                labels.extend(shelf_bins.iter().map(|bin| Label::bin(&shelf, bin)));
+            }
+        }
+        Ok(labels)
+    }
+}
@@ -1,7 +1,13 @@
 pub mod about;
 pub mod authentication;
+pub mod bom;
+pub mod category;
 pub mod item;
+pub mod label;
+pub mod reorder;
 pub mod room;
 pub mod shelf;
 pub mod stock;
+pub mod stocktake;
 pub mod user;
+pub mod warehouse;
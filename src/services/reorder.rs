@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use log::{error, info};
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
+
+use crate::common::ListingSpec;
+use crate::databases::database::{Database, Error, Listing};
+use crate::errors::ServiceError;
+use crate::mailer;
+use crate::models::item::ItemId;
+use crate::models::reorder::{ReorderLevel, ReorderPoint, ReorderPointId};
+use crate::models::room::RoomId;
+use crate::models::user::{UserId, UserProfile};
+
+/// How many low-stock events a slow listener may fall behind before missing some.
+const LOW_STOCK_EVENTS_CAPACITY: usize = 64;
+
+pub struct Service {
+    reorder_repository: Arc<DbReorderRepository>,
+    mailer: Arc<mailer::Service>,
+    low_stock: broadcast::Sender<ReorderLevel>,
+}
+
+impl Service {
+    #[must_use]
+    pub fn new(reorder_repository: Arc<DbReorderRepository>, mailer: Arc<mailer::Service>) -> Self {
+        let (low_stock, _) = broadcast::channel(LOW_STOCK_EVENTS_CAPACITY);
+        Self {
+            reorder_repository,
+            mailer,
+            low_stock,
+        }
+    }
+    /// Listen to the reorder points the stock falls under from now on.
+    #[must_use]
+    pub fn subscribe_low_stock(&self) -> broadcast::Receiver<ReorderLevel> {
+        self.low_stock.subscribe()
+    }
+    /// Set the levels to keep an item within, in a room or across every room.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a `ServiceError::ReorderLevelsNotValid` unless `0 <= min_level <= max_level`.
+    pub async fn set_reorder_point(
+        &self,
+        item_id: ItemId,
+        room_id: Option<RoomId>,
+        min_level: i64,
+        max_level: i64,
+    ) -> Result<ReorderPointId, ServiceError> {
+        if min_level < 0 || max_level < min_level {
+            return Err(ServiceError::ReorderLevelsNotValid);
+        }
+        self.reorder_repository
+            .upsert(item_id, room_id, min_level, max_level)
+            .await
+            .map_err(ServiceError::from)
+    }
+    pub async fn remove_reorder_point(&self, reorder_point_id: ReorderPointId) -> Result<(), ServiceError> {
+        self.reorder_repository
+            .delete_one(reorder_point_id)
+            .await
+            .map_err(ServiceError::from)
+    }
+    pub async fn get_reorder_points(
+        &self,
+        spec: &ListingSpec,
+        item_id: Option<ItemId>,
+    ) -> Result<Listing<ReorderPoint>, ServiceError> {
+        self.reorder_repository
+            .get_many(spec, item_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    pub async fn get_below_reorder_point(
+        &self,
+        spec: &ListingSpec,
+        room_id: Option<RoomId>,
+    ) -> Result<Listing<ReorderLevel>, ServiceError> {
+        self.reorder_repository
+            .get_many_below(spec, room_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    pub async fn subscribe(&self, user_id: &UserId) -> Result<(), ServiceError> {
+        self.reorder_repository.subscribe(*user_id).await.map_err(ServiceError::from)
+    }
+    pub async fn unsubscribe(&self, user_id: &UserId) -> Result<(), ServiceError> {
+        self.reorder_repository
+            .unsubscribe(*user_id)
+            .await
+            .map_err(ServiceError::from)
+    }
+    /// Evaluate the reorder points of an item, or of every item when `item_id` is `None`, publish a
+    /// low-stock event for each one the stock just fell under and alert the subscribers of them. Reorder points the stock got back
+    /// above are re-armed. Returns the reorder points just fallen under.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the levels can not be read or updated. Failing to mail
+    /// a subscriber is only logged.
+    pub async fn check(&self, item_id: Option<ItemId>) -> Result<Vec<ReorderLevel>, ServiceError> {
+        let levels = self
+            .reorder_repository
+            .get_levels(item_id)
+            .await
+            .map_err(ServiceError::from)?;
+        let mut crossed = Vec::new();
+        for mut level in levels {
+            let below = level.is_below();
+            if below == level.below_since.is_some() {
+                continue;
+            }
+            self.reorder_repository
+                .set_below(level.reorder_point_id, below)
+                .await
+                .map_err(ServiceError::from)?;
+            if below {
+                info!(
+                    "Low stock: item {} in {} has {} available, under its min level of {}",
+                    level.item_id,
+                    level.room_name.as_deref().unwrap_or("all rooms"),
+                    level.available,
+                    level.min_level
+                );
+                level.below_since = Some(OffsetDateTime::now_utc());
+                // nobody listening is no error
+                let _ = self.low_stock.send(level.clone());
+                crossed.push(level);
+            }
+        }
+        if !crossed.is_empty() {
+            self.alert(&crossed).await?;
+        }
+        Ok(crossed)
+    }
+    async fn alert(&self, levels: &[ReorderLevel]) -> Result<(), ServiceError> {
+        let subscribers = self.reorder_repository.get_subscribers().await.map_err(ServiceError::from)?;
+        for subscriber in subscribers {
+            if let Err(e) = self
+                .mailer
+                .send_low_stock_mail(&subscriber.email, &subscriber.username, levels)
+                .await
+            {
+                error!("Failed to alert {} of low stock: {e}", subscriber.username);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct DbReorderRepository {
+    database: Arc<Box<dyn Database>>,
+}
+
+impl DbReorderRepository {
+    #[must_use]
+    pub fn new(database: Arc<Box<dyn Database>>) -> Self {
+        Self { database }
+    }
+    pub async fn upsert(
+        &self,
+        item_id: ItemId,
+        room_id: Option<RoomId>,
+        min_level: i64,
+        max_level: i64,
+    ) -> Result<ReorderPointId, Error> {
+        self.database
+            .upsert_reorder_point(item_id, room_id, min_level, max_level)
+            .await
+    }
+    pub async fn delete_one(&self, reorder_point_id: ReorderPointId) -> Result<(), Error> {
+        self.database.delete_reorder_point(reorder_point_id).await
+    }
+    pub async fn get_many(&self, spec: &ListingSpec, item_id: Option<ItemId>) -> Result<Listing<ReorderPoint>, Error> {
+        self.database
+            .get_reorder_points(spec.offset, spec.limit, &spec.sort, item_id)
+            .await
+    }
+    pub async fn get_many_below(&self, spec: &ListingSpec, room_id: Option<RoomId>) -> Result<Listing<ReorderLevel>, Error> {
+        self.database
+            .get_reorder_levels_below(spec.offset, spec.limit, &spec.sort, room_id)
+            .await
+    }
+    pub async fn get_levels(&self, item_id: Option<ItemId>) -> Result<Vec<ReorderLevel>, Error> {
+        self.database.get_reorder_levels(item_id).await
+    }
+    pub async fn set_below(&self, reorder_point_id: ReorderPointId, below: bool) -> Result<(), Error> {
+        self.database.set_reorder_point_below(reorder_point_id, below).await
+    }
+    pub async fn subscribe(&self, user_id: UserId) -> Result<(), Error> {
+        self.database.insert_stock_alert_subscription(user_id).await
+    }
+    pub async fn unsubscribe(&self, user_id: UserId) -> Result<(), Error> {
+        self.database.delete_stock_alert_subscription(user_id).await
+    }
+    pub async fn get_subscribers(&self) -> Result<Vec<UserProfile>, Error> {
+        self.database.get_stock_alert_subscribers().await
+    }
+}
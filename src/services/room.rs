@@ -3,80 +3,151 @@ use std::sync::Arc;
 use crate::common::{BatchDelResult, ListingSpec};
 use crate::databases::database::{Database, Error, Listing};
 use crate::errors::ServiceError;
+use crate::models::bin::BinId;
+use crate::models::category::{Category, CategoryId};
+use crate::models::movement::MovementMeta;
 use crate::models::room::{Room, RoomId};
-use crate::web::api::v1::contexts::room::forms::AddRoomForm;
+use crate::models::shelf::ShelfId;
+use crate::models::user::UserId;
+use crate::models::warehouse::{Warehouse, WarehouseId};
+use crate::services::user::DbUserRepository;
+use crate::web::api::v1::contexts::room::forms::{AddRoomForm, UpdateRoomForm};
 
 pub struct Service {
     room_repository: Arc<DbRoomRepository>,
+    user_repository: Arc<DbUserRepository>,
 }
 
 impl Service {
     #[must_use]
-    pub fn new(room_repository: Arc<DbRoomRepository>) -> Self {
-        Self { room_repository }
+    pub fn new(room_repository: Arc<DbRoomRepository>, user_repository: Arc<DbUserRepository>) -> Self {
+        Self {
+            room_repository,
+            user_repository,
+        }
     }
     pub async fn add_room(
         &self,
         registration_form: &AddRoomForm, /*, opt_user_id: Option<UserId>*/
     ) -> Result<RoomId, ServiceError> {
+        self.check_warehouse(&registration_form.warehouse_id).await?;
         if let Some(desc) = &registration_form.description {
             if desc.len() > 200 {
                 return Err(ServiceError::DescNotValid);
             }
             return self
                 .room_repository
-                .add_with_desc(&registration_form.name, desc)
+                .add_with_desc(&registration_form.name, desc, &registration_form.warehouse_id)
                 .await
                 .map_err(|_| ServiceError::InternalServerError);
         }
         self.room_repository
-            .add(&registration_form.name)
+            .add(&registration_form.name, &registration_form.warehouse_id)
             .await
             .map_err(|_| ServiceError::InternalServerError)
     }
-    pub async fn close_room(&self, room_id: &RoomId) -> Result<(), ServiceError> {
+    /// Archive a room along with its shelves, which hides them from listings and closes them to
+    /// deposits. The stock left on the shelves moves to `shelf_to`, into `bin_to` when given.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::RoomNotFound` if the room does not exist or is archived already.
+    /// * `ServiceError::LocationNotEmpty` if stock remains and there is no `shelf_to`.
+    /// * `ServiceError::LocationArchived` if `shelf_to` is archived, or in the room.
+    pub async fn close_room(
+        &self,
+        room_id: &RoomId,
+        shelf_to: Option<ShelfId>,
+        bin_to: Option<BinId>,
+        meta: &MovementMeta,
+    ) -> Result<(), ServiceError> {
         self.room_repository
-            .delete_one(&room_id)
+            .archive(room_id, shelf_to, bin_to, meta)
             .await
-            .map_err(|error: Error| match error {
-                Error::RoomNotFound => ServiceError::RoomNotFound,
-                _ => ServiceError::InternalServerError,
-            })
+            .map_err(ServiceError::from)
     }
-    pub async fn close_rooms(&self, ids: &Vec<RoomId>) -> Result<BatchDelResult, ServiceError> {
+    /// Restore an archived room along with the shelves archived with it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a `ServiceError::RoomNotFound` if the room does not exist or is
+    /// not archived.
+    pub async fn restore_room(&self, room_id: &RoomId) -> Result<(), ServiceError> {
+        self.room_repository.restore(room_id).await.map_err(ServiceError::from)
+    }
+    /// Archive the empty rooms among `ids` along with their shelves, reporting why each of the others failed. With `dry_run` nothing is changed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a `ServiceError::InternalServerError` if the database fails.
+    pub async fn close_rooms(&self, ids: &Vec<RoomId>, dry_run: bool) -> Result<BatchDelResult, ServiceError> {
         self.room_repository
-            .delete_many(ids)
+            .delete_many(ids, dry_run)
             .await
-            .map_err(|error: Error| match error {
-                Error::RoomNotFound => ServiceError::RoomNotFound,
-                _ => ServiceError::InternalServerError,
-            })
+            .map_err(ServiceError::from)
     }
 
-    pub async fn update_room(&self, room_id: &RoomId, name: &str, desc: &Option<String>) -> Result<(), ServiceError> {
+    /// Update a room. Only an administrator may move it to another warehouse, and only once no
+    /// stock is left in it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::RoomNotFound` if the room does not exist.
+    /// * `ServiceError::Unauthorized` if the warehouse changes and the user is not an administrator.
+    /// * `ServiceError::WarehouseNotFound` if the warehouse does not exist.
+    /// * `ServiceError::RoomHasStock` if the warehouse changes while stock is left in the room.
+    pub async fn update_room(
+        &self,
+        user_id: &UserId,
+        room_id: &RoomId,
+        name: &str,
+        desc: &Option<String>,
+        warehouse_id: &WarehouseId,
+    ) -> Result<(), ServiceError> {
+        let room = self.room_repository.get_one(room_id).await.map_err(ServiceError::from)?;
+        if room.warehouse_id != *warehouse_id {
+            self.check_administrator(user_id).await?;
+            self.check_warehouse(warehouse_id).await?;
+        }
         self.room_repository
-            .update(&room_id, name, desc)
+            .update(room_id, name, desc, warehouse_id)
             .await
-            .map_err(|error: Error| match error {
-                Error::RoomNotFound => ServiceError::RoomNotFound,
-                _ => ServiceError::InternalServerError,
-            })
+            .map_err(ServiceError::from)
     }
-    pub async fn update_room_name(&self, room_id: &RoomId, name: &str) -> Result<(), ServiceError> {
-        self.room_repository
-            .update_name(&room_id, &name)
-            .await
-            .map_err(|error: Error| match error {
-                Error::RoomNotFound => ServiceError::RoomNotFound,
-                _ => ServiceError::InternalServerError,
-            })
+    /// Update the fields of a room given in `form`, keeping the others, as `update_room` does.
+    ///
+    /// # Errors
+    ///
+    /// This function will return the errors of `update_room`, or a `ServiceError::DescNotValid` if
+    /// the description is longer than 200 characters.
+    pub async fn patch_room(&self, user_id: &UserId, room_id: &RoomId, form: &UpdateRoomForm) -> Result<(), ServiceError> {
+        if form.description.as_ref().is_some_and(|desc| desc.len() > 200) {
+            return Err(ServiceError::DescNotValid);
+        }
+        let room = self.room_repository.get_one(room_id).await.map_err(ServiceError::from)?;
+        let name = form.name.as_ref().unwrap_or(&room.name);
+        let desc = form.description.clone().or(room.description);
+        let warehouse_id = form.warehouse_id.unwrap_or(room.warehouse_id);
+        self.update_room(user_id, room_id, name, &desc, &warehouse_id).await
     }
-    pub async fn update_room_desc(&self, room_id: &RoomId, desc: &str) -> Result<(), ServiceError> {
+    async fn check_administrator(&self, user_id: &UserId) -> Result<(), ServiceError> {
+        let user = self.user_repository.get_compact(user_id).await?;
+        if !user.administrator {
+            return Err(ServiceError::Unauthorized);
+        }
+        Ok(())
+    }
+    async fn check_warehouse(&self, warehouse_id: &WarehouseId) -> Result<(), ServiceError> {
         self.room_repository
-            .update_name(&room_id, &desc)
+            .get_warehouse(warehouse_id)
             .await
+            .map(|_| ())
             .map_err(|error: Error| match error {
-                Error::RoomNotFound => ServiceError::RoomNotFound,
+                Error::WarehouseNotFound => ServiceError::WarehouseNotFound,
                 _ => ServiceError::InternalServerError,
             })
     }
@@ -98,6 +169,39 @@ impl Service {
             .await
             .map_err(|_| ServiceError::InternalServerError)
     }
+    pub async fn get_archived_rooms(&self) -> Result<Vec<Room>, ServiceError> {
+        self.room_repository
+            .get_archived()
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    /// Get the categories a room is restricted to, none when it takes any item.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a `ServiceError::RoomNotFound` if the room does not exist.
+    pub async fn get_room_categories(&self, room_id: &RoomId) -> Result<Vec<Category>, ServiceError> {
+        self.room_repository.get_one(room_id).await.map_err(ServiceError::from)?;
+        self.room_repository
+            .get_categories(room_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    /// Restrict a room to items of `category_ids`, so that put-away only suggests its shelves for
+    /// those. An empty list lifts the restriction.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::RoomNotFound` if the room does not exist.
+    /// * `ServiceError::CategoryNotFound` if one of the categories does not exist.
+    pub async fn restrict_room(&self, room_id: &RoomId, category_ids: &[CategoryId]) -> Result<(), ServiceError> {
+        self.room_repository
+            .set_categories(room_id, category_ids)
+            .await
+            .map_err(ServiceError::from)
+    }
 }
 
 pub struct DbRoomRepository {
@@ -109,26 +213,43 @@ impl DbRoomRepository {
     pub fn new(database: Arc<Box<dyn Database>>) -> Self {
         Self { database }
     }
-    pub async fn add(&self, name: &str) -> Result<RoomId, Error> {
-        self.database.insert_room_and_get_id(name).await
+    pub async fn add(&self, name: &str, warehouse_id: &WarehouseId) -> Result<RoomId, Error> {
+        self.database.insert_room_and_get_id(name, *warehouse_id).await
     }
-    pub async fn add_with_desc(&self, name: &str, desc: &str) -> Result<RoomId, Error> {
-        self.database.insert_room_with_desc_and_get_id(name, desc).await
+    pub async fn add_with_desc(&self, name: &str, desc: &str, warehouse_id: &WarehouseId) -> Result<RoomId, Error> {
+        self.database
+            .insert_room_with_desc_and_get_id(name, desc, *warehouse_id)
+            .await
     }
-    pub async fn delete_one(&self, room_id: &RoomId) -> Result<(), Error> {
-        self.database.delete_room(*room_id).await
+    pub async fn archive(
+        &self,
+        room_id: &RoomId,
+        shelf_to: Option<ShelfId>,
+        bin_to: Option<BinId>,
+        meta: &MovementMeta,
+    ) -> Result<(), Error> {
+        self.database.archive_room(*room_id, shelf_to, bin_to, meta).await
     }
-    pub async fn delete_many(&self, ids: &Vec<RoomId>) -> Result<BatchDelResult, Error> {
-        self.database.delete_rooms(ids).await
+    pub async fn restore(&self, room_id: &RoomId) -> Result<(), Error> {
+        self.database.restore_room(*room_id).await
     }
-    pub async fn update(&self, room_id: &RoomId, name: &str, desc: &Option<String>) -> Result<(), Error> {
-        self.database.update_room(*room_id, name, desc).await
+    pub async fn get_archived(&self) -> Result<Vec<Room>, Error> {
+        self.database.get_archived_rooms().await
     }
-    pub async fn update_name(&self, room_id: &RoomId, name: &str) -> Result<(), Error> {
-        self.database.update_room_name(*room_id, name).await
+    pub async fn delete_many(&self, ids: &Vec<RoomId>, dry_run: bool) -> Result<BatchDelResult, Error> {
+        self.database.delete_rooms(ids, dry_run).await
     }
-    pub async fn update_desc(&self, room_id: &RoomId, desc: &str) -> Result<(), Error> {
-        self.database.update_room_desc(*room_id, desc).await
+    pub async fn update(
+        &self,
+        room_id: &RoomId,
+        name: &str,
+        desc: &Option<String>,
+        warehouse_id: &WarehouseId,
+    ) -> Result<(), Error> {
+        self.database.update_room(*room_id, name, desc, *warehouse_id).await
+    }
+    pub async fn get_warehouse(&self, warehouse_id: &WarehouseId) -> Result<Warehouse, Error> {
+        self.database.get_warehouse_from_id(*warehouse_id).await
     }
     pub async fn get_one(&self, room_id: &RoomId) -> Result<Room, Error> {
         self.database.get_room_from_id(*room_id).await
@@ -139,4 +260,10 @@ impl DbRoomRepository {
     pub async fn get_all(&self) -> Result<Vec<Room>, Error> {
         self.database.get_all_rooms().await
     }
+    pub async fn get_categories(&self, room_id: &RoomId) -> Result<Vec<Category>, Error> {
+        self.database.get_room_categories(*room_id).await
+    }
+    pub async fn set_categories(&self, room_id: &RoomId, category_ids: &[CategoryId]) -> Result<(), Error> {
+        self.database.set_room_categories(*room_id, category_ids).await
+    }
 }
@@ -3,8 +3,13 @@ use std::sync::Arc;
 use crate::common::{BatchDelResult, ListingSpec};
 use crate::databases::database::{Database, Error, Listing};
 use crate::errors::ServiceError;
-use crate::models::room::RoomId;
-use crate::models::shelf::{Shelf, ShelfId};
+use crate::models::bin::{Bin, BinAddress, BinId};
+use crate::models::movement::MovementMeta;
+use crate::models::room::{Room, RoomId};
+use crate::models::shelf::{Shelf, ShelfCapacity, ShelfId, ShelfLimits};
+
+/// Most layers of a shelf, and most positions on each of them.
+const MAX_BINS_PER_SIDE: i64 = 99;
 
 pub struct Service {
     shelf_repository: Arc<DbShelfRepository>,
@@ -15,31 +20,76 @@ impl Service {
     pub fn new(shelf_repository: Arc<DbShelfRepository>) -> Self {
         Self { shelf_repository }
     }
-    pub async fn add_shelf(&self, name: &str, layer: i64, room_id: RoomId) -> Result<ShelfId, ServiceError> {
+    /// Add a shelf divided in `layers` of `positions` bins each.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::ShelfLayoutNotValid` if there are not 1 to 99 layers of 1 to 99 positions.
+    /// * `ServiceError::RoomNotFound` if the room does not exist.
+    /// * `ServiceError::LocationArchived` if the room is archived.
+    pub async fn add_shelf(
+        &self,
+        name: &str,
+        layer: i64,
+        room_id: RoomId,
+        layers: i64,
+        positions: i64,
+    ) -> Result<ShelfId, ServiceError> {
+        Self::check_layout(layers, positions)?;
+        self.check_room_open(room_id).await?;
         self.shelf_repository
-            .add(name, layer, room_id)
+            .add(name, layer, room_id, layers, positions)
             .await
             .map_err(|_| ServiceError::InternalServerError)
     }
-    pub async fn remove_shelf(&self, shelf_id: &ShelfId) -> Result<(), ServiceError> {
+    /// Archive a shelf, which hides it from listings and closes it to deposits. The stock left on it
+    /// moves to `shelf_to`, into `bin_to` when given.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::ShelfNotFound` if the shelf does not exist or is archived already.
+    /// * `ServiceError::LocationNotEmpty` if stock remains and there is no `shelf_to`.
+    /// * `ServiceError::LocationArchived` if `shelf_to` is archived, or the shelf itself.
+    pub async fn remove_shelf(
+        &self,
+        shelf_id: &ShelfId,
+        shelf_to: Option<ShelfId>,
+        bin_to: Option<BinId>,
+        meta: &MovementMeta,
+    ) -> Result<(), ServiceError> {
         self.shelf_repository
-            .delete_one(&shelf_id)
+            .archive(shelf_id, shelf_to, bin_to, meta)
             .await
-            .map_err(|error: Error| match error {
-                Error::ShelfNotFound => ServiceError::ShelfNotFound,
-                _ => ServiceError::InternalServerError,
-            })
+            .map_err(ServiceError::from)
+    }
+    /// Restore an archived shelf.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::ShelfNotFound` if the shelf does not exist or is not archived.
+    /// * `ServiceError::LocationArchived` if its room is archived.
+    pub async fn restore_shelf(&self, shelf_id: &ShelfId) -> Result<(), ServiceError> {
+        self.shelf_repository.restore(shelf_id).await.map_err(ServiceError::from)
     }
-    pub async fn remove_shelves(&self, ids: &Vec<ShelfId>) -> Result<BatchDelResult, ServiceError> {
+    /// Archive the empty shelves among `ids`, reporting why each of the others failed. With `dry_run` nothing is changed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a `ServiceError::InternalServerError` if the database fails.
+    pub async fn remove_shelves(&self, ids: &Vec<ShelfId>, dry_run: bool) -> Result<BatchDelResult, ServiceError> {
         self.shelf_repository
-            .delete_many(ids)
+            .delete_many(ids, dry_run)
             .await
-            .map_err(|error: Error| match error {
-                Error::ShelfNotFound => ServiceError::ShelfNotFound,
-                _ => ServiceError::InternalServerError,
-            })
+            .map_err(ServiceError::from)
     }
     pub async fn update_shelf(&self, shelf_id: &ShelfId, name: &str, layer: i64, room_id: RoomId) -> Result<(), ServiceError> {
+        self.check_room_open(room_id).await?;
         self.shelf_repository
             .update(&shelf_id, &name, layer, room_id)
             .await
@@ -67,6 +117,7 @@ impl Service {
             })
     }
     pub async fn update_shelf_room(&self, shelf_id: &ShelfId, room_id: RoomId) -> Result<(), ServiceError> {
+        self.check_room_open(room_id).await?;
         self.shelf_repository
             .update_room(&shelf_id, room_id)
             .await
@@ -76,6 +127,127 @@ impl Service {
                 _ => ServiceError::InternalServerError,
             })
     }
+    /// Divide a shelf anew, keeping the number of layers or positions not given. Bins added are
+    /// empty, bins removed must be.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::ShelfLayoutNotValid` if there are not 1 to 99 layers of 1 to 99 positions.
+    /// * `ServiceError::ShelfNotFound` if the shelf does not exist.
+    /// * `ServiceError::BinNotEmpty` if a bin to remove holds stock.
+    pub async fn update_shelf_layout(
+        &self,
+        shelf_id: &ShelfId,
+        layers: Option<i64>,
+        positions: Option<i64>,
+    ) -> Result<(), ServiceError> {
+        let shelf = self.get_shelf(shelf_id).await?;
+        let layers = layers.unwrap_or(shelf.layers);
+        let positions = positions.unwrap_or(shelf.positions);
+        Self::check_layout(layers, positions)?;
+        self.shelf_repository
+            .update_layout(shelf_id, layers, positions)
+            .await
+            .map_err(|error: Error| match error {
+                Error::ShelfNotFound => ServiceError::ShelfNotFound,
+                Error::BinNotEmpty => ServiceError::BinNotEmpty,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    fn check_layout(layers: i64, positions: i64) -> Result<(), ServiceError> {
+        if (1..=MAX_BINS_PER_SIDE).contains(&layers) && (1..=MAX_BINS_PER_SIDE).contains(&positions) {
+            Ok(())
+        } else {
+            Err(ServiceError::ShelfLayoutNotValid)
+        }
+    }
+    /// Refuse to put a shelf in a room that does not exist or is archived.
+    async fn check_room_open(&self, room_id: RoomId) -> Result<(), ServiceError> {
+        let room = self
+            .shelf_repository
+            .get_room(room_id)
+            .await
+            .map_err(|_| ServiceError::RoomNotFound)?;
+        if room.archived_at.is_some() {
+            return Err(ServiceError::LocationArchived);
+        }
+        Ok(())
+    }
+    /// Get the bins of a shelf with their addresses.
+    pub async fn get_bins(&self, shelf_id: &ShelfId) -> Result<Vec<Bin>, ServiceError> {
+        self.get_shelf(shelf_id).await?;
+        let bins = self
+            .shelf_repository
+            .get_bins(shelf_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)?;
+        Ok(bins.into_iter().map(Bin::addressed).collect())
+    }
+    /// Find the bin an address such as `R01-S03-L2-P04` points at. An address without the room
+    /// finds the bin by its shelf alone.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::BinAddressNotValid` if the address does not read as one.
+    /// * `ServiceError::BinNotFound` if there is no such bin, or the shelf is not in the room the
+    ///   address names.
+    pub async fn resolve_bin(&self, address: &str) -> Result<Bin, ServiceError> {
+        let address: BinAddress = address.parse().map_err(|_| ServiceError::BinAddressNotValid)?;
+        let bin = self
+            .shelf_repository
+            .get_bin_at(&address.shelf_id, address.layer, address.position)
+            .await
+            .map_err(|error: Error| match error {
+                Error::BinNotFound => ServiceError::BinNotFound,
+                _ => ServiceError::InternalServerError,
+            })?;
+        if !address.is_in_room_of(&bin) {
+            return Err(ServiceError::BinNotFound);
+        }
+        Ok(bin.addressed())
+    }
+    /// Set what a shelf may hold at most, `None` lifting a limit.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::PayloadNotValid` if a limit is negative.
+    /// * `ServiceError::ShelfNotFound` if the shelf does not exist.
+    pub async fn update_shelf_limits(&self, shelf_id: &ShelfId, limits: &ShelfLimits) -> Result<(), ServiceError> {
+        let maxima = [limits.max_units, limits.max_weight_g, limits.max_volume_mm3];
+        if maxima.into_iter().flatten().any(|x| x < 0) {
+            return Err(ServiceError::PayloadNotValid);
+        }
+        self.shelf_repository
+            .update_limits(shelf_id, limits)
+            .await
+            .map_err(|error: Error| match error {
+                Error::ShelfNotFound => ServiceError::ShelfNotFound,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    /// Get how much of its capacity a shelf uses and what is left of it.
+    pub async fn get_shelf_capacity(&self, shelf_id: &ShelfId) -> Result<ShelfCapacity, ServiceError> {
+        self.shelf_repository
+            .get_capacity(shelf_id)
+            .await
+            .map_err(|error: Error| match error {
+                Error::ShelfNotFound => ServiceError::ShelfNotFound,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    /// Get how much of their capacity the shelves use, those of `room_id` when given.
+    pub async fn get_shelf_capacities(&self, room_id: Option<RoomId>) -> Result<Vec<ShelfCapacity>, ServiceError> {
+        self.shelf_repository
+            .get_capacities(room_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
     pub async fn get_shelf(&self, shelf_id: &ShelfId) -> Result<Shelf, ServiceError> {
         self.shelf_repository
             .get_one(shelf_id)
@@ -88,6 +260,12 @@ impl Service {
             .await
             .map_err(|_| ServiceError::InternalServerError)
     }
+    pub async fn get_archived_shelves(&self, room_id: Option<RoomId>) -> Result<Vec<Shelf>, ServiceError> {
+        self.shelf_repository
+            .get_archived(room_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
     pub async fn get_all_shelves(&self, room_id: Option<RoomId>) -> Result<Vec<Shelf>, ServiceError> {
         self.shelf_repository
             .get_all(room_id)
@@ -105,14 +283,31 @@ impl DbShelfRepository {
     pub fn new(database: Arc<Box<dyn Database>>) -> Self {
         Self { database }
     }
-    pub async fn add(&self, name: &str, layer: i64, room_id: RoomId) -> Result<ShelfId, Error> {
-        self.database.insert_shelf_and_get_id(name, layer, room_id).await
+    pub async fn add(&self, name: &str, layer: i64, room_id: RoomId, layers: i64, positions: i64) -> Result<ShelfId, Error> {
+        self.database
+            .insert_shelf_and_get_id(name, layer, room_id, layers, positions)
+            .await
+    }
+    pub async fn archive(
+        &self,
+        shelf_id: &ShelfId,
+        shelf_to: Option<ShelfId>,
+        bin_to: Option<BinId>,
+        meta: &MovementMeta,
+    ) -> Result<(), Error> {
+        self.database.archive_shelf(*shelf_id, shelf_to, bin_to, meta).await
+    }
+    pub async fn restore(&self, shelf_id: &ShelfId) -> Result<(), Error> {
+        self.database.restore_shelf(*shelf_id).await
     }
-    pub async fn delete_one(&self, shelf_id: &ShelfId) -> Result<(), Error> {
-        self.database.delete_shelf(*shelf_id).await
+    pub async fn get_archived(&self, room_id: Option<RoomId>) -> Result<Vec<Shelf>, Error> {
+        self.database.get_archived_shelves(room_id).await
     }
-    pub async fn delete_many(&self, ids: &Vec<ShelfId>) -> Result<BatchDelResult, Error> {
-        self.database.delete_shelves(ids).await
+    pub async fn get_room(&self, room_id: RoomId) -> Result<Room, Error> {
+        self.database.get_room_from_id(room_id).await
+    }
+    pub async fn delete_many(&self, ids: &Vec<ShelfId>, dry_run: bool) -> Result<BatchDelResult, Error> {
+        self.database.delete_shelves(ids, dry_run).await
     }
     pub async fn update(&self, shelf_id: &ShelfId, name: &str, layer: i64, room_id: RoomId) -> Result<(), Error> {
         self.database.update_shelf(*shelf_id, name, layer, room_id).await
@@ -126,6 +321,24 @@ impl DbShelfRepository {
     pub async fn update_room(&self, shelf_id: &ShelfId, room_id: RoomId) -> Result<(), Error> {
         self.database.update_shelf_room(*shelf_id, room_id).await
     }
+    pub async fn update_layout(&self, shelf_id: &ShelfId, layers: i64, positions: i64) -> Result<(), Error> {
+        self.database.update_shelf_layout(*shelf_id, layers, positions).await
+    }
+    pub async fn get_bins(&self, shelf_id: &ShelfId) -> Result<Vec<Bin>, Error> {
+        self.database.get_bins_of_shelf(*shelf_id).await
+    }
+    pub async fn get_bin_at(&self, shelf_id: &ShelfId, layer: i64, position: i64) -> Result<Bin, Error> {
+        self.database.get_bin_at(*shelf_id, layer, position).await
+    }
+    pub async fn update_limits(&self, shelf_id: &ShelfId, limits: &ShelfLimits) -> Result<(), Error> {
+        self.database.update_shelf_limits(*shelf_id, limits).await
+    }
+    pub async fn get_capacity(&self, shelf_id: &ShelfId) -> Result<ShelfCapacity, Error> {
+        self.database.get_shelf_capacity(*shelf_id).await
+    }
+    pub async fn get_capacities(&self, room_id: Option<RoomId>) -> Result<Vec<ShelfCapacity>, Error> {
+        self.database.get_shelf_capacities(room_id).await
+    }
     pub async fn get_one(&self, shelf_id: &ShelfId) -> Result<Shelf, Error> {
         self.database.get_shelf_from_id(*shelf_id).await
     }
@@ -1,55 +1,353 @@
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
 
-use crate::common::ListingSpec;
+use time::{Date, Duration, OffsetDateTime};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::common::{
+    DeadStockCriteria, ExpiryCriteria, ListingSpec, MovementCriteria, PutawayCriteria, ReservationCriteria, ValuationCriteria,
+};
 use crate::databases::database::{Database, Error, Listing};
 use crate::errors::ServiceError;
-use crate::models::item::{ItemId, ItemInRoom, ItemOnShelf, ItemXShelf};
+use crate::models::aging::{AgingLine, DeadStock, AGING_BUCKETS};
+use crate::models::batch::{BatchLine, BatchMode, StockLine, StockOp};
+use crate::models::bin::{BinId, ItemInBin};
+use crate::models::classification::{classify, Consumption, ItemClass};
+use crate::models::item::{
+    Item, ItemDimensions, ItemId, ItemInRoom, ItemInWarehouse, ItemOnShelf, ItemUnit, ItemXShelf, Lot, LotInRoom, StockPick,
+    UnitQuantities,
+};
+use crate::models::movement::{MovementMeta, SnapshotId, StockMovement};
+use crate::models::policy::{FlaggedStock, StockPolicy};
+use crate::models::putaway::PutawaySuggestion;
+use crate::models::reservation::{NewReservation, Reservation, ReservationId};
 use crate::models::room::RoomId;
-use crate::models::shelf::ShelfId;
+use crate::models::shelf::{ShelfCapacity, ShelfId};
+use crate::models::status::StockStatus;
+use crate::models::unit::{StockUnit, UnitHistory, UnitId};
+use crate::models::user::UserId;
+use crate::models::valuation::{Cost, UnitCost, ValuationGroup, ValuationLine};
+use crate::models::warehouse::WarehouseId;
+use crate::services::user::DbUserRepository;
+
+/// What came of a batch: whether it was kept, and the result of every line in order.
+#[derive(Debug)]
+pub struct BatchOutcome {
+    pub committed: bool,
+    pub lines: Vec<Result<Vec<ItemXShelf>, ServiceError>>,
+}
 
 pub struct Service {
     stock_repository: Arc<DbStockRepository>,
+    user_repository: Arc<DbUserRepository>,
+    /// Items whose stock changed, for the reorder checker.
+    stock_changes: UnboundedSender<ItemId>,
+    /// The shelf layer put-away suggestions look at first.
+    putaway_layer: i64,
+    /// How many days of withdrawals items are classified by, and in how many periods.
+    classification_window: u32,
+    classification_periods: u32,
 }
 
 impl Service {
     #[must_use]
-    pub fn new(stock_repository: Arc<DbStockRepository>) -> Self {
-        Self { stock_repository }
+    pub fn new(
+        stock_repository: Arc<DbStockRepository>,
+        user_repository: Arc<DbUserRepository>,
+        stock_changes: UnboundedSender<ItemId>,
+        putaway_layer: i64,
+        classification_window: u32,
+        classification_periods: u32,
+    ) -> Self {
+        Self {
+            stock_repository,
+            user_repository,
+            stock_changes,
+            putaway_layer,
+            classification_window,
+            classification_periods,
+        }
     }
-    pub async fn withdraw_item(&self, item_id: &ItemId, count: i64, shelf_id: ShelfId) -> Result<(), ServiceError> {
-        self.stock_repository
-            .withdraw(item_id, count, shelf_id)
+    /// Have the reorder points of the item checked. Nothing is checked once the checker stopped.
+    fn stock_changed(&self, item_id: ItemId) {
+        let _ = self.stock_changes.send(item_id);
+    }
+    /// Turn a count in `unit` into a count in the item's base unit. Counts without a unit are
+    /// already in the base unit.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::UnitNotFound` if the item has no such unit.
+    /// * `ServiceError::PayloadNotValid` if the count overflows once converted.
+    async fn to_base(&self, item_id: ItemId, count: i64, unit: Option<&str>) -> Result<i64, ServiceError> {
+        let Some(unit) = unit else {
+            return Ok(count);
+        };
+        let factor = self
+            .stock_repository
+            .get_unit_factor(item_id, unit)
             .await
-            .map_err(|error: Error| match error {
-                Error::ItemNotFound => ServiceError::ItemNotFound,
-                _ => ServiceError::InternalServerError,
-            })
+            .map_err(ServiceError::from)?;
+        count.checked_mul(factor).ok_or(ServiceError::PayloadNotValid)
     }
-    pub async fn deposit_item(&self, item_id: &ItemId, count: i64, shelf_id: ShelfId) -> Result<(), ServiceError> {
-        self.stock_repository
-            .deposit(item_id, count, shelf_id)
+    /// What `count` items cost at `unit_cost` each.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::CostNotValid` if the unit cost is negative, the currency not a three-letter code or the
+    ///   total cost overflows.
+    fn total_cost(unit_cost: Option<&UnitCost>, count: i64) -> Result<Option<Cost>, ServiceError> {
+        match unit_cost {
+            Some(x) if x.unit_cost < 0 || x.currency.len() != 3 || !x.currency.bytes().all(|b| b.is_ascii_uppercase()) => {
+                Err(ServiceError::CostNotValid)
+            }
+            Some(x) => Ok(Some(Cost {
+                value: x.unit_cost.checked_mul(count).ok_or(ServiceError::CostNotValid)?,
+                currency: x.currency.clone(),
+            })),
+            None => Ok(None),
+        }
+    }
+    /// Count a line of a batch in the base unit of its item, at the total cost of a deposit.
+    async fn to_batch_line(&self, x: StockLine) -> Result<BatchLine, ServiceError> {
+        let mut op = x.op;
+        let cost = match &op {
+            StockOp::Deposit(line) => Self::total_cost(x.unit_cost.as_ref(), line.count)?,
+            _ => None,
+        };
+        let line = op.line_mut();
+        line.count = self.to_base(line.item_id, line.count, x.unit.as_deref()).await?;
+        Ok(BatchLine {
+            op,
+            cost,
+            reason: x.reason,
+            expected_count: x.expected_count,
+        })
+    }
+    /// The factors of every item that has `unit`, by item.
+    async fn unit_factors(&self, unit: Option<&str>) -> Result<HashMap<ItemId, ItemUnit>, ServiceError> {
+        let Some(unit) = unit else {
+            return Ok(HashMap::new());
+        };
+        let units = self
+            .stock_repository
+            .get_unit_factors(unit)
             .await
-            .map_err(|error: Error| match error {
-                Error::ItemNotFound => ServiceError::ItemNotFound,
-                _ => ServiceError::InternalServerError,
-            })
+            .map_err(|_| ServiceError::InternalServerError)?;
+        Ok(units.into_iter().map(|x| (x.item_id, x)).collect())
     }
+    pub async fn withdraw_item(
+        &self,
+        item_id: &ItemId,
+        count: i64,
+        unit: Option<&str>,
+        shelf_id: ShelfId,
+        pick: &StockPick,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, ServiceError> {
+        let count = self.to_base(*item_id, count, unit).await?;
+        let stocks = self
+            .stock_repository
+            .withdraw(item_id, count, shelf_id, pick, meta)
+            .await
+            .map_err(ServiceError::from)?;
+        self.stock_changed(*item_id);
+        Ok(stocks)
+    }
+    /// Put items onto a shelf, at a cost when given.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::CostNotValid` if the unit cost is negative, the currency not a three-letter code or the
+    ///   total cost overflows.
+    /// * `ServiceError::CurrencyMismatch` if the stock of the item is costed in another currency.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn deposit_item(
+        &self,
+        item_id: &ItemId,
+        count: i64,
+        unit: Option<&str>,
+        shelf_id: ShelfId,
+        bin_id: Option<BinId>,
+        lot: &Lot,
+        status: StockStatus,
+        serials: &[String],
+        unit_cost: Option<&UnitCost>,
+        meta: &MovementMeta,
+    ) -> Result<ItemXShelf, ServiceError> {
+        let cost = Self::total_cost(unit_cost, count)?;
+        let count = self.to_base(*item_id, count, unit).await?;
+        let stock = self
+            .stock_repository
+            .deposit(item_id, count, shelf_id, bin_id, lot, status, serials, cost.as_ref(), meta)
+            .await
+            .map_err(ServiceError::from)?;
+        self.stock_changed(*item_id);
+        Ok(stock)
+    }
+    #[allow(clippy::too_many_arguments)]
     pub async fn transfer_item(
         &self,
         item_id: &ItemId,
         count: i64,
+        unit: Option<&str>,
         shelf_from: ShelfId,
         shelf_to: ShelfId,
-    ) -> Result<(), ServiceError> {
-        self.stock_repository
-            .transfer(item_id, count, shelf_from, shelf_to)
+        bin_to: Option<BinId>,
+        pick: &StockPick,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, ServiceError> {
+        let count = self.to_base(*item_id, count, unit).await?;
+        let stocks = self
+            .stock_repository
+            .transfer(item_id, count, shelf_from, shelf_to, bin_to, pick, meta)
+            .await
+            .map_err(ServiceError::from)?;
+        self.stock_changed(*item_id);
+        Ok(stocks)
+    }
+    /// Put stock on a shelf in another status, taking it from the status `pick.status` names.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::StatusUnchanged` if the stock is already in that status.
+    /// * `ServiceError::InsufficientItem` if the shelf holds less than that in `pick.status`.
+    /// * `ServiceError::StockReserved` if the stock left available is held by others.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn change_status(
+        &self,
+        item_id: &ItemId,
+        count: i64,
+        unit: Option<&str>,
+        shelf_id: ShelfId,
+        pick: &StockPick,
+        status: StockStatus,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, ServiceError> {
+        if pick.status == status {
+            return Err(ServiceError::StatusUnchanged);
+        }
+        let count = self.to_base(*item_id, count, unit).await?;
+        let stocks = self
+            .stock_repository
+            .change_status(item_id, count, shelf_id, pick, status, meta)
             .await
-            .map_err(|error: Error| match error {
-                Error::ItemNotFound => ServiceError::ItemNotFound,
-                _ => ServiceError::InternalServerError,
+            .map_err(ServiceError::from)?;
+        self.stock_changed(*item_id);
+        Ok(stocks)
+    }
+    /// Run a list of deposit, withdraw and transfer lines in one transaction. Lines that fail carry
+    /// their own error. When an all-or-nothing batch fails, the lines that went through carry
+    /// `ServiceError::BatchAborted` as nothing of them was kept.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::PayloadNotValid` if the batch has no lines.
+    pub async fn apply_batch(
+        &self,
+        lines: Vec<StockLine>,
+        mode: BatchMode,
+        meta: &MovementMeta,
+    ) -> Result<BatchOutcome, ServiceError> {
+        if lines.is_empty() {
+            return Err(ServiceError::PayloadNotValid);
+        }
+        let atomic = mode == BatchMode::AllOrNothing;
+        // lines that cannot even be counted never reach the database
+        let mut batch = Vec::with_capacity(lines.len());
+        let mut rejected = Vec::with_capacity(lines.len());
+        for x in lines {
+            match self.to_batch_line(x).await {
+                Ok(line) => {
+                    batch.push(line);
+                    rejected.push(None);
+                }
+                Err(e) => rejected.push(Some(e)),
+            }
+        }
+        if atomic && rejected.iter().any(Option::is_some) {
+            let lines = rejected
+                .into_iter()
+                .map(|e| Err(e.unwrap_or(ServiceError::BatchAborted)))
+                .collect();
+            return Ok(BatchOutcome { committed: false, lines });
+        }
+        let results = self
+            .stock_repository
+            .apply_batch(&batch, mode, meta)
+            .await
+            .map_err(ServiceError::from)?;
+        let committed = !atomic || results.iter().all(Result::is_ok);
+        if committed {
+            let mut item_ids: Vec<ItemId> = batch
+                .iter()
+                .zip(&results)
+                .filter(|(_, result)| result.is_ok())
+                .map(|(x, _)| x.op.line().item_id)
+                .collect();
+            item_ids.sort_unstable();
+            item_ids.dedup();
+            for item_id in item_ids {
+                self.stock_changed(item_id);
+            }
+        }
+        let mut results = results.into_iter();
+        let lines = rejected
+            .into_iter()
+            .map(|e| match (e, results.next()) {
+                (Some(e), _) => Err(e),
+                (None, Some(Ok(stocks))) if committed => Ok(stocks),
+                (None, Some(Ok(_))) => Err(ServiceError::BatchAborted),
+                (None, Some(Err(e))) => Err(ServiceError::from(e)),
+                (None, None) => Err(ServiceError::InternalServerError),
             })
+            .collect();
+        Ok(BatchOutcome { committed, lines })
     }
-    pub async fn convert_item(&self, from: Vec<ItemXShelf>, into: Vec<ItemXShelf>) -> Result<(), ServiceError> {
+    /// Undo a past stock operation, a convert or batch included, recording who undid it and why.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::PayloadNotValid` if no reason is given.
+    /// * `ServiceError::OperationNotFound` if there is no such operation.
+    /// * `ServiceError::OperationReversed` if it was undone already.
+    /// * `ServiceError::StockConsumed` if stock it brought onto a shelf is no longer there.
+    pub async fn reverse_operation(&self, correlation_id: &str, meta: &MovementMeta) -> Result<Vec<ItemXShelf>, ServiceError> {
+        match meta.reason.as_deref() {
+            Some(reason) if !reason.trim().is_empty() => {}
+            _ => return Err(ServiceError::PayloadNotValid),
+        }
+        let stocks = self
+            .stock_repository
+            .reverse(correlation_id, meta)
+            .await
+            .map_err(ServiceError::from)?;
+        let mut item_ids: Vec<ItemId> = stocks.iter().map(|x| x.item_id).collect();
+        item_ids.sort_unstable();
+        item_ids.dedup();
+        for item_id in item_ids {
+            self.stock_changed(item_id);
+        }
+        Ok(stocks)
+    }
+    pub async fn convert_item(
+        &self,
+        from: Vec<ItemXShelf>,
+        into: Vec<ItemXShelf>,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, ServiceError> {
         let len_from = from.len();
         if len_from == 0 {
             return Err(ServiceError::SourceMustBePositive);
@@ -58,26 +356,470 @@ impl Service {
         if len_into == 0 {
             return Err(ServiceError::TargetMustBePositive);
         }
-        self.stock_repository
-            .convert(from, into)
+        let mut item_ids: Vec<ItemId> = from.iter().chain(into.iter()).map(|x| x.item_id).collect();
+        item_ids.sort_unstable();
+        item_ids.dedup();
+        let stocks = self
+            .stock_repository
+            .convert(from, into, meta)
+            .await
+            .map_err(ServiceError::from)?;
+        for item_id in item_ids {
+            self.stock_changed(item_id);
+        }
+        Ok(stocks)
+    }
+    /// The shelves among `shelf_ids` holding more than their capacity allows, to warn about once stock
+    /// was put on them. Shelves whose capacity cannot be read are left out.
+    pub async fn over_capacity(&self, shelf_ids: impl IntoIterator<Item = ShelfId>) -> Vec<ShelfCapacity> {
+        let mut shelf_ids: Vec<ShelfId> = shelf_ids.into_iter().collect();
+        shelf_ids.sort_unstable();
+        shelf_ids.dedup();
+        let mut over = Vec::new();
+        for shelf_id in shelf_ids {
+            if let Ok(capacity) = self.stock_repository.get_capacity(shelf_id).await {
+                if capacity.exceeded() {
+                    over.push(capacity);
+                }
+            }
+        }
+        over
+    }
+    /// Shelves to put `criteria.count` incoming items away on, best first: those already holding the
+    /// item, then those taking all of the count, those nearest the put-away layer and those with the
+    /// most room. Shelves without room left for the item are left out.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::CountMustBePositive` if the count is not positive.
+    /// * `ServiceError::ItemNotFound` if the item does not exist.
+    /// * `ServiceError::UnitNotFound` if the item has no such unit.
+    pub async fn suggest_putaway(&self, criteria: &PutawayCriteria) -> Result<Vec<PutawaySuggestion>, ServiceError> {
+        if criteria.count <= 0 {
+            return Err(ServiceError::CountMustBePositive);
+        }
+        let item = self
+            .stock_repository
+            .get_item(criteria.item_id)
+            .await
+            .map_err(ServiceError::from)?;
+        let count = self
+            .to_base(criteria.item_id, criteria.count, criteria.unit.as_deref())
+            .await?;
+        let dimensions = ItemDimensions::from(&item);
+        let mut suggestions = self
+            .stock_repository
+            .get_putaway_candidates(criteria.item_id, criteria.room_id)
             .await
-            .map_err(|error: Error| match error {
-                Error::ItemNotFound => ServiceError::ItemNotFound,
-                _ => ServiceError::InternalServerError,
+            .map_err(ServiceError::from)?;
+        for x in &mut suggestions {
+            x.fits = x.shelf.room_for(&dimensions).map_or(count, |room| room.min(count));
+        }
+        suggestions.retain(|x| x.fits > 0);
+        suggestions.sort_by_key(|x| {
+            (
+                x.on_shelf == 0,
+                x.fits < count,
+                (x.layer - self.putaway_layer).abs(),
+                -x.fits,
+                x.shelf.shelf_id,
+            )
+        });
+        Ok(suggestions)
+    }
+    /// Items on every shelf, of `warehouse_id` only when given, with their quantities also in `unit`
+    /// for the items that have it. With `as_of` the stock is the one that stood on the shelves at that time.
+    pub async fn get_items_on_shelves(
+        &self,
+        spec: &ListingSpec,
+        warehouse_id: Option<WarehouseId>,
+        unit: Option<&str>,
+        as_of: Option<OffsetDateTime>,
+    ) -> Result<Listing<ItemOnShelf>, ServiceError> {
+        let mut listing = match as_of {
+            Some(as_of) => {
+                self.stock_repository
+                    .get_many_on_shelves_as_of(spec, None, warehouse_id, as_of)
+                    .await
+            }
+            None => self.stock_repository.get_many_on_shelves(spec, warehouse_id).await,
+        }
+        .map_err(|_| ServiceError::InternalServerError)?;
+        self.fill_on_shelf_units(&mut listing.data, unit).await?;
+        Ok(listing)
+    }
+    pub async fn get_items_on_shelf(
+        &self,
+        spec: &ListingSpec,
+        shelf_id: ShelfId,
+        unit: Option<&str>,
+        as_of: Option<OffsetDateTime>,
+    ) -> Result<Listing<ItemOnShelf>, ServiceError> {
+        let mut listing = match as_of {
+            Some(as_of) => {
+                self.stock_repository
+                    .get_many_on_shelves_as_of(spec, Some(shelf_id), None, as_of)
+                    .await
+            }
+            None => self.stock_repository.get_many_on_shelf(spec, shelf_id).await,
+        }
+        .map_err(|_| ServiceError::InternalServerError)?;
+        self.fill_on_shelf_units(&mut listing.data, unit).await?;
+        Ok(listing)
+    }
+    /// Items on a shelf per bin, along with the address of the bin.
+    pub async fn get_items_in_bins(&self, shelf_id: ShelfId) -> Result<Vec<ItemInBin>, ServiceError> {
+        let items = self
+            .stock_repository
+            .get_all_in_bins(shelf_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)?;
+        Ok(items
+            .into_iter()
+            .map(|x| ItemInBin {
+                bin: x.bin.addressed(),
+                ..x
             })
+            .collect())
+    }
+    /// Items in every room, of `warehouse_id` only when given, with their quantities also in `unit`
+    /// for the items that have it. With `as_of` the stock is the one that stood in the rooms at that time.
+    pub async fn get_items_in_rooms(
+        &self,
+        spec: &ListingSpec,
+        warehouse_id: Option<WarehouseId>,
+        unit: Option<&str>,
+        as_of: Option<OffsetDateTime>,
+    ) -> Result<Listing<ItemInRoom>, ServiceError> {
+        let mut listing = match as_of {
+            Some(as_of) => {
+                self.stock_repository
+                    .get_many_in_rooms_as_of(spec, None, warehouse_id, as_of)
+                    .await
+            }
+            None => self.stock_repository.get_many_in_rooms(spec, warehouse_id).await,
+        }
+        .map_err(|_| ServiceError::InternalServerError)?;
+        self.fill_in_room_units(&mut listing.data, unit).await?;
+        Ok(listing)
+    }
+    pub async fn get_items_in_room(
+        &self,
+        spec: &ListingSpec,
+        room_id: RoomId,
+        unit: Option<&str>,
+        as_of: Option<OffsetDateTime>,
+    ) -> Result<Listing<ItemInRoom>, ServiceError> {
+        let mut listing = match as_of {
+            Some(as_of) => {
+                self.stock_repository
+                    .get_many_in_rooms_as_of(spec, Some(room_id), None, as_of)
+                    .await
+            }
+            None => self.stock_repository.get_many_in_room(spec, room_id).await,
+        }
+        .map_err(|_| ServiceError::InternalServerError)?;
+        self.fill_in_room_units(&mut listing.data, unit).await?;
+        Ok(listing)
+    }
+    /// Items per warehouse, in `warehouse_id` only when given, with their quantities also in `unit` for
+    /// the items that have it. With `as_of` the stock is the one that stood in the warehouses at that time.
+    pub async fn get_items_in_warehouses(
+        &self,
+        spec: &ListingSpec,
+        warehouse_id: Option<WarehouseId>,
+        unit: Option<&str>,
+        as_of: Option<OffsetDateTime>,
+    ) -> Result<Listing<ItemInWarehouse>, ServiceError> {
+        let mut listing = match as_of {
+            Some(as_of) => {
+                self.stock_repository
+                    .get_many_in_warehouses_as_of(spec, warehouse_id, as_of)
+                    .await
+            }
+            None => self.stock_repository.get_many_in_warehouses(spec, warehouse_id).await,
+        }
+        .map_err(|_| ServiceError::InternalServerError)?;
+        self.fill_in_warehouse_units(&mut listing.data, unit).await?;
+        Ok(listing)
+    }
+    /// Record the current stock, for the stock of later times to be rebuilt from it.
+    pub async fn take_snapshot(&self) -> Result<SnapshotId, ServiceError> {
+        self.stock_repository
+            .snapshot()
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    /// Value the costed stock per room, warehouse or category as it stood at a time, now when not given.
+    pub async fn get_valuation(&self, criteria: &ValuationCriteria) -> Result<Vec<ValuationLine>, ServiceError> {
+        self.stock_repository
+            .get_valuation(
+                criteria.by,
+                criteria.warehouse_id,
+                criteria.as_of.unwrap_or_else(OffsetDateTime::now_utc),
+            )
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    async fn fill_on_shelf_units(&self, items: &mut [ItemOnShelf], unit: Option<&str>) -> Result<(), ServiceError> {
+        let units = self.unit_factors(unit).await?;
+        for item in items {
+            item.in_unit = units
+                .get(&item.item_id)
+                .map(|x| UnitQuantities::new(x, item.count, item.reserved, item.available));
+        }
+        Ok(())
+    }
+    async fn fill_in_room_units(&self, items: &mut [ItemInRoom], unit: Option<&str>) -> Result<(), ServiceError> {
+        let units = self.unit_factors(unit).await?;
+        for item in items {
+            item.in_unit = units
+                .get(&item.item_id)
+                .map(|x| UnitQuantities::new(x, item.count, item.reserved, item.available));
+        }
+        Ok(())
+    }
+    async fn fill_in_warehouse_units(&self, items: &mut [ItemInWarehouse], unit: Option<&str>) -> Result<(), ServiceError> {
+        let units = self.unit_factors(unit).await?;
+        for item in items {
+            item.in_unit = units
+                .get(&item.item_id)
+                .map(|x| UnitQuantities::new(x, item.count, item.reserved, item.available));
+        }
+        Ok(())
+    }
+    pub async fn get_lots_expiring(
+        &self,
+        spec: &ListingSpec,
+        criteria: &ExpiryCriteria,
+    ) -> Result<Listing<LotInRoom>, ServiceError> {
+        let until = OffsetDateTime::now_utc()
+            .date()
+            .saturating_add(Duration::days(i64::from(criteria.days)));
+        self.stock_repository
+            .get_lots_expiring(spec, until, criteria.room_id, criteria.warehouse_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    /// The stock of every item per room, in `room_id` and `warehouse_id` only when given, bucketed by how
+    /// long it has been sitting there.
+    pub async fn get_stock_aging(
+        &self,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Vec<AgingLine>, ServiceError> {
+        let now = OffsetDateTime::now_utc();
+        // stock received the last day of a bucket is still in it
+        let cutoffs = AGING_BUCKETS.map(|days| now.saturating_sub(Duration::days(days + 1)));
+        self.stock_repository
+            .get_aging(cutoffs, room_id, warehouse_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    /// The items in stock nothing was withdrawn of for `criteria.days` days, the longest idle first.
+    pub async fn get_dead_stock(&self, criteria: &DeadStockCriteria) -> Result<Vec<DeadStock>, ServiceError> {
+        let since = OffsetDateTime::now_utc().saturating_sub(Duration::days(i64::from(criteria.days)));
+        self.stock_repository
+            .get_dead_stock(since, criteria.room_id, criteria.warehouse_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
     }
-    pub async fn get_items_on_shelf(&self, spec: &ListingSpec, shelf_id: ShelfId) -> Result<Listing<ItemOnShelf>, ServiceError> {
+    pub async fn get_unit_history(&self, item_id: ItemId, serial: &str) -> Result<UnitHistory, ServiceError> {
+        let unit = self
+            .stock_repository
+            .get_unit(item_id, serial)
+            .await
+            .map_err(ServiceError::from)?;
+        let movements = self
+            .stock_repository
+            .get_unit_movements(unit.unit_id)
+            .await
+            .map_err(ServiceError::from)?;
+        Ok(UnitHistory { unit, movements })
+    }
+    pub async fn get_movements(
+        &self,
+        spec: &ListingSpec,
+        criteria: &MovementCriteria,
+    ) -> Result<Listing<StockMovement>, ServiceError> {
         self.stock_repository
-            .get_many_on_shelf(spec, shelf_id)
+            .get_movements(spec, criteria)
             .await
             .map_err(|_| ServiceError::InternalServerError)
     }
-    pub async fn get_items_in_room(&self, spec: &ListingSpec, room_id: RoomId) -> Result<Listing<ItemInRoom>, ServiceError> {
+    /// Hold stock on a shelf or in a room for the user of the reservation.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::ReservationPlaceNotValid` unless exactly one of the shelf and the room is given.
+    /// * `ServiceError::InsufficientItem` if there is not that much stock left to hold.
+    pub async fn reserve_item(&self, reservation: &NewReservation) -> Result<ReservationId, ServiceError> {
+        if reservation.shelf_id.is_some() == reservation.room_id.is_some() {
+            return Err(ServiceError::ReservationPlaceNotValid);
+        }
+        let reservation_id = self.stock_repository.reserve(reservation).await.map_err(ServiceError::from)?;
+        self.stock_changed(reservation.item_id);
+        Ok(reservation_id)
+    }
+    pub async fn get_reservation(&self, reservation_id: ReservationId) -> Result<Reservation, ServiceError> {
         self.stock_repository
-            .get_many_in_room(spec, room_id)
+            .get_reservation(reservation_id)
+            .await
+            .map_err(ServiceError::from)
+    }
+    pub async fn get_reservations(
+        &self,
+        spec: &ListingSpec,
+        criteria: &ReservationCriteria,
+    ) -> Result<Listing<Reservation>, ServiceError> {
+        self.stock_repository
+            .get_reservations(spec, criteria)
             .await
             .map_err(|_| ServiceError::InternalServerError)
     }
+    /// Release a hold. Only its owner and administrators may do so.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::ReservationNotFound` if the hold does not exist.
+    /// * `ServiceError::Unauthorized` if the user neither owns the hold nor is an administrator.
+    pub async fn release_reservation(&self, reservation_id: ReservationId, user_id: &UserId) -> Result<(), ServiceError> {
+        let reservation = self.get_reservation(reservation_id).await?;
+        if reservation.user_id != *user_id {
+            let user = self.user_repository.get_compact(user_id).await?;
+            if !user.administrator {
+                return Err(ServiceError::Unauthorized);
+            }
+        }
+        self.stock_repository
+            .release(reservation_id)
+            .await
+            .map_err(ServiceError::from)?;
+        self.stock_changed(reservation.item_id);
+        Ok(())
+    }
+    /// Release every hold past its expiry and report a stock change for each of their items,
+    /// returning how many were released.
+    pub async fn release_expired_reservations(&self) -> Result<u64, ServiceError> {
+        let item_ids = self.stock_repository.release_expired().await.map_err(ServiceError::from)?;
+        for item_id in item_ids.iter().collect::<BTreeSet<_>>() {
+            self.stock_changed(*item_id);
+        }
+        Ok(item_ids.len() as u64)
+    }
+    /// Classify every item by what was withdrawn of it over the classification window and store
+    /// the classes on the items.
+    pub async fn classify_items(&self) -> Result<Vec<ItemClass>, ServiceError> {
+        let periods = self.classification_periods.max(1);
+        let length = Duration::days(i64::from(self.classification_window)) / periods;
+        let now = OffsetDateTime::now_utc();
+        let mut consumption = Vec::new();
+        for i in (0..periods).rev() {
+            let to = now.saturating_sub(length * i);
+            let from = to.saturating_sub(length);
+            consumption.push(
+                self.stock_repository
+                    .get_consumption(from, to)
+                    .await
+                    .map_err(|_| ServiceError::InternalServerError)?,
+            );
+        }
+        let item_ids: Vec<ItemId> = self
+            .stock_repository
+            .get_all_items()
+            .await
+            .map_err(|_| ServiceError::InternalServerError)?
+            .iter()
+            .map(|x| x.item_id)
+            .collect();
+        let classes = classify(&item_ids, &consumption);
+        self.stock_repository
+            .set_item_classes(&classes)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)?;
+        Ok(classes)
+    }
+    /// Classify every item now rather than on the next scheduled run. Only administrators may do so.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::Unauthorized` if the user is not an administrator.
+    pub async fn reclassify_items(&self, user_id: &UserId) -> Result<Vec<ItemClass>, ServiceError> {
+        self.check_administrator(user_id).await?;
+        self.classify_items().await
+    }
+    /// Set how far stock may be taken off the shelves of a room, or of every room without a
+    /// policy of their own. Only administrators may do so.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::Unauthorized` if the user is not an administrator.
+    /// * `ServiceError::RoomNotFound` if the room does not exist.
+    pub async fn set_stock_policy(&self, policy: &StockPolicy, user_id: &UserId) -> Result<(), ServiceError> {
+        self.check_administrator(user_id).await?;
+        self.stock_repository.set_policy(policy).await.map_err(ServiceError::from)
+    }
+    /// Drop the policy of a room, or the global one when `room_id` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::Unauthorized` if the user is not an administrator.
+    /// * `ServiceError::StockPolicyNotFound` if no policy is set there.
+    pub async fn remove_stock_policy(&self, room_id: Option<RoomId>, user_id: &UserId) -> Result<(), ServiceError> {
+        self.check_administrator(user_id).await?;
+        self.stock_repository.remove_policy(room_id).await.map_err(ServiceError::from)
+    }
+    pub async fn get_stock_policies(&self) -> Result<Vec<StockPolicy>, ServiceError> {
+        self.stock_repository
+            .get_policies()
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    /// The lots taken below zero and not reconciled yet, on the shelves of a room and a warehouse when given.
+    pub async fn get_flagged_stocks(
+        &self,
+        spec: &ListingSpec,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<FlaggedStock>, ServiceError> {
+        self.stock_repository
+            .get_flagged(spec, room_id, warehouse_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    /// Mark the stock of an item on a shelf as reconciled, returning how many lots were flagged.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::Unauthorized` if the user is not an administrator.
+    pub async fn clear_stock_flags(&self, item_id: ItemId, shelf_id: ShelfId, user_id: &UserId) -> Result<u64, ServiceError> {
+        self.check_administrator(user_id).await?;
+        self.stock_repository
+            .clear_flags(item_id, shelf_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    async fn check_administrator(&self, user_id: &UserId) -> Result<(), ServiceError> {
+        let user = self.user_repository.get_compact(user_id).await?;
+        if !user.administrator {
+            return Err(ServiceError::Unauthorized);
+        }
+        Ok(())
+    }
 }
 
 pub struct DbStockRepository {
@@ -89,32 +831,276 @@ impl DbStockRepository {
     pub fn new(database: Arc<Box<dyn Database>>) -> Self {
         Self { database }
     }
-    pub async fn withdraw(&self, item_id: &ItemId, count: i64, shelf_id: ShelfId) -> Result<(), Error> {
-        self.database.withdraw_items(*item_id, count, shelf_id).await
+    pub async fn withdraw(
+        &self,
+        item_id: &ItemId,
+        count: i64,
+        shelf_id: ShelfId,
+        pick: &StockPick,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, Error> {
+        self.database.withdraw_items(*item_id, count, shelf_id, pick, meta).await
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub async fn deposit(
+        &self,
+        item_id: &ItemId,
+        count: i64,
+        shelf_id: ShelfId,
+        bin_id: Option<BinId>,
+        lot: &Lot,
+        status: StockStatus,
+        serials: &[String],
+        cost: Option<&Cost>,
+        meta: &MovementMeta,
+    ) -> Result<ItemXShelf, Error> {
+        self.database
+            .deposit_items(*item_id, count, shelf_id, bin_id, lot, status, serials, cost, meta)
+            .await
+    }
+    pub async fn change_status(
+        &self,
+        item_id: &ItemId,
+        count: i64,
+        shelf_id: ShelfId,
+        pick: &StockPick,
+        status: StockStatus,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, Error> {
+        self.database
+            .change_stock_status(*item_id, count, shelf_id, pick, status, meta)
+            .await
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub async fn transfer(
+        &self,
+        item_id: &ItemId,
+        count: i64,
+        shelf_from: ShelfId,
+        shelf_to: ShelfId,
+        bin_to: Option<BinId>,
+        pick: &StockPick,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, Error> {
+        self.database
+            .transfer_items(*item_id, count, shelf_from, shelf_to, bin_to, pick, meta)
+            .await
     }
-    pub async fn deposit(&self, item_id: &ItemId, count: i64, shelf_id: ShelfId) -> Result<(), Error> {
-        self.database.deposit_items(*item_id, count, shelf_id).await
+    pub async fn apply_batch(
+        &self,
+        lines: &[BatchLine],
+        mode: BatchMode,
+        meta: &MovementMeta,
+    ) -> Result<Vec<Result<Vec<ItemXShelf>, Error>>, Error> {
+        self.database.apply_stock_batch(lines, mode, meta).await
     }
-    pub async fn transfer(&self, item_id: &ItemId, count: i64, shelf_from: ShelfId, shelf_to: ShelfId) -> Result<(), Error> {
-        self.database.transfer_items(*item_id, count, shelf_from, shelf_to).await
+    pub async fn reverse(&self, correlation_id: &str, meta: &MovementMeta) -> Result<Vec<ItemXShelf>, Error> {
+        self.database.reverse_operation(correlation_id, meta).await
     }
-    pub async fn convert(&self, from: Vec<ItemXShelf>, into: Vec<ItemXShelf>) -> Result<(), Error> {
-        self.database.convert_items(from, into).await
+    pub async fn convert(
+        &self,
+        from: Vec<ItemXShelf>,
+        into: Vec<ItemXShelf>,
+        meta: &MovementMeta,
+    ) -> Result<Vec<ItemXShelf>, Error> {
+        self.database.convert_items(from, into, meta).await
     }
-    pub async fn get_many_on_shelves(&self, spec: &ListingSpec) -> Result<Listing<ItemOnShelf>, Error> {
-        self.database.get_stocks_on_shelves(spec.offset, spec.limit, &spec.sort).await
+    pub async fn get_many_on_shelves(
+        &self,
+        spec: &ListingSpec,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<ItemOnShelf>, Error> {
+        self.database
+            .get_stocks_on_shelves(spec.offset, spec.limit, &spec.sort, warehouse_id)
+            .await
     }
     pub async fn get_many_on_shelf(&self, spec: &ListingSpec, shelf_id: ShelfId) -> Result<Listing<ItemOnShelf>, Error> {
         self.database
             .get_stocks_on_shelf(spec.offset, spec.limit, &spec.sort, shelf_id)
             .await
     }
-    pub async fn get_many_in_rooms(&self, spec: &ListingSpec) -> Result<Listing<ItemInRoom>, Error> {
-        self.database.get_stocks_in_rooms(spec.offset, spec.limit, &spec.sort).await
+    pub async fn get_many_in_rooms(
+        &self,
+        spec: &ListingSpec,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<ItemInRoom>, Error> {
+        self.database
+            .get_stocks_in_rooms(spec.offset, spec.limit, &spec.sort, warehouse_id)
+            .await
     }
     pub async fn get_many_in_room(&self, spec: &ListingSpec, room_id: RoomId) -> Result<Listing<ItemInRoom>, Error> {
         self.database
             .get_stocks_in_room(spec.offset, spec.limit, &spec.sort, room_id)
             .await
     }
+    pub async fn get_many_in_warehouses(
+        &self,
+        spec: &ListingSpec,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<ItemInWarehouse>, Error> {
+        self.database
+            .get_stocks_in_warehouses(spec.offset, spec.limit, &spec.sort, warehouse_id)
+            .await
+    }
+    pub async fn get_lots_expiring(
+        &self,
+        spec: &ListingSpec,
+        until: Date,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<LotInRoom>, Error> {
+        self.database
+            .get_lots_expiring(spec.offset, spec.limit, &spec.sort, until, room_id, warehouse_id)
+            .await
+    }
+    pub async fn get_aging(
+        &self,
+        cutoffs: [OffsetDateTime; 3],
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Vec<AgingLine>, Error> {
+        self.database.get_stock_aging(cutoffs, room_id, warehouse_id).await
+    }
+    pub async fn get_dead_stock(
+        &self,
+        since: OffsetDateTime,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Vec<DeadStock>, Error> {
+        self.database.get_dead_stock(since, room_id, warehouse_id).await
+    }
+    pub async fn get_unit(&self, item_id: ItemId, serial: &str) -> Result<StockUnit, Error> {
+        self.database.get_unit(item_id, serial).await
+    }
+    pub async fn get_unit_movements(&self, unit_id: UnitId) -> Result<Vec<StockMovement>, Error> {
+        self.database.get_unit_movements(unit_id).await
+    }
+    pub async fn get_movements(&self, spec: &ListingSpec, criteria: &MovementCriteria) -> Result<Listing<StockMovement>, Error> {
+        self.database
+            .get_movements(spec.offset, spec.limit, &spec.sort, criteria)
+            .await
+    }
+    pub async fn reserve(&self, reservation: &NewReservation) -> Result<ReservationId, Error> {
+        self.database.insert_reservation(reservation).await
+    }
+    pub async fn get_reservation(&self, reservation_id: ReservationId) -> Result<Reservation, Error> {
+        self.database.get_reservation(reservation_id).await
+    }
+    pub async fn get_reservations(
+        &self,
+        spec: &ListingSpec,
+        criteria: &ReservationCriteria,
+    ) -> Result<Listing<Reservation>, Error> {
+        self.database
+            .get_reservations(spec.offset, spec.limit, &spec.sort, criteria)
+            .await
+    }
+    pub async fn release(&self, reservation_id: ReservationId) -> Result<(), Error> {
+        self.database.delete_reservation(reservation_id).await
+    }
+    pub async fn release_expired(&self) -> Result<Vec<ItemId>, Error> {
+        self.database.delete_expired_reservations().await
+    }
+    pub async fn get_unit_factor(&self, item_id: ItemId, unit: &str) -> Result<i64, Error> {
+        self.database.get_unit_factor(item_id, unit).await
+    }
+    pub async fn get_unit_factors(&self, unit: &str) -> Result<Vec<ItemUnit>, Error> {
+        self.database.get_unit_factors(unit).await
+    }
+    pub async fn get_all_in_bins(&self, shelf_id: ShelfId) -> Result<Vec<ItemInBin>, Error> {
+        self.database.get_stocks_in_bins(shelf_id).await
+    }
+    pub async fn get_all_of_item(&self, item_id: ItemId) -> Result<Vec<ItemOnShelf>, Error> {
+        self.database.get_stocks_of_item(item_id).await
+    }
+    pub async fn get_many_on_shelves_as_of(
+        &self,
+        spec: &ListingSpec,
+        shelf_id: Option<ShelfId>,
+        warehouse_id: Option<WarehouseId>,
+        as_of: OffsetDateTime,
+    ) -> Result<Listing<ItemOnShelf>, Error> {
+        self.database
+            .get_stocks_on_shelves_as_of(spec.offset, spec.limit, &spec.sort, shelf_id, warehouse_id, as_of)
+            .await
+    }
+    pub async fn get_many_in_rooms_as_of(
+        &self,
+        spec: &ListingSpec,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+        as_of: OffsetDateTime,
+    ) -> Result<Listing<ItemInRoom>, Error> {
+        self.database
+            .get_stocks_in_rooms_as_of(spec.offset, spec.limit, &spec.sort, room_id, warehouse_id, as_of)
+            .await
+    }
+    pub async fn get_many_in_warehouses_as_of(
+        &self,
+        spec: &ListingSpec,
+        warehouse_id: Option<WarehouseId>,
+        as_of: OffsetDateTime,
+    ) -> Result<Listing<ItemInWarehouse>, Error> {
+        self.database
+            .get_stocks_in_warehouses_as_of(spec.offset, spec.limit, &spec.sort, warehouse_id, as_of)
+            .await
+    }
+    pub async fn snapshot(&self) -> Result<SnapshotId, Error> {
+        self.database.insert_stock_snapshot().await
+    }
+    pub async fn get_valuation(
+        &self,
+        group: ValuationGroup,
+        warehouse_id: Option<WarehouseId>,
+        as_of: OffsetDateTime,
+    ) -> Result<Vec<ValuationLine>, Error> {
+        self.database.get_valuation(group, warehouse_id, as_of).await
+    }
+    pub async fn set_policy(&self, policy: &StockPolicy) -> Result<(), Error> {
+        self.database.upsert_stock_policy(policy).await
+    }
+    pub async fn remove_policy(&self, room_id: Option<RoomId>) -> Result<(), Error> {
+        self.database.delete_stock_policy(room_id).await
+    }
+    pub async fn get_policies(&self) -> Result<Vec<StockPolicy>, Error> {
+        self.database.get_stock_policies().await
+    }
+    pub async fn get_policy_of_shelf(&self, shelf_id: ShelfId) -> Result<StockPolicy, Error> {
+        self.database.get_stock_policy_of_shelf(shelf_id).await
+    }
+    pub async fn get_capacity(&self, shelf_id: ShelfId) -> Result<ShelfCapacity, Error> {
+        self.database.get_shelf_capacity(shelf_id).await
+    }
+    pub async fn get_item(&self, item_id: ItemId) -> Result<Item, Error> {
+        self.database.get_item_from_id(item_id).await
+    }
+    pub async fn get_all_items(&self) -> Result<Vec<Item>, Error> {
+        self.database.get_all_items(None, None).await
+    }
+    pub async fn get_consumption(&self, from: OffsetDateTime, to: OffsetDateTime) -> Result<Vec<Consumption>, Error> {
+        self.database.get_consumption(from, to).await
+    }
+    pub async fn set_item_classes(&self, classes: &[ItemClass]) -> Result<(), Error> {
+        self.database.update_item_classes(classes).await
+    }
+    pub async fn get_putaway_candidates(
+        &self,
+        item_id: ItemId,
+        room_id: Option<RoomId>,
+    ) -> Result<Vec<PutawaySuggestion>, Error> {
+        self.database.get_putaway_candidates(item_id, room_id).await
+    }
+    pub async fn get_flagged(
+        &self,
+        spec: &ListingSpec,
+        room_id: Option<RoomId>,
+        warehouse_id: Option<WarehouseId>,
+    ) -> Result<Listing<FlaggedStock>, Error> {
+        self.database
+            .get_flagged_stocks(spec.offset, spec.limit, &spec.sort, room_id, warehouse_id)
+            .await
+    }
+    pub async fn clear_flags(&self, item_id: ItemId, shelf_id: ShelfId) -> Result<u64, Error> {
+        self.database.clear_stock_flags(item_id, shelf_id).await
+    }
 }
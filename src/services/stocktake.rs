@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::common::ListingSpec;
+use crate::databases::database::{Database, Error, Listing};
+use crate::errors::ServiceError;
+use crate::models::item::{ItemId, ItemXShelf};
+use crate::models::movement::MovementMeta;
+use crate::models::room::RoomId;
+use crate::models::shelf::ShelfId;
+use crate::models::stocktake::{NewStocktake, Stocktake, StocktakeCount, StocktakeId, StocktakeLine};
+use crate::models::user::UserId;
+use crate::services::user::DbUserRepository;
+
+pub struct Service {
+    stocktake_repository: Arc<DbStocktakeRepository>,
+    user_repository: Arc<DbUserRepository>,
+    /// Items whose stock changed, for the reorder checker.
+    stock_changes: UnboundedSender<ItemId>,
+}
+
+impl Service {
+    #[must_use]
+    pub fn new(
+        stocktake_repository: Arc<DbStocktakeRepository>,
+        user_repository: Arc<DbUserRepository>,
+        stock_changes: UnboundedSender<ItemId>,
+    ) -> Self {
+        Self {
+            stocktake_repository,
+            user_repository,
+            stock_changes,
+        }
+    }
+    /// Open a count session on the shelves of a room or on a set of shelves. Only administrators
+    /// supervise stocktakes.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::Unauthorized` if the user is not an administrator.
+    /// * `ServiceError::StocktakeScopeNotValid` unless exactly one of the room and the shelves is given.
+    /// * `ServiceError::StocktakeInProgress` if one of the shelves is already being counted.
+    pub async fn open_stocktake(
+        &self,
+        room_id: Option<RoomId>,
+        mut shelf_ids: Vec<ShelfId>,
+        frozen: bool,
+        blind: bool,
+        user_id: &UserId,
+    ) -> Result<StocktakeId, ServiceError> {
+        self.check_supervisor(user_id).await?;
+        if room_id.is_some() != shelf_ids.is_empty() {
+            return Err(ServiceError::StocktakeScopeNotValid);
+        }
+        shelf_ids.sort_unstable();
+        shelf_ids.dedup();
+        let stocktake = NewStocktake {
+            room_id,
+            shelf_ids,
+            frozen,
+            blind,
+            user_id: *user_id,
+        };
+        self.stocktake_repository.create(&stocktake).await.map_err(ServiceError::from)
+    }
+    pub async fn get_stocktake(&self, stocktake_id: StocktakeId) -> Result<Stocktake, ServiceError> {
+        self.stocktake_repository
+            .get_one(stocktake_id)
+            .await
+            .map_err(ServiceError::from)
+    }
+    pub async fn get_stocktakes(&self, spec: &ListingSpec) -> Result<Listing<Stocktake>, ServiceError> {
+        self.stocktake_repository
+            .get_many(spec)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    /// Get the lines of a stocktake. Counters of a blind stocktake do not get to see the expected
+    /// quantities, administrators always do.
+    pub async fn get_stocktake_lines(
+        &self,
+        spec: &ListingSpec,
+        stocktake_id: StocktakeId,
+        user_id: &UserId,
+    ) -> Result<Listing<StocktakeLine>, ServiceError> {
+        let stocktake = self.get_stocktake(stocktake_id).await?;
+        let mut lines = self
+            .stocktake_repository
+            .get_lines(spec, stocktake_id)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)?;
+        if stocktake.blind && !self.user_repository.get_compact(user_id).await?.administrator {
+            lines.data.iter_mut().for_each(StocktakeLine::hide_expected);
+        }
+        Ok(lines)
+    }
+    /// Record what counters found, replacing their earlier counts of the same (item, shelf).
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::CountedMustNotBeNegative` if a counted quantity is negative.
+    /// * `ServiceError::StocktakeClosed` if the stocktake is no longer open.
+    /// * `ServiceError::ShelfNotInStocktake` if a shelf is not counted by the stocktake.
+    pub async fn submit_counts(
+        &self,
+        stocktake_id: StocktakeId,
+        counts: &[StocktakeCount],
+        user_id: &UserId,
+    ) -> Result<(), ServiceError> {
+        if counts.iter().any(|x| x.counted < 0) {
+            return Err(ServiceError::CountedMustNotBeNegative);
+        }
+        self.stocktake_repository
+            .count(stocktake_id, counts, *user_id)
+            .await
+            .map_err(ServiceError::from)
+    }
+    /// Close a stocktake and post its variances to the stock, under the stocktake as the reason.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::Unauthorized` if the user is not an administrator.
+    /// * `ServiceError::StocktakeClosed` if the stocktake is no longer open.
+    pub async fn approve_stocktake(&self, stocktake_id: StocktakeId, user_id: &UserId) -> Result<Vec<ItemXShelf>, ServiceError> {
+        self.check_supervisor(user_id).await?;
+        let meta = MovementMeta {
+            user_id: *user_id,
+            reason: Some(format!("stocktake {stocktake_id}")),
+            expected_count: None,
+        };
+        let stocks = self
+            .stocktake_repository
+            .approve(stocktake_id, &meta)
+            .await
+            .map_err(ServiceError::from)?;
+        let mut item_ids: Vec<ItemId> = stocks.iter().map(|x| x.item_id).collect();
+        item_ids.sort_unstable();
+        item_ids.dedup();
+        for item_id in item_ids {
+            let _ = self.stock_changes.send(item_id);
+        }
+        Ok(stocks)
+    }
+    /// Close a stocktake without touching the stock, lifting its freeze.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::Unauthorized` if the user is not an administrator.
+    /// * `ServiceError::StocktakeClosed` if the stocktake is no longer open.
+    pub async fn cancel_stocktake(&self, stocktake_id: StocktakeId, user_id: &UserId) -> Result<(), ServiceError> {
+        self.check_supervisor(user_id).await?;
+        self.stocktake_repository
+            .cancel(stocktake_id, *user_id)
+            .await
+            .map_err(ServiceError::from)
+    }
+    async fn check_supervisor(&self, user_id: &UserId) -> Result<(), ServiceError> {
+        let user = self.user_repository.get_compact(user_id).await?;
+        if !user.administrator {
+            return Err(ServiceError::Unauthorized);
+        }
+        Ok(())
+    }
+}
+
+pub struct DbStocktakeRepository {
+    database: Arc<Box<dyn Database>>,
+}
+
+impl DbStocktakeRepository {
+    #[must_use]
+    pub fn new(database: Arc<Box<dyn Database>>) -> Self {
+        Self { database }
+    }
+    pub async fn create(&self, stocktake: &NewStocktake) -> Result<StocktakeId, Error> {
+        self.database.insert_stocktake(stocktake).await
+    }
+    pub async fn get_one(&self, stocktake_id: StocktakeId) -> Result<Stocktake, Error> {
+        self.database.get_stocktake(stocktake_id).await
+    }
+    pub async fn get_many(&self, spec: &ListingSpec) -> Result<Listing<Stocktake>, Error> {
+        self.database.get_stocktakes(spec.offset, spec.limit, &spec.sort).await
+    }
+    pub async fn get_lines(&self, spec: &ListingSpec, stocktake_id: StocktakeId) -> Result<Listing<StocktakeLine>, Error> {
+        self.database
+            .get_stocktake_lines(spec.offset, spec.limit, &spec.sort, stocktake_id)
+            .await
+    }
+    pub async fn count(&self, stocktake_id: StocktakeId, counts: &[StocktakeCount], user_id: UserId) -> Result<(), Error> {
+        self.database.upsert_stocktake_counts(stocktake_id, counts, user_id).await
+    }
+    pub async fn approve(&self, stocktake_id: StocktakeId, meta: &MovementMeta) -> Result<Vec<ItemXShelf>, Error> {
+        self.database.approve_stocktake(stocktake_id, meta).await
+    }
+    pub async fn cancel(&self, stocktake_id: StocktakeId, user_id: UserId) -> Result<(), Error> {
+        self.database.cancel_stocktake(stocktake_id, user_id).await
+    }
+}
@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use crate::databases::database::{Database, Error};
+use crate::errors::ServiceError;
+use crate::models::user::UserId;
+use crate::models::warehouse::{Warehouse, WarehouseId};
+use crate::services::user::DbUserRepository;
+use crate::web::api::v1::contexts::warehouse::forms::{AddWarehouseForm, UpdateWarehouseForm};
+
+pub struct Service {
+    warehouse_repository: Arc<DbWarehouseRepository>,
+    user_repository: Arc<DbUserRepository>,
+}
+
+impl Service {
+    #[must_use]
+    pub fn new(warehouse_repository: Arc<DbWarehouseRepository>, user_repository: Arc<DbUserRepository>) -> Self {
+        Self {
+            warehouse_repository,
+            user_repository,
+        }
+    }
+    /// Add a warehouse, in UTC unless another time zone is given. Only administrators may do so.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::Unauthorized` if the user is not an administrator.
+    /// * `ServiceError::WarehouseNotValid` if the code, name or time zone is not valid.
+    /// * `ServiceError::WarehouseCodeTaken` if another warehouse has the code.
+    pub async fn add_warehouse(&self, warehouse_form: &AddWarehouseForm, user_id: &UserId) -> Result<WarehouseId, ServiceError> {
+        self.check_administrator(user_id).await?;
+        let time_zone = warehouse_form.time_zone.as_deref().unwrap_or("UTC");
+        if !code_is_valid(&warehouse_form.code) || warehouse_form.name.is_empty() || !time_zone_is_valid(time_zone) {
+            return Err(ServiceError::WarehouseNotValid);
+        }
+        self.warehouse_repository
+            .add(&warehouse_form.code, &warehouse_form.name, &warehouse_form.address, time_zone)
+            .await
+            .map_err(|error: Error| match error {
+                Error::WarehouseCodeTaken => ServiceError::WarehouseCodeTaken,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    /// Change a warehouse. Only administrators may do so.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::Unauthorized` if the user is not an administrator.
+    /// * `ServiceError::WarehouseNotValid` if the code, name or time zone is not valid.
+    /// * `ServiceError::WarehouseNotFound` if there is no such warehouse.
+    /// * `ServiceError::WarehouseCodeTaken` if another warehouse has the code.
+    pub async fn update_warehouse(
+        &self,
+        warehouse_id: &WarehouseId,
+        warehouse_form: &UpdateWarehouseForm,
+        user_id: &UserId,
+    ) -> Result<(), ServiceError> {
+        self.check_administrator(user_id).await?;
+        if warehouse_form.code.as_deref().is_some_and(|x| !code_is_valid(x))
+            || warehouse_form.name.as_deref().is_some_and(str::is_empty)
+            || warehouse_form.time_zone.as_deref().is_some_and(|x| !time_zone_is_valid(x))
+        {
+            return Err(ServiceError::WarehouseNotValid);
+        }
+        self.warehouse_repository
+            .update(warehouse_id, warehouse_form)
+            .await
+            .map_err(|error: Error| match error {
+                Error::WarehouseNotFound => ServiceError::WarehouseNotFound,
+                Error::WarehouseCodeTaken => ServiceError::WarehouseCodeTaken,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    /// Remove a warehouse, once its rooms were moved or closed. Only administrators may do so.
+    ///
+    /// # Errors
+    ///
+    /// This function will return a:
+    ///
+    /// * `ServiceError::Unauthorized` if the user is not an administrator.
+    /// * `ServiceError::WarehouseNotFound` if there is no such warehouse.
+    /// * `ServiceError::WarehouseNotEmpty` if rooms are still in it.
+    pub async fn remove_warehouse(&self, warehouse_id: &WarehouseId, user_id: &UserId) -> Result<(), ServiceError> {
+        self.check_administrator(user_id).await?;
+        self.warehouse_repository
+            .delete_one(warehouse_id)
+            .await
+            .map_err(|error: Error| match error {
+                Error::WarehouseNotFound => ServiceError::WarehouseNotFound,
+                Error::WarehouseNotEmpty => ServiceError::WarehouseNotEmpty,
+                _ => ServiceError::InternalServerError,
+            })
+    }
+    pub async fn get_warehouse(&self, warehouse_id: &WarehouseId) -> Result<Warehouse, ServiceError> {
+        self.warehouse_repository
+            .get_one(warehouse_id)
+            .await
+            .map_err(|_| ServiceError::WarehouseNotFound)
+    }
+    pub async fn get_all_warehouses(&self) -> Result<Vec<Warehouse>, ServiceError> {
+        self.warehouse_repository
+            .get_all()
+            .await
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+    async fn check_administrator(&self, user_id: &UserId) -> Result<(), ServiceError> {
+        let user = self.user_repository.get_compact(user_id).await?;
+        if !user.administrator {
+            return Err(ServiceError::Unauthorized);
+        }
+        Ok(())
+    }
+}
+
+/// A code is up to 16 ASCII letters, digits or dashes.
+fn code_is_valid(code: &str) -> bool {
+    !code.is_empty() && code.len() <= 16 && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// A time zone is one the IANA database knows, such as `UTC` or `America/Argentina/Buenos_Aires`.
+fn time_zone_is_valid(time_zone: &str) -> bool {
+    time_zone.parse::<chrono_tz::Tz>().is_ok()
+}
+
+pub struct DbWarehouseRepository {
+    database: Arc<Box<dyn Database>>,
+}
+
+impl DbWarehouseRepository {
+    #[must_use]
+    pub fn new(database: Arc<Box<dyn Database>>) -> Self {
+        Self { database }
+    }
+    pub async fn add(&self, code: &str, name: &str, address: &Option<String>, time_zone: &str) -> Result<WarehouseId, Error> {
+        self.database.insert_warehouse(code, name, address, time_zone).await
+    }
+    pub async fn update(&self, warehouse_id: &WarehouseId, form: &UpdateWarehouseForm) -> Result<(), Error> {
+        self.database
+            .update_warehouse(
+                *warehouse_id,
+                form.code.as_deref(),
+                form.name.as_deref(),
+                form.address.as_deref(),
+                form.time_zone.as_deref(),
+            )
+            .await
+    }
+    pub async fn delete_one(&self, warehouse_id: &WarehouseId) -> Result<(), Error> {
+        self.database.delete_warehouse(*warehouse_id).await
+    }
+    pub async fn get_one(&self, warehouse_id: &WarehouseId) -> Result<Warehouse, Error> {
+        self.database.get_warehouse_from_id(*warehouse_id).await
+    }
+    pub async fn get_all(&self) -> Result<Vec<Warehouse>, Error> {
+        self.database.get_warehouses().await
+    }
+}
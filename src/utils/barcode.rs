@@ -0,0 +1,196 @@
+//! Code 128 barcodes, which every handheld scanner reads.
+
+/// Bar and space widths in modules of the symbols, by value.
+const PATTERNS: [[u8; 6]; 106] = [
+    [2, 1, 2, 2, 2, 2],
+    [2, 2, 2, 1, 2, 2],
+    [2, 2, 2, 2, 2, 1],
+    [1, 2, 1, 2, 2, 3],
+    [1, 2, 1, 3, 2, 2],
+    [1, 3, 1, 2, 2, 2],
+    [1, 2, 2, 2, 1, 3],
+    [1, 2, 2, 3, 1, 2],
+    [1, 3, 2, 2, 1, 2],
+    [2, 2, 1, 2, 1, 3],
+    [2, 2, 1, 3, 1, 2],
+    [2, 3, 1, 2, 1, 2],
+    [1, 1, 2, 2, 3, 2],
+    [1, 2, 2, 1, 3, 2],
+    [1, 2, 2, 2, 3, 1],
+    [1, 1, 3, 2, 2, 2],
+    [1, 2, 3, 1, 2, 2],
+    [1, 2, 3, 2, 2, 1],
+    [2, 2, 3, 2, 1, 1],
+    [2, 2, 1, 1, 3, 2],
+    [2, 2, 1, 2, 3, 1],
+    [2, 1, 3, 2, 1, 2],
+    [2, 2, 3, 1, 1, 2],
+    [3, 1, 2, 1, 3, 1],
+    [3, 1, 1, 2, 2, 2],
+    [3, 2, 1, 1, 2, 2],
+    [3, 2, 1, 2, 2, 1],
+    [3, 1, 2, 2, 1, 2],
+    [3, 2, 2, 1, 1, 2],
+    [3, 2, 2, 2, 1, 1],
+    [2, 1, 2, 1, 2, 3],
+    [2, 1, 2, 3, 2, 1],
+    [2, 3, 2, 1, 2, 1],
+    [1, 1, 1, 3, 2, 3],
+    [1, 3, 1, 1, 2, 3],
+    [1, 3, 1, 3, 2, 1],
+    [1, 1, 2, 3, 1, 3],
+    [1, 3, 2, 1, 1, 3],
+    [1, 3, 2, 3, 1, 1],
+    [2, 1, 1, 3, 1, 3],
+    [2, 3, 1, 1, 1, 3],
+    [2, 3, 1, 3, 1, 1],
+    [1, 1, 2, 1, 3, 3],
+    [1, 1, 2, 3, 3, 1],
+    [1, 3, 2, 1, 3, 1],
+    [1, 1, 3, 1, 2, 3],
+    [1, 1, 3, 3, 2, 1],
+    [1, 3, 3, 1, 2, 1],
+    [3, 1, 3, 1, 2, 1],
+    [2, 1, 1, 3, 3, 1],
+    [2, 3, 1, 1, 3, 1],
+    [2, 1, 3, 1, 1, 3],
+    [2, 1, 3, 3, 1, 1],
+    [2, 1, 3, 1, 3, 1],
+    [3, 1, 1, 1, 2, 3],
+    [3, 1, 1, 3, 2, 1],
+    [3, 3, 1, 1, 2, 1],
+    [3, 1, 2, 1, 1, 3],
+    [3, 1, 2, 3, 1, 1],
+    [3, 3, 2, 1, 1, 1],
+    [3, 1, 4, 1, 1, 1],
+    [2, 2, 1, 4, 1, 1],
+    [4, 3, 1, 1, 1, 1],
+    [1, 1, 1, 2, 2, 4],
+    [1, 1, 1, 4, 2, 2],
+    [1, 2, 1, 1, 2, 4],
+    [1, 2, 1, 4, 2, 1],
+    [1, 4, 1, 1, 2, 2],
+    [1, 4, 1, 2, 2, 1],
+    [1, 1, 2, 2, 1, 4],
+    [1, 1, 2, 4, 1, 2],
+    [1, 2, 2, 1, 1, 4],
+    [1, 2, 2, 4, 1, 1],
+    [1, 4, 2, 1, 1, 2],
+    [1, 4, 2, 2, 1, 1],
+    [2, 4, 1, 2, 1, 1],
+    [2, 2, 1, 1, 1, 4],
+    [4, 1, 3, 1, 1, 1],
+    [2, 4, 1, 1, 1, 2],
+    [1, 3, 4, 1, 1, 1],
+    [1, 1, 1, 2, 4, 2],
+    [1, 2, 1, 1, 4, 2],
+    [1, 2, 1, 2, 4, 1],
+    [1, 1, 4, 2, 1, 2],
+    [1, 2, 4, 1, 1, 2],
+    [1, 2, 4, 2, 1, 1],
+    [4, 1, 1, 2, 1, 2],
+    [4, 2, 1, 1, 1, 2],
+    [4, 2, 1, 2, 1, 1],
+    [2, 1, 2, 1, 4, 1],
+    [2, 1, 4, 1, 2, 1],
+    [4, 1, 2, 1, 2, 1],
+    [1, 1, 1, 1, 4, 3],
+    [1, 1, 1, 3, 4, 1],
+    [1, 3, 1, 1, 4, 1],
+    [1, 1, 4, 1, 1, 3],
+    [1, 1, 4, 3, 1, 1],
+    [4, 1, 1, 1, 1, 3],
+    [4, 1, 1, 3, 1, 1],
+    [1, 1, 3, 1, 4, 1],
+    [1, 1, 4, 1, 3, 1],
+    [3, 1, 1, 1, 4, 1],
+    [4, 1, 1, 1, 3, 1],
+    [2, 1, 1, 4, 1, 2],
+    [2, 1, 1, 2, 1, 4],
+    [2, 1, 1, 2, 3, 2],
+];
+
+/// The stop symbol, ending with its termination bar.
+const STOP: [u8; 7] = [2, 3, 3, 1, 1, 1, 2];
+
+const START_B: usize = 104;
+
+/// Encode `data` as Code 128 in code set B, returning the widths of its bars and spaces in
+/// modules, starting with a bar and without quiet zones. `None` when `data` is empty or holds
+/// characters other than printable ASCII.
+#[must_use]
+pub fn code128(data: &str) -> Option<Vec<u8>> {
+    if data.is_empty() {
+        return None;
+    }
+    let mut values = vec![START_B];
+    for c in data.chars() {
+        if !(' '..='~').contains(&c) {
+            return None;
+        }
+        values.push(c as usize - 32);
+    }
+    let checksum = values.iter().enumerate().map(|(i, v)| i.max(1) * v).sum::<usize>() % 103;
+    values.push(checksum);
+    let mut widths: Vec<u8> = values.into_iter().flat_map(|v| PATTERNS[v]).collect();
+    widths.extend(STOP);
+    Some(widths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{code128, PATTERNS, STOP};
+
+    /// The symbol as the modules of the spec tables, `1` for a bar and `0` for a space.
+    fn modules(widths: &[u8]) -> String {
+        widths
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (if i % 2 == 0 { "1" } else { "0" }).repeat(usize::from(*w)))
+            .collect()
+    }
+
+    #[test]
+    fn it_should_have_symbols_of_11_modules_with_an_even_bar_count() {
+        for pattern in PATTERNS {
+            assert_eq!(pattern.iter().map(|x| u32::from(*x)).sum::<u32>(), 11);
+            assert_eq!(pattern.iter().step_by(2).map(|x| u32::from(*x)).sum::<u32>() % 2, 0);
+        }
+        assert_eq!(modules(&STOP), "1100011101011");
+    }
+
+    #[test]
+    fn it_should_encode_a_single_character() {
+        // start B, `A`, checksum 34 and stop
+        assert_eq!(
+            code128("A").map(|x| modules(&x)),
+            Some("11010010000".to_owned() + "10100011000" + "10001011000" + "1100011101011")
+        );
+    }
+
+    #[test]
+    fn it_should_weigh_the_checksum_by_position() {
+        // (104 + 33 * 1 + 34 * 2) % 103 = 102, which is FNC1
+        assert_eq!(
+            code128("AB").map(|x| modules(&x)),
+            Some("11010010000".to_owned() + "10100011000" + "10001011000" + "11110101110" + "1100011101011")
+        );
+    }
+
+    #[test]
+    fn it_should_encode_a_bin_address() {
+        let widths = code128("S03-L2-P04").unwrap();
+
+        // start, ten characters, checksum and stop
+        assert_eq!(modules(&widths).len(), 11 * 12 + 13);
+        // (104 + 51 + 2*16 + 3*19 + 4*13 + 5*44 + 6*18 + 7*13 + 8*48 + 9*16 + 10*20) % 103 = 1
+        assert_eq!(modules(&widths)[11 * 11..11 * 12], modules(&PATTERNS[1]));
+    }
+
+    #[test]
+    fn it_should_not_encode_what_code_set_b_does_not_hold() {
+        assert_eq!(code128(""), None);
+        assert_eq!(code128("R01\n"), None);
+        assert_eq!(code128("Käse"), None);
+    }
+}
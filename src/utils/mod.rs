@@ -1,2 +1,3 @@
+pub mod barcode;
+pub mod clock;
 pub mod validation;
-pub mod clock;
\ No newline at end of file
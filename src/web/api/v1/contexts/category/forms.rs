@@ -0,0 +1,7 @@
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AddCategoryForm {
+    pub name: String,
+    pub description: Option<String>,
+}
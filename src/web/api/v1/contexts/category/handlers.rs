@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::common::AppData;
+use crate::models::category::CategoryId;
+use crate::web::api::v1::extractors::bearer_token::Extract;
+use crate::web::api::v1::responses::OkResponseData;
+
+use super::forms::AddCategoryForm;
+use super::responses;
+
+#[allow(clippy::unused_async)]
+pub async fn add_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Json(category_form): Json<AddCategoryForm>,
+) -> Response {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+    match app_data.category_service.add_category(&category_form).await {
+        Ok(category_id) => responses::mutated_category(category_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn delete_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(category_id): Path<CategoryId>,
+) -> Response {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+    match app_data.category_service.remove_category(&category_id).await {
+        Ok(()) => responses::mutated_category(category_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_all_handler(Extension(app_data): Extension<Arc<AppData>>, Extract(maybe_bearer_token): Extract) -> Response {
+    match app_data.category_service.get_all_categories().await {
+        Ok(categories) => Json(OkResponseData { data: categories }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
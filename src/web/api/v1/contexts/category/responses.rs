@@ -0,0 +1,8 @@
+use axum::Json;
+
+use crate::models::category::CategoryId;
+use crate::web::api::v1::responses::OkResponseData;
+
+pub fn mutated_category(category_id: CategoryId) -> Json<OkResponseData<CategoryId>> {
+    Json(OkResponseData { data: category_id })
+}
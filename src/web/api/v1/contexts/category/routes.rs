@@ -0,0 +1,10 @@
+use axum::routing::{delete, get};
+use axum::Router;
+
+use super::handlers::{add_handler, delete_handler, get_all_handler};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(get_all_handler).post(add_handler))
+        .route("/:id", delete(delete_handler))
+}
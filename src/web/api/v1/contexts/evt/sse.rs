@@ -1,27 +1,44 @@
-use axum::response::sse::Event;
-use axum::response::Sse;
-use axum_extra::{headers, TypedHeader};
-use futures::{stream, Stream};
 use std::convert::Infallible;
+use std::sync::Arc;
 use std::time::Duration;
-use tokio_stream::StreamExt;
 
-pub async fn sse_handler(
-    TypedHeader(user_agent): TypedHeader<headers::UserAgent>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    println!("`{}` connected", user_agent.as_str());
+use axum::response::sse::{Event, KeepAlive};
+use axum::response::{IntoResponse, Response, Sse};
+use axum::Extension;
+use futures::{stream, Stream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+
+use crate::common::AppData;
+use crate::models::reorder::ReorderLevel;
+use crate::web::api::v1::extractors::bearer_token::Extract;
 
-    // A `Stream` that repeats an event every second
-    //
-    // You can also create streams from tokio channels using the wrappers in
-    // https://docs.rs/tokio-stream
-    let stream = stream::repeat_with(|| Event::default().data("hello from SSE"))
-        .map(Ok)
-        .throttle(Duration::from_secs(10));
+/// Stream the server events, for now a `low_stock` event carrying the `ReorderLevel` each time
+/// the stock of an item falls under its reorder point.
+pub async fn sse_handler(Extension(app_data): Extension<Arc<AppData>>, Extract(maybe_bearer_token): Extract) -> Response {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+
+    let low_stock = app_data.reorder_service.subscribe_low_stock();
+
+    Sse::new(low_stock_events(low_stock))
+        .keep_alive(KeepAlive::new().interval(Duration::from_secs(15)))
+        .into_response()
+}
 
-    Sse::new(stream).keep_alive(
-        axum::response::sse::KeepAlive::new()
-            .interval(Duration::from_secs(1))
-            .text("keep-alive-text"),
-    )
+fn low_stock_events(receiver: Receiver<ReorderLevel>) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(level) => {
+                    let event = Event::default().event("low_stock").json_data(&level).ok()?;
+                    return Some((Ok(event), receiver));
+                }
+                // a listener too slow to keep up misses the oldest events rather than the stream
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
 }
@@ -1,10 +1,19 @@
 use serde_derive::{Deserialize, Serialize};
 
+use crate::models::category::CategoryId;
+use crate::models::item::ItemDimensions;
+use crate::models::valuation::CostingMethod;
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AddItemForm {
     pub name: String,
     pub description: Option<String>,
     pub sn: String,
+    /// Track every unit in stock by its own serial number.
+    #[serde(default)]
+    pub serialized: bool,
+    /// Unit every count of the item is kept in, `pcs` when omitted.
+    pub base_unit: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -12,4 +21,15 @@ pub struct UpdateItemForm {
     pub name: Option<String>,
     pub description: Option<String>,
     pub sn: Option<String>,
+    pub costing: Option<CostingMethod>,
+    pub category_id: Option<CategoryId>,
+    /// Weight and dimensions of one base unit, replacing all four at once.
+    pub dimensions: Option<ItemDimensions>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ItemUnitForm {
+    pub unit: String,
+    /// How many of the base unit one of `unit` holds.
+    pub factor: i64,
 }
@@ -4,13 +4,13 @@ use axum::extract::{Path, Query};
 use axum::response::{IntoResponse, Response};
 use axum::{Extension, Json};
 
-use crate::common::{AppData, ListingCriteria, PagedConf};
+use crate::common::{AppData, BatchDelCriteria, ItemClassFilter, ListingCriteria, PagedConf};
 use crate::errors::ServiceError;
-use crate::models::item::ItemId;
+use crate::models::item::{ItemId, DEFAULT_BASE_UNIT};
 use crate::web::api::v1::extractors::bearer_token::Extract;
 use crate::web::api::v1::responses::OkResponseData;
 
-use super::forms::{AddItemForm, UpdateItemForm};
+use super::forms::{AddItemForm, ItemUnitForm, UpdateItemForm};
 use super::responses;
 
 #[allow(clippy::unused_async)]
@@ -19,17 +19,22 @@ pub async fn add_handler(
     Extract(maybe_bearer_token): Extract,
     Json(item_form): Json<AddItemForm>,
 ) -> Response {
+    let base_unit = item_form.base_unit.as_deref().unwrap_or(DEFAULT_BASE_UNIT);
     if let Some(desc) = &item_form.description {
         return match app_data
             .item_service
-            .add_item_with_desc(&item_form.name, &desc, &item_form.sn)
+            .add_item_with_desc(&item_form.name, &desc, &item_form.sn, item_form.serialized, base_unit)
             .await
         {
             Ok(item_id) => responses::mutated_item(item_id).into_response(),
             Err(error) => error.into_response(),
         };
     }
-    match app_data.item_service.add_item(&item_form.name, &item_form.sn).await {
+    match app_data
+        .item_service
+        .add_item(&item_form.name, &item_form.sn, item_form.serialized, base_unit)
+        .await
+    {
         Ok(item_id) => responses::mutated_item(item_id).into_response(),
         Err(error) => error.into_response(),
     }
@@ -51,9 +56,13 @@ pub async fn delete_handler(
 pub async fn batch_delete_handler(
     Extension(app_data): Extension<Arc<AppData>>,
     Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<BatchDelCriteria>,
     Json(ids): Json<Vec<i64>>,
 ) -> Response {
-    match app_data.item_service.remove_items(&ids).await {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+    match app_data.item_service.remove_items(&ids, criteria.dry_run).await {
         Ok(res) => Json(res).into_response(),
         Err(error) => error.into_response(),
     }
@@ -101,6 +110,24 @@ pub async fn patch_handler(
             Err(error) => error.into_response(),
         };
     }
+    if let Some(costing) = item_form.costing {
+        return match app_data.item_service.update_item_costing(&item_id, costing).await {
+            Ok(()) => responses::mutated_item(item_id).into_response(),
+            Err(error) => error.into_response(),
+        };
+    }
+    if let Some(category_id) = item_form.category_id {
+        return match app_data.item_service.update_item_category(&item_id, category_id).await {
+            Ok(()) => responses::mutated_item(item_id).into_response(),
+            Err(error) => error.into_response(),
+        };
+    }
+    if let Some(dimensions) = &item_form.dimensions {
+        return match app_data.item_service.update_item_dimensions(&item_id, dimensions).await {
+            Ok(()) => responses::mutated_item(item_id).into_response(),
+            Err(error) => error.into_response(),
+        };
+    }
     ServiceError::PayloadNotValid.into_response()
 }
 
@@ -122,18 +149,78 @@ pub async fn get_paged_handler(
     Extract(maybe_bearer_token): Extract,
     Query(criteria): Query<ListingCriteria>,
     Query(paged_conf): Query<PagedConf>,
+    Query(filter): Query<ItemClassFilter>,
 ) -> Response {
     if let Some(b) = paged_conf.all {
         if b {
-            return match app_data.item_service.get_all_items().await {
+            return match app_data.item_service.get_all_items(&filter).await {
                 Ok(items) => Json(OkResponseData { data: items }).into_response(),
                 Err(error) => error.into_response(),
             };
         }
     }
     let spec = app_data.cfg.spec_from_criteria(&criteria).await;
-    match app_data.item_service.get_items(&spec).await {
+    match app_data.item_service.get_items(&spec, &filter).await {
         Ok(items) => Json(OkResponseData { data: items }).into_response(),
         Err(error) => error.into_response(),
     }
 }
+
+#[allow(clippy::unused_async)]
+pub async fn get_units_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(item_id): Path<ItemId>,
+) -> Response {
+    match app_data.item_service.get_item_units(&item_id).await {
+        Ok(units) => Json(OkResponseData { data: units }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_cost_layers_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(item_id): Path<ItemId>,
+) -> Response {
+    match app_data.item_service.get_item_cost_layers(&item_id).await {
+        Ok(layers) => Json(OkResponseData { data: layers }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn set_unit_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(item_id): Path<ItemId>,
+    Json(unit_form): Json<ItemUnitForm>,
+) -> Response {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+    match app_data
+        .item_service
+        .set_item_unit(&item_id, &unit_form.unit, unit_form.factor)
+        .await
+    {
+        Ok(()) => responses::mutated_item(item_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn delete_unit_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path((item_id, unit)): Path<(ItemId, String)>,
+) -> Response {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+    match app_data.item_service.remove_item_unit(&item_id, &unit).await {
+        Ok(()) => responses::mutated_item(item_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
@@ -2,7 +2,8 @@ use axum::routing::{delete, get};
 use axum::Router;
 
 use super::handlers::{
-    add_handler, batch_delete_handler, delete_handler, get_handler, get_paged_handler, patch_handler, update_handler,
+    add_handler, batch_delete_handler, delete_handler, delete_unit_handler, get_cost_layers_handler, get_handler,
+    get_paged_handler, get_units_handler, patch_handler, set_unit_handler, update_handler,
 };
 
 pub fn router() -> Router {
@@ -15,4 +16,7 @@ pub fn router() -> Router {
                 .patch(patch_handler)
                 .get(get_handler),
         )
+        .route("/:id/units", get(get_units_handler).put(set_unit_handler))
+        .route("/:id/units/:unit", delete(delete_unit_handler))
+        .route("/:id/cost-layers", get(get_cost_layers_handler))
 }
@@ -1,7 +1,9 @@
 pub mod about;
+pub mod category;
 pub mod evt;
 pub mod item;
 pub mod room;
 pub mod shelf;
 pub mod stock;
 pub mod user;
+pub mod warehouse;
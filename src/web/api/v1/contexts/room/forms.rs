@@ -1,13 +1,17 @@
 use serde_derive::{Deserialize, Serialize};
 
+use crate::models::warehouse::WarehouseId;
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AddRoomForm {
     pub name: String,
     pub description: Option<String>,
+    pub warehouse_id: WarehouseId,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct UpdateRoomForm {
     pub name: Option<String>,
     pub description: Option<String>,
+    pub warehouse_id: Option<WarehouseId>,
 }
@@ -4,12 +4,14 @@ use axum::extract::{Extension, Path, Query};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 
-use crate::common::ListingCriteria;
-use crate::common::{AppData, PagedConf};
+use crate::common::{AppData, ArchiveCriteria, BatchDelCriteria, ExtraArchived, PagedConf};
+use crate::common::{LabelCriteria, ListingCriteria};
 use crate::errors::ServiceError;
+use crate::models::category::CategoryId;
+use crate::models::movement::MovementMeta;
 use crate::models::room::RoomId;
 use crate::web::api::v1::extractors::bearer_token::Extract;
-use crate::web::api::v1::responses::OkResponseData;
+use crate::web::api::v1::responses::{labels_response, OkResponseData};
 
 use super::forms::{AddRoomForm, UpdateRoomForm};
 use super::responses;
@@ -35,8 +37,37 @@ pub async fn delete_handler(
     Extension(app_data): Extension<Arc<AppData>>,
     Extract(maybe_bearer_token): Extract,
     Path(room_id): Path<RoomId>,
+    Query(criteria): Query<ArchiveCriteria>,
 ) -> Response {
-    match app_data.room_service.close_room(&room_id).await {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let meta = MovementMeta {
+        user_id,
+        reason: criteria.reason,
+        expected_count: None,
+    };
+    match app_data
+        .room_service
+        .close_room(&room_id, criteria.shelf_to, criteria.bin_to, &meta)
+        .await
+    {
+        Ok(_) => responses::mutated_room(room_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn restore_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(room_id): Path<RoomId>,
+) -> Response {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+    match app_data.room_service.restore_room(&room_id).await {
         Ok(_) => responses::mutated_room(room_id).into_response(),
         Err(error) => error.into_response(),
     }
@@ -46,9 +77,13 @@ pub async fn delete_handler(
 pub async fn batch_delete_handler(
     Extension(app_data): Extension<Arc<AppData>>,
     Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<BatchDelCriteria>,
     Json(ids): Json<Vec<i64>>,
 ) -> Response {
-    match app_data.room_service.close_rooms(&ids).await {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+    match app_data.room_service.close_rooms(&ids, criteria.dry_run).await {
         Ok(res) => Json(res).into_response(),
         Err(error) => error.into_response(),
     }
@@ -61,12 +96,22 @@ pub async fn update_handler(
     Path(room_id): Path<RoomId>,
     Json(room_form): Json<AddRoomForm>,
 ) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
     match app_data
         .room_service
-        .update_room(&room_id, &room_form.name, &room_form.description)
+        .update_room(
+            &user_id,
+            &room_id,
+            &room_form.name,
+            &room_form.description,
+            &room_form.warehouse_id,
+        )
         .await
     {
-        Ok(_) => responses::mutated_room(room_id).into_response(),
+        Ok(()) => responses::mutated_room(room_id).into_response(),
         Err(error) => error.into_response(),
     }
 }
@@ -78,19 +123,17 @@ pub async fn patch_handler(
     Path(room_id): Path<RoomId>,
     Json(room_form): Json<UpdateRoomForm>,
 ) -> Response {
-    if let Some(name) = &room_form.name {
-        return match app_data.room_service.update_room_name(&room_id, &name).await {
-            Ok(_) => responses::mutated_room(room_id).into_response(),
-            Err(error) => error.into_response(),
-        };
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    if room_form.name.is_none() && room_form.description.is_none() && room_form.warehouse_id.is_none() {
+        return ServiceError::PayloadNotValid.into_response();
     }
-    if let Some(desc) = &room_form.description {
-        return match app_data.room_service.update_room_desc(&room_id, &desc).await {
-            Ok(_) => responses::mutated_room(room_id).into_response(),
-            Err(error) => error.into_response(),
-        };
+    match app_data.room_service.patch_room(&user_id, &room_id, &room_form).await {
+        Ok(()) => responses::mutated_room(room_id).into_response(),
+        Err(error) => error.into_response(),
     }
-    ServiceError::PayloadNotValid.into_response()
 }
 
 #[allow(clippy::unused_async)]
@@ -115,7 +158,14 @@ pub async fn get_paged_handler(
     Extract(maybe_bearer_token): Extract,
     Query(criteria): Query<ListingCriteria>,
     Query(paged_conf): Query<PagedConf>,
+    Query(extra_archived): Query<ExtraArchived>,
 ) -> Response {
+    if extra_archived.archived == Some(true) {
+        return match app_data.room_service.get_archived_rooms().await {
+            Ok(rooms) => Json(OkResponseData { data: rooms }).into_response(),
+            Err(error) => error.into_response(),
+        };
+    }
     if let Some(b) = paged_conf.all {
         if b {
             return match app_data.room_service.get_all_rooms().await {
@@ -130,3 +180,59 @@ pub async fn get_paged_handler(
         Err(error) => error.into_response(),
     }
 }
+
+#[allow(clippy::unused_async)]
+pub async fn get_label_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(room_id): Path<RoomId>,
+    Query(criteria): Query<LabelCriteria>,
+) -> Response {
+    match app_data.label_service.room_label(&room_id).await {
+        Ok(label) => labels_response(criteria.format, &[label]),
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Print the labels of all shelves of a room, and of their bins with `bins=true`.
+#[allow(clippy::unused_async)]
+pub async fn get_shelf_labels_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(room_id): Path<RoomId>,
+    Query(criteria): Query<LabelCriteria>,
+) -> Response {
+    match app_data.label_service.room_shelf_labels(&room_id, criteria.bins).await {
+        Ok(labels) => labels_response(criteria.format, &labels),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_categories_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(room_id): Path<RoomId>,
+) -> Response {
+    match app_data.room_service.get_room_categories(&room_id).await {
+        Ok(categories) => Json(OkResponseData { data: categories }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+/// Restrict a room to the items of the categories given, an empty list lifting the restriction.
+#[allow(clippy::unused_async)]
+pub async fn set_categories_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(room_id): Path<RoomId>,
+    Json(category_ids): Json<Vec<CategoryId>>,
+) -> Response {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+    match app_data.room_service.restrict_room(&room_id, &category_ids).await {
+        Ok(()) => responses::mutated_room(room_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
@@ -1,8 +1,9 @@
-use axum::routing::{delete, get};
+use axum::routing::{delete, get, post};
 use axum::Router;
 
 use super::handlers::{
-    add_handler, batch_delete_handler, delete_handler, get_handler, get_paged_handler, patch_handler, update_handler,
+    add_handler, batch_delete_handler, delete_handler, get_categories_handler, get_handler, get_label_handler, get_paged_handler,
+    get_shelf_labels_handler, patch_handler, restore_handler, set_categories_handler, update_handler,
 };
 
 pub fn router() -> Router {
@@ -15,4 +16,8 @@ pub fn router() -> Router {
                 .patch(patch_handler)
                 .get(get_handler),
         )
+        .route("/:id/restore", post(restore_handler))
+        .route("/:id/label", get(get_label_handler))
+        .route("/:id/labels", get(get_shelf_labels_handler))
+        .route("/:id/categories", get(get_categories_handler).put(set_categories_handler))
 }
@@ -1,12 +1,17 @@
 use serde_derive::{Deserialize, Serialize};
 
 use crate::models::room::RoomId;
+use crate::models::shelf::ShelfLimits;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AddShelfForm {
     pub name: String,
     pub layer: i64,
     pub room_id: RoomId,
+    /// Layers of bins the shelf is divided in, 1 when omitted.
+    pub layers: Option<i64>,
+    /// Bins on each layer, 1 when omitted.
+    pub positions: Option<i64>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -14,4 +19,10 @@ pub struct UpdateShelfForm {
     pub name: Option<String>,
     pub layer: Option<i64>,
     pub room_id: Option<RoomId>,
+    /// What the shelf may hold at most, replacing all its limits at once.
+    pub limits: Option<ShelfLimits>,
+    /// Layers of bins to divide the shelf in, along with `positions` or keeping the current ones.
+    pub layers: Option<i64>,
+    /// Bins on each layer, along with `layers` or keeping the current ones.
+    pub positions: Option<i64>,
 }
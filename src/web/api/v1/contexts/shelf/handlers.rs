@@ -4,12 +4,13 @@ use axum::extract::{Extension, Path, Query};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 
-use crate::common::{AppData, ExtraRoomId};
+use crate::common::{AppData, ArchiveCriteria, BatchDelCriteria, ExtraArchived, ExtraRoomId, LabelCriteria};
 use crate::common::{ListingCriteria, PagedConf};
 use crate::errors::ServiceError;
+use crate::models::movement::MovementMeta;
 use crate::models::shelf::ShelfId;
 use crate::web::api::v1::extractors::bearer_token::Extract;
-use crate::web::api::v1::responses::OkResponseData;
+use crate::web::api::v1::responses::{labels_response, OkResponseData};
 
 use super::forms::{AddShelfForm, UpdateShelfForm};
 use super::responses;
@@ -22,7 +23,13 @@ pub async fn add_handler(
 ) -> Response {
     match app_data
         .shelf_service
-        .add_shelf(&shelf_form.name, shelf_form.layer, shelf_form.room_id)
+        .add_shelf(
+            &shelf_form.name,
+            shelf_form.layer,
+            shelf_form.room_id,
+            shelf_form.layers.unwrap_or(1),
+            shelf_form.positions.unwrap_or(1),
+        )
         .await
     {
         Ok(shelf_id) => responses::mutated_shelf(shelf_id).into_response(),
@@ -35,8 +42,37 @@ pub async fn delete_handler(
     Extension(app_data): Extension<Arc<AppData>>,
     Extract(maybe_bearer_token): Extract,
     Path(shelf_id): Path<ShelfId>,
+    Query(criteria): Query<ArchiveCriteria>,
 ) -> Response {
-    match app_data.shelf_service.remove_shelf(&shelf_id).await {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let meta = MovementMeta {
+        user_id,
+        reason: criteria.reason,
+        expected_count: None,
+    };
+    match app_data
+        .shelf_service
+        .remove_shelf(&shelf_id, criteria.shelf_to, criteria.bin_to, &meta)
+        .await
+    {
+        Ok(_) => responses::mutated_shelf(shelf_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn restore_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(shelf_id): Path<ShelfId>,
+) -> Response {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+    match app_data.shelf_service.restore_shelf(&shelf_id).await {
         Ok(_) => responses::mutated_shelf(shelf_id).into_response(),
         Err(error) => error.into_response(),
     }
@@ -46,9 +82,13 @@ pub async fn delete_handler(
 pub async fn batch_delete_handler(
     Extension(app_data): Extension<Arc<AppData>>,
     Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<BatchDelCriteria>,
     Json(ids): Json<Vec<i64>>,
 ) -> Response {
-    match app_data.shelf_service.remove_shelves(&ids).await {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+    match app_data.shelf_service.remove_shelves(&ids, criteria.dry_run).await {
         Ok(res) => Json(res).into_response(),
         Err(error) => error.into_response(),
     }
@@ -66,7 +106,17 @@ pub async fn update_handler(
         .update_shelf(&shelf_id, &shelf_form.name, shelf_form.layer, shelf_form.room_id)
         .await
     {
-        Ok(_) => responses::mutated_shelf(shelf_id).into_response(),
+        Ok(_) if shelf_form.layers.is_none() && shelf_form.positions.is_none() => {
+            responses::mutated_shelf(shelf_id).into_response()
+        }
+        Ok(_) => match app_data
+            .shelf_service
+            .update_shelf_layout(&shelf_id, shelf_form.layers, shelf_form.positions)
+            .await
+        {
+            Ok(()) => responses::mutated_shelf(shelf_id).into_response(),
+            Err(error) => error.into_response(),
+        },
         Err(error) => error.into_response(),
     }
 }
@@ -96,6 +146,22 @@ pub async fn patch_handler(
             Err(error) => error.into_response(),
         };
     }
+    if let Some(limits) = &shelf_form.limits {
+        return match app_data.shelf_service.update_shelf_limits(&shelf_id, limits).await {
+            Ok(()) => responses::mutated_shelf(shelf_id).into_response(),
+            Err(error) => error.into_response(),
+        };
+    }
+    if shelf_form.layers.is_some() || shelf_form.positions.is_some() {
+        return match app_data
+            .shelf_service
+            .update_shelf_layout(&shelf_id, shelf_form.layers, shelf_form.positions)
+            .await
+        {
+            Ok(()) => responses::mutated_shelf(shelf_id).into_response(),
+            Err(error) => error.into_response(),
+        };
+    }
     ServiceError::PayloadNotValid.into_response()
 }
 
@@ -118,7 +184,14 @@ pub async fn get_paged_handler(
     Query(criteria): Query<ListingCriteria>,
     Query(extra_room): Query<ExtraRoomId>,
     Query(paged_conf): Query<PagedConf>,
+    Query(extra_archived): Query<ExtraArchived>,
 ) -> Response {
+    if extra_archived.archived == Some(true) {
+        return match app_data.shelf_service.get_archived_shelves(extra_room.room_id).await {
+            Ok(shelves) => Json(OkResponseData { data: shelves }).into_response(),
+            Err(error) => error.into_response(),
+        };
+    }
     if let Some(b) = paged_conf.all {
         if b {
             return match app_data.shelf_service.get_all_shelves(extra_room.room_id).await {
@@ -133,3 +206,64 @@ pub async fn get_paged_handler(
         Err(error) => error.into_response(),
     }
 }
+
+#[allow(clippy::unused_async)]
+pub async fn get_capacity_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(shelf_id): Path<ShelfId>,
+) -> Response {
+    match app_data.shelf_service.get_shelf_capacity(&shelf_id).await {
+        Ok(capacity) => Json(OkResponseData { data: capacity }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_capacities_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(extra_room): Query<ExtraRoomId>,
+) -> Response {
+    match app_data.shelf_service.get_shelf_capacities(extra_room.room_id).await {
+        Ok(capacities) => Json(OkResponseData { data: capacities }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_bins_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(shelf_id): Path<ShelfId>,
+) -> Response {
+    match app_data.shelf_service.get_bins(&shelf_id).await {
+        Ok(bins) => Json(OkResponseData { data: bins }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn resolve_bin_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(address): Path<String>,
+) -> Response {
+    match app_data.shelf_service.resolve_bin(&address).await {
+        Ok(bin) => Json(OkResponseData { data: bin }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_label_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(shelf_id): Path<ShelfId>,
+    Query(criteria): Query<LabelCriteria>,
+) -> Response {
+    match app_data.label_service.shelf_labels(&shelf_id, criteria.bins).await {
+        Ok(labels) => labels_response(criteria.format, &labels),
+        Err(error) => error.into_response(),
+    }
+}
@@ -1,13 +1,16 @@
-use axum::routing::{delete, get};
+use axum::routing::{delete, get, post};
 use axum::Router;
 
 use super::handlers::{
-    add_handler, batch_delete_handler, delete_handler, get_handler, get_paged_handler, patch_handler, update_handler,
+    add_handler, batch_delete_handler, delete_handler, get_bins_handler, get_capacities_handler, get_capacity_handler,
+    get_handler, get_label_handler, get_paged_handler, patch_handler, resolve_bin_handler, restore_handler, update_handler,
 };
 
 pub fn router() -> Router {
     Router::new()
         .route("/", get(get_paged_handler).post(add_handler).delete(batch_delete_handler))
+        .route("/capacity", get(get_capacities_handler))
+        .route("/bins/:address", get(resolve_bin_handler))
         .route(
             "/:id",
             delete(delete_handler)
@@ -15,4 +18,8 @@ pub fn router() -> Router {
                 .patch(patch_handler)
                 .get(get_handler),
         )
+        .route("/:id/capacity", get(get_capacity_handler))
+        .route("/:id/bins", get(get_bins_handler))
+        .route("/:id/restore", post(restore_handler))
+        .route("/:id/label", get(get_label_handler))
 }
@@ -1,13 +1,48 @@
 use serde_derive::{Deserialize, Serialize};
+use time::serde::iso8601;
+use time::{Date, OffsetDateTime};
 
+use crate::models::batch::BatchMode;
+use crate::models::bin::BinId;
+use crate::models::bom::BomComponent;
 use crate::models::item::{ItemId, ItemXShelf};
+use crate::models::room::RoomId;
 use crate::models::shelf::ShelfId;
+use crate::models::status::StockStatus;
+use crate::models::stocktake::StocktakeCount;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ItemOnShelfForm {
     pub item_id: ItemId,
     pub shelf_id: ShelfId,
+    /// Bin of the shelf to deposit into or withdraw from. Deposits go into the first bin of the
+    /// shelf and withdrawals draw from any bin when omitted.
+    pub bin_id: Option<BinId>,
     pub count: i64,
+    /// Unit of `count`, the item's base unit when omitted.
+    pub unit: Option<String>,
+    /// Lot to deposit into or withdraw from. Withdrawing without a lot picks
+    /// the lots first-expired-first-out.
+    pub lot_no: Option<String>,
+    /// Only used by deposits.
+    pub mfg_date: Option<Date>,
+    /// Only used by deposits.
+    pub expiry_date: Option<Date>,
+    /// One serial per unit, required for serialized items.
+    #[serde(default)]
+    pub serials: Vec<String>,
+    /// Status to deposit into or withdraw from, `available` when omitted.
+    #[serde(default)]
+    pub status: StockStatus,
+    /// Only used by deposits, what one `unit` cost in minor units of `currency`.
+    pub unit_cost: Option<i64>,
+    /// Only used by deposits, required along with `unit_cost`.
+    pub currency: Option<String>,
+    /// Why the stock is moved, kept in the movement history.
+    pub reason: Option<String>,
+    /// Items of the item the shelf is known to hold in the base unit. The request fails with a
+    /// conflict when the shelf holds another count by then.
+    pub expected_count: Option<i64>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -15,11 +50,169 @@ pub struct TransferItemForm {
     pub item_id: ItemId,
     pub shelf_from: ShelfId,
     pub shelf_to: ShelfId,
+    /// Bin of `shelf_from` to move from, any bin when omitted.
+    pub bin_from: Option<BinId>,
+    /// Bin of `shelf_to` to move to, its first bin when omitted.
+    pub bin_to: Option<BinId>,
     pub count: i64,
+    /// Unit of `count`, the item's base unit when omitted.
+    pub unit: Option<String>,
+    /// Lot to move, first-expired-first-out when omitted.
+    pub lot_no: Option<String>,
+    /// One serial per unit, required for serialized items.
+    #[serde(default)]
+    pub serials: Vec<String>,
+    /// Status of the stock to move, kept on arrival. `available` when omitted.
+    #[serde(default)]
+    pub status: StockStatus,
+    pub reason: Option<String>,
+    /// Items of the item `shelf_from` is known to hold in the base unit, see `ItemOnShelfForm`.
+    pub expected_count: Option<i64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StockStatusForm {
+    pub item_id: ItemId,
+    pub shelf_id: ShelfId,
+    /// Bin of the shelf to change the stock in, any bin when omitted. The stock stays in its bin.
+    pub bin_id: Option<BinId>,
+    pub count: i64,
+    /// Unit of `count`, the item's base unit when omitted.
+    pub unit: Option<String>,
+    /// Lot to change, first-expired-first-out when omitted.
+    pub lot_no: Option<String>,
+    /// One serial per unit, required for serialized items.
+    #[serde(default)]
+    pub serials: Vec<String>,
+    /// Status the stock is in now, `available` when omitted.
+    #[serde(default)]
+    pub from: StockStatus,
+    pub to: StockStatus,
+    /// Why the status changes, kept in the movement history.
+    pub reason: Option<String>,
+    /// Items of the item the shelf is known to hold in the base unit, see `ItemOnShelfForm`.
+    pub expected_count: Option<i64>,
+}
+
+/// A line of a batch, `op` naming what it does.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum StockLineForm {
+    Deposit(ItemOnShelfForm),
+    Withdraw(ItemOnShelfForm),
+    Transfer(TransferItemForm),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StockBatchForm {
+    /// `all_or_nothing` when omitted.
+    #[serde(default)]
+    pub mode: BatchMode,
+    pub lines: Vec<StockLineForm>,
+    /// Why the stock is moved, for the lines without a reason of their own.
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReverseForm {
+    /// Why the operation is undone, kept in the movement history.
+    pub reason: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ConvertItemForm {
     pub from: Vec<ItemXShelf>,
     pub into: Vec<ItemXShelf>,
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AddReservationForm {
+    pub item_id: ItemId,
+    /// Hold on this shelf, or else on `room_id`.
+    pub shelf_id: Option<ShelfId>,
+    pub room_id: Option<RoomId>,
+    pub count: i64,
+    pub note: Option<String>,
+    #[serde(default, with = "iso8601::option")]
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReorderPointForm {
+    pub item_id: ItemId,
+    /// Levels of the item in this room, or across every room when omitted.
+    pub room_id: Option<RoomId>,
+    pub min_level: i64,
+    pub max_level: i64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OpenStocktakeForm {
+    /// Count every shelf of the room, or else the shelves listed.
+    pub room_id: Option<RoomId>,
+    #[serde(default)]
+    pub shelf_ids: Vec<ShelfId>,
+    /// Refuse stock moves on the counted shelves until the session is closed.
+    #[serde(default)]
+    pub freeze: bool,
+    /// Hide expected quantities from counters.
+    #[serde(default)]
+    pub blind: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StocktakeCountsForm {
+    pub counts: Vec<StocktakeCount>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BomForm {
+    pub output_item_id: ItemId,
+    /// How many of the output one assembly yields.
+    pub output_count: i64,
+    pub components: Vec<BomComponent>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AssembleForm {
+    /// How many times to run the recipe.
+    pub quantity: i64,
+    /// Where the output goes.
+    pub shelf_id: ShelfId,
+    /// Where to take components from, picked automatically for components not listed.
+    #[serde(default)]
+    pub from: Vec<ItemXShelf>,
+    /// One serial per unit of output, required for serialized items.
+    #[serde(default)]
+    pub serials: Vec<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DisassembleForm {
+    /// How many times to run the recipe backwards.
+    pub quantity: i64,
+    /// Where the components go.
+    pub shelf_id: ShelfId,
+    /// Where to take the output from, picked automatically when empty.
+    #[serde(default)]
+    pub from: Vec<ItemXShelf>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StockPolicyForm {
+    /// The room the policy applies to, every room without a policy of its own when not given.
+    pub room_id: Option<RoomId>,
+    #[serde(default)]
+    pub allow_empty: bool,
+    #[serde(default)]
+    pub keep_empty: bool,
+    #[serde(default)]
+    pub allow_negative: bool,
+    /// Let deposits, transfers and conversions fill shelves past their capacity with a warning.
+    #[serde(default)]
+    pub warn_over_capacity: bool,
 }
@@ -4,21 +4,51 @@ use axum::extract::{Path, Query};
 use axum::response::{IntoResponse, Response};
 use axum::{Extension, Json};
 
-use crate::common::{AppData, ListingCriteria};
+use crate::common::{
+    AppData, DeadStockCriteria, ExpiryCriteria, ExtraAsOf, ExtraItemId, ExtraRoomId, ExtraUnit, ExtraWarehouseId,
+    ListingCriteria, MovementCriteria, PutawayCriteria, ReservationCriteria, ValuationCriteria,
+};
+use crate::errors::ServiceError;
+use crate::models::batch::{StockLine, StockOp};
+use crate::models::bom::{BomId, NewBom};
+use crate::models::item::{ItemId, ItemXShelf, Lot, StockPick};
+use crate::models::movement::MovementMeta;
+use crate::models::policy::StockPolicy;
+use crate::models::reorder::ReorderPointId;
+use crate::models::reservation::{NewReservation, ReservationId};
 use crate::models::room::RoomId;
 use crate::models::shelf::ShelfId;
+use crate::models::stocktake::StocktakeId;
+use crate::models::valuation::UnitCost;
+use crate::models::warehouse::WarehouseId;
 use crate::web::api::v1::extractors::bearer_token::Extract;
 use crate::web::api::v1::responses::OkResponseData;
 
-use super::forms::{ConvertItemForm, ItemOnShelfForm, TransferItemForm};
+use super::forms::{
+    AddReservationForm, AssembleForm, BomForm, ConvertItemForm, DisassembleForm, ItemOnShelfForm, OpenStocktakeForm,
+    ReorderPointForm, ReverseForm, StockBatchForm, StockLineForm, StockPolicyForm, StockStatusForm, StocktakeCountsForm,
+    TransferItemForm,
+};
+use super::responses;
 
 #[allow(clippy::unused_async)]
 pub async fn get_items_on_shelves_handler(
     Extension(app_data): Extension<Arc<AppData>>,
     Extract(maybe_bearer_token): Extract,
     Query(criteria): Query<ListingCriteria>,
+    Query(extra): Query<ExtraUnit>,
+    Query(past): Query<ExtraAsOf>,
+    Query(site): Query<ExtraWarehouseId>,
 ) -> Response {
-    todo!("todo")
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data
+        .stock_service
+        .get_items_on_shelves(&spec, site.warehouse_id, extra.unit.as_deref(), past.as_of)
+        .await
+    {
+        Ok(items) => Json(OkResponseData { data: items }).into_response(),
+        Err(error) => error.into_response(),
+    }
 }
 
 #[allow(clippy::unused_async)]
@@ -27,8 +57,30 @@ pub async fn get_items_on_shelf_handler(
     Extract(maybe_bearer_token): Extract,
     Path(shelf_id): Path<ShelfId>,
     Query(criteria): Query<ListingCriteria>,
+    Query(extra): Query<ExtraUnit>,
+    Query(past): Query<ExtraAsOf>,
+) -> Response {
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data
+        .stock_service
+        .get_items_on_shelf(&spec, shelf_id, extra.unit.as_deref(), past.as_of)
+        .await
+    {
+        Ok(items) => Json(OkResponseData { data: items }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_items_in_bins_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(shelf_id): Path<ShelfId>,
 ) -> Response {
-    todo!("todo")
+    match app_data.stock_service.get_items_in_bins(shelf_id).await {
+        Ok(items) => Json(OkResponseData { data: items }).into_response(),
+        Err(error) => error.into_response(),
+    }
 }
 
 #[allow(clippy::unused_async)]
@@ -36,8 +88,19 @@ pub async fn get_items_in_rooms_handler(
     Extension(app_data): Extension<Arc<AppData>>,
     Extract(maybe_bearer_token): Extract,
     Query(criteria): Query<ListingCriteria>,
+    Query(extra): Query<ExtraUnit>,
+    Query(past): Query<ExtraAsOf>,
+    Query(site): Query<ExtraWarehouseId>,
 ) -> Response {
-    todo!("todo")
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data
+        .stock_service
+        .get_items_in_rooms(&spec, site.warehouse_id, extra.unit.as_deref(), past.as_of)
+        .await
+    {
+        Ok(items) => Json(OkResponseData { data: items }).into_response(),
+        Err(error) => error.into_response(),
+    }
 }
 
 #[allow(clippy::unused_async)]
@@ -46,8 +109,57 @@ pub async fn get_items_in_room_handler(
     Extract(maybe_bearer_token): Extract,
     Path(room_id): Path<RoomId>,
     Query(criteria): Query<ListingCriteria>,
+    Query(extra): Query<ExtraUnit>,
+    Query(past): Query<ExtraAsOf>,
 ) -> Response {
-    todo!("todo")
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data
+        .stock_service
+        .get_items_in_room(&spec, room_id, extra.unit.as_deref(), past.as_of)
+        .await
+    {
+        Ok(items) => Json(OkResponseData { data: items }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_items_in_warehouses_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<ListingCriteria>,
+    Query(extra): Query<ExtraUnit>,
+    Query(past): Query<ExtraAsOf>,
+) -> Response {
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data
+        .stock_service
+        .get_items_in_warehouses(&spec, None, extra.unit.as_deref(), past.as_of)
+        .await
+    {
+        Ok(items) => Json(OkResponseData { data: items }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_items_in_warehouse_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(warehouse_id): Path<WarehouseId>,
+    Query(criteria): Query<ListingCriteria>,
+    Query(extra): Query<ExtraUnit>,
+    Query(past): Query<ExtraAsOf>,
+) -> Response {
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data
+        .stock_service
+        .get_items_in_warehouses(&spec, Some(warehouse_id), extra.unit.as_deref(), past.as_of)
+        .await
+    {
+        Ok(items) => Json(OkResponseData { data: items }).into_response(),
+        Err(error) => error.into_response(),
+    }
 }
 
 #[allow(clippy::unused_async)]
@@ -56,12 +168,39 @@ pub async fn transfer_handler(
     Extract(maybe_bearer_token): Extract,
     Json(item_form): Json<TransferItemForm>,
 ) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let meta = MovementMeta {
+        user_id,
+        reason: item_form.reason,
+        expected_count: item_form.expected_count,
+    };
+    let pick = StockPick {
+        lot_no: item_form.lot_no,
+        serials: item_form.serials,
+        status: item_form.status,
+        bin_id: item_form.bin_from,
+    };
     match app_data
         .stock_service
-        .transfer_item(&item_form.item_id, item_form.count, item_form.shelf_from, item_form.shelf_to)
+        .transfer_item(
+            &item_form.item_id,
+            item_form.count,
+            item_form.unit.as_deref(),
+            item_form.shelf_from,
+            item_form.shelf_to,
+            item_form.bin_to,
+            &pick,
+            &meta,
+        )
         .await
     {
-        Ok(_) => Json(OkResponseData { data: "todo" }).into_response(),
+        Ok(stock) => {
+            let over_capacity = app_data.stock_service.over_capacity([item_form.shelf_to]).await;
+            responses::stocked(stock, over_capacity).into_response()
+        }
         Err(error) => error.into_response(),
     }
 }
@@ -72,12 +211,34 @@ pub async fn withdraw_handler(
     Extract(maybe_bearer_token): Extract,
     Json(item_form): Json<ItemOnShelfForm>,
 ) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let meta = MovementMeta {
+        user_id,
+        reason: item_form.reason,
+        expected_count: item_form.expected_count,
+    };
+    let pick = StockPick {
+        lot_no: item_form.lot_no,
+        serials: item_form.serials,
+        status: item_form.status,
+        bin_id: item_form.bin_id,
+    };
     match app_data
         .stock_service
-        .withdraw_item(&item_form.item_id, item_form.count, item_form.shelf_id)
+        .withdraw_item(
+            &item_form.item_id,
+            item_form.count,
+            item_form.unit.as_deref(),
+            item_form.shelf_id,
+            &pick,
+            &meta,
+        )
         .await
     {
-        Ok(_) => Json(OkResponseData { data: "todo" }).into_response(),
+        Ok(stocks) => responses::mutated_stocks(stocks).into_response(),
         Err(error) => error.into_response(),
     }
 }
@@ -88,12 +249,169 @@ pub async fn deposit_handler(
     Extract(maybe_bearer_token): Extract,
     Json(item_form): Json<ItemOnShelfForm>,
 ) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let meta = MovementMeta {
+        user_id,
+        reason: item_form.reason,
+        expected_count: item_form.expected_count,
+    };
+    let lot = Lot {
+        lot_no: item_form.lot_no,
+        mfg_date: item_form.mfg_date,
+        expiry_date: item_form.expiry_date,
+    };
+    let unit_cost = match (item_form.unit_cost, item_form.currency) {
+        (Some(unit_cost), Some(currency)) => Some(UnitCost { unit_cost, currency }),
+        (None, None) => None,
+        _ => return ServiceError::CostNotValid.into_response(),
+    };
     match app_data
         .stock_service
-        .deposit_item(&item_form.item_id, item_form.count, item_form.shelf_id)
+        .deposit_item(
+            &item_form.item_id,
+            item_form.count,
+            item_form.unit.as_deref(),
+            item_form.shelf_id,
+            item_form.bin_id,
+            &lot,
+            item_form.status,
+            &item_form.serials,
+            unit_cost.as_ref(),
+            &meta,
+        )
         .await
     {
-        Ok(_) => Json(OkResponseData { data: "todo" }).into_response(),
+        Ok(stock) => {
+            let over_capacity = app_data.stock_service.over_capacity([stock.shelf_id]).await;
+            responses::stocked(stock, over_capacity).into_response()
+        }
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn batch_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Json(batch_form): Json<StockBatchForm>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let meta = MovementMeta {
+        user_id,
+        reason: batch_form.reason,
+        expected_count: None,
+    };
+    let lines = match batch_form.lines.into_iter().map(stock_line).collect::<Result<Vec<_>, _>>() {
+        Ok(lines) => lines,
+        Err(error) => return error.into_response(),
+    };
+    let shelves_to: Vec<Option<ShelfId>> = lines.iter().map(|x| x.op.shelf_to()).collect();
+    match app_data.stock_service.apply_batch(lines, batch_form.mode, &meta).await {
+        Ok(outcome) => {
+            // only the lines kept put stock anywhere
+            let kept = shelves_to
+                .into_iter()
+                .zip(&outcome.lines)
+                .filter(|(_, line)| outcome.committed && line.is_ok())
+                .filter_map(|(shelf_id, _)| shelf_id);
+            let over_capacity = app_data.stock_service.over_capacity(kept).await;
+            responses::batch(outcome, over_capacity)
+        }
+        Err(error) => error.into_response(),
+    }
+}
+
+/// The stock operation a line of a batch asks for.
+fn stock_line(form: StockLineForm) -> Result<StockLine, ServiceError> {
+    match form {
+        StockLineForm::Deposit(item_form) => {
+            let unit_cost = match (item_form.unit_cost, item_form.currency) {
+                (Some(unit_cost), Some(currency)) => Some(UnitCost { unit_cost, currency }),
+                (None, None) => None,
+                _ => return Err(ServiceError::CostNotValid),
+            };
+            let lot = Lot {
+                lot_no: item_form.lot_no,
+                mfg_date: item_form.mfg_date,
+                expiry_date: item_form.expiry_date,
+            };
+            let line = ItemXShelf {
+                status: item_form.status,
+                bin_id: item_form.bin_id,
+                serials: item_form.serials,
+                ..ItemXShelf::new(item_form.item_id, item_form.shelf_id, item_form.count, &lot)
+            };
+            Ok(StockLine {
+                op: StockOp::Deposit(line),
+                unit: item_form.unit,
+                unit_cost,
+                reason: item_form.reason,
+                expected_count: item_form.expected_count,
+            })
+        }
+        StockLineForm::Withdraw(item_form) => {
+            let pick = StockPick {
+                lot_no: item_form.lot_no,
+                serials: item_form.serials,
+                status: item_form.status,
+                bin_id: item_form.bin_id,
+            };
+            let line = ItemXShelf::picked(item_form.item_id, item_form.shelf_id, item_form.count, &pick);
+            Ok(StockLine {
+                op: StockOp::Withdraw(line),
+                unit: item_form.unit,
+                unit_cost: None,
+                reason: item_form.reason,
+                expected_count: item_form.expected_count,
+            })
+        }
+        StockLineForm::Transfer(item_form) => {
+            let pick = StockPick {
+                lot_no: item_form.lot_no,
+                serials: item_form.serials,
+                status: item_form.status,
+                bin_id: item_form.bin_from,
+            };
+            let line = ItemXShelf::picked(item_form.item_id, item_form.shelf_from, item_form.count, &pick);
+            Ok(StockLine {
+                op: StockOp::Transfer {
+                    line,
+                    shelf_to: item_form.shelf_to,
+                    bin_to: item_form.bin_to,
+                },
+                unit: item_form.unit,
+                unit_cost: None,
+                reason: item_form.reason,
+                expected_count: item_form.expected_count,
+            })
+        }
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn reverse_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(correlation_id): Path<String>,
+    Json(reverse_form): Json<ReverseForm>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let meta = MovementMeta {
+        user_id,
+        reason: Some(reverse_form.reason),
+        expected_count: None,
+    };
+    match app_data.stock_service.reverse_operation(&correlation_id, &meta).await {
+        Ok(stocks) => responses::mutated_stocks(stocks).into_response(),
         Err(error) => error.into_response(),
     }
 }
@@ -104,8 +422,663 @@ pub async fn convert_handler(
     Extract(maybe_bearer_token): Extract,
     Json(item_form): Json<ConvertItemForm>,
 ) -> Response {
-    match app_data.stock_service.convert_item(item_form.from, item_form.into).await {
-        Ok(_) => Json(OkResponseData { data: "todo" }).into_response(),
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let meta = MovementMeta {
+        user_id,
+        reason: item_form.reason,
+        expected_count: None,
+    };
+    let shelves_to: Vec<ShelfId> = item_form.into.iter().map(|x| x.shelf_id).collect();
+    match app_data
+        .stock_service
+        .convert_item(item_form.from, item_form.into, &meta)
+        .await
+    {
+        Ok(stock) => {
+            let over_capacity = app_data.stock_service.over_capacity(shelves_to).await;
+            responses::stocked(stock, over_capacity).into_response()
+        }
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn change_status_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Json(status_form): Json<StockStatusForm>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let meta = MovementMeta {
+        user_id,
+        reason: status_form.reason,
+        expected_count: status_form.expected_count,
+    };
+    let pick = StockPick {
+        lot_no: status_form.lot_no,
+        serials: status_form.serials,
+        status: status_form.from,
+        bin_id: status_form.bin_id,
+    };
+    match app_data
+        .stock_service
+        .change_status(
+            &status_form.item_id,
+            status_form.count,
+            status_form.unit.as_deref(),
+            status_form.shelf_id,
+            &pick,
+            status_form.to,
+            &meta,
+        )
+        .await
+    {
+        Ok(stocks) => responses::mutated_stocks(stocks).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_lots_expiring_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<ListingCriteria>,
+    Query(expiry_criteria): Query<ExpiryCriteria>,
+) -> Response {
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data.stock_service.get_lots_expiring(&spec, &expiry_criteria).await {
+        Ok(lots) => Json(OkResponseData { data: lots }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_stock_aging_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(extra): Query<ExtraRoomId>,
+    Query(site): Query<ExtraWarehouseId>,
+) -> Response {
+    match app_data.stock_service.get_stock_aging(extra.room_id, site.warehouse_id).await {
+        Ok(lines) => Json(OkResponseData { data: lines }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_dead_stock_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<DeadStockCriteria>,
+) -> Response {
+    match app_data.stock_service.get_dead_stock(&criteria).await {
+        Ok(items) => Json(OkResponseData { data: items }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn classify_handler(Extension(app_data): Extension<Arc<AppData>>, Extract(maybe_bearer_token): Extract) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data.stock_service.reclassify_items(&user_id).await {
+        Ok(classes) => Json(OkResponseData { data: classes }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_putaway_suggestions_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<PutawayCriteria>,
+) -> Response {
+    match app_data.stock_service.suggest_putaway(&criteria).await {
+        Ok(suggestions) => Json(OkResponseData { data: suggestions }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_valuation_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<ValuationCriteria>,
+) -> Response {
+    match app_data.stock_service.get_valuation(&criteria).await {
+        Ok(lines) => Json(OkResponseData { data: lines }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_unit_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path((item_id, serial)): Path<(ItemId, String)>,
+) -> Response {
+    match app_data.stock_service.get_unit_history(item_id, &serial).await {
+        Ok(unit) => Json(OkResponseData { data: unit }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_movements_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<ListingCriteria>,
+    Query(movement_criteria): Query<MovementCriteria>,
+) -> Response {
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data.stock_service.get_movements(&spec, &movement_criteria).await {
+        Ok(movements) => Json(OkResponseData { data: movements }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn reserve_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Json(reservation_form): Json<AddReservationForm>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let reservation = NewReservation {
+        item_id: reservation_form.item_id,
+        shelf_id: reservation_form.shelf_id,
+        room_id: reservation_form.room_id,
+        count: reservation_form.count,
+        user_id,
+        note: reservation_form.note,
+        expires_at: reservation_form.expires_at,
+    };
+    match app_data.stock_service.reserve_item(&reservation).await {
+        Ok(reservation_id) => responses::mutated_reservation(reservation_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_reservations_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<ListingCriteria>,
+    Query(reservation_criteria): Query<ReservationCriteria>,
+) -> Response {
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data.stock_service.get_reservations(&spec, &reservation_criteria).await {
+        Ok(reservations) => Json(OkResponseData { data: reservations }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_reservation_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(reservation_id): Path<ReservationId>,
+) -> Response {
+    match app_data.stock_service.get_reservation(reservation_id).await {
+        Ok(reservation) => Json(OkResponseData { data: reservation }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn release_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(reservation_id): Path<ReservationId>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data.stock_service.release_reservation(reservation_id, &user_id).await {
+        Ok(()) => responses::mutated_reservation(reservation_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn set_reorder_point_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Json(reorder_point_form): Json<ReorderPointForm>,
+) -> Response {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+    match app_data
+        .reorder_service
+        .set_reorder_point(
+            reorder_point_form.item_id,
+            reorder_point_form.room_id,
+            reorder_point_form.min_level,
+            reorder_point_form.max_level,
+        )
+        .await
+    {
+        Ok(reorder_point_id) => responses::mutated_reorder_point(reorder_point_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_reorder_points_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<ListingCriteria>,
+    Query(extra): Query<ExtraItemId>,
+) -> Response {
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data.reorder_service.get_reorder_points(&spec, extra.item_id).await {
+        Ok(reorder_points) => Json(OkResponseData { data: reorder_points }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn delete_reorder_point_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(reorder_point_id): Path<ReorderPointId>,
+) -> Response {
+    if let Err(error) = app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        return error.into_response();
+    }
+    match app_data.reorder_service.remove_reorder_point(reorder_point_id).await {
+        Ok(()) => responses::mutated_reorder_point(reorder_point_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_below_reorder_point_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<ListingCriteria>,
+    Query(extra): Query<ExtraRoomId>,
+) -> Response {
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data.reorder_service.get_below_reorder_point(&spec, extra.room_id).await {
+        Ok(levels) => Json(OkResponseData { data: levels }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn subscribe_alerts_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data.reorder_service.subscribe(&user_id).await {
+        Ok(()) => Json(OkResponseData {
+            data: "Subscribed to low stock alerts.".to_string(),
+        })
+        .into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn unsubscribe_alerts_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data.reorder_service.unsubscribe(&user_id).await {
+        Ok(()) => Json(OkResponseData {
+            data: "Unsubscribed from low stock alerts.".to_string(),
+        })
+        .into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn open_stocktake_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Json(stocktake_form): Json<OpenStocktakeForm>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data
+        .stocktake_service
+        .open_stocktake(
+            stocktake_form.room_id,
+            stocktake_form.shelf_ids,
+            stocktake_form.freeze,
+            stocktake_form.blind,
+            &user_id,
+        )
+        .await
+    {
+        Ok(stocktake_id) => responses::mutated_stocktake(stocktake_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_stocktakes_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<ListingCriteria>,
+) -> Response {
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data.stocktake_service.get_stocktakes(&spec).await {
+        Ok(stocktakes) => Json(OkResponseData { data: stocktakes }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_stocktake_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(stocktake_id): Path<StocktakeId>,
+) -> Response {
+    match app_data.stocktake_service.get_stocktake(stocktake_id).await {
+        Ok(stocktake) => Json(OkResponseData { data: stocktake }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_stocktake_lines_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(stocktake_id): Path<StocktakeId>,
+    Query(criteria): Query<ListingCriteria>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data
+        .stocktake_service
+        .get_stocktake_lines(&spec, stocktake_id, &user_id)
+        .await
+    {
+        Ok(lines) => Json(OkResponseData { data: lines }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn count_stocktake_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(stocktake_id): Path<StocktakeId>,
+    Json(counts_form): Json<StocktakeCountsForm>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data
+        .stocktake_service
+        .submit_counts(stocktake_id, &counts_form.counts, &user_id)
+        .await
+    {
+        Ok(()) => responses::mutated_stocktake(stocktake_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn approve_stocktake_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(stocktake_id): Path<StocktakeId>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data.stocktake_service.approve_stocktake(stocktake_id, &user_id).await {
+        Ok(stocks) => responses::mutated_stocks(stocks).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn cancel_stocktake_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(stocktake_id): Path<StocktakeId>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data.stocktake_service.cancel_stocktake(stocktake_id, &user_id).await {
+        Ok(()) => responses::mutated_stocktake(stocktake_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn add_bom_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Json(bom_form): Json<BomForm>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let bom = NewBom {
+        output_item_id: bom_form.output_item_id,
+        output_count: bom_form.output_count,
+        components: bom_form.components,
+        note: bom_form.note,
+        user_id,
+    };
+    match app_data.bom_service.add_bom(&bom).await {
+        Ok(bom_id) => responses::mutated_bom(bom_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_boms_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<ListingCriteria>,
+    Query(extra): Query<ExtraItemId>,
+) -> Response {
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data.bom_service.get_boms(&spec, extra.item_id).await {
+        Ok(boms) => Json(OkResponseData { data: boms }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_bom_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(bom_id): Path<BomId>,
+) -> Response {
+    match app_data.bom_service.get_bom(bom_id).await {
+        Ok(bom) => Json(OkResponseData { data: bom }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn assemble_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(bom_id): Path<BomId>,
+    Json(assemble_form): Json<AssembleForm>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let meta = MovementMeta {
+        user_id,
+        reason: assemble_form.reason,
+        expected_count: None,
+    };
+    match app_data
+        .bom_service
+        .assemble(
+            bom_id,
+            assemble_form.quantity,
+            assemble_form.shelf_id,
+            assemble_form.from,
+            assemble_form.serials,
+            &meta,
+        )
+        .await
+    {
+        Ok(assembly) => responses::assembly(assembly),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn disassemble_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(bom_id): Path<BomId>,
+    Json(disassemble_form): Json<DisassembleForm>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let meta = MovementMeta {
+        user_id,
+        reason: disassemble_form.reason,
+        expected_count: None,
+    };
+    match app_data
+        .bom_service
+        .disassemble(
+            bom_id,
+            disassemble_form.quantity,
+            disassemble_form.shelf_id,
+            disassemble_form.from,
+            &meta,
+        )
+        .await
+    {
+        Ok(assembly) => responses::assembly(assembly),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_stock_policies_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+) -> Response {
+    match app_data.stock_service.get_stock_policies().await {
+        Ok(policies) => Json(OkResponseData { data: policies }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn set_stock_policy_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Json(stock_policy_form): Json<StockPolicyForm>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    let policy = StockPolicy {
+        room_id: stock_policy_form.room_id,
+        allow_empty: stock_policy_form.allow_empty,
+        keep_empty: stock_policy_form.keep_empty,
+        allow_negative: stock_policy_form.allow_negative,
+        warn_over_capacity: stock_policy_form.warn_over_capacity,
+    };
+    match app_data.stock_service.set_stock_policy(&policy, &user_id).await {
+        Ok(()) => Json(OkResponseData { data: policy }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn delete_stock_policy_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(extra): Query<ExtraRoomId>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data.stock_service.remove_stock_policy(extra.room_id, &user_id).await {
+        Ok(()) => Json(OkResponseData {
+            data: "Stock policy removed.".to_string(),
+        })
+        .into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_flagged_stocks_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Query(criteria): Query<ListingCriteria>,
+    Query(extra): Query<ExtraRoomId>,
+    Query(site): Query<ExtraWarehouseId>,
+) -> Response {
+    let spec = app_data.cfg.spec_from_criteria(&criteria).await;
+    match app_data
+        .stock_service
+        .get_flagged_stocks(&spec, extra.room_id, site.warehouse_id)
+        .await
+    {
+        Ok(flagged) => Json(OkResponseData { data: flagged }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn clear_stock_flags_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path((item_id, shelf_id)): Path<(ItemId, ShelfId)>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data.stock_service.clear_stock_flags(item_id, shelf_id, &user_id).await {
+        Ok(cleared) => Json(OkResponseData { data: cleared }).into_response(),
         Err(error) => error.into_response(),
     }
 }
@@ -0,0 +1,132 @@
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+
+use crate::errors::{http_status_code_for_service_error, ServiceError};
+use crate::models::bom::{Assembly, BomId, Shortage};
+use crate::models::item::ItemXShelf;
+use crate::models::reorder::ReorderPointId;
+use crate::models::reservation::ReservationId;
+use crate::models::shelf::ShelfCapacity;
+use crate::models::stocktake::StocktakeId;
+use crate::services::stock::BatchOutcome;
+use crate::web::api::v1::responses::OkResponseData;
+
+pub fn mutated_stocks(stocks: Vec<ItemXShelf>) -> Json<OkResponseData<Vec<ItemXShelf>>> {
+    Json(OkResponseData { data: stocks })
+}
+
+#[derive(Serialize, Debug)]
+pub struct StockedResponseData<T> {
+    pub data: T,
+    /// shelves the stock went to that now hold more than their capacity allows
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<ShelfCapacity>,
+}
+
+/// Stock put on shelves, with a warning for every one of them left past its capacity.
+pub fn stocked<T: Serialize>(data: T, over_capacity: Vec<ShelfCapacity>) -> Json<StockedResponseData<T>> {
+    Json(StockedResponseData {
+        data,
+        warnings: over_capacity,
+    })
+}
+
+pub fn mutated_reservation(reservation_id: ReservationId) -> Json<OkResponseData<ReservationId>> {
+    Json(OkResponseData { data: reservation_id })
+}
+
+pub fn mutated_reorder_point(reorder_point_id: ReorderPointId) -> Json<OkResponseData<ReorderPointId>> {
+    Json(OkResponseData { data: reorder_point_id })
+}
+
+pub fn mutated_stocktake(stocktake_id: StocktakeId) -> Json<OkResponseData<StocktakeId>> {
+    Json(OkResponseData { data: stocktake_id })
+}
+
+pub fn mutated_bom(bom_id: BomId) -> Json<OkResponseData<BomId>> {
+    Json(OkResponseData { data: bom_id })
+}
+
+#[derive(Serialize, Debug)]
+pub struct BatchLineData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stocks: Option<Vec<ItemXShelf>>,
+    /// status code the line would have failed with on its own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BatchData {
+    pub committed: bool,
+    pub lines: Vec<BatchLineData>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BatchResponseData {
+    pub message: String,
+    pub data: BatchData,
+}
+
+/// The result of every line of a batch, along with a conflict status when nothing of it was kept.
+pub fn batch(outcome: BatchOutcome, over_capacity: Vec<ShelfCapacity>) -> Response {
+    let lines = outcome
+        .lines
+        .into_iter()
+        .map(|line| match line {
+            Ok(stocks) => BatchLineData {
+                stocks: Some(stocks),
+                status: None,
+                error: None,
+            },
+            Err(error) => BatchLineData {
+                stocks: None,
+                status: Some(http_status_code_for_service_error(&error).as_u16()),
+                error: Some(error.to_string()),
+            },
+        })
+        .collect();
+    let data = BatchData {
+        committed: outcome.committed,
+        lines,
+    };
+    if data.committed {
+        return stocked(data, over_capacity).into_response();
+    }
+    let error = ServiceError::BatchAborted;
+    (
+        http_status_code_for_service_error(&error),
+        Json(BatchResponseData {
+            message: error.to_string(),
+            data,
+        }),
+    )
+        .into_response()
+}
+
+#[derive(Serialize, Debug)]
+pub struct ShortageResponseData {
+    pub message: String,
+    pub data: Vec<Shortage>,
+}
+
+/// The stock resulting from an assembly, or the items missing for it along with a conflict status.
+pub fn assembly(assembly: Assembly) -> Response {
+    match assembly {
+        Assembly::Converted(stocks) => mutated_stocks(stocks).into_response(),
+        Assembly::Short(shortages) => {
+            let error = ServiceError::ComponentsShort;
+            (
+                http_status_code_for_service_error(&error),
+                Json(ShortageResponseData {
+                    message: error.to_string(),
+                    data: shortages,
+                }),
+            )
+                .into_response()
+        }
+    }
+}
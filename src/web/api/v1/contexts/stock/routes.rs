@@ -1,19 +1,70 @@
-use axum::routing::{delete, get, patch, post};
+use axum::routing::{delete, get, patch, post, put};
 use axum::Router;
 
 use super::handlers::{
-    convert_handler, deposit_handler, get_items_in_room_handler, get_items_in_rooms_handler, get_items_on_shelf_handler,
-    get_items_on_shelves_handler, transfer_handler, withdraw_handler,
+    add_bom_handler, approve_stocktake_handler, assemble_handler, batch_handler, cancel_stocktake_handler, change_status_handler,
+    classify_handler, clear_stock_flags_handler, convert_handler, count_stocktake_handler, delete_reorder_point_handler,
+    delete_stock_policy_handler, deposit_handler, disassemble_handler, get_below_reorder_point_handler, get_bom_handler,
+    get_boms_handler, get_dead_stock_handler, get_flagged_stocks_handler, get_items_in_bins_handler, get_items_in_room_handler,
+    get_items_in_rooms_handler, get_items_in_warehouse_handler, get_items_in_warehouses_handler, get_items_on_shelf_handler,
+    get_items_on_shelves_handler, get_lots_expiring_handler, get_movements_handler, get_putaway_suggestions_handler,
+    get_reorder_points_handler, get_reservation_handler, get_reservations_handler, get_stock_aging_handler,
+    get_stock_policies_handler, get_stocktake_handler, get_stocktake_lines_handler, get_stocktakes_handler, get_unit_handler,
+    get_valuation_handler, open_stocktake_handler, release_handler, reserve_handler, reverse_handler, set_reorder_point_handler,
+    set_stock_policy_handler, subscribe_alerts_handler, transfer_handler, unsubscribe_alerts_handler, withdraw_handler,
 };
 
 pub fn router() -> Router {
     Router::new()
         .route("/shelf", get(get_items_on_shelves_handler))
         .route("/shelf/:id", get(get_items_on_shelf_handler))
+        .route("/shelf/:id/bins", get(get_items_in_bins_handler))
         .route("/room", get(get_items_in_rooms_handler))
         .route("/room/:id", get(get_items_in_room_handler))
+        .route("/warehouse", get(get_items_in_warehouses_handler))
+        .route("/warehouse/:id", get(get_items_in_warehouse_handler))
         .route("/withdraw", delete(withdraw_handler))
         .route("/deposit", post(deposit_handler))
         .route("/transfer", patch(transfer_handler))
         .route("/convert", patch(convert_handler))
+        .route("/batch", post(batch_handler))
+        .route("/status", patch(change_status_handler))
+        .route("/expiring", get(get_lots_expiring_handler))
+        .route("/aging", get(get_stock_aging_handler))
+        .route("/dead", get(get_dead_stock_handler))
+        .route("/classification", post(classify_handler))
+        .route("/putaway-suggestions", get(get_putaway_suggestions_handler))
+        .route("/movements", get(get_movements_handler))
+        .route("/operations/:id/reverse", post(reverse_handler))
+        .route("/valuation", get(get_valuation_handler))
+        .route("/unit/:item_id/:serial", get(get_unit_handler))
+        .route("/reservations", get(get_reservations_handler).post(reserve_handler))
+        .route("/reservations/:id", get(get_reservation_handler).delete(release_handler))
+        .route(
+            "/reorder-points",
+            get(get_reorder_points_handler).put(set_reorder_point_handler),
+        )
+        .route("/reorder-points/:id", delete(delete_reorder_point_handler))
+        .route("/below-reorder-point", get(get_below_reorder_point_handler))
+        .route("/stocktakes", get(get_stocktakes_handler).post(open_stocktake_handler))
+        .route("/stocktakes/:id", get(get_stocktake_handler).delete(cancel_stocktake_handler))
+        .route("/stocktakes/:id/lines", get(get_stocktake_lines_handler))
+        .route("/stocktakes/:id/counts", put(count_stocktake_handler))
+        .route("/stocktakes/:id/approve", post(approve_stocktake_handler))
+        .route("/boms", get(get_boms_handler).post(add_bom_handler))
+        .route("/boms/:id", get(get_bom_handler))
+        .route("/boms/:id/assemble", post(assemble_handler))
+        .route("/boms/:id/disassemble", post(disassemble_handler))
+        .route(
+            "/policies",
+            get(get_stock_policies_handler)
+                .put(set_stock_policy_handler)
+                .delete(delete_stock_policy_handler),
+        )
+        .route("/flagged", get(get_flagged_stocks_handler))
+        .route("/flagged/:item_id/:shelf_id", delete(clear_stock_flags_handler))
+        .route(
+            "/alerts/subscription",
+            put(subscribe_alerts_handler).delete(unsubscribe_alerts_handler),
+        )
 }
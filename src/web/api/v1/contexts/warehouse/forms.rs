@@ -0,0 +1,18 @@
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AddWarehouseForm {
+    pub code: String,
+    pub name: String,
+    pub address: Option<String>,
+    /// IANA name of the time zone of the site, `UTC` when omitted
+    pub time_zone: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UpdateWarehouseForm {
+    pub code: Option<String>,
+    pub name: Option<String>,
+    pub address: Option<String>,
+    pub time_zone: Option<String>,
+}
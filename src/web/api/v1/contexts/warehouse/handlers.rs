@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::common::AppData;
+use crate::models::warehouse::WarehouseId;
+use crate::web::api::v1::extractors::bearer_token::Extract;
+use crate::web::api::v1::responses::OkResponseData;
+
+use super::forms::{AddWarehouseForm, UpdateWarehouseForm};
+use super::responses;
+
+#[allow(clippy::unused_async)]
+pub async fn add_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Json(warehouse_form): Json<AddWarehouseForm>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data.warehouse_service.add_warehouse(&warehouse_form, &user_id).await {
+        Ok(warehouse_id) => responses::mutated_warehouse(warehouse_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn patch_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(warehouse_id): Path<WarehouseId>,
+    Json(warehouse_form): Json<UpdateWarehouseForm>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data
+        .warehouse_service
+        .update_warehouse(&warehouse_id, &warehouse_form, &user_id)
+        .await
+    {
+        Ok(()) => responses::mutated_warehouse(warehouse_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn delete_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(warehouse_id): Path<WarehouseId>,
+) -> Response {
+    let user_id = match app_data.auth.get_user_id_from_bearer_token(&maybe_bearer_token).await {
+        Ok(user_id) => user_id,
+        Err(error) => return error.into_response(),
+    };
+    match app_data.warehouse_service.remove_warehouse(&warehouse_id, &user_id).await {
+        Ok(()) => responses::mutated_warehouse(warehouse_id).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_handler(
+    Extension(app_data): Extension<Arc<AppData>>,
+    Extract(maybe_bearer_token): Extract,
+    Path(warehouse_id): Path<WarehouseId>,
+) -> Response {
+    match app_data.warehouse_service.get_warehouse(&warehouse_id).await {
+        Ok(warehouse) => Json(OkResponseData { data: warehouse }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[allow(clippy::unused_async)]
+pub async fn get_all_handler(Extension(app_data): Extension<Arc<AppData>>, Extract(maybe_bearer_token): Extract) -> Response {
+    match app_data.warehouse_service.get_all_warehouses().await {
+        Ok(warehouses) => Json(OkResponseData { data: warehouses }).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
@@ -0,0 +1,4 @@
+pub mod forms;
+pub mod handlers;
+pub mod responses;
+pub mod routes;
@@ -0,0 +1,8 @@
+use axum::Json;
+
+use crate::models::warehouse::WarehouseId;
+use crate::web::api::v1::responses::OkResponseData;
+
+pub fn mutated_warehouse(warehouse_id: WarehouseId) -> Json<OkResponseData<WarehouseId>> {
+    Json(OkResponseData { data: warehouse_id })
+}
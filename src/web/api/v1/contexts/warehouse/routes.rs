@@ -0,0 +1,10 @@
+use axum::routing::get;
+use axum::Router;
+
+use super::handlers::{add_handler, delete_handler, get_all_handler, get_handler, patch_handler};
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(get_all_handler).post(add_handler))
+        .route("/:id", get(get_handler).patch(patch_handler).delete(delete_handler))
+}
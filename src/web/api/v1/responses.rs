@@ -6,6 +6,7 @@ use serde_json::json;
 
 use crate::databases::database;
 use crate::errors::{http_status_code_for_service_error, map_database_error_to_service_error, ServiceError};
+use crate::models::label::{Label, LabelFormat};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct OkResponseData<T> {
@@ -50,3 +51,20 @@ pub fn json_error_response(status_code: StatusCode, error_response_data: &ErrorR
     )
         .into_response()
 }
+
+/// Labels rendered as a document to print, which browsers show rather than download.
+#[must_use]
+pub fn labels_response(format: LabelFormat, labels: &[Label]) -> Response {
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("inline; filename=\"labels.{}\"", format.extension()),
+            ),
+        ],
+        format.render(labels),
+    )
+        .into_response()
+}
@@ -15,7 +15,7 @@ use crate::web::api::v1::contexts::evt::sse::sse_handler;
 use crate::web::api::v1::contexts::evt::wss::ws_handler;
 
 //fixme we may use tower_http::auth layer
-use super::contexts::{about, item, room, shelf, user};
+use super::contexts::{about, category, item, room, shelf, stock, user, warehouse};
 
 pub const API_VERSION_URL_PREFIX: &str = "api/v1";
 
@@ -27,9 +27,12 @@ async fn fallback() -> (StatusCode, Json<Value>) {
 pub fn router(app_data: Arc<AppData>) -> Router {
     let v1_api_routes = Router::new()
         .nest("/user", user::routes::router())
+        .nest("/warehouses", warehouse::routes::router())
         .nest("/rooms", room::routes::router())
         .nest("/shelf", shelf::routes::router())
-        .nest("/items", item::routes::router());
+        .nest("/items", item::routes::router())
+        .nest("/categories", category::routes::router())
+        .nest("/stock", stock::routes::router());
 
     let router = Router::new()
         .route("/health_check", get(health_check_handler))